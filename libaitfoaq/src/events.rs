@@ -1,98 +1,432 @@
 use serde::{Deserialize, Serialize};
 
-use crate::state::{Board, ClueHandle, ContestantHandle, Points};
+use crate::state::{Board, ClueField, ClueHandle, ClueRef, ContestantHandle, Options, Points};
 #[cfg(doc)]
 use crate::state::{Contestant, GamePhase, GameState};
 
 #[cfg_attr(doc, aquamarine::aquamarine)]
-/// ```mermaid
-/// stateDiagram-v2
-///    state GameLoop: Game Loop
-///    state optional_waging <<choice>>
-///    state next_or_end <<choice>>
+/// How events move the game between [GamePhase]s, generated from
+/// [TRANSITIONS](crate::transitions::TRANSITIONS)
 ///
-///    [*] --> Preparing
-///    Preparing --> Connecting: OpenLobby
-///    Connecting --> Picking: StartGame
-///    state GameLoop {
-///        Picking --> optional_waging: Pick
-///        optional_waging --> Clue: if can_wager == false
-///        optional_waging --> Waging: if can_wager == true
-///        Waging --> Clue: SetWage
-///        Clue --> Buzzing: ClueFullyShown
-///        Clue --> next_or_end: FinishClue
-///        Buzzing --> Buzzed: Buzz
-///        Buzzing --> next_or_end: FinishClue
-///        Buzzed --> Clue: RejectAnswer
-///        Buzzed --> Resolution: AcceptAnswer
-///        Buzzed --> Resolution: FinishClue
-///        Resolution --> next_or_end: FinishClue
-///        next_or_end --> Score: if clues_left <= 0
-///        next_or_end --> Picking: if clues_left > 0
-///    }
-///    Score --> [*]
-/// ```
+/// include_mmd!("doc/transitions.mmd")
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all_fields = "snake_case", tag = "type")]
 pub enum Event {
     /// Change settings before starting the game.
-    /// Only allowed in [GamePhase::Preparing]. Can be repeated.
-    Settings,
-    /// Load a [Board] of clues.
     /// Only allowed in [GamePhase::Preparing]. Can be repeated, which replaces
-    /// the already loaded board.
+    /// all [Options].
+    Settings(Options),
+    /// Load a [Board] of clues.
+    /// Only allowed in [GamePhase::Preparing] and [GamePhase::Connecting], so
+    /// a wrong board can still be replaced once contestants joined. Can be
+    /// repeated, which replaces the already loaded board.
     LoadBoard(Board),
+    /// Reorder the categories of the loaded [Board], the same way for the
+    /// same seed so replays end up with the same board.
+    /// Only allowed in [GamePhase::Preparing].
+    ShuffleBoard { seed: u64 },
+    /// Correct a field of a single clue of the loaded [Board].
+    /// Only allowed in [GamePhase::Preparing], except for [ClueField::Hint]
+    /// which the moderator can amend at any time.
+    EditClue {
+        clue: ClueHandle,
+        field: ClueField,
+        value: String,
+    },
+    /// Rename a category of the loaded [Board].
+    /// Only allowed in [GamePhase::Preparing].
+    EditCategory { category: usize, title: String },
     /// Allow players to connect.
     /// Transitions from [GamePhase::Preparing] to [GamePhase::Connecting].
+    /// Repeating it in [GamePhase::Connecting] does nothing.
     OpenLobby,
 
     /// Initial registration of a contestant. Adds a [Contestant] to [GameState].
     /// Only allowed in [GamePhase::Connecting].
     ConnectContestant { name_hint: String },
+    /// Adds a named [Contestant] for each of `names`, for events where
+    /// everyone is known beforehand. They aren't connected until someone
+    /// claims their seat with [Event::ReconnectContestant]. Blank names are
+    /// skipped. Only allowed in [GamePhase::Preparing] and
+    /// [GamePhase::Connecting].
+    ImportContestants { names: Vec<String> },
     /// Mark a [Contestant] as disconnected. This does not remove them, they can
     /// join at a later time, and optionally halt the game until then.
+    /// If they were about to answer, the clue goes back to
+    /// [GamePhase::Buzzing] like with [Event::WithdrawBuzz], without a
+    /// penalty. Exclusive clues keep waiting for them. If they were picking,
+    /// the pick passes to the next connected contestant like with
+    /// [Event::PassPick], unless nobody else is connected. Reconnecting
+    /// doesn't undo either.
     DisconnectContestant { contestant: ContestantHandle },
-    /// Reconnect a [Contestant] and resume the game.
+    /// Reconnect a [Contestant] and resume the game. Does nothing if they're
+    /// already connected.
     ReconnectContestant { contestant: ContestantHandle },
+    /// Show the [Contestants](Contestant) in this order, like the podiums
+    /// stand in the room. Has to name every contestant exactly once. Only
+    /// allowed in [GamePhase::Connecting], once the game is over they're
+    /// shown by rank.
+    ReorderContestants { order: Vec<ContestantHandle> },
     /// Properly name a [Contestant]. This might happen during an introduction
     /// round. Can also happen after [GamePhase::Connecting].
     NameContestant { index: usize, name: String },
-    /// Add points to the contestant's score
-    AwardPoints { contestant: ContestantHandle, points: u32 },
-    /// Subtract points from the contestant's score
-    RevokePoints { contestant: ContestantHandle, points: u32 },
+    /// Add points to the contestant's score. Once the game is locked the
+    /// `reason` can't be blank, see [Event::StartGame]. Recorded in
+    /// [GameState::corrections], for the `clue` if there is one.
+    AwardPoints {
+        contestant: ContestantHandle,
+        points: u32,
+        /// Older journals don't have it
+        #[serde(default)]
+        reason: Option<String>,
+        /// Older journals don't have it
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        clue: Option<ClueHandle>,
+    },
+    /// Subtract points from the contestant's score, like [Event::AwardPoints]
+    RevokePoints {
+        contestant: ContestantHandle,
+        points: u32,
+        #[serde(default)]
+        reason: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        clue: Option<ClueHandle>,
+    },
+    /// Change several scores at once, like a bonus for everyone who solved a
+    /// clue of a category. Either every change is applied or none, see
+    /// [Error::InvalidAdjustments](crate::Error::InvalidAdjustments). The
+    /// `reason` can't be blank once the game is locked.
+    AdjustScores {
+        changes: Vec<(ContestantHandle, Points)>,
+        reason: String,
+    },
+    /// Transition from [GamePhase::Connecting] to [GamePhase::Practice], so
+    /// contestants can try their buzzers before the game. Starts counting
+    /// [GameState::practice_wins] from zero. Repeating it in
+    /// [GamePhase::Practice] does nothing.
+    StartPractice,
+    /// Let everyone buzz again in [GamePhase::Practice], turning the lights
+    /// off
+    ResetPractice,
+    /// Transition from [GamePhase::Practice] back to [GamePhase::Connecting]
+    EndPractice,
     /// Transition from [GamePhase::Connecting] to [GamePhase::Picking]. No new
-    /// [Contestants](Contestant) can connect afterwards.
+    /// [Contestants](Contestant) can connect afterwards. Locks the game: the
+    /// events of [Event::is_structural] are refused and changing scores by
+    /// hand needs a reason, until [Event::Unlock].
     StartGame,
+    /// Lifts the lock of [Event::StartGame] for the rest of the game, which
+    /// [GameState::unlocked] remembers. Only allowed while locked.
+    Unlock,
     /// Transition from [GamePhase::Picking] to [GamePhase::Waging] or
     /// [GamePhase::Clue] depending on
     /// [Clue::can_wager](crate::state::Clue::can_wager) of the picked clue.
-    Pick { clue: ClueHandle },
+    Pick { clue: ClueRef },
+    /// The picking [Contestant] defers the pick to another connected one, or
+    /// to the next connected one after them if `to` is `None`. Only allowed
+    /// in [GamePhase::Picking].
+    PassPick { to: Option<ContestantHandle> },
 
     /// Transition from [GamePhase::Waging] to [GamePhase::Clue].
-    /// A [Contestant] waging some of their [Points].
+    /// A [Contestant] waging some of their [Points], within the bounds of
+    /// [GamePhase::Waging]. The wager becomes the points of the clue.
+    /// `0` skips the wager, for the moderator to unblock a
+    /// [GameState::wager_stalled](crate::state::GameState::wager_stalled).
+    /// It's allowed regardless, as disconnects aren't part of the journal.
+    /// With [Options::confirm_wagers] other wagers only become pending, see
+    /// [Event::ConfirmWage], and another one replaces them.
     SetWage { points: Points },
+    /// Commit the pending wager of [Event::SetWage], transition from
+    /// [GamePhase::Waging] to [GamePhase::Clue]
+    ConfirmWage,
+    /// Drop the pending wager of [Event::SetWage] to enter another one. Only
+    /// allowed in [GamePhase::Waging].
+    CancelWage,
 
     /// Transition from [GamePhase::Clue] to [GamePhase::Buzzing]. During
     /// [GamePhase::Clue] [Contestants](Contestant) can't buzz in so everyone
-    /// gets a chance to fully hear the prompt.
+    /// gets a chance to fully hear the prompt. Repeating it in
+    /// [GamePhase::Buzzing] does nothing.
     // todo: make skippable with setting so contestants can buzz in immedieately
     ClueFullyShown,
 
     /// A [Contestant] buzzing in. Transtion from [GamePhase::Buzzing] to
-    /// [GamePhase::Buzzed]
+    /// [GamePhase::Buzzed]. In [GamePhase::Practice] the first one since
+    /// [Event::ResetPractice] wins, without any points.
     Buzz { contestant: ContestantHandle },
+    /// The answer of the [Contestant] who buzzed, typed instead of spoken.
+    /// Replaces an earlier one, the moderator still has to accept or reject
+    /// it.
+    SubmitAnswer { contestant: ContestantHandle, text: String },
 
-    /// Transition from [GamePhase::Buzzed] to [GamePhase::Resolution].
+    /// Transition from [GamePhase::Buzzed] to [GamePhase::Resolution], or
+    /// to [GamePhase::Clue] exclusive to the same [Contestant] if the part
+    /// has a [Clue::followup](crate::state::Clue::followup).
     AcceptAnswer,
-    /// Transition from [GamePhase::Buzzed] to [GamePhase::Buzzing].
+    /// Transition from [GamePhase::Buzzed] back to [GamePhase::Buzzing]
+    /// without a penalty, as if the [Contestant] never buzzed. Not allowed
+    /// for exclusive clues, nobody else could buzz.
+    WithdrawBuzz,
+    /// Transition from [GamePhase::Buzzed] to [GamePhase::Buzzing], or to
+    /// [GamePhase::Resolution] for exclusive clues with
+    /// [Options::exclusive_single_attempt]. Counts towards
+    /// [Options::auto_reveal_hint_after], which reveals the hint with
+    /// [GameState::hint_revealed] until the clue is resolved.
     RejectAnswer,
 
-    /// Reveal the moderator hint to the contestants in [GamePhase::Resolution]
+    /// Reveal the moderator hint to the contestants in [GamePhase::Resolution].
+    /// Already revealed if it was revealed automatically before.
     RevealHint,
     /// Transition from [GamePhase::Resolution] to [GamePhase::Score] or back to
     /// [GamePhase::Picking]. Can also be used to skip answering a prompt from
     /// [GamePhase::Clue], [GamePhase::Buzzing], or [GamePhase::Buzzed] without
-    /// awarding/changing points.
+    /// awarding/changing points, or to abandon a wager in
+    /// [GamePhase::Waging], e.g. if the clue was picked by mistake. Skipping
+    /// has to be `confirmed` with [Options::confirm_skip]. The next pick is
+    /// for whoever answered the last clue right, or else the last one who
+    /// picked.
+    FinishClue {
+        /// Older journals don't have it
+        #[serde(default)]
+        confirmed: bool,
+    },
+}
+
+impl Event {
+    /// The name of the variant, the same as the `type` of the event in
+    /// journals. Stable across changes to the fields, e.g. to summarize a
+    /// journal.
+    pub fn name(&self) -> &'static str {
+        self.kind().name()
+    }
+
+    /// The variant without its fields
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Self::Settings(_) => EventKind::Settings,
+            Self::LoadBoard(_) => EventKind::LoadBoard,
+            Self::ShuffleBoard { .. } => EventKind::ShuffleBoard,
+            Self::EditClue { .. } => EventKind::EditClue,
+            Self::EditCategory { .. } => EventKind::EditCategory,
+            Self::OpenLobby => EventKind::OpenLobby,
+            Self::ConnectContestant { .. } => EventKind::ConnectContestant,
+            Self::ImportContestants { .. } => EventKind::ImportContestants,
+            Self::DisconnectContestant { .. } => EventKind::DisconnectContestant,
+            Self::ReconnectContestant { .. } => EventKind::ReconnectContestant,
+            Self::ReorderContestants { .. } => EventKind::ReorderContestants,
+            Self::NameContestant { .. } => EventKind::NameContestant,
+            Self::AwardPoints { .. } => EventKind::AwardPoints,
+            Self::RevokePoints { .. } => EventKind::RevokePoints,
+            Self::AdjustScores { .. } => EventKind::AdjustScores,
+            Self::StartPractice => EventKind::StartPractice,
+            Self::ResetPractice => EventKind::ResetPractice,
+            Self::EndPractice => EventKind::EndPractice,
+            Self::StartGame => EventKind::StartGame,
+            Self::Unlock => EventKind::Unlock,
+            Self::Pick { .. } => EventKind::Pick,
+            Self::PassPick { .. } => EventKind::PassPick,
+            Self::SetWage { .. } => EventKind::SetWage,
+            Self::ConfirmWage => EventKind::ConfirmWage,
+            Self::CancelWage => EventKind::CancelWage,
+            Self::ClueFullyShown => EventKind::ClueFullyShown,
+            Self::Buzz { .. } => EventKind::Buzz,
+            Self::SubmitAnswer { .. } => EventKind::SubmitAnswer,
+            Self::AcceptAnswer => EventKind::AcceptAnswer,
+            Self::WithdrawBuzz => EventKind::WithdrawBuzz,
+            Self::RejectAnswer => EventKind::RejectAnswer,
+            Self::RevealHint => EventKind::RevealHint,
+            Self::FinishClue { .. } => EventKind::FinishClue,
+        }
+    }
+
+    /// If the event changes the board or the settings, which isn't allowed
+    /// once the game is locked. Hints can still be amended.
+    pub fn is_structural(&self) -> bool {
+        match self {
+            Self::Settings(_) | Self::LoadBoard(_) | Self::ShuffleBoard { .. } | Self::EditCategory { .. } => true,
+            Self::EditClue { field, .. } => *field != ClueField::Hint,
+            _ => false,
+        }
+    }
+
+    /// If the event only tracks whether a controller is present, see
+    /// [Contestant::connected]. Those don't change the game itself, so they
+    /// don't belong in its history and replaying a game without them leads
+    /// to the same state.
+    pub fn is_presence(&self) -> bool {
+        matches!(self, Self::DisconnectContestant { .. } | Self::ReconnectContestant { .. })
+    }
+
+    /// What applying the same event twice in a row does, e.g. because a
+    /// journal was written by hand
+    pub fn idempotency(&self) -> Idempotency {
+        match self {
+            Self::Settings(_)
+            | Self::LoadBoard(_)
+            | Self::EditClue { .. }
+            | Self::EditCategory { .. }
+            | Self::OpenLobby
+            | Self::DisconnectContestant { .. }
+            | Self::ReconnectContestant { .. }
+            | Self::ReorderContestants { .. }
+            | Self::NameContestant { .. }
+            | Self::ClueFullyShown
+            | Self::SubmitAnswer { .. }
+            | Self::CancelWage
+            | Self::StartPractice
+            | Self::ResetPractice
+            | Self::RevealHint => Idempotency::NoOp,
+            Self::ShuffleBoard { .. }
+            | Self::ConnectContestant { .. }
+            | Self::AwardPoints { .. }
+            | Self::RevokePoints { .. }
+            | Self::AdjustScores { .. }
+            | Self::PassPick { .. } => Idempotency::Cumulative,
+            Self::StartGame
+            | Self::EndPractice
+            | Self::Unlock
+            // the names are taken by then, see [Options::unique_names]
+            | Self::ImportContestants { .. }
+            | Self::Pick { .. }
+            | Self::SetWage { .. }
+            | Self::ConfirmWage
+            | Self::Buzz { .. }
+            | Self::AcceptAnswer
+            | Self::WithdrawBuzz
+            | Self::RejectAnswer
+            | Self::FinishClue { .. } => Idempotency::Refused,
+        }
+    }
+}
+
+/// See [Event::idempotency]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Idempotency {
+    /// The repetition succeeds and leaves the game as it was
+    NoOp,
+    /// The repetition succeeds and changes the game again, like adding
+    /// another contestant
+    Cumulative,
+    /// The game moved on, so the repetition fails, usually with
+    /// [Error::WrongPhase](crate::Error::WrongPhase)
+    Refused,
+}
+
+/// The variants of [Event] without their fields, serialized by
+/// [Event::name]. See [Game::legal_events](crate::Game::legal_events).
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum EventKind {
+    Settings,
+    LoadBoard,
+    ShuffleBoard,
+    EditClue,
+    EditCategory,
+    OpenLobby,
+    ConnectContestant,
+    ImportContestants,
+    DisconnectContestant,
+    ReconnectContestant,
+    ReorderContestants,
+    NameContestant,
+    AwardPoints,
+    RevokePoints,
+    AdjustScores,
+    StartPractice,
+    ResetPractice,
+    EndPractice,
+    StartGame,
+    Unlock,
+    Pick,
+    PassPick,
+    SetWage,
+    ConfirmWage,
+    CancelWage,
+    ClueFullyShown,
+    Buzz,
+    SubmitAnswer,
+    AcceptAnswer,
+    WithdrawBuzz,
+    RejectAnswer,
+    RevealHint,
     FinishClue,
 }
+
+impl EventKind {
+    /// In the order of [Event], which is also how they're sorted
+    pub const ALL: &'static [Self] = &[
+        Self::Settings,
+        Self::LoadBoard,
+        Self::ShuffleBoard,
+        Self::EditClue,
+        Self::EditCategory,
+        Self::OpenLobby,
+        Self::ConnectContestant,
+        Self::ImportContestants,
+        Self::DisconnectContestant,
+        Self::ReconnectContestant,
+        Self::ReorderContestants,
+        Self::NameContestant,
+        Self::AwardPoints,
+        Self::RevokePoints,
+        Self::AdjustScores,
+        Self::StartPractice,
+        Self::ResetPractice,
+        Self::EndPractice,
+        Self::StartGame,
+        Self::Unlock,
+        Self::Pick,
+        Self::PassPick,
+        Self::SetWage,
+        Self::ConfirmWage,
+        Self::CancelWage,
+        Self::ClueFullyShown,
+        Self::Buzz,
+        Self::SubmitAnswer,
+        Self::AcceptAnswer,
+        Self::WithdrawBuzz,
+        Self::RejectAnswer,
+        Self::RevealHint,
+        Self::FinishClue,
+    ];
+
+    /// The name of the variant, see [Event::name]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Settings => "Settings",
+            Self::LoadBoard => "LoadBoard",
+            Self::ShuffleBoard => "ShuffleBoard",
+            Self::EditClue => "EditClue",
+            Self::EditCategory => "EditCategory",
+            Self::OpenLobby => "OpenLobby",
+            Self::ConnectContestant => "ConnectContestant",
+            Self::ImportContestants => "ImportContestants",
+            Self::DisconnectContestant => "DisconnectContestant",
+            Self::ReconnectContestant => "ReconnectContestant",
+            Self::ReorderContestants => "ReorderContestants",
+            Self::NameContestant => "NameContestant",
+            Self::AwardPoints => "AwardPoints",
+            Self::RevokePoints => "RevokePoints",
+            Self::AdjustScores => "AdjustScores",
+            Self::StartPractice => "StartPractice",
+            Self::ResetPractice => "ResetPractice",
+            Self::EndPractice => "EndPractice",
+            Self::StartGame => "StartGame",
+            Self::Unlock => "Unlock",
+            Self::Pick => "Pick",
+            Self::PassPick => "PassPick",
+            Self::SetWage => "SetWage",
+            Self::ConfirmWage => "ConfirmWage",
+            Self::CancelWage => "CancelWage",
+            Self::ClueFullyShown => "ClueFullyShown",
+            Self::Buzz => "Buzz",
+            Self::SubmitAnswer => "SubmitAnswer",
+            Self::AcceptAnswer => "AcceptAnswer",
+            Self::WithdrawBuzz => "WithdrawBuzz",
+            Self::RejectAnswer => "RejectAnswer",
+            Self::RevealHint => "RevealHint",
+            Self::FinishClue => "FinishClue",
+        }
+    }
+}
@@ -1,18 +1,67 @@
 pub mod events;
+pub mod lint;
+pub mod matching;
+pub mod print;
+#[cfg(feature = "sim")]
+pub mod sim;
 pub mod state;
+pub mod transitions;
 
 #[cfg(test)]
 mod test_state_machine;
 
-use events::Event;
+use std::borrow::Cow;
+
+use events::{Event, EventKind};
 use state::*;
 
+/// The smallest wager, unless no clue is worth that much
+const MIN_WAGER: Points = 5;
+/// Longer answers are cut off, in characters
+const MAX_ANSWER_LENGTH: usize = 200;
+
+/// Called with the kind of every event that was applied successfully and the
+/// phase it resulted in
+pub type Observer = Box<dyn Fn(EventKind, &GamePhase) + Send>;
+
 #[derive(Debug)]
 pub struct Game {
     phase: GamePhase,
     board: Board,
     contestants: Vec<Contestant>,
     options: Options,
+    /// See [GameState::newly_completed_category]
+    newly_completed_category: Option<usize>,
+    /// Wrong answers to the clue being played, see
+    /// [Options::auto_reveal_hint_after]
+    rejected_answers: u8,
+    /// See [GameState::followup]
+    followup: usize,
+    /// See [Event::StartGame]
+    locked: bool,
+    /// See [Event::Unlock]
+    unlocked: bool,
+    /// See [Game::accept_missing_reasons]
+    missing_reasons: bool,
+    /// Who picks next unless someone else answers correctly: the last one
+    /// who picked, or who got the last clue right
+    control: Option<ContestantHandle>,
+    /// See [GameState::corrections]
+    corrections: Vec<Correction>,
+    /// What's left of the points of the clue being played after
+    /// [Options::decay_on_rejection], `None` until an answer was rejected
+    decayed_value: Option<Points>,
+    /// See [GameState::practice_wins], kept until the next practice
+    practice_wins: Vec<u32>,
+    observer: Option<Observed>,
+}
+
+struct Observed(Observer);
+
+impl std::fmt::Debug for Observed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Observer")
+    }
 }
 
 impl Game {
@@ -24,40 +73,211 @@ impl Game {
             },
             contestants: Vec::with_capacity(4),
             options: Options::default(),
+            newly_completed_category: None,
+            rejected_answers: 0,
+            followup: 0,
+            locked: false,
+            unlocked: false,
+            missing_reasons: false,
+            control: None,
+            corrections: Vec::new(),
+            decayed_value: None,
+            practice_wins: Vec::new(),
+            observer: None,
         }
     }
 
+    /// Replaces the observer, the game doesn't produce any output on its own
+    pub fn set_observer(&mut self, observer: Observer) {
+        self.observer = Some(Observed(observer));
+    }
+
+    /// See [Game::apply_ref], for callers that don't need the event
+    /// afterwards. The board of [Event::LoadBoard] is moved instead of copied.
     pub fn apply(&mut self, event: Event) -> Result<GameState, Error> {
         match event {
-            Event::LoadBoard(board) => self.load_board(board)?,
-            Event::OpenLobby => self.open_lobby()?,
-            Event::ConnectContestant { name_hint } => self.connect_contestant(name_hint)?,
-            Event::ReconnectContestant { contestant } => self.reconnect_contestant(contestant)?,
-            Event::DisconnectContestant { contestant } => self.disconnect_contestant(contestant)?,
-            Event::NameContestant { index, name } => self.name_contestant(index, name)?,
-            Event::AwardPoints { contestant, points } => self.modify_score(contestant, points as i32)?,
-            Event::RevokePoints { contestant, points } => self.modify_score(contestant, -1*(points as i32))?,
-            Event::StartGame => self.start_game()?,
-            Event::Pick { clue } => self.pick(clue)?,
-            Event::ClueFullyShown => self.clue_fully_shown()?,
-            Event::Buzz { contestant } => self.buzz(contestant)?,
-            Event::SetWage { points } => self.set_wage(points)?,
-            Event::AcceptAnswer => self.accept_answer()?,
-            Event::RejectAnswer => self.reject_answer()?,
-            Event::RevealHint => self.reveal_hint()?,
-            Event::FinishClue => self.finish_clue()?,
-            _ => todo!("other events"),
+            Event::LoadBoard(board) => {
+                self.apply_with(EventKind::LoadBoard, true, |game| game.load_board(Cow::Owned(board)))
+            }
+            event => self.apply_ref(&event),
+        }
+    }
+
+    /// Only what the game keeps of the event is copied, like the board of
+    /// [Event::LoadBoard], and only once the event is allowed. Callers that
+    /// still need the event, e.g. to journal it, don't have to clone it.
+    pub fn apply_ref(&mut self, event: &Event) -> Result<GameState, Error> {
+        self.apply_with(event.kind(), event.is_structural(), |game| match event {
+            Event::Settings(options) => game.settings(options),
+            Event::LoadBoard(board) => game.load_board(Cow::Borrowed(board)),
+            Event::ShuffleBoard { seed } => game.shuffle_board(*seed),
+            Event::EditClue { clue, field, value } => game.edit_clue(*clue, *field, value),
+            Event::EditCategory { category, title } => game.edit_category(*category, title),
+            Event::OpenLobby => game.open_lobby(),
+            Event::ConnectContestant { name_hint } => game.connect_contestant(name_hint),
+            Event::ImportContestants { names } => game.import_contestants(names),
+            Event::ReconnectContestant { contestant } => game.reconnect_contestant(*contestant),
+            Event::DisconnectContestant { contestant } => game.disconnect_contestant(*contestant),
+            Event::ReorderContestants { order } => game.reorder_contestants(order),
+            Event::NameContestant { index, name } => game.name_contestant(*index, name),
+            Event::AwardPoints { contestant, points, reason, clue } => {
+                game.modify_score(*contestant, Points::from(*points), reason.as_deref(), *clue)
+            }
+            Event::RevokePoints { contestant, points, reason, clue } => {
+                game.modify_score(*contestant, -Points::from(*points), reason.as_deref(), *clue)
+            }
+            Event::AdjustScores { changes, reason } => game.adjust_scores(changes, reason),
+            Event::StartPractice => game.start_practice(),
+            Event::ResetPractice => game.reset_practice(),
+            Event::EndPractice => game.end_practice(),
+            Event::StartGame => game.start_game(),
+            Event::Unlock => game.unlock(),
+            Event::Pick { clue } => game.pick(clue),
+            Event::PassPick { to } => game.pass_pick(*to),
+            Event::ClueFullyShown => game.clue_fully_shown(),
+            Event::Buzz { contestant } => game.buzz(*contestant),
+            Event::SubmitAnswer { contestant, text } => game.submit_answer(*contestant, text),
+            Event::SetWage { points } => game.set_wage(*points),
+            Event::ConfirmWage => game.confirm_wage(),
+            Event::CancelWage => game.cancel_wage(),
+            Event::AcceptAnswer => game.accept_answer(),
+            Event::WithdrawBuzz => game.withdraw_buzz(),
+            Event::RejectAnswer => game.reject_answer(),
+            Event::RevealHint => game.reveal_hint(),
+            Event::FinishClue { confirmed } => game.finish_clue(*confirmed),
+        })
+    }
+
+    /// The part of [Game::apply] and [Game::apply_ref] around the transition
+    /// itself
+    fn apply_with(
+        &mut self,
+        kind: EventKind,
+        structural: bool,
+        transition: impl FnOnce(&mut Self) -> Result<(), Error>,
+    ) -> Result<GameState, Error> {
+        self.newly_completed_category = None;
+        if self.locked && structural {
+            return Err(Error::GameLocked);
+        }
+        transition(self)?;
+        if let Some(Observed(observer)) = &self.observer {
+            observer(kind, &self.phase);
         }
         Ok(self.get_game_state())
     }
 
+    /// For journals written before score changes had a reason: a locked game
+    /// takes [Event::AwardPoints] and [Event::RevokePoints] without one.
+    /// Blank reasons are still refused.
+    pub fn accept_missing_reasons(&mut self) {
+        self.missing_reasons = true;
+    }
+
+    /// The current phase, without building the whole [GameState]
+    pub fn phase(&self) -> &GamePhase {
+        &self.phase
+    }
+
     pub fn get_game_state(&self) -> GameState {
+        let mut contestants = self.contestants.clone();
+        for (contestant, name) in contestants.iter_mut().zip(Contestant::display_names(&self.contestants)) {
+            contestant.display_name = name;
+        }
         GameState {
-            contestants: self.contestants.clone(),
+            contestants,
             board: self.board.clone(),
             phase: self.phase.clone(),
             options: self.options.clone(),
+            progress: self.board.summary(),
+            newly_completed_category: self.newly_completed_category,
+            locked: self.locked,
+            unlocked: self.unlocked,
+            hint_revealed: matches!(self.phase, GamePhase::Clue { .. } | GamePhase::Buzzing { .. } | GamePhase::Buzzed { .. })
+                && self.hint_revealed(),
+            final_standings: match self.phase {
+                GamePhase::Score => Standing::rank(&self.contestants),
+                _ => vec![],
+            },
+            wager_stalled: match self.phase {
+                GamePhase::Waging { contestant, .. } => self.contestants.get(contestant).is_some_and(|c| !c.connected),
+                _ => false,
+            },
+            followup: self.followup,
+            legal_events: self.legal_events(),
+            corrections: self.corrections.clone(),
+            current_value: match self.phase {
+                GamePhase::Clue { clue, .. } | GamePhase::Buzzing { clue } | GamePhase::Buzzed { clue, .. } => {
+                    self.current_value(&clue).ok()
+                },
+                _ => None,
+            },
+            practice_wins: match self.phase {
+                GamePhase::Practice { .. } => self.practice_wins.clone(),
+                _ => vec![],
+            },
+        }
+    }
+
+    /// The kinds of events that can succeed now, with the right parameters.
+    /// Worked out from the phase, the options and the board, so UIs can
+    /// offer exactly those.
+    pub fn legal_events(&self) -> Vec<EventKind> {
+        use EventKind::*;
+        let has_clues = self.board.categories.iter().any(|c| !c.clues.is_empty());
+        let has_contestants = !self.contestants.is_empty();
+        let mut legal = vec![];
+        legal.extend(has_clues.then_some(EditClue));
+        if has_contestants {
+            legal.extend([DisconnectContestant, ReconnectContestant, NameContestant, AwardPoints, RevokePoints]);
+        }
+        // nothing to change is fine, too
+        legal.push(AdjustScores);
+        legal.extend(self.locked.then_some(Unlock));
+        match &self.phase {
+            GamePhase::Preparing => {
+                legal.extend([Settings, LoadBoard, ShuffleBoard, OpenLobby, ImportContestants]);
+                legal.extend((!self.board.categories.is_empty()).then_some(EditCategory));
+            }
+            GamePhase::Connecting => {
+                legal.extend([LoadBoard, OpenLobby, ConnectContestant, ImportContestants, ReorderContestants]);
+                if has_contestants {
+                    legal.extend([Buzz, StartPractice, StartGame]);
+                }
+            }
+            GamePhase::Practice { .. } => {
+                legal.extend([StartPractice, ResetPractice, EndPractice]);
+                legal.extend(has_contestants.then_some(Buzz));
+            }
+            GamePhase::Picking { .. } => {
+                legal.extend(has_clues.then_some(Pick));
+                legal.extend(self.contestants.iter().any(|c| c.connected).then_some(PassPick));
+            }
+            GamePhase::Waging { pending_wager, .. } => {
+                legal.extend([SetWage, CancelWage, FinishClue]);
+                legal.extend(pending_wager.is_some().then_some(ConfirmWage));
+            }
+            GamePhase::Clue { .. } => legal.extend([ClueFullyShown, FinishClue]),
+            GamePhase::Buzzing { .. } => {
+                legal.extend([ClueFullyShown, FinishClue]);
+                legal.extend(has_contestants.then_some(Buzz));
+            }
+            GamePhase::Buzzed { clue, contestant, exclusive, .. } => {
+                legal.extend([SubmitAnswer, FinishClue]);
+                legal.extend((!exclusive).then_some(WithdrawBuzz));
+                // scores can't leave the cap
+                let points = self.current_value(clue);
+                let score = self.contestants.get(*contestant).map(|c| c.points);
+                if let (Ok(points), Some(score)) = (points, score) {
+                    legal.extend(add_points(score, points).is_ok().then_some(AcceptAnswer));
+                    legal.extend(add_points(score, -points).is_ok().then_some(RejectAnswer));
+                }
+            }
+            GamePhase::Resolution { .. } => legal.extend([RevealHint, FinishClue]),
+            GamePhase::Score => legal.extend(has_contestants.then_some(Buzz)),
         }
+        legal.sort();
+        legal
     }
 
     /// When loading game state from a file, no contestants are actually connected
@@ -67,17 +287,79 @@ impl Game {
         }
     }
 
-    fn load_board(&mut self, board: Board) -> Result<(), Error> {
+    fn settings(&mut self, options: &Options) -> Result<(), Error> {
+        if !matches!(&self.phase, GamePhase::Preparing) {
+            return Err(Error::WrongPhase {
+                is: self.phase.clone(),
+            });
+        }
+        self.options = options.clone();
+        Ok(())
+    }
+
+    /// Contestants, their scores and lights are kept
+    /// Checked before the board of [Event::LoadBoard] is copied or moved
+    /// Borrowed boards are only copied once the phase allows them
+    fn load_board(&mut self, board: Cow<Board>) -> Result<(), Error> {
+        if !matches!(&self.phase, GamePhase::Preparing | GamePhase::Connecting) {
+            return Err(Error::WrongPhase {
+                is: self.phase.clone(),
+            });
+        }
+        self.board = board.into_owned();
+        Ok(())
+    }
+
+    fn shuffle_board(&mut self, seed: u64) -> Result<(), Error> {
+        if !matches!(&self.phase, GamePhase::Preparing) {
+            return Err(Error::WrongPhase {
+                is: self.phase.clone(),
+            });
+        }
+        self.board.shuffle(seed);
+        Ok(())
+    }
+
+    fn edit_clue(&mut self, handle: ClueHandle, field: ClueField, value: &str) -> Result<(), Error> {
+        if field != ClueField::Hint && !matches!(&self.phase, GamePhase::Preparing) {
+            return Err(Error::WrongPhase {
+                is: self.phase.clone(),
+            });
+        }
+        let clue = self.board.get_mut(&handle)?;
+        match field {
+            ClueField::Clue if value.trim().is_empty() => return Err(Error::EmptyClue),
+            ClueField::Clue => clue.clue = value.to_owned(),
+            ClueField::Response => clue.response = value.to_owned(),
+            ClueField::Hint => clue.hint = value.to_owned(),
+            ClueField::Points => {
+                clue.points = match value.trim().parse() {
+                    Ok(points) if (1..=MAX_POINTS).contains(&points) => points,
+                    _ => return Err(Error::InvalidPoints),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn edit_category(&mut self, category: usize, title: &str) -> Result<(), Error> {
         if !matches!(&self.phase, GamePhase::Preparing) {
             return Err(Error::WrongPhase {
                 is: self.phase.clone(),
             });
         }
-        self.board = board;
+        self.board
+            .categories
+            .get_mut(category)
+            .ok_or(Error::CategoryNotFound)?
+            .title = title.to_owned();
         Ok(())
     }
 
     fn open_lobby(&mut self) -> Result<(), Error> {
+        if matches!(&self.phase, GamePhase::Connecting) {
+            return Ok(());
+        }
         if !matches!(&self.phase, GamePhase::Preparing) {
             return Err(Error::WrongPhase {
                 is: self.phase.clone(),
@@ -87,7 +369,7 @@ impl Game {
         Ok(())
     }
 
-    fn connect_contestant(&mut self, hint: String) -> Result<(), Error> {
+    fn connect_contestant(&mut self, hint: &str) -> Result<(), Error> {
         if !matches!(&self.phase, GamePhase::Connecting) {
             return Err(Error::WrongPhase {
                 is: self.phase.clone(),
@@ -95,14 +377,67 @@ impl Game {
         }
         self.contestants.push(Contestant {
             name: None,
-            name_hint: hint,
+            name_hint: hint.to_owned(),
             points: 0 as Points,
             indicate: false,
             connected: true,
+            display_name: String::new(),
+            display_position: self.contestants.len(),
         });
         Ok(())
     }
 
+    /// All or none, so a list with a taken name can be fixed and sent again
+    fn import_contestants(&mut self, names: &[String]) -> Result<(), Error> {
+        if !matches!(&self.phase, GamePhase::Preparing | GamePhase::Connecting) {
+            return Err(Error::WrongPhase {
+                is: self.phase.clone(),
+            });
+        }
+        let names: Vec<&str> = names.iter().map(|name| name.trim()).filter(|name| !name.is_empty()).collect();
+        if self.options.unique_names {
+            let mut taken: Vec<String> = self.contestants.iter()
+                .flat_map(|c| c.name.iter().chain([&c.name_hint]))
+                .map(|name| name.trim().to_lowercase())
+                .collect();
+            for name in &names {
+                if taken.contains(&name.to_lowercase()) {
+                    return Err(Error::NameTaken);
+                }
+                taken.push(name.to_lowercase());
+            }
+        }
+        for name in names {
+            self.contestants.push(Contestant {
+                name: Some(name.to_owned()),
+                name_hint: name.to_owned(),
+                points: 0 as Points,
+                indicate: false,
+                connected: false,
+                display_name: String::new(),
+                display_position: self.contestants.len(),
+            });
+        }
+        Ok(())
+    }
+
+    fn reorder_contestants(&mut self, order: &[ContestantHandle]) -> Result<(), Error> {
+        if !matches!(&self.phase, GamePhase::Connecting) {
+            return Err(Error::WrongPhase {
+                is: self.phase.clone(),
+            });
+        }
+        let mut sorted = order.to_vec();
+        sorted.sort_unstable();
+        if !sorted.iter().copied().eq(0..self.contestants.len()) {
+            return Err(Error::InvalidOrder);
+        }
+        for (position, &contestant) in order.iter().enumerate() {
+            self.contestants[contestant].display_position = position;
+        }
+        Ok(())
+    }
+
     fn reconnect_contestant(&mut self, index: ContestantHandle) -> Result<(), Error> {
         self.contestants
             .get_mut(index)
@@ -111,27 +446,137 @@ impl Game {
         Ok(())
     }
 
+    /// Nobody waits for a contestant who left, see [Event::DisconnectContestant]
     fn disconnect_contestant(&mut self, index: ContestantHandle) -> Result<(), Error> {
         self.contestants
             .get_mut(index)
             .ok_or(Error::ContestantNotFound)?
             .connected = false;
+        match self.phase {
+            GamePhase::Buzzed { contestant, exclusive: false, .. } if contestant == index => self.withdraw_buzz()?,
+            GamePhase::Picking { contestant } if contestant == index => {
+                if let Some(next) = self.next_connected(index) {
+                    self.phase = GamePhase::Picking { contestant: next };
+                }
+            }
+            _ => {}
+        }
         Ok(())
     }
 
-    fn name_contestant(&mut self, index: ContestantHandle, name: String) -> Result<(), Error> {
+    fn name_contestant(&mut self, index: ContestantHandle, name: &str) -> Result<(), Error> {
+        let taken = |other: &str| other.trim().to_lowercase() == name.trim().to_lowercase();
+        if self.options.unique_names
+            && self.contestants.iter().enumerate().any(|(i, c)| {
+                i != index && (c.name.as_deref().is_some_and(taken) || taken(&c.name_hint))
+            })
+        {
+            return Err(Error::NameTaken);
+        }
         self.contestants
             .get_mut(index)
             .ok_or(Error::ContestantNotFound)?
-            .name = Some(name);
+            .name = Some(name.to_owned());
         Ok(())
     }
 
-    fn modify_score(&mut self, index: ContestantHandle, points: Points) -> Result<(), Error> {
-        self.contestants
-            .get_mut(index)
-            .ok_or(Error::ContestantNotFound)?
-            .points += points;
+    /// Lifts the lock of a started game, see [Event::Unlock]
+    fn unlock(&mut self) -> Result<(), Error> {
+        if !self.locked {
+            return Err(Error::NotLocked);
+        }
+        self.locked = false;
+        self.unlocked = true;
+        Ok(())
+    }
+
+    /// Manual corrections by the moderator. They don't change who is
+    /// indicated while a clue is played, but outside of the game loop, where
+    /// buzzing only toggles the lights, they turn all of them off. Reasons
+    /// are only checked, they are kept in the journal. Without one the event
+    /// is from before reasons existed.
+    fn modify_score(&mut self, index: ContestantHandle, points: Points, reason: Option<&str>, clue: Option<ClueHandle>) -> Result<(), Error> {
+        let missing = match reason {
+            Some(reason) => reason.trim().is_empty(),
+            None => !self.missing_reasons,
+        };
+        if self.locked && missing {
+            return Err(Error::ReasonRequired);
+        }
+        if let Some(clue) = &clue {
+            self.board.get(clue)?;
+        }
+        let c = self.contestants.get_mut(index).ok_or(Error::ContestantNotFound)?;
+        c.points = add_points(c.points, points)?;
+        self.clear_corrected_indicators();
+        self.corrections.push(Correction { contestant: index, points, clue, reason: reason.map(str::to_owned) });
+        Ok(())
+    }
+
+    /// Like [Game::modify_score], for all changes or none
+    fn adjust_scores(&mut self, changes: &[(ContestantHandle, Points)], reason: &str) -> Result<(), Error> {
+        if self.locked && reason.trim().is_empty() {
+            return Err(Error::ReasonRequired);
+        }
+        let scores = Contestant::adjusted_scores(&self.contestants, changes)?;
+        for (c, points) in self.contestants.iter_mut().zip(scores) {
+            c.points = points;
+        }
+        self.clear_corrected_indicators();
+        self.corrections.extend(changes.iter().map(|&(contestant, points)| Correction {
+            contestant,
+            points,
+            clue: None,
+            reason: Some(reason.to_owned()),
+        }));
+        Ok(())
+    }
+
+    /// Turns off the lights after a correction, see [Game::modify_score]
+    fn clear_corrected_indicators(&mut self) {
+        if matches!(self.phase, GamePhase::Connecting | GamePhase::Score) {
+            for c in self.contestants.iter_mut() {
+                c.indicate = false;
+            }
+        }
+    }
+
+    fn start_practice(&mut self) -> Result<(), Error> {
+        if matches!(&self.phase, GamePhase::Practice { .. }) {
+            return Ok(());
+        }
+        if !matches!(&self.phase, GamePhase::Connecting) {
+            return Err(Error::WrongPhase {
+                is: self.phase.clone(),
+            });
+        }
+        if self.contestants.is_empty() {
+            return Err(Error::NoContestants);
+        }
+        self.phase = GamePhase::Practice { winner: None };
+        self.practice_wins = vec![0; self.contestants.len()];
+        for contestant in self.contestants.iter_mut() {
+            contestant.indicate = false;
+        }
+        Ok(())
+    }
+
+    fn reset_practice(&mut self) -> Result<(), Error> {
+        let GamePhase::Practice { winner } = &mut self.phase else {
+            return Err(Error::WrongPhase {
+                is: self.phase.clone(),
+            });
+        };
+        *winner = None;
+        for contestant in self.contestants.iter_mut() {
+            contestant.indicate = false;
+        }
+        Ok(())
+    }
+
+    fn end_practice(&mut self) -> Result<(), Error> {
+        self.reset_practice()?;
+        self.phase = GamePhase::Connecting;
         Ok(())
     }
 
@@ -147,25 +592,76 @@ impl Game {
         self.phase = GamePhase::Picking {
             contestant: self.random_contestant(),
         };
-        for (i, contestant) in self.contestants.iter_mut().enumerate() {
+        self.locked = true;
+        for contestant in self.contestants.iter_mut() {
             contestant.indicate = false;
         }
         Ok(())
     }
 
-    fn pick(&mut self, clue: ClueHandle) -> Result<(), Error> {
+    fn pick(&mut self, clue: &ClueRef) -> Result<(), Error> {
         let GamePhase::Picking { contestant } = self.phase else {
             return Err(Error::WrongPhase {
                 is: self.phase.clone(),
             });
         };
-        self.phase = GamePhase::Clue {
-            clue,
-            exclusive: self.board.get(&clue)?.exclusive.then_some(contestant),
+        let clue = self.board.handle(clue)?;
+        let picked = self.board.get(&clue)?;
+        let (can_wager, exclusive) = (picked.can_wager, picked.exclusive);
+        let phase = if can_wager {
+            let score = self.contestants.get(contestant).ok_or(Error::ContestantNotFound)?.points;
+            // like on TV: up to everything they have, or the most valuable
+            // clue if they have less
+            let max_wager = score.max(self.board.most_valuable());
+            GamePhase::Waging {
+                clue,
+                contestant,
+                min_wager: MIN_WAGER.min(max_wager),
+                max_wager,
+                pending_wager: None,
+            }
+        } else {
+            GamePhase::Clue {
+                clue,
+                exclusive: exclusive.then_some(contestant),
+            }
+        };
+        self.board.get_mut(&clue)?.value_revealed = true;
+        self.rejected_answers = 0;
+        self.followup = 0;
+        self.decayed_value = None;
+        self.control = Some(contestant);
+        self.phase = phase;
+        Ok(())
+    }
+
+    fn pass_pick(&mut self, to: Option<ContestantHandle>) -> Result<(), Error> {
+        let GamePhase::Picking { contestant } = self.phase else {
+            return Err(Error::WrongPhase {
+                is: self.phase.clone(),
+            });
+        };
+        let to = match to {
+            Some(to) => {
+                if !self.contestants.get(to).ok_or(Error::ContestantNotFound)?.connected {
+                    return Err(Error::ContestantDisconnected);
+                }
+                to
+            }
+            None => self.next_connected(contestant).ok_or(Error::NoContestants)?,
         };
+        self.phase = GamePhase::Picking { contestant: to };
         Ok(())
     }
 
+    /// The next connected contestant after `contestant`, wrapping around
+    fn next_connected(&self, contestant: ContestantHandle) -> Option<ContestantHandle> {
+        let count = self.contestants.len();
+        (1..count)
+            .map(|offset| (contestant + offset) % count)
+            .find(|&c| self.contestants[c].connected)
+    }
+
     fn clue_fully_shown(&mut self) -> Result<(), Error> {
         self.phase = match self.phase {
             GamePhase::Clue {
@@ -175,7 +671,14 @@ impl Game {
             GamePhase::Clue {
                 clue,
                 exclusive: Some(contestant),
-            } => GamePhase::Buzzed { clue, contestant },
+            } => {
+                // nobody buzzed, but the light should show who answers
+                self.indicate_contestant(contestant)?;
+                // followups go to the same contestant, but are open to
+                // everyone after a wrong answer
+                GamePhase::Buzzed { clue, contestant, answer: None, exclusive: self.followup == 0 }
+            }
+            GamePhase::Buzzing { clue } => GamePhase::Buzzing { clue },
             _ => {
                 return Err(Error::WrongPhase {
                     is: self.phase.clone(),
@@ -193,9 +696,21 @@ impl Game {
                 self.phase = GamePhase::Buzzed {
                     clue,
                     contestant: contestant_index,
+                    answer: None,
+                    exclusive: false,
                 };
                 Ok(())
             }
+            // only the first one counts, later ones are too slow
+            GamePhase::Practice { ref mut winner } => {
+                let wins = self.practice_wins.get_mut(contestant_index).ok_or(Error::ContestantNotFound)?;
+                if winner.is_none() {
+                    *winner = Some(contestant_index);
+                    *wins += 1;
+                    self.indicate_contestant(contestant_index)?;
+                }
+                Ok(())
+            }
             // allow toggling the indication lights in the lobby and the end
             GamePhase::Connecting | GamePhase::Score => {
                 self.contestants
@@ -210,13 +725,63 @@ impl Game {
         }
     }
 
+    fn submit_answer(&mut self, contestant: ContestantHandle, text: &str) -> Result<(), Error> {
+        let GamePhase::Buzzed { answer, contestant: answering, .. } = &mut self.phase else {
+            return Err(Error::WrongPhase {
+                is: self.phase.clone(),
+            });
+        };
+        if *answering != contestant {
+            return Err(Error::NotAnswering);
+        }
+        let text: String = text
+            .chars()
+            .filter(|c| !c.is_control())
+            .take(MAX_ANSWER_LENGTH)
+            .collect();
+        *answer = Some(text.trim().to_owned());
+        Ok(())
+    }
+
     fn set_wage(&mut self, points: Points) -> Result<(), Error> {
-        let GamePhase::Waging { clue, contestant } = self.phase else {
+        let GamePhase::Waging { clue, contestant, min_wager, max_wager, ref mut pending_wager } = self.phase else {
             return Err(Error::WrongPhase {
                 is: self.phase.clone(),
             });
         };
-        // todo: modify points of clue to wager
+        if points != 0 && !(min_wager..=max_wager).contains(&points) {
+            return Err(Error::InvalidWager { min: min_wager, max: max_wager });
+        }
+        // skipping the wager doesn't need a confirmation
+        if self.options.confirm_wagers && points != 0 {
+            *pending_wager = Some(points);
+            return Ok(());
+        }
+        self.wage(clue, contestant, points)
+    }
+
+    fn confirm_wage(&mut self) -> Result<(), Error> {
+        let GamePhase::Waging { clue, contestant, pending_wager, .. } = self.phase else {
+            return Err(Error::WrongPhase {
+                is: self.phase.clone(),
+            });
+        };
+        let points = pending_wager.ok_or(Error::NoPendingWager)?;
+        self.wage(clue, contestant, points)
+    }
+
+    fn cancel_wage(&mut self) -> Result<(), Error> {
+        let GamePhase::Waging { ref mut pending_wager, .. } = self.phase else {
+            return Err(Error::WrongPhase {
+                is: self.phase.clone(),
+            });
+        };
+        *pending_wager = None;
+        Ok(())
+    }
+
+    fn wage(&mut self, clue: ClueHandle, contestant: ContestantHandle, points: Points) -> Result<(), Error> {
+        self.board.get_mut(&clue)?.points = points;
         self.phase = GamePhase::Clue {
             clue,
             exclusive: Some(contestant),
@@ -225,30 +790,58 @@ impl Game {
     }
 
     fn accept_answer(&mut self) -> Result<(), Error> {
-        let GamePhase::Buzzed { clue, contestant } = self.phase else {
+        let GamePhase::Buzzed { clue, contestant, .. } = self.phase else {
             return Err(Error::WrongPhase {
                 is: self.phase.clone(),
             });
         };
-        let points = self.board.get(&clue)?.points;
+        let points = self.current_value(&clue)?;
+        let chained = self.board.part(&clue, self.followup)?.followup.is_some();
         let c = self.contestants.get_mut(contestant).ok_or(Error::ContestantNotFound)?;
-        c.points += points;
+        c.points = add_points(c.points, points)?;
         c.indicate = false;
-        self.phase = GamePhase::Resolution { clue, contestant, show_hint: false };
+        self.control = Some(contestant);
+        self.phase = if chained {
+            self.followup += 1;
+            self.rejected_answers = 0;
+            self.decayed_value = None;
+            GamePhase::Clue { clue, exclusive: Some(contestant) }
+        } else {
+            GamePhase::Resolution { clue, contestant, show_hint: self.hint_revealed() }
+        };
+        Ok(())
+    }
+
+    fn withdraw_buzz(&mut self) -> Result<(), Error> {
+        let GamePhase::Buzzed { clue, contestant, exclusive: false, .. } = self.phase else {
+            return Err(Error::WrongPhase {
+                is: self.phase.clone(),
+            });
+        };
+        self.contestants.get_mut(contestant).ok_or(Error::ContestantNotFound)?.indicate = false;
+        self.phase = GamePhase::Buzzing { clue };
         Ok(())
     }
 
     fn reject_answer(&mut self) -> Result<(), Error> {
-        let GamePhase::Buzzed { clue, contestant } = self.phase else {
+        let GamePhase::Buzzed { clue, contestant, exclusive, .. } = self.phase else {
             return Err(Error::WrongPhase {
                 is: self.phase.clone(),
             });
         };
-        let points = self.board.get(&clue)?.points;
+        // the penalty is what the answer would have won
+        let points = self.current_value(&clue)?;
         let c = self.contestants.get_mut(contestant).ok_or(Error::ContestantNotFound)?;
-        c.points -= points;
+        c.points = add_points(c.points, -points)?;
         c.indicate = false;
-        self.phase = GamePhase::Buzzing { clue };
+        self.rejected_answers = self.rejected_answers.saturating_add(1);
+        if let Some(percent) = self.options.decay_on_rejection {
+            self.decayed_value = Some((points.saturating_mul(Points::from(percent.min(100))) / 100).max(0));
+        }
+        self.phase = match exclusive && self.options.exclusive_single_attempt {
+            true => GamePhase::Resolution { clue, contestant, show_hint: self.hint_revealed() },
+            false => GamePhase::Buzzing { clue },
+        };
         Ok(())
     }
 
@@ -262,31 +855,42 @@ impl Game {
         Ok(())
     }
 
-    fn finish_clue(&mut self) -> Result<(), Error> {
+    /// With [Options::confirm_skip], clues that weren't resolved yet are only
+    /// skipped once confirmed
+    fn finish_clue(&mut self, confirmed: bool) -> Result<(), Error> {
+        let skips = matches!(self.phase, GamePhase::Clue { .. } | GamePhase::Buzzing { .. } | GamePhase::Buzzed { .. });
+        if skips && self.options.confirm_skip && !confirmed {
+            return Err(Error::ConfirmationRequired { action: "FinishClue" });
+        }
         match self.phase {
+            // abandons the wager, the waging contestant resolves the clue
+            GamePhase::Waging { clue, contestant, .. } => {
+                self.solve(&clue)?;
+                self.phase = GamePhase::Resolution { clue, contestant, show_hint: false };
+            }
             GamePhase::Clue { clue, exclusive } => {
-                self.board.mark_solved(&clue)?;
+                self.solve(&clue)?;
                 self.phase = GamePhase::Resolution {
                     clue,
                     contestant: exclusive.unwrap_or_else(|| self.random_contestant()),
-                    show_hint: false
+                    show_hint: self.hint_revealed()
                 };
             }
             GamePhase::Buzzing { clue } => {
-                self.board.mark_solved(&clue)?;
+                self.solve(&clue)?;
                 self.phase = GamePhase::Resolution {
                     clue,
                     contestant: self.random_contestant(),
-                    show_hint: false
+                    show_hint: self.hint_revealed()
                 };
             }
-            GamePhase::Buzzed { clue, contestant } => {
-                self.board.mark_solved(&clue)?;
-                self.phase = GamePhase::Resolution { clue, contestant, show_hint: false };
+            GamePhase::Buzzed { clue, contestant, .. } => {
+                self.solve(&clue)?;
+                self.phase = GamePhase::Resolution { clue, contestant, show_hint: self.hint_revealed() };
             }
-            GamePhase::Resolution { clue, contestant, .. } => {
-                self.board.mark_solved(&clue)?;
-                self.phase = self.next_or_end(Some(contestant));
+            GamePhase::Resolution { clue, .. } => {
+                self.solve(&clue)?;
+                self.phase = self.next_or_end();
             }
             _ => {
                 return Err(Error::WrongPhase {
@@ -300,20 +904,44 @@ impl Game {
         Ok(())
     }
 
-    fn next_or_end(&mut self, contestant: Option<ContestantHandle>) -> GamePhase {
-        if self
-            .board
-            .categories
-            .iter()
-            .flat_map(|c| c.clues.iter())
-            .all(|c| c.solved)
-        {
-            GamePhase::Score
-        } else {
-            GamePhase::Picking {
-                contestant: contestant.unwrap_or_else(|| self.random_contestant()),
-            }
+    /// The points of the part being played, see [GameState::current_value]
+    fn current_value(&self, clue: &ClueHandle) -> Result<Points, Error> {
+        match self.decayed_value {
+            Some(points) => Ok(points),
+            None => Ok(self.board.part(clue, self.followup)?.points),
+        }
+    }
+
+    /// If enough answers were rejected to show the hint, it stays shown
+    /// until the clue is finished
+    fn hint_revealed(&self) -> bool {
+        self.options.auto_reveal_hint_after.is_some_and(|after| self.rejected_answers >= after)
+    }
+
+    /// Remembers the category if this was its last clue. Resolved clues are
+    /// finished a second time, which doesn't complete the category again.
+    fn solve(&mut self, clue: &ClueHandle) -> Result<(), Error> {
+        let was_solved = self.board.get(clue)?.solved;
+        self.board.mark_solved(clue)?;
+        if !was_solved && self.board.categories[clue.0].clues.iter().all(|c| c.solved) {
+            self.newly_completed_category = Some(clue.0);
+        }
+        Ok(())
+    }
+
+    /// Whoever has control picks, or the next one if they left meanwhile
+    fn next_or_end(&mut self) -> GamePhase {
+        if self.board.remaining() == 0 {
+            return GamePhase::Score;
         }
+        let contestant = match self.control {
+            Some(control) if !self.contestants.get(control).is_some_and(|c| c.connected) => {
+                self.next_connected(control).unwrap_or(control)
+            }
+            Some(control) => control,
+            None => self.random_contestant(),
+        };
+        GamePhase::Picking { contestant }
     }
 
     fn indicate_contestant(&mut self, contestant_handle: ContestantHandle) -> Result<(), Error> {
@@ -323,28 +951,35 @@ impl Game {
         Ok(())
     }
 
-    /// Draws a random contestant based on the memory layout of all contestants names, which varies
-    /// from match to match, and their points, which varies from round to round.
+    /// Draws a random contestant with [SplitMix64], seeded with everyone's
+    /// names and points and the clues left, which vary from match to match
+    /// and from round to round. Replaying the same events draws the same
+    /// contestant, also after a restart.
     ///
     /// Two consecutive calls will return the same contestant when neither the points nor their
     /// names have changed. One could argue that's a feature. I do.
-    ///
-    /// Initialisator: https://xkcd.com/221/
     fn random_contestant(&self) -> ContestantHandle {
-        let random = self.contestants.iter().fold(
-            4, // fair dice roll
-            |entropy, c| {
-                entropy
-                    ^ (c.name.as_ref().unwrap_or(&c.name_hint).as_str().as_ptr() as usize)
-                        .rotate_left(c.points as u32)
-            },
-        );
+        // FNV-1a, unlike std's hashers it stays the same across releases
+        let seed = self.contestants.iter()
+            .flat_map(|c| {
+                let name = c.name.as_ref().unwrap_or(&c.name_hint).bytes();
+                name.chain(c.points.to_le_bytes())
+            })
+            .chain((self.board.remaining() as u64).to_le_bytes())
+            .fold(0xcbf29ce484222325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3));
 
-        dbg!(random, random % self.contestants.len());
-        random % self.contestants.len()
+        SplitMix64(seed).below(self.contestants.len())
     }
 }
 
+/// Adds to a score, as long as it stays within [MAX_POINTS] either way
+fn add_points(score: Points, points: Points) -> Result<Points, Error> {
+    score
+        .checked_add(points)
+        .filter(|score| score.abs() <= MAX_POINTS)
+        .ok_or(Error::InvalidPoints)
+}
+
 impl Default for Game {
     fn default() -> Self {
         Self::new()
@@ -355,8 +990,42 @@ impl Default for Game {
 pub enum Error {
     WrongPhase { is: GamePhase },
     ContestantNotFound,
+    ContestantDisconnected,
+    /// Only the [Contestant] who buzzed may answer
+    NotAnswering,
     NoContestants,
     ClueNotFound,
+    CategoryNotFound,
+    /// See [Board::validate]
+    EmptyBoard,
+    /// The categories don't have the same number of clues
+    UnevenBoard,
+    EmptyClue,
+    InvalidPoints,
+    InvalidWager { min: Points, max: Points },
+    /// See [Event::ConfirmWage]
+    NoPendingWager,
+    /// More than [MAX_FOLLOWUPS] parts follow a clue
+    ChainTooLong,
+    /// The game started, see [Event::StartGame]
+    GameLocked,
+    /// See [Event::Unlock]
+    NotLocked,
+    /// Scores can't change without a reason once the game started
+    ReasonRequired,
+    /// The positions of the changes of [Event::AdjustScores] that name
+    /// nobody or would leave [MAX_POINTS], counting from 0
+    InvalidAdjustments { entries: Vec<usize> },
+    /// Another contestant is called like that, see [Options::unique_names]
+    NameTaken,
+    /// Not every contestant appears exactly once, see
+    /// [Event::ReorderContestants]
+    InvalidOrder,
+    /// The event would skip something, like scoring a clue, and has to be
+    /// sent again confirmed. See [Options::confirm_skip].
+    ConfirmationRequired { action: &'static str },
+    /// Two categories or two clues have the same id, see [Clue::id]
+    DuplicateId,
 }
 
 #[cfg(test)]
@@ -366,12 +1035,12 @@ mod tests {
     fn get_test_board(cs: usize, qs: usize) -> Board {
         Board {
             categories: (1..(cs + 1))
-                .into_iter()
                 .map(|c| Category {
                     title: format!("Category {}", c),
+                    id: None,
                     clues: (1..(qs + 1))
-                        .into_iter()
                         .map(|q| Clue {
+                            id: None,
                             clue: format!("clue {}", q),
                             response: format!("clue {}", q),
                             hint: format!("clue {}", q),
@@ -379,6 +1048,9 @@ mod tests {
                             can_wager: q == 4 && c == 2,
                             exclusive: q == 4 && c == 2,
                             solved: false,
+                            followup: None,
+                            value_hidden: false,
+                            value_revealed: false,
                         })
                         .collect(),
                 })
@@ -386,12 +1058,10 @@ mod tests {
         }
     }
 
-    #[test]
-    fn it_works() {
-        let g = Game::default();
-        let mut test_board = get_test_board(2, 2);
-        let r = vec![
-            Event::LoadBoard(test_board.clone()),
+    /// Plays a whole game on a 2x2 board with a single contestant
+    fn it_works_script(board: &Board) -> Vec<Event> {
+        vec![
+            Event::LoadBoard(board.clone()),
             Event::OpenLobby,
             Event::ConnectContestant {
                 name_hint: "test_contestant_hint".to_owned(),
@@ -401,47 +1071,1553 @@ mod tests {
                 name: "Test Contestant".to_owned(),
             },
             Event::StartGame,
-            Event::Pick { clue: (0, 0) },
+            Event::Pick { clue: (0, 0).into() },
             Event::ClueFullyShown,
             Event::Buzz { contestant: 0 },
             Event::RejectAnswer,
-            Event::FinishClue,
-            Event::Pick { clue: (0, 1) },
+            Event::FinishClue { confirmed: false },
+            Event::FinishClue { confirmed: false },
+            Event::Pick { clue: (0, 1).into() },
             Event::ClueFullyShown,
             Event::Buzz { contestant: 0 },
             Event::AcceptAnswer,
-            Event::FinishClue,
-            Event::Pick { clue: (1, 0) },
+            Event::FinishClue { confirmed: false },
+            Event::Pick { clue: (1, 0).into() },
             Event::ClueFullyShown,
             Event::Buzz { contestant: 0 },
             Event::AcceptAnswer,
-            Event::FinishClue,
-            Event::Pick { clue: (1, 1) },
+            Event::FinishClue { confirmed: false },
+            Event::Pick { clue: (1, 1).into() },
             Event::ClueFullyShown,
             Event::Buzz { contestant: 0 },
             Event::AcceptAnswer,
-            Event::FinishClue,
+            Event::FinishClue { confirmed: false },
         ]
-        .into_iter()
-        .fold(g, |mut g, e| {
-            g.apply(e.clone())
-                .expect(format!("could not apply event {:?}", e).as_str());
-            g
-        });
+    }
+
+    #[test]
+    fn it_works() {
+        let g = Game::default();
+        let mut test_board = get_test_board(2, 2);
+        let r = it_works_script(&test_board)
+            .into_iter()
+            .fold(g, |mut g, e| {
+                g.apply(e.clone())
+                    .unwrap_or_else(|_| panic!("could not apply event {:?}", e));
+                g
+            });
 
-        // mark all clues as solved in our comparison
+        // mark all clues as picked and solved in our comparison
         for clue in test_board
             .categories
             .iter_mut()
             .flat_map(|c| c.clues.iter_mut())
         {
             clue.solved = true;
+            clue.value_revealed = true;
         }
 
         assert_eq!(r.board, test_board);
         assert_eq!(r.contestants.len(), 1);
         assert_eq!(r.contestants[0].name, Some("Test Contestant".to_owned()));
-        assert_eq!(r.contestants[0].points, 500 as Points);
+        assert_eq!(r.contestants[0].points, 400 as Points);
         assert!(matches!(r.phase, GamePhase::Score));
     }
+
+    #[test]
+    fn observer_sees_every_transition() {
+        let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut g = Game::default();
+        let recorder = seen.clone();
+        g.set_observer(Box::new(move |kind, phase| {
+            recorder.lock().unwrap().push(format!("{} -> {:?}", kind.name(), phase));
+        }));
+        for event in it_works_script(&get_test_board(2, 2)) {
+            g.apply(event).unwrap();
+        }
+        // rejected events aren't observed
+        assert!(g.apply(Event::OpenLobby).is_err());
+
+        let clue = |clue: &str| {
+            [
+                format!("Pick -> Clue {{ clue: {}, exclusive: None }}", clue),
+                format!("ClueFullyShown -> Buzzing {{ clue: {} }}", clue),
+                format!("Buzz -> Buzzed {{ clue: {}, contestant: 0, answer: None, exclusive: false }}", clue),
+            ]
+        };
+        let resolution = |clue: &str| format!("Resolution {{ clue: {}, contestant: 0, show_hint: false }}", clue);
+        let mut expected = vec![
+            "LoadBoard -> Preparing".to_owned(),
+            "OpenLobby -> Connecting".to_owned(),
+            "ConnectContestant -> Connecting".to_owned(),
+            "NameContestant -> Connecting".to_owned(),
+            "StartGame -> Picking { contestant: 0 }".to_owned(),
+        ];
+        expected.extend(clue("(0, 0)"));
+        expected.push("RejectAnswer -> Buzzing { clue: (0, 0) }".to_owned());
+        expected.push(format!("FinishClue -> {}", resolution("(0, 0)")));
+        expected.push("FinishClue -> Picking { contestant: 0 }".to_owned());
+        for c in ["(0, 1)", "(1, 0)", "(1, 1)"] {
+            expected.extend(clue(c));
+            expected.push(format!("AcceptAnswer -> {}", resolution(c)));
+            expected.push("FinishClue -> Picking { contestant: 0 }".to_owned());
+        }
+        *expected.last_mut().unwrap() = "FinishClue -> Score".to_owned();
+        assert_eq!(*seen.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn practice_buzzes_win_without_points() {
+        let indicated = |g: &Game| -> Vec<bool> { g.contestants.iter().map(|c| c.indicate).collect() };
+        let mut g = Game::default();
+        g.apply(Event::OpenLobby).unwrap();
+        assert!(matches!(g.apply(Event::StartPractice), Err(Error::NoContestants)));
+        for name_hint in ["a", "b", "c"] {
+            g.apply(Event::ConnectContestant { name_hint: name_hint.to_owned() }).unwrap();
+        }
+        // lights toggled in the lobby are turned off
+        g.apply(Event::Buzz { contestant: 0 }).unwrap();
+        let state = g.apply(Event::StartPractice).unwrap();
+        assert_eq!(state.phase, GamePhase::Practice { winner: None });
+        assert_eq!(state.practice_wins, [0, 0, 0]);
+        assert_eq!(indicated(&g), [false, false, false]);
+
+        g.apply(Event::Buzz { contestant: 2 }).unwrap();
+        let state = g.apply(Event::Buzz { contestant: 1 }).unwrap();
+        assert_eq!(state.phase, GamePhase::Practice { winner: Some(2) });
+        assert_eq!(indicated(&g), [false, false, true]);
+        assert!(matches!(g.apply(Event::Buzz { contestant: 3 }), Err(Error::ContestantNotFound)));
+        assert!(matches!(g.apply(Event::ConnectContestant { name_hint: "late".to_owned() }), Err(Error::WrongPhase { .. })));
+        assert!(matches!(g.apply(Event::StartGame), Err(Error::WrongPhase { .. })));
+
+        g.apply(Event::ResetPractice).unwrap();
+        assert_eq!(indicated(&g), [false, false, false]);
+        g.apply(Event::Buzz { contestant: 1 }).unwrap();
+        g.apply(Event::ResetPractice).unwrap();
+        let state = g.apply(Event::Buzz { contestant: 2 }).unwrap();
+        assert_eq!(state.practice_wins, [0, 1, 2]);
+        assert!(state.contestants.iter().all(|c| c.points == 0));
+        assert!(state.corrections.is_empty());
+
+        let state = g.apply(Event::EndPractice).unwrap();
+        assert_eq!(state.phase, GamePhase::Connecting);
+        assert_eq!(state.practice_wins, Vec::<u32>::new());
+        assert_eq!(indicated(&g), [false, false, false]);
+        assert!(g.apply(Event::ResetPractice).is_err());
+        // counted from zero again
+        assert_eq!(g.apply(Event::StartPractice).unwrap().practice_wins, [0, 0, 0]);
+        g.apply(Event::EndPractice).unwrap();
+        assert!(matches!(g.apply(Event::StartGame).unwrap().phase, GamePhase::Picking { .. }));
+    }
+
+    #[test]
+    fn score_corrections_only_clear_indicators_outside_the_game_loop() {
+        let script = it_works_script(&get_test_board(2, 2));
+        let mut g = Game::default();
+        let reason = || Some("correction".to_owned());
+        for event in script {
+            g.apply(event).unwrap();
+            if matches!(g.phase, GamePhase::Connecting | GamePhase::Score) && !g.contestants.is_empty() {
+                // toggles the light
+                g.apply(Event::Buzz { contestant: 0 }).unwrap();
+            }
+            if g.contestants.is_empty() {
+                assert!(g.apply(Event::AwardPoints { contestant: 0, points: 100, reason: reason(), clue: None }).is_err());
+                continue;
+            }
+            let indicated = g.contestants[0].indicate;
+            let points = g.contestants[0].points;
+            g.apply(Event::AwardPoints { contestant: 0, points: 100, reason: reason(), clue: None }).unwrap();
+            g.apply(Event::RevokePoints { contestant: 0, points: 100, reason: reason(), clue: None }).unwrap();
+            assert_eq!(g.contestants[0].points, points);
+            match g.phase {
+                GamePhase::Connecting | GamePhase::Score => assert!(!g.contestants[0].indicate, "{:?}", g.phase),
+                _ => assert_eq!(g.contestants[0].indicate, indicated, "{:?}", g.phase),
+            }
+        }
+        assert!(matches!(g.phase, GamePhase::Score));
+
+        // beyond the old i32 limits
+        let score = g.contestants[0].points;
+        g.apply(Event::AwardPoints { contestant: 0, points: u32::MAX, reason: reason(), clue: None }).unwrap();
+        assert_eq!(g.contestants[0].points, score + u32::MAX as Points);
+        assert!(g.contestants[0].points > i32::MAX as Points);
+        for _ in 0..3 {
+            g.apply(Event::RevokePoints { contestant: 0, points: u32::MAX, reason: reason(), clue: None }).unwrap();
+        }
+        assert!(g.contestants[0].points < i32::MIN as Points);
+    }
+
+    #[test]
+    fn scores_stay_within_the_cap() {
+        let mut g = Game::default();
+        g.contestants.push(Contestant {
+            name: None,
+            name_hint: "pi-1".to_owned(),
+            points: MAX_POINTS - 1,
+            indicate: false,
+            connected: true,
+            display_name: String::new(),
+            display_position: 0,
+        });
+        g.apply(Event::AwardPoints { contestant: 0, points: 1, reason: None, clue: None }).unwrap();
+        assert!(matches!(g.apply(Event::AwardPoints { contestant: 0, points: 1, reason: None, clue: None }), Err(Error::InvalidPoints)));
+        assert_eq!(g.contestants[0].points, MAX_POINTS);
+
+        g.contestants[0].points = -MAX_POINTS;
+        assert!(matches!(g.apply(Event::RevokePoints { contestant: 0, points: 1, reason: None, clue: None }), Err(Error::InvalidPoints)));
+        assert_eq!(g.contestants[0].points, -MAX_POINTS);
+
+        let mut g = Game::default();
+        g.apply(Event::LoadBoard(get_test_board(1, 1))).unwrap();
+        let edit = |points: Points| Event::EditClue { clue: (0, 0), field: ClueField::Points, value: points.to_string() };
+        g.apply(edit(MAX_POINTS)).unwrap();
+        assert!(matches!(g.apply(edit(MAX_POINTS + 1)), Err(Error::InvalidPoints)));
+    }
+
+    #[test]
+    fn i32_points_still_parse() {
+        let event: Event = serde_json::from_str(r#"{"type": "SetWage", "points": 2147483647}"#).unwrap();
+        assert!(matches!(event, Event::SetWage { points } if points == i32::MAX as Points));
+        let clue: Clue = serde_json::from_str(r#"{
+            "clue": "", "response": "", "hint": "", "points": -2147483648,
+            "can_wager": false, "exclusive": false, "solved": false
+        }"#).unwrap();
+        assert_eq!(clue.points, i32::MIN as Points);
+        // and wider ones are written as plain numbers
+        let event = serde_json::to_value(Event::SetWage { points: MAX_POINTS }).unwrap();
+        assert_eq!(event["points"], serde_json::json!(9007199254740991u64));
+    }
+
+    #[test]
+    fn progress_follows_the_game() {
+        let board = get_test_board(2, 2);
+        let mut g = Game::default();
+        assert_eq!(g.get_game_state().progress, BoardSummary::default());
+        let mut progress = vec![];
+        for event in it_works_script(&board) {
+            let finishes = matches!(event, Event::FinishClue { .. });
+            let state = g.apply(event).unwrap();
+            if finishes {
+                progress.push((state.progress.clues_remaining, state.progress.remaining_per_category));
+            }
+        }
+        // the first clue is finished twice, once to resolve it
+        assert_eq!(progress, [
+            (3, vec![1, 2]),
+            (3, vec![1, 2]),
+            (2, vec![0, 2]),
+            (1, vec![0, 1]),
+            (0, vec![0, 0]),
+        ]);
+        let summary = g.get_game_state().progress;
+        assert_eq!((summary.clues_total, summary.clues_solved, summary.percent_solved), (4, 4, 100));
+        assert_eq!(g.board.remaining(), 0);
+
+        let mut board = get_test_board(3, 1);
+        board.mark_solved(&(1, 0)).unwrap();
+        assert_eq!(board.summary().percent_solved, 33);
+        assert_eq!(board.remaining(), 2);
+    }
+
+    /// A single contestant with `points` picking the wagerable clue
+    fn waging_with(points: u32) -> Game {
+        let mut g = Game::default();
+        for event in [
+            Event::LoadBoard(get_test_board(2, 4)),
+            Event::OpenLobby,
+            Event::ConnectContestant { name_hint: "pi-1".to_owned() },
+            Event::AwardPoints { contestant: 0, points, reason: None, clue: None },
+            Event::StartGame,
+            Event::Pick { clue: (1, 3).into() },
+        ] {
+            g.apply(event).unwrap();
+        }
+        g
+    }
+
+    #[test]
+    fn wagers_are_bounded() {
+        let mut g = waging_with(200);
+        assert!(matches!(g.phase, GamePhase::Waging { clue: (1, 3), contestant: 0, min_wager: 5, max_wager: 400, pending_wager: None }));
+        assert!(matches!(g.apply(Event::SetWage { points: 5000 }), Err(Error::InvalidWager { min: 5, max: 400 })));
+        assert!(matches!(g.apply(Event::SetWage { points: 4 }), Err(Error::InvalidWager { .. })));
+        assert!(matches!(g.phase, GamePhase::Waging { .. }));
+
+        // a true daily double
+        let mut g = waging_with(1000);
+        assert!(matches!(g.phase, GamePhase::Waging { max_wager: 1000, .. }));
+        g.apply(Event::SetWage { points: 1000 }).unwrap();
+        assert!(matches!(g.phase, GamePhase::Clue { clue: (1, 3), exclusive: Some(0) }));
+        g.apply(Event::ClueFullyShown).unwrap();
+        let state = g.apply(Event::AcceptAnswer).unwrap();
+        assert_eq!(state.contestants[0].points, 2000);
+    }
+
+    #[test]
+    fn wagers_can_require_confirmation() {
+        let mut g = waging_with(200);
+        g.options.confirm_wagers = true;
+        let pending = |g: &Game| match g.phase {
+            GamePhase::Waging { pending_wager, .. } => pending_wager,
+            _ => panic!("{:?}", g.phase),
+        };
+        assert!(matches!(g.apply(Event::SetWage { points: 5000 }), Err(Error::InvalidWager { .. })));
+        assert!(matches!(g.apply(Event::ConfirmWage), Err(Error::NoPendingWager)));
+        g.apply(Event::SetWage { points: 300 }).unwrap();
+        assert_eq!(pending(&g), Some(300));
+        // a typo is replaced
+        g.apply(Event::SetWage { points: 350 }).unwrap();
+        assert_eq!(pending(&g), Some(350));
+        g.apply(Event::CancelWage).unwrap();
+        assert_eq!(pending(&g), None);
+        assert!(matches!(g.apply(Event::ConfirmWage), Err(Error::NoPendingWager)));
+        g.apply(Event::SetWage { points: 250 }).unwrap();
+        let state = g.apply(Event::ConfirmWage).unwrap();
+        assert!(matches!(state.phase, GamePhase::Clue { clue: (1, 3), exclusive: Some(0) }));
+        assert_eq!(state.board.get(&(1, 3)).unwrap().points, 250);
+        assert!(matches!(g.apply(Event::CancelWage), Err(Error::WrongPhase { .. })));
+
+        // skipping the wager doesn't wait
+        let mut g = waging_with(200);
+        g.options.confirm_wagers = true;
+        g.apply(Event::SetWage { points: 300 }).unwrap();
+        g.apply(Event::SetWage { points: 0 }).unwrap();
+        assert!(matches!(g.phase, GamePhase::Clue { .. }));
+
+        // without the option nothing is pending
+        let mut g = waging_with(200);
+        assert!(matches!(g.apply(Event::ConfirmWage), Err(Error::NoPendingWager)));
+        g.apply(Event::CancelWage).unwrap();
+        g.apply(Event::SetWage { points: 300 }).unwrap();
+        assert!(matches!(g.phase, GamePhase::Clue { .. }));
+    }
+
+    #[test]
+    fn stalled_wagers_resume_or_are_overridden() {
+        let mut g = waging_with(200);
+        let waging = g.phase.clone();
+        assert!(!g.get_game_state().wager_stalled);
+        let state = g.apply(Event::DisconnectContestant { contestant: 0 }).unwrap();
+        assert!(state.wager_stalled);
+        assert_eq!(state.phase, waging);
+        // the contestant returning carries on as if nothing happened
+        let state = g.apply(Event::ReconnectContestant { contestant: 0 }).unwrap();
+        assert!(!state.wager_stalled);
+        assert_eq!(state.phase, waging);
+        g.apply(Event::SetWage { points: 300 }).unwrap();
+        assert_eq!(g.board.get(&(1, 3)).unwrap().points, 300);
+
+        // or the moderator wagers nothing for them
+        let mut g = waging_with(200);
+        g.apply(Event::DisconnectContestant { contestant: 0 }).unwrap();
+        let state = g.apply(Event::SetWage { points: 0 }).unwrap();
+        assert!(matches!(state.phase, GamePhase::Clue { clue: (1, 3), exclusive: Some(0) }));
+        assert!(!state.wager_stalled);
+        g.apply(Event::ClueFullyShown).unwrap();
+        let state = g.apply(Event::AcceptAnswer).unwrap();
+        assert_eq!(state.contestants[0].points, 200);
+
+        // or abandons the clue
+        let mut g = waging_with(200);
+        g.apply(Event::DisconnectContestant { contestant: 0 }).unwrap();
+        let state = g.apply(Event::FinishClue { confirmed: false }).unwrap();
+        assert!(matches!(state.phase, GamePhase::Resolution { clue: (1, 3), contestant: 0, .. }));
+    }
+
+    #[test]
+    fn wagers_can_be_abandoned() {
+        let mut g = waging_with(200);
+        let state = g.apply(Event::FinishClue { confirmed: false }).unwrap();
+        assert!(matches!(state.phase, GamePhase::Resolution { clue: (1, 3), contestant: 0, show_hint: false }));
+        assert_eq!(state.contestants[0].points, 200);
+        assert_eq!(state.board.get(&(1, 3)).unwrap().points, 400);
+        assert!(state.board.get(&(1, 3)).unwrap().solved);
+        let state = g.apply(Event::FinishClue { confirmed: false }).unwrap();
+        assert!(matches!(state.phase, GamePhase::Picking { contestant: 0 }));
+        assert_eq!(state.progress.clues_remaining, 7);
+    }
+
+    #[test]
+    fn exclusive_clues_indicate_who_answers() {
+        let indicated = |g: &Game| -> Vec<bool> { g.contestants.iter().map(|c| c.indicate).collect() };
+        for ending in [Event::AcceptAnswer, Event::RejectAnswer, Event::FinishClue { confirmed: false }] {
+            let mut g = Game::default();
+            for event in [
+                Event::LoadBoard(get_test_board(2, 4)),
+                Event::OpenLobby,
+                Event::ConnectContestant { name_hint: "pi-1".to_owned() },
+                Event::ConnectContestant { name_hint: "pi-2".to_owned() },
+                Event::StartGame,
+            ] {
+                g.apply(event).unwrap();
+            }
+            // not exclusive, nobody is indicated until someone buzzes
+            for event in [Event::Pick { clue: (0, 0).into() }, Event::ClueFullyShown] {
+                g.apply(event).unwrap();
+                assert_eq!(indicated(&g), [false, false]);
+            }
+            g.apply(Event::FinishClue { confirmed: false }).unwrap();
+            g.apply(Event::FinishClue { confirmed: false }).unwrap();
+
+            let GamePhase::Picking { contestant } = g.phase else { panic!("not picking") };
+            g.apply(Event::Pick { clue: (1, 3).into() }).unwrap();
+            g.apply(Event::SetWage { points: 100 }).unwrap();
+            assert_eq!(indicated(&g), [false, false]);
+            g.apply(Event::ClueFullyShown).unwrap();
+            let expected: Vec<_> = (0..2).map(|c| c == contestant).collect();
+            assert_eq!(indicated(&g), expected);
+
+            g.apply(ending.clone()).unwrap();
+            assert_eq!(indicated(&g), [false, false], "after {:?}", ending);
+        }
+    }
+
+    #[test]
+    fn exclusive_clues_can_allow_a_single_attempt() {
+        for single in [true, false] {
+            let mut board = get_test_board(2, 4);
+            board.categories[0].clues[0].exclusive = true;
+            let mut g = Game::default();
+            for event in [
+                Event::Settings(Options { exclusive_single_attempt: single, ..Options::default() }),
+                Event::LoadBoard(board),
+                Event::OpenLobby,
+                Event::ConnectContestant { name_hint: "pi-1".to_owned() },
+                Event::ConnectContestant { name_hint: "pi-2".to_owned() },
+                Event::StartGame,
+            ] {
+                g.apply(event).unwrap();
+            }
+            // exclusive, then waged on
+            for (clue, wager) in [((0, 0), None), ((1, 3), Some(300))] {
+                let GamePhase::Picking { contestant } = g.phase else { panic!("not picking") };
+                g.apply(Event::Pick { clue: clue.into() }).unwrap();
+                if let Some(points) = wager {
+                    g.apply(Event::SetWage { points }).unwrap();
+                }
+                let state = g.apply(Event::ClueFullyShown).unwrap();
+                assert!(matches!(state.phase, GamePhase::Buzzed { exclusive: true, .. }));
+                let before = state.contestants[contestant].points;
+                let state = g.apply(Event::RejectAnswer).unwrap();
+                assert_eq!(state.contestants[contestant].points, before - wager.unwrap_or(100));
+                match single {
+                    true => assert_eq!(state.phase, GamePhase::Resolution { clue, contestant, show_hint: false }),
+                    false => {
+                        assert_eq!(state.phase, GamePhase::Buzzing { clue });
+                        let state = g.apply(Event::Buzz { contestant: 1 - contestant }).unwrap();
+                        assert!(matches!(state.phase, GamePhase::Buzzed { exclusive: false, .. }));
+                        g.apply(Event::FinishClue { confirmed: false }).unwrap();
+                    }
+                }
+                g.apply(Event::FinishClue { confirmed: false }).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn picks_can_be_passed() {
+        let mut g = Game::default();
+        for event in [
+            Event::LoadBoard(get_test_board(2, 2)),
+            Event::OpenLobby,
+            Event::ConnectContestant { name_hint: "pi-1".to_owned() },
+            Event::ConnectContestant { name_hint: "pi-2".to_owned() },
+            Event::ConnectContestant { name_hint: "pi-3".to_owned() },
+        ] {
+            g.apply(event).unwrap();
+        }
+        assert!(matches!(g.apply(Event::PassPick { to: Some(0) }), Err(Error::WrongPhase { .. })));
+        g.apply(Event::StartGame).unwrap();
+        let picker = |g: &Game| match g.phase {
+            GamePhase::Picking { contestant } => contestant,
+            _ => panic!("not picking: {:?}", g.phase),
+        };
+
+        g.apply(Event::PassPick { to: Some(2) }).unwrap();
+        assert_eq!(picker(&g), 2);
+        // to the next one, wrapping around
+        g.apply(Event::PassPick { to: None }).unwrap();
+        assert_eq!(picker(&g), 0);
+        // skipping disconnected contestants
+        g.apply(Event::DisconnectContestant { contestant: 1 }).unwrap();
+        g.apply(Event::PassPick { to: None }).unwrap();
+        assert_eq!(picker(&g), 2);
+
+        assert!(matches!(g.apply(Event::PassPick { to: Some(1) }), Err(Error::ContestantDisconnected)));
+        assert!(matches!(g.apply(Event::PassPick { to: Some(3) }), Err(Error::ContestantNotFound)));
+        g.apply(Event::DisconnectContestant { contestant: 0 }).unwrap();
+        assert!(matches!(g.apply(Event::PassPick { to: None }), Err(Error::NoContestants)));
+        assert_eq!(picker(&g), 2);
+    }
+
+    #[test]
+    fn control_stays_until_someone_answers_correctly() {
+        let mut g = Game::default();
+        for event in [
+            Event::LoadBoard(get_test_board(2, 4)),
+            Event::OpenLobby,
+            Event::ConnectContestant { name_hint: "pi-1".to_owned() },
+            Event::ConnectContestant { name_hint: "pi-2".to_owned() },
+            Event::ConnectContestant { name_hint: "pi-3".to_owned() },
+            Event::StartGame,
+            Event::PassPick { to: Some(1) },
+        ] {
+            g.apply(event).unwrap();
+        }
+        // picks the clue, applies the events and finishes the clue, returns
+        // who picks next
+        let mut play = |clue: ClueHandle, events: Vec<Event>| {
+            g.apply(Event::Pick { clue: clue.into() }).unwrap();
+            g.apply(Event::ClueFullyShown).unwrap();
+            for event in events {
+                g.apply(event).unwrap();
+            }
+            loop {
+                match g.apply(Event::FinishClue { confirmed: true }).unwrap().phase {
+                    GamePhase::Picking { contestant } => return contestant,
+                    GamePhase::Resolution { .. } => continue,
+                    phase => panic!("not picking: {:?}", phase),
+                }
+            }
+        };
+        let wrong = |contestant| [Event::Buzz { contestant }, Event::RejectAnswer];
+        let right = |contestant| [Event::Buzz { contestant }, Event::AcceptAnswer];
+
+        // everyone answered wrong
+        assert_eq!(play((0, 0), [wrong(0), wrong(2)].concat()), 1);
+        // skipped without answers
+        assert_eq!(play((0, 1), vec![]), 1);
+        // right after a wrong one
+        assert_eq!(play((0, 2), [wrong(1), right(2)].concat()), 2);
+        assert_eq!(play((0, 3), right(0).to_vec()), 0);
+        // the one in control left during the clue
+        assert_eq!(play((1, 0), vec![Event::DisconnectContestant { contestant: 0 }]), 1);
+    }
+
+    #[test]
+    fn disconnects_hand_on_the_clue_and_the_pick() {
+        let mut board = get_test_board(2, 2);
+        board.categories[1].clues[0].exclusive = true;
+        let mut g = Game::default();
+        for event in [
+            Event::LoadBoard(board),
+            Event::OpenLobby,
+            Event::ConnectContestant { name_hint: "pi-1".to_owned() },
+            Event::ConnectContestant { name_hint: "pi-2".to_owned() },
+            Event::StartGame,
+            Event::PassPick { to: Some(0) },
+        ] {
+            g.apply(event).unwrap();
+        }
+
+        // the pick goes to the next one, and stays there after reconnecting
+        g.apply(Event::DisconnectContestant { contestant: 0 }).unwrap();
+        assert_eq!(g.phase, GamePhase::Picking { contestant: 1 });
+        g.apply(Event::ReconnectContestant { contestant: 0 }).unwrap();
+        assert_eq!(g.phase, GamePhase::Picking { contestant: 1 });
+        // unless there is nobody else
+        g.apply(Event::DisconnectContestant { contestant: 0 }).unwrap();
+        g.apply(Event::DisconnectContestant { contestant: 1 }).unwrap();
+        assert_eq!(g.phase, GamePhase::Picking { contestant: 1 });
+        g.apply(Event::ReconnectContestant { contestant: 0 }).unwrap();
+        g.apply(Event::ReconnectContestant { contestant: 1 }).unwrap();
+
+        // the clue is open again, without a penalty
+        g.apply(Event::Pick { clue: (0, 0).into() }).unwrap();
+        g.apply(Event::ClueFullyShown).unwrap();
+        g.apply(Event::Buzz { contestant: 0 }).unwrap();
+        let state = g.apply(Event::DisconnectContestant { contestant: 0 }).unwrap();
+        assert_eq!(state.phase, GamePhase::Buzzing { clue: (0, 0) });
+        assert_eq!(state.contestants[0].points, 0);
+        assert!(!state.contestants[0].indicate);
+        assert!(!state.hint_revealed);
+        // reconnecting right after doesn't bring the turn back
+        let state = g.apply(Event::ReconnectContestant { contestant: 0 }).unwrap();
+        assert_eq!(state.phase, GamePhase::Buzzing { clue: (0, 0) });
+        g.apply(Event::Buzz { contestant: 0 }).unwrap();
+        let state = g.apply(Event::WithdrawBuzz).unwrap();
+        assert_eq!(state.phase, GamePhase::Buzzing { clue: (0, 0) });
+        g.apply(Event::Buzz { contestant: 1 }).unwrap();
+        g.apply(Event::AcceptAnswer).unwrap();
+        g.apply(Event::FinishClue { confirmed: false }).unwrap();
+
+        // exclusive clues wait for them
+        g.apply(Event::Pick { clue: (1, 0).into() }).unwrap();
+        let state = g.apply(Event::ClueFullyShown).unwrap();
+        let GamePhase::Buzzed { contestant, .. } = state.phase else { panic!("not buzzed") };
+        let state = g.apply(Event::DisconnectContestant { contestant }).unwrap();
+        assert!(matches!(state.phase, GamePhase::Buzzed { exclusive: true, .. }));
+        assert!(!state.legal_events.contains(&EventKind::WithdrawBuzz));
+        assert!(matches!(g.apply(Event::WithdrawBuzz), Err(Error::WrongPhase { .. })));
+    }
+
+    #[test]
+    fn state_json_names_the_phase() {
+        let mut g = Game::default();
+        for event in it_works_script(&get_test_board(1, 1)).into_iter().take(7) {
+            g.apply(event).unwrap();
+        }
+        let state = serde_json::to_value(g.get_game_state()).unwrap();
+        assert_eq!(state["phase"], serde_json::json!({"Buzzing": {"clue": [0, 0]}}));
+        assert_eq!(state["phase_name"], "buzzing");
+
+        // phase_name is ignored when reading states back
+        let state: GameState = serde_json::from_value(state).unwrap();
+        assert!(matches!(state.phase, GamePhase::Buzzing { clue: (0, 0) }));
+        assert_eq!(GamePhase::Resolution { clue: (0, 0), contestant: 0, show_hint: true }.name(), "resolution");
+    }
+
+    #[test]
+    fn event_names_match_the_journal() {
+        let events = [
+            Event::Settings(Options::default()),
+            Event::LoadBoard(get_test_board(1, 1)),
+            Event::ShuffleBoard { seed: 4 },
+            Event::EditClue { clue: (0, 0), field: ClueField::Hint, value: "hint".to_owned() },
+            Event::EditCategory { category: 0, title: "title".to_owned() },
+            Event::OpenLobby,
+            Event::ConnectContestant { name_hint: "pi-1".to_owned() },
+            Event::DisconnectContestant { contestant: 0 },
+            Event::ReconnectContestant { contestant: 0 },
+            Event::NameContestant { index: 0, name: "name".to_owned() },
+            Event::AwardPoints { contestant: 0, points: 100, reason: None, clue: None },
+            Event::RevokePoints { contestant: 0, points: 100, reason: None, clue: None },
+            Event::StartGame,
+            Event::Pick { clue: (0, 0).into() },
+            Event::PassPick { to: None },
+            Event::SetWage { points: 100 },
+            Event::ClueFullyShown,
+            Event::Buzz { contestant: 0 },
+            Event::AcceptAnswer,
+            Event::RejectAnswer,
+            Event::RevealHint,
+            Event::FinishClue { confirmed: false },
+        ];
+        for event in events {
+            assert_eq!(serde_json::to_value(&event).unwrap()["type"], event.name());
+        }
+    }
+
+    #[test]
+    fn edits_survive_replay() {
+        let journal = vec![
+            Event::LoadBoard(get_test_board(2, 2)),
+            Event::EditClue {
+                clue: (1, 0),
+                field: ClueField::Clue,
+                value: "fixed typo".to_owned(),
+            },
+            Event::EditClue {
+                clue: (1, 0),
+                field: ClueField::Points,
+                value: "150".to_owned(),
+            },
+            Event::EditCategory {
+                category: 0,
+                title: "Renamed".to_owned(),
+            },
+            Event::OpenLobby,
+            Event::EditClue {
+                clue: (1, 0),
+                field: ClueField::Hint,
+                value: "amended".to_owned(),
+            },
+        ];
+        let replay = |journal: &Vec<Event>| {
+            journal.iter().fold(Game::default(), |mut g, e| {
+                g.apply(e.clone())
+                    .unwrap_or_else(|_| panic!("could not apply event {:?}", e));
+                g
+            })
+        };
+        let r = replay(&journal).get_game_state();
+        assert_eq!(r.board.categories[0].title, "Renamed");
+        let clue = r.board.get(&(1, 0)).unwrap();
+        assert_eq!(clue.clue, "fixed typo");
+        assert_eq!(clue.points, 150);
+        assert_eq!(clue.hint, "amended");
+
+        let journal = serde_json::to_string(&journal).unwrap();
+        let journal: Vec<Event> = serde_json::from_str(&journal).unwrap();
+        assert_eq!(replay(&journal).get_game_state().board, r.board);
+    }
+
+    #[test]
+    fn invalid_edits_are_rejected() {
+        let mut g = Game::default();
+        g.apply(Event::LoadBoard(get_test_board(2, 2))).unwrap();
+        let edit = |field, value: &str| Event::EditClue {
+            clue: (0, 0),
+            field,
+            value: value.to_owned(),
+        };
+        assert!(matches!(g.apply(edit(ClueField::Clue, " ")), Err(Error::EmptyClue)));
+        assert!(matches!(g.apply(edit(ClueField::Points, "0")), Err(Error::InvalidPoints)));
+        assert!(matches!(g.apply(edit(ClueField::Points, "many")), Err(Error::InvalidPoints)));
+        g.apply(Event::OpenLobby).unwrap();
+        assert!(matches!(g.apply(edit(ClueField::Response, "late")), Err(Error::WrongPhase { .. })));
+    }
+
+    #[test]
+    fn shuffles_are_reproducible() {
+        let shuffled = |seed| {
+            let mut g = Game::default();
+            g.apply(Event::LoadBoard(get_test_board(6, 1))).unwrap();
+            g.apply(Event::ShuffleBoard { seed }).unwrap();
+            g.board.categories.iter().map(|c| c.title.clone()).collect::<Vec<_>>()
+        };
+        // pinned, journals rely on it
+        assert_eq!(
+            shuffled(42),
+            ["Category 5", "Category 4", "Category 1", "Category 3", "Category 6", "Category 2"]
+        );
+        assert_eq!(shuffled(7), shuffled(7));
+        assert_ne!(shuffled(7), shuffled(42));
+
+        let mut g = Game::default();
+        g.apply(Event::OpenLobby).unwrap();
+        assert!(matches!(g.apply(Event::ShuffleBoard { seed: 2 }), Err(Error::WrongPhase { .. })));
+    }
+
+    #[test]
+    fn clues_can_be_picked_by_id() {
+        let mut board = get_test_board(6, 2);
+        for (c, category) in board.categories.iter_mut().enumerate() {
+            category.id = Some(format!("category-{}", c));
+            for (q, clue) in category.clues.iter_mut().enumerate() {
+                clue.id = Some(format!("clue-{}-{}", c, q));
+            }
+        }
+        let mut g = Game::default();
+        for event in [
+            Event::LoadBoard(board),
+            Event::ShuffleBoard { seed: 42 },
+            Event::OpenLobby,
+            Event::ConnectContestant { name_hint: "a".to_owned() },
+            Event::StartGame,
+        ] {
+            g.apply(event).unwrap();
+        }
+        let moved = g.board.resolve("clue-0-1").unwrap();
+        assert_eq!(moved, (2, 1));
+        assert_eq!(g.board.resolve("clue-9-9"), None);
+
+        let unknown = serde_json::from_str(r#"{"type": "Pick", "clue": "clue-9-9"}"#).unwrap();
+        assert!(matches!(g.apply(unknown), Err(Error::ClueNotFound)));
+        let state = g.apply(serde_json::from_str(r#"{"type": "Pick", "clue": "clue-0-1"}"#).unwrap()).unwrap();
+        assert_eq!(state.phase, GamePhase::Clue { clue: moved, exclusive: None });
+        // positions still work, and are journaled the same as before
+        let by_handle: Event = serde_json::from_str(r#"{"type": "Pick", "clue": [1, 0]}"#).unwrap();
+        assert!(matches!(&by_handle, Event::Pick { clue: ClueRef::ByHandle((1, 0)) }));
+        assert_eq!(serde_json::to_value(&by_handle).unwrap()["clue"], serde_json::json!([1, 0]));
+    }
+
+    #[test]
+    fn hidden_values_are_redacted() {
+        let mut g = Game::default();
+        for event in [
+            Event::Settings(Options { hide_values: true, ..Options::default() }),
+            Event::LoadBoard(get_test_board(2, 2)),
+            Event::OpenLobby,
+            Event::ConnectContestant { name_hint: "pi-1".to_owned() },
+            Event::StartGame,
+            Event::Pick { clue: (0, 0).into() },
+            Event::ClueFullyShown,
+            Event::Buzz { contestant: 0 },
+            Event::AcceptAnswer,
+        ] {
+            g.apply(event).unwrap();
+        }
+        // scored with the real value
+        assert_eq!(g.contestants[0].points, 100);
+        let points = |s: &GameState| s.board.clue_rows().iter().flatten().map(|(_, c)| c.points).collect::<Vec<_>>();
+        let state = g.get_game_state();
+        assert_eq!(points(&state), [100, 100, 200, 200]);
+        // the picked clue is revealed
+        assert_eq!(points(&state.redacted()), [100, 0, 0, 0]);
+        g.apply(Event::FinishClue { confirmed: false }).unwrap();
+        assert_eq!(points(&g.get_game_state().redacted()), [100, 0, 0, 0]);
+        assert!(!serde_json::to_string(&g.get_game_state().redacted()).unwrap().contains("200"));
+
+        g.options.hide_values = false;
+        assert_eq!(points(&g.get_game_state().redacted()), [100, 100, 200, 200]);
+    }
+
+    #[test]
+    fn single_values_are_revealed_by_picking() {
+        let mut board = get_test_board(2, 2);
+        for clue in &mut board.categories[1].clues {
+            clue.value_hidden = true;
+        }
+        let mut g = Game::default();
+        for event in [
+            Event::LoadBoard(board),
+            Event::OpenLobby,
+            Event::ConnectContestant { name_hint: "pi-1".to_owned() },
+            Event::StartGame,
+        ] {
+            g.apply(event).unwrap();
+        }
+        let points = |s: &GameState| s.board.clue_rows().iter().flatten().map(|(_, c)| c.points).collect::<Vec<_>>();
+        assert_eq!(points(&g.get_game_state().redacted()), [100, 0, 200, 0]);
+        assert_eq!(points(&g.get_game_state()), [100, 100, 200, 200]);
+
+        let state = g.apply(Event::Pick { clue: (1, 1).into() }).unwrap();
+        assert!(state.board.categories[1].clues[1].value_revealed);
+        assert_eq!(points(&state.redacted()), [100, 0, 200, 200]);
+        // and stays revealed, even if it wasn't played
+        g.apply(Event::FinishClue { confirmed: false }).unwrap();
+        let mut state = g.get_game_state();
+        state.board.categories[1].clues[1].solved = false;
+        assert_eq!(points(&state.redacted()), [100, 0, 200, 200]);
+        let json = serde_json::to_value(state.redacted()).unwrap();
+        assert_eq!(json["board"]["categories"][1]["clues"][0]["value_hidden"], true);
+        assert_eq!(json["board"]["categories"][1]["clues"][1]["value_revealed"], true);
+        assert!(json["board"]["categories"][0]["clues"][0].get("value_hidden").is_none());
+    }
+
+    #[test]
+    fn answers_can_be_typed() {
+        let mut g = Game::default();
+        for event in [
+            Event::LoadBoard(get_test_board(2, 2)),
+            Event::OpenLobby,
+            Event::ConnectContestant { name_hint: "pi-1".to_owned() },
+            Event::ConnectContestant { name_hint: "pi-2".to_owned() },
+            Event::StartGame,
+            Event::Pick { clue: (0, 0).into() },
+            Event::ClueFullyShown,
+        ] {
+            g.apply(event).unwrap();
+        }
+        let submit = |g: &mut Game, contestant, text: &str| g.apply(Event::SubmitAnswer { contestant, text: text.to_owned() });
+        let answer = |g: &Game| match &g.phase {
+            GamePhase::Buzzed { answer, .. } => answer.clone(),
+            _ => panic!("not buzzed: {:?}", g.phase),
+        };
+        assert!(matches!(submit(&mut g, 1, "clue 1"), Err(Error::WrongPhase { .. })));
+        g.apply(Event::Buzz { contestant: 1 }).unwrap();
+        assert_eq!(answer(&g), None);
+
+        submit(&mut g, 1, " clue\u{0}\n 2 ").unwrap();
+        assert_eq!(answer(&g).as_deref(), Some("clue 2"));
+        // replaces the first one
+        submit(&mut g, 1, &"1".repeat(MAX_ANSWER_LENGTH + 10)).unwrap();
+        assert_eq!(answer(&g).unwrap().len(), MAX_ANSWER_LENGTH);
+        assert!(matches!(submit(&mut g, 0, "clue 1"), Err(Error::NotAnswering)));
+        assert_eq!(answer(&g).unwrap().len(), MAX_ANSWER_LENGTH);
+        assert!(!matches!(g.get_game_state().redacted().phase, GamePhase::Buzzed { answer: Some(_), .. }));
+
+        // a new attempt starts without an answer
+        g.apply(Event::RejectAnswer).unwrap();
+        g.apply(Event::Buzz { contestant: 0 }).unwrap();
+        assert_eq!(answer(&g), None);
+    }
+
+    #[test]
+    fn completed_categories_are_celebrated_once() {
+        let mut g = Game::default();
+        for event in [
+            Event::LoadBoard(get_test_board(2, 2)),
+            Event::OpenLobby,
+            Event::ConnectContestant { name_hint: "pi-1".to_owned() },
+            Event::StartGame,
+        ] {
+            g.apply(event).unwrap();
+        }
+        let mut play = |clue| -> Vec<(Option<usize>, Vec<bool>)> {
+            [Event::Pick { clue: ClueRef::from(clue) }, Event::ClueFullyShown, Event::Buzz { contestant: 0 }, Event::AcceptAnswer, Event::FinishClue { confirmed: false }]
+                .into_iter()
+                .map(|event| g.apply(event).unwrap())
+                .map(|state| (state.newly_completed_category, state.progress.completed_categories))
+                .collect()
+        };
+        play((0, 0));
+        let states = play((0, 1));
+        assert_eq!(states[4], (Some(0), vec![true, false]));
+        assert!(states[..4].iter().all(|(newly, completed)| newly.is_none() && completed == &[false, false]));
+
+        let state = g.apply(Event::Pick { clue: (1, 0).into() }).unwrap();
+        assert_eq!(state.newly_completed_category, None);
+        assert_eq!(state.progress.completed_categories, [true, false]);
+        g.apply(Event::FinishClue { confirmed: false }).unwrap();
+        g.apply(Event::FinishClue { confirmed: false }).unwrap();
+        // skipped clues complete categories too, but only when they are first finished
+        g.apply(Event::Pick { clue: (1, 1).into() }).unwrap();
+        assert_eq!(g.apply(Event::FinishClue { confirmed: false }).unwrap().newly_completed_category, Some(1));
+        let state = g.apply(Event::FinishClue { confirmed: false }).unwrap();
+        assert_eq!(state.newly_completed_category, None);
+        assert_eq!(state.progress.completed_categories, [true, true]);
+
+        // older states don't have it
+        let json = serde_json::to_value(&state).unwrap();
+        assert_eq!(json["newly_completed_category"], serde_json::Value::Null);
+        let mut json = json.as_object().unwrap().clone();
+        json.remove("newly_completed_category");
+        json["progress"].as_object_mut().unwrap().remove("completed_categories");
+        let state: GameState = serde_json::from_value(json.into()).unwrap();
+        assert!(state.progress.completed_categories.is_empty());
+    }
+
+    #[test]
+    fn boards_can_be_replaced_in_the_lobby() {
+        let events = [
+            Event::LoadBoard(get_test_board(1, 1)),
+            Event::OpenLobby,
+            Event::ConnectContestant { name_hint: "pi-1".to_owned() },
+            Event::NameContestant { index: 0, name: "Alice".to_owned() },
+            Event::AwardPoints { contestant: 0, points: 50, reason: None, clue: None },
+            Event::Buzz { contestant: 0 },
+            Event::LoadBoard(get_test_board(2, 2)),
+        ];
+        let mut g = Game::default();
+        for event in events.clone() {
+            g.apply(event).unwrap();
+        }
+        let state = g.get_game_state();
+        assert!(matches!(state.phase, GamePhase::Connecting));
+        assert_eq!(state.board, get_test_board(2, 2));
+        let alice = &state.contestants[0];
+        assert_eq!((alice.name.as_deref(), alice.points, alice.indicate, alice.connected), (Some("Alice"), 50, true, true));
+
+        // replays the same way
+        let journal: Vec<String> = events.iter().map(|e| serde_json::to_string(e).unwrap()).collect();
+        let mut replayed = Game::default();
+        for line in journal {
+            replayed.apply(serde_json::from_str(&line).unwrap()).unwrap();
+        }
+        assert_eq!(replayed.get_game_state().board, state.board);
+        assert_eq!(replayed.get_game_state().contestants[0].points, 50);
+
+        g.apply(Event::StartGame).unwrap();
+        assert!(matches!(g.apply(Event::LoadBoard(get_test_board(1, 1))), Err(Error::GameLocked)));
+    }
+
+    #[test]
+    fn skipping_clues_can_require_confirmation() {
+        let mut g = Game::default();
+        for event in [
+            Event::Settings(Options { confirm_skip: true, ..Options::default() }),
+            Event::LoadBoard(get_test_board(2, 2)),
+            Event::OpenLobby,
+            Event::ConnectContestant { name_hint: "pi-1".to_owned() },
+            Event::StartGame,
+            Event::Pick { clue: (0, 0).into() },
+        ] {
+            g.apply(event).unwrap();
+        }
+        let unconfirmed = Event::FinishClue { confirmed: false };
+        for next in [Event::ClueFullyShown, Event::Buzz { contestant: 0 }] {
+            assert!(matches!(g.apply(unconfirmed.clone()), Err(Error::ConfirmationRequired { action: "FinishClue" })));
+            g.apply(next).unwrap();
+        }
+        assert!(matches!(g.apply(unconfirmed.clone()), Err(Error::ConfirmationRequired { .. })));
+        g.apply(Event::FinishClue { confirmed: true }).unwrap();
+        // resolving is never gated
+        assert!(matches!(g.phase, GamePhase::Resolution { .. }));
+        g.apply(unconfirmed.clone()).unwrap();
+
+        g.apply(Event::Pick { clue: (0, 1).into() }).unwrap();
+        g.apply(Event::ClueFullyShown).unwrap();
+        g.apply(Event::Buzz { contestant: 0 }).unwrap();
+        g.apply(Event::AcceptAnswer).unwrap();
+        g.apply(unconfirmed.clone()).unwrap();
+        assert!(matches!(g.phase, GamePhase::Picking { .. }));
+
+        // older journals don't confirm, and didn't have to
+        let event: Event = serde_json::from_str(r#"{"type": "FinishClue"}"#).unwrap();
+        assert!(matches!(event, Event::FinishClue { confirmed: false }));
+        let mut g = Game::default();
+        for event in [
+            Event::LoadBoard(get_test_board(2, 2)),
+            Event::OpenLobby,
+            Event::ConnectContestant { name_hint: "pi-1".to_owned() },
+            Event::StartGame,
+            Event::Pick { clue: (0, 0).into() },
+            event,
+        ] {
+            g.apply(event).unwrap();
+        }
+        assert!(matches!(g.phase, GamePhase::Resolution { .. }));
+    }
+
+    #[test]
+    fn boards_are_validated() {
+        Board::demo().validate().unwrap();
+        get_test_board(3, 4).validate().unwrap();
+        let demo = Board::demo();
+        assert!(demo.categories.iter().flat_map(|c| c.clues.iter()).any(|c| c.can_wager && c.exclusive));
+
+        assert!(matches!(Board { categories: vec![] }.validate(), Err(Error::EmptyBoard)));
+        assert!(matches!(get_test_board(2, 0).validate(), Err(Error::EmptyBoard)));
+        let mut uneven = get_test_board(2, 2);
+        uneven.categories[1].clues.pop();
+        assert!(matches!(uneven.validate(), Err(Error::UnevenBoard)));
+        let mut empty = get_test_board(2, 2);
+        empty.categories[0].clues[1].clue = " ".to_owned();
+        assert!(matches!(empty.validate(), Err(Error::EmptyClue)));
+        let mut worthless = get_test_board(2, 2);
+        worthless.categories[1].clues[0].points = 0;
+        assert!(matches!(worthless.validate(), Err(Error::InvalidPoints)));
+        let mut duplicate = get_test_board(2, 2);
+        duplicate.categories[0].clues[0].id = Some("same".to_owned());
+        duplicate.categories[1].clues[1].id = Some("same".to_owned());
+        assert!(matches!(duplicate.validate(), Err(Error::DuplicateId)));
+        duplicate.categories[1].clues[1].id = Some("other".to_owned());
+        duplicate.categories[0].id = Some("same".to_owned());
+        duplicate.validate().unwrap();
+        duplicate.categories[1].id = Some("same".to_owned());
+        assert!(matches!(duplicate.validate(), Err(Error::DuplicateId)));
+    }
+
+    /// Chains `parts` clues onto clue (0, 0), worth 1000, 2000 and so on
+    fn chained(board: &mut Board, parts: usize) {
+        let mut clue = &mut board.categories[0].clues[0];
+        clue.followup = None;
+        for n in 1..=parts {
+            clue = clue.followup.insert(Box::new(Clue {
+                clue: format!("part {}", n + 1),
+                response: format!("part {}", n + 1),
+                points: 1000 * n as Points,
+                ..clue.clone()
+            }));
+        }
+    }
+
+    #[test]
+    fn clue_chains() {
+        let mut board = get_test_board(2, 2);
+        chained(&mut board, 1);
+        board.validate().unwrap();
+        let mut g = Game::default();
+        for event in [
+            Event::LoadBoard(board),
+            Event::OpenLobby,
+            Event::ConnectContestant { name_hint: "pi-1".to_owned() },
+            Event::ConnectContestant { name_hint: "pi-2".to_owned() },
+            Event::StartGame,
+            Event::Pick { clue: (0, 0).into() },
+            Event::ClueFullyShown,
+            Event::Buzz { contestant: 1 },
+        ] {
+            g.apply(event).unwrap();
+        }
+        assert_eq!(g.get_game_state().current_clue().unwrap().clue, "clue 1");
+        // the second part goes to the same contestant
+        let state = g.apply(Event::AcceptAnswer).unwrap();
+        assert_eq!(state.phase, GamePhase::Clue { clue: (0, 0), exclusive: Some(1) });
+        assert_eq!((state.followup, state.contestants[1].points), (1, 100));
+        assert_eq!(state.current_clue().unwrap().clue, "part 2");
+        assert!(!state.board.get(&(0, 0)).unwrap().solved);
+        g.apply(Event::ClueFullyShown).unwrap();
+        // and is scored on its own
+        let state = g.apply(Event::RejectAnswer).unwrap();
+        assert_eq!(state.phase, GamePhase::Buzzing { clue: (0, 0) });
+        assert_eq!(state.contestants[1].points, -900);
+        g.apply(Event::Buzz { contestant: 0 }).unwrap();
+        let state = g.apply(Event::AcceptAnswer).unwrap();
+        assert!(matches!(state.phase, GamePhase::Resolution { clue: (0, 0), contestant: 0, .. }));
+        assert_eq!((state.contestants[0].points, state.current_clue().unwrap().response.as_str()), (1000, "part 2"));
+        g.apply(Event::FinishClue { confirmed: false }).unwrap();
+        let state = g.apply(Event::Pick { clue: (0, 1).into() }).unwrap();
+        assert!(state.board.get(&(0, 0)).unwrap().solved);
+        assert_eq!((state.followup, state.current_clue().unwrap().clue.as_str()), (0, "clue 2"));
+
+        let mut board = get_test_board(2, 2);
+        chained(&mut board, MAX_FOLLOWUPS);
+        board.validate().unwrap();
+        chained(&mut board, MAX_FOLLOWUPS + 1);
+        assert!(matches!(board.validate(), Err(Error::ChainTooLong)));
+        chained(&mut board, 1);
+        board.categories[0].clues[0].followup.as_mut().unwrap().points = 0;
+        assert!(matches!(board.validate(), Err(Error::InvalidPoints)));
+    }
+
+    #[test]
+    fn final_standings_share_ranks() {
+        let contestants = |points: &[Points]| -> Vec<Contestant> {
+            points.iter().enumerate().map(|(i, &points)| Contestant {
+                name: None,
+                name_hint: format!("pi-{}", i),
+                points,
+                indicate: false,
+                connected: true,
+                display_name: String::new(),
+                display_position: i,
+            }).collect()
+        };
+        let ranked = |points: &[Points]| -> Vec<(usize, ContestantHandle, Points)> {
+            Standing::rank(&contestants(points)).into_iter().map(|s| (s.rank, s.contestant, s.points)).collect()
+        };
+        assert_eq!(ranked(&[100, 300, 100, 300, 0]), [(1, 1, 300), (1, 3, 300), (3, 0, 100), (3, 2, 100), (5, 4, 0)]);
+        assert_eq!(ranked(&[-200, -100, -300]), [(1, 1, -100), (2, 0, -200), (3, 2, -300)]);
+        assert_eq!(ranked(&[-500]), [(1, 0, -500)]);
+        assert_eq!(ranked(&[]), []);
+
+        let mut g = Game::default();
+        for event in [
+            Event::LoadBoard(get_test_board(1, 1)),
+            Event::OpenLobby,
+            Event::ConnectContestant { name_hint: "pi-1".to_owned() },
+            Event::ConnectContestant { name_hint: "pi-2".to_owned() },
+            Event::StartGame,
+            Event::Pick { clue: (0, 0).into() },
+        ] {
+            assert!(g.apply(event).unwrap().final_standings.is_empty());
+        }
+        g.apply(Event::ClueFullyShown).unwrap();
+        g.apply(Event::Buzz { contestant: 1 }).unwrap();
+        g.apply(Event::AcceptAnswer).unwrap();
+        let state = g.apply(Event::FinishClue { confirmed: false }).unwrap();
+        assert!(matches!(state.phase, GamePhase::Score));
+        assert_eq!(state.final_standings, Standing::rank(&state.contestants));
+        assert_eq!(state.final_standings[0].contestant, 1);
+    }
+
+    #[test]
+    fn legal_events_cap_the_decayed_value() {
+        let mut g = Game::default();
+        for event in [
+            Event::Settings(Options { decay_on_rejection: Some(50), ..Options::default() }),
+            Event::LoadBoard(get_test_board(1, 2)),
+            Event::OpenLobby,
+            Event::ConnectContestant { name_hint: "pi-1".to_owned() },
+            Event::ConnectContestant { name_hint: "pi-2".to_owned() },
+            Event::StartGame,
+            Event::Pick { clue: (0, 1).into() },
+            Event::ClueFullyShown,
+            Event::Buzz { contestant: 1 },
+            Event::RejectAnswer,
+        ] {
+            g.apply(event).unwrap();
+        }
+        // the clue is worth 200, but only 100 are left after the rejection
+        g.contestants[0].points = MAX_POINTS - 150;
+        let state = g.apply(Event::Buzz { contestant: 0 }).unwrap();
+        assert_eq!(state.current_value, Some(100));
+        assert!(state.legal_events.contains(&EventKind::AcceptAnswer));
+        assert_eq!(g.apply(Event::AcceptAnswer).unwrap().contestants[0].points, MAX_POINTS - 50);
+    }
+
+    #[test]
+    fn rejected_answers_decay_the_value() {
+        let mut board = get_test_board(1, 2);
+        board.categories[0].clues[0].points = 250;
+        let mut g = Game::default();
+        for event in [
+            Event::Settings(Options { decay_on_rejection: Some(50), ..Options::default() }),
+            Event::LoadBoard(board),
+            Event::OpenLobby,
+            Event::ConnectContestant { name_hint: "pi-1".to_owned() },
+            Event::ConnectContestant { name_hint: "pi-2".to_owned() },
+            Event::ConnectContestant { name_hint: "pi-3".to_owned() },
+            Event::StartGame,
+        ] {
+            g.apply(event).unwrap();
+        }
+        let points = |state: &GameState| state.contestants.iter().map(|c| c.points).collect::<Vec<_>>();
+        assert_eq!(g.get_game_state().current_value, None);
+        assert_eq!(g.apply(Event::Pick { clue: (0, 0).into() }).unwrap().current_value, Some(250));
+        g.apply(Event::ClueFullyShown).unwrap();
+
+        // each wrong answer costs what it would have won
+        g.apply(Event::Buzz { contestant: 0 }).unwrap();
+        let state = g.apply(Event::RejectAnswer).unwrap();
+        assert_eq!((points(&state), state.current_value), (vec![-250, 0, 0], Some(125)));
+        g.apply(Event::Buzz { contestant: 1 }).unwrap();
+        let state = g.apply(Event::RejectAnswer).unwrap();
+        assert_eq!((points(&state), state.current_value), (vec![-250, -125, 0], Some(62)));
+        assert_eq!(g.apply(Event::Buzz { contestant: 2 }).unwrap().current_value, Some(62));
+        let state = g.apply(Event::AcceptAnswer).unwrap();
+        assert_eq!((points(&state), state.current_value), (vec![-250, -125, 62], None));
+        assert_eq!(state.board.categories[0].clues[0].points, 250);
+
+        // the next clue starts at its full value again
+        g.apply(Event::FinishClue { confirmed: false }).unwrap();
+        let contestant = match g.get_game_state().phase {
+            GamePhase::Picking { contestant } => contestant,
+            phase => panic!("{:?}", phase),
+        };
+        assert_eq!(g.apply(Event::Pick { clue: (0, 1).into() }).unwrap().current_value, Some(200));
+        g.apply(Event::ClueFullyShown).unwrap();
+        g.apply(Event::Buzz { contestant }).unwrap();
+        let state = g.apply(Event::AcceptAnswer).unwrap();
+        assert_eq!(points(&state)[contestant], [-250, -125, 62][contestant] + 200);
+
+        // without the option, every wrong answer costs the whole value
+        let mut g = Game::default();
+        for event in [
+            Event::LoadBoard(get_test_board(1, 2)),
+            Event::OpenLobby,
+            Event::ConnectContestant { name_hint: "pi-1".to_owned() },
+            Event::ConnectContestant { name_hint: "pi-2".to_owned() },
+            Event::StartGame,
+            Event::Pick { clue: (0, 0).into() },
+            Event::ClueFullyShown,
+            Event::Buzz { contestant: 0 },
+            Event::RejectAnswer,
+            Event::Buzz { contestant: 1 },
+        ] {
+            g.apply(event).unwrap();
+        }
+        assert_eq!(g.get_game_state().current_value, Some(100));
+        assert_eq!(points(&g.apply(Event::AcceptAnswer).unwrap()), [-100, 100]);
+    }
+
+    #[test]
+    fn hints_are_revealed_after_rejected_answers() {
+        let start = |auto_reveal_hint_after| {
+            let mut g = Game::default();
+            for event in [
+                Event::Settings(Options { auto_reveal_hint_after, ..Options::default() }),
+                Event::LoadBoard(get_test_board(1, 2)),
+                Event::OpenLobby,
+                Event::ConnectContestant { name_hint: "pi-1".to_owned() },
+                Event::ConnectContestant { name_hint: "pi-2".to_owned() },
+                Event::StartGame,
+                Event::Pick { clue: (0, 0).into() },
+                Event::ClueFullyShown,
+            ] {
+                g.apply(event).unwrap();
+            }
+            g
+        };
+        let reject = |g: &mut Game, contestant| {
+            g.apply(Event::Buzz { contestant }).unwrap();
+            g.apply(Event::RejectAnswer).unwrap()
+        };
+
+        let mut g = start(Some(2));
+        assert!(!reject(&mut g, 0).hint_revealed);
+        let state = reject(&mut g, 1);
+        assert!(matches!(state.phase, GamePhase::Buzzing { .. }) && state.hint_revealed);
+        assert!(g.apply(Event::Buzz { contestant: 0 }).unwrap().hint_revealed);
+        let state = g.apply(Event::AcceptAnswer).unwrap();
+        assert!(matches!(state.phase, GamePhase::Resolution { show_hint: true, .. }) && !state.hint_revealed);
+        g.apply(Event::RevealHint).unwrap();
+
+        // counted per clue
+        g.apply(Event::FinishClue { confirmed: false }).unwrap();
+        assert!(!g.apply(Event::Pick { clue: (0, 1).into() }).unwrap().hint_revealed);
+        g.apply(Event::ClueFullyShown).unwrap();
+        assert!(!reject(&mut g, 0).hint_revealed);
+        g.apply(Event::FinishClue { confirmed: false }).unwrap();
+        assert!(matches!(g.get_game_state().phase, GamePhase::Resolution { show_hint: false, .. }));
+
+        let mut g = start(None);
+        for _ in 0..5 {
+            assert!(!reject(&mut g, 0).hint_revealed);
+        }
+        let state = g.apply(Event::FinishClue { confirmed: false }).unwrap();
+        assert!(matches!(state.phase, GamePhase::Resolution { show_hint: false, .. }));
+        assert!(matches!(g.apply(Event::RevealHint).unwrap().phase, GamePhase::Resolution { show_hint: true, .. }));
+
+        assert!(start(Some(0)).get_game_state().hint_revealed);
+    }
+
+    #[test]
+    fn started_games_are_locked() {
+        let mut g = Game::default();
+        for event in [
+            Event::LoadBoard(get_test_board(2, 2)),
+            Event::OpenLobby,
+            Event::ConnectContestant { name_hint: "pi-1".to_owned() },
+            // no reason needed before the start
+            Event::AwardPoints { contestant: 0, points: 100, reason: Some(String::new()), clue: None },
+        ] {
+            assert!(!g.apply(event).unwrap().locked);
+        }
+        assert!(matches!(g.apply(Event::Unlock), Err(Error::NotLocked)));
+        assert!(g.apply(Event::StartGame).unwrap().locked);
+
+        let edit = |field| Event::EditClue { clue: (0, 0), field, value: "100".to_owned() };
+        for event in [
+            Event::LoadBoard(get_test_board(1, 1)),
+            Event::Settings(Options::default()),
+            Event::ShuffleBoard { seed: 1 },
+            Event::EditCategory { category: 0, title: "renamed".to_owned() },
+            edit(ClueField::Clue),
+            edit(ClueField::Response),
+            edit(ClueField::Points),
+        ] {
+            assert!(matches!(g.apply(event.clone()), Err(Error::GameLocked)), "{:?}", event);
+        }
+        g.apply(edit(ClueField::Hint)).unwrap();
+
+        let award = |reason: Option<&str>| Event::AwardPoints { contestant: 0, points: 50, reason: reason.map(str::to_owned), clue: None };
+        let revoke = |reason: &str| Event::RevokePoints { contestant: 0, points: 50, reason: Some(reason.to_owned()), clue: None };
+        assert!(matches!(g.apply(award(Some(" "))), Err(Error::ReasonRequired)));
+        assert!(matches!(g.apply(revoke("")), Err(Error::ReasonRequired)));
+        g.apply(award(Some("misheard the answer"))).unwrap();
+        g.apply(revoke("typo")).unwrap();
+        assert!(matches!(g.apply(award(None)), Err(Error::ReasonRequired)));
+        // older journals don't have reasons
+        g.accept_missing_reasons();
+        g.apply(award(None)).unwrap();
+        assert!(matches!(g.apply(award(Some(""))), Err(Error::ReasonRequired)));
+        assert_eq!(g.contestants[0].points, 150);
+
+        let reasoned: Event = serde_json::from_str(&serde_json::to_string(&award(Some("typo"))).unwrap()).unwrap();
+        assert!(matches!(reasoned, Event::AwardPoints { reason: Some(r), .. } if r == "typo"));
+        let legacy: Event = serde_json::from_str(r#"{"type": "AwardPoints", "contestant": 0, "points": 5}"#).unwrap();
+        assert!(matches!(legacy, Event::AwardPoints { reason: None, .. }));
+
+        let state = g.apply(Event::Unlock).unwrap();
+        assert!(!state.locked && state.unlocked);
+        assert!(matches!(g.apply(Event::Unlock), Err(Error::NotLocked)));
+        g.apply(award(Some(""))).unwrap();
+        // the phase still has to fit
+        assert!(matches!(g.apply(Event::LoadBoard(get_test_board(1, 1))), Err(Error::WrongPhase { .. })));
+    }
+
+    #[test]
+    fn corrections_are_attributed_to_clues() {
+        let mut g = Game::new();
+        for event in [
+            Event::LoadBoard(get_test_board(2, 2)),
+            Event::OpenLobby,
+            Event::ConnectContestant { name_hint: "a".to_owned() },
+            Event::ConnectContestant { name_hint: "b".to_owned() },
+        ] {
+            g.apply(event).unwrap();
+        }
+        let legacy: Event = serde_json::from_str(r#"{"type": "AwardPoints", "contestant": 0, "points": 5, "reason": null}"#).unwrap();
+        let attributed = r#"{"type": "RevokePoints", "contestant": 1, "points": 100, "reason": "misheard", "clue": [1, 0]}"#;
+        let attributed: Event = serde_json::from_str(attributed).unwrap();
+        assert!(matches!(legacy, Event::AwardPoints { clue: None, .. }));
+        assert!(!serde_json::to_string(&legacy).unwrap().contains("clue"));
+        g.apply(legacy).unwrap();
+        g.apply(attributed).unwrap();
+        let missing = Event::AwardPoints { contestant: 0, points: 5, reason: None, clue: Some((2, 0)) };
+        assert!(matches!(g.apply(missing), Err(Error::ClueNotFound)));
+        let state = g.apply(Event::AdjustScores { changes: vec![(0, 10), (1, -10)], reason: "bonus".to_owned() }).unwrap();
+
+        let corrections: Vec<_> = state.corrections.iter().map(|c| (c.contestant, c.points, c.clue)).collect();
+        assert_eq!(corrections, [(0, 5, None), (1, -100, Some((1, 0))), (0, 10, None), (1, -10, None)]);
+        assert_eq!(state.corrections[1].reason.as_deref(), Some("misheard"));
+        let sent = serde_json::to_value(&state).unwrap();
+        assert_eq!(sent["corrections"][1]["clue"], serde_json::json!([1, 0]));
+    }
+
+    #[test]
+    fn points_are_formatted() {
+        let format = |points_format: PointsFormat, points_label: Option<&str>| {
+            let options = Options { points_format, points_label: points_label.map(str::to_owned), ..Options::default() };
+            [1500, -1500, 0, 999, -1_234_567].map(|points| options.format_points(points))
+        };
+        let currency = |symbol: &str, position| PointsFormat::Currency { symbol: symbol.to_owned(), position };
+        assert_eq!(format(PointsFormat::PlainNumber, None), ["1500", "-1500", "0", "999", "-1234567"]);
+        assert_eq!(format(PointsFormat::Thousands, None), ["1,500", "-1,500", "0", "999", "-1,234,567"]);
+        assert_eq!(
+            format(currency("€", SymbolPosition::Before), None),
+            ["€1,500", "-€1,500", "€0", "€999", "-€1,234,567"],
+        );
+        assert_eq!(
+            format(currency("CHF", SymbolPosition::After), None),
+            ["1,500 CHF", "-1,500 CHF", "0 CHF", "999 CHF", "-1,234,567 CHF"],
+        );
+        assert_eq!(format(PointsFormat::PlainNumber, Some("⭐")), ["1500 ⭐", "-1500 ⭐", "0 ⭐", "999 ⭐", "-1234567 ⭐"]);
+        assert_eq!(format(PointsFormat::Thousands, Some(" ")), format(PointsFormat::Thousands, None));
+
+        let options: Options = serde_json::from_str(
+            r#"{"points_label": "stars", "points_format": {"currency": {"symbol": "$", "position": "before"}}}"#,
+        ).unwrap();
+        assert_eq!(options.format_points(MAX_POINTS), "$9,007,199,254,740,991 stars");
+        let plain: Options = serde_json::from_str(r#"{"points_format": "thousands"}"#).unwrap();
+        assert_eq!(plain.points_format, PointsFormat::Thousands);
+    }
+
+    #[test]
+    fn scores_are_adjusted_together() {
+        let mut g = Game::default();
+        for event in [
+            Event::LoadBoard(get_test_board(1, 1)),
+            Event::OpenLobby,
+            Event::ConnectContestant { name_hint: "a".to_owned() },
+            Event::ConnectContestant { name_hint: "b".to_owned() },
+            Event::ConnectContestant { name_hint: "c".to_owned() },
+            Event::StartGame,
+        ] {
+            g.apply(event).unwrap();
+        }
+        let adjust = |changes: &[(ContestantHandle, Points)], reason: &str| {
+            Event::AdjustScores { changes: changes.to_vec(), reason: reason.to_owned() }
+        };
+        let scores = |g: &Game| g.contestants.iter().map(|c| c.points).collect::<Vec<_>>();
+
+        let invalid = g.apply(adjust(&[(0, 200), (3, 200), (1, MAX_POINTS + 1), (2, 200)], "bonus"));
+        assert!(matches!(invalid, Err(Error::InvalidAdjustments { entries }) if entries == [1, 2]));
+        assert_eq!(scores(&g), [0, 0, 0]);
+        assert!(matches!(g.apply(adjust(&[(0, 200)], " ")), Err(Error::ReasonRequired)));
+
+        let state = g.apply(adjust(&[(0, 200), (2, 200), (0, -300)], "bonus")).unwrap();
+        assert_eq!(state.contestants.iter().map(|c| c.points).collect::<Vec<_>>(), [-100, 0, 200]);
+        g.apply(adjust(&[], "nothing")).unwrap();
+        assert_eq!(scores(&g), [-100, 0, 200]);
+    }
+
+    #[test]
+    fn names_are_unique() {
+        let lobby = |options| {
+            let mut g = Game::default();
+            g.apply(Event::LoadBoard(get_test_board(1, 1))).unwrap();
+            g.apply(Event::Settings(options)).unwrap();
+            g.apply(Event::OpenLobby).unwrap();
+            for name_hint in ["pi-1", "pi-2", "pi-3"] {
+                g.apply(Event::ConnectContestant { name_hint: name_hint.to_owned() }).unwrap();
+            }
+            g
+        };
+        let name = |index, name: &str| Event::NameContestant { index, name: name.to_owned() };
+        let mut g = lobby(Options::default());
+        g.apply(name(0, "Alex")).unwrap();
+        assert!(matches!(g.apply(name(1, " alex")), Err(Error::NameTaken)));
+        assert!(matches!(g.apply(name(1, "PI-3")), Err(Error::NameTaken)));
+        // keeping your own name is fine
+        g.apply(name(0, "ALEX")).unwrap();
+
+        let mut g = lobby(Options { unique_names: false, ..Options::default() });
+        g.apply(name(0, "ALEX")).unwrap();
+        g.apply(name(1, "alex")).unwrap();
+        let state = g.apply(name(2, "Alex")).unwrap();
+        let shown: Vec<_> = state.contestants.iter().map(|c| c.display_name.as_str()).collect();
+        assert_eq!(shown, ["ALEX", "alex (2)", "Alex (3)"]);
+        assert_eq!(serde_json::to_value(&state).unwrap()["contestants"][1]["display_name"], "alex (2)");
+    }
+
+    #[test]
+    fn contestants_can_be_reordered() {
+        let mut g = Game::new();
+        g.apply(Event::LoadBoard(get_test_board(2, 2))).unwrap();
+        g.apply(Event::OpenLobby).unwrap();
+        for name_hint in ["a", "b", "c"] {
+            g.apply(Event::ConnectContestant { name_hint: name_hint.to_owned() }).unwrap();
+        }
+        assert_eq!(g.get_game_state().display_order(), [0, 1, 2]);
+        let state = g.apply(Event::ReorderContestants { order: vec![2, 0, 1] }).unwrap();
+        assert_eq!(state.display_order(), [2, 0, 1]);
+        assert_eq!(state.contestants[2].display_position, 0);
+        // handles don't change
+        assert_eq!(state.contestants[2].name_hint, "c");
+
+        let reorder = |g: &mut Game, order: Vec<ContestantHandle>| g.apply(Event::ReorderContestants { order });
+        assert!(matches!(reorder(&mut g, vec![2, 0]), Err(Error::InvalidOrder)));
+        assert!(matches!(reorder(&mut g, vec![2, 0, 0]), Err(Error::InvalidOrder)));
+        assert!(matches!(reorder(&mut g, vec![2, 0, 1, 3]), Err(Error::InvalidOrder)));
+        assert!(matches!(reorder(&mut g, vec![2, 0, 3]), Err(Error::InvalidOrder)));
+        assert_eq!(g.get_game_state().display_order(), [2, 0, 1]);
+
+        g.apply(Event::StartGame).unwrap();
+        assert!(matches!(reorder(&mut g, vec![0, 1, 2]), Err(Error::WrongPhase { .. })));
+    }
+
+    #[test]
+    fn contestants_can_be_imported() {
+        let mut g = Game::new();
+        g.apply(Event::LoadBoard(get_test_board(2, 2))).unwrap();
+        let import = |names: &[&str]| Event::ImportContestants { names: names.iter().map(|&n| n.to_owned()).collect() };
+        g.apply(import(&["Alex", " ", " Sam "])).unwrap();
+        g.apply(Event::OpenLobby).unwrap();
+        g.apply(Event::ConnectContestant { name_hint: "pi-1".to_owned() }).unwrap();
+        // nothing is imported if any name is taken
+        assert!(matches!(g.apply(import(&["Kim", "sam"])), Err(Error::NameTaken)));
+        assert!(matches!(g.apply(import(&["Kim", "kim"])), Err(Error::NameTaken)));
+        let state = g.apply(import(&["Kim"])).unwrap();
+        let names: Vec<_> = state.contestants.iter().map(|c| (c.display_name.as_str(), c.connected)).collect();
+        assert_eq!(names, [("Alex", false), ("Sam", false), ("pi-1", true), ("Kim", false)]);
+
+        g.apply(Event::ReconnectContestant { contestant: 1 }).unwrap();
+        g.apply(Event::StartGame).unwrap();
+        assert!(matches!(g.apply(import(&["Lou"])), Err(Error::WrongPhase { .. })));
+    }
+
+    /// Each kind of event applied twice in the phase it's meant for
+    #[test]
+    fn repetitions_follow_the_idempotency_policy() {
+        use events::Idempotency;
+
+        let board = get_test_board(2, 4);
+        let then = |before: &[Event], events: &[Event]| [before, events].concat();
+        let loaded = vec![Event::LoadBoard(board.clone())];
+        let lobby = then(&loaded, &[
+            Event::OpenLobby,
+            Event::ConnectContestant { name_hint: "a".to_owned() },
+            Event::ConnectContestant { name_hint: "b".to_owned() },
+            Event::ConnectContestant { name_hint: "c".to_owned() },
+        ]);
+        let practice = then(&lobby, &[Event::StartPractice, Event::Buzz { contestant: 1 }]);
+        let picking = then(&lobby, &[Event::StartGame]);
+        let waging = then(&picking, &[Event::Pick { clue: (1, 3).into() }]);
+        let confirming = [
+            vec![Event::Settings(Options { confirm_wagers: true, ..Options::default() })],
+            then(&waging, &[Event::SetWage { points: 100 }]),
+        ].concat();
+        let clue = then(&picking, &[Event::Pick { clue: (0, 0).into() }]);
+        let buzzing = then(&clue, &[Event::ClueFullyShown]);
+        let buzzed = then(&buzzing, &[Event::Buzz { contestant: 0 }]);
+        let resolution = then(&buzzed, &[Event::AcceptAnswer]);
+        let table = [
+            (vec![], Event::Settings(Options::default())),
+            (vec![], Event::LoadBoard(board.clone())),
+            (loaded.clone(), Event::ShuffleBoard { seed: 2 }),
+            (loaded.clone(), Event::EditClue { clue: (0, 0), field: ClueField::Hint, value: "hint".to_owned() }),
+            (loaded.clone(), Event::EditCategory { category: 0, title: "title".to_owned() }),
+            (loaded.clone(), Event::OpenLobby),
+            (lobby.clone(), Event::ConnectContestant { name_hint: "d".to_owned() }),
+            (lobby.clone(), Event::ImportContestants { names: vec!["e".to_owned()] }),
+            (lobby.clone(), Event::DisconnectContestant { contestant: 0 }),
+            (lobby.clone(), Event::ReconnectContestant { contestant: 0 }),
+            (lobby.clone(), Event::ReorderContestants { order: vec![2, 0, 1] }),
+            (lobby.clone(), Event::NameContestant { index: 0, name: "name".to_owned() }),
+            (lobby.clone(), Event::AwardPoints { contestant: 0, points: 10, reason: None, clue: None }),
+            (lobby.clone(), Event::RevokePoints { contestant: 0, points: 10, reason: None, clue: None }),
+            (lobby.clone(), Event::AdjustScores { changes: vec![(0, 10), (1, -10)], reason: String::new() }),
+            (lobby.clone(), Event::StartPractice),
+            (practice.clone(), Event::ResetPractice),
+            (practice, Event::EndPractice),
+            (lobby.clone(), Event::StartGame),
+            (picking.clone(), Event::Unlock),
+            (picking.clone(), Event::Pick { clue: (0, 0).into() }),
+            (picking.clone(), Event::PassPick { to: None }),
+            (waging.clone(), Event::SetWage { points: 0 }),
+            (waging.clone(), Event::CancelWage),
+            (confirming, Event::ConfirmWage),
+            (clue, Event::ClueFullyShown),
+            (buzzing, Event::Buzz { contestant: 0 }),
+            (buzzed.clone(), Event::SubmitAnswer { contestant: 0, text: "answer".to_owned() }),
+            (buzzed.clone(), Event::AcceptAnswer),
+            (buzzed.clone(), Event::WithdrawBuzz),
+            (buzzed, Event::RejectAnswer),
+            (resolution.clone(), Event::RevealHint),
+            (resolution, Event::FinishClue { confirmed: false }),
+        ];
+        let mut names = vec![];
+        for (setup, event) in table {
+            let mut g = Game::new();
+            for event in setup {
+                g.apply(event).unwrap();
+            }
+            let once = serde_json::to_value(g.apply(event.clone()).unwrap()).unwrap();
+            let twice = g.apply(event.clone()).map(|state| serde_json::to_value(state).unwrap());
+            match event.idempotency() {
+                Idempotency::NoOp => assert_eq!(twice.unwrap(), once, "{}", event.name()),
+                Idempotency::Cumulative => assert_ne!(twice.unwrap(), once, "{}", event.name()),
+                Idempotency::Refused => assert!(twice.is_err(), "{}", event.name()),
+            }
+            names.push(event.name());
+        }
+        let every_kind = transitions::TRANSITIONS.iter().map(|t| t.event).chain(transitions::EVERY_PHASE.iter().copied());
+        for name in every_kind {
+            assert!(names.contains(&name), "{} has no idempotency case", name);
+        }
+
+        // outside of those phases they still conflict
+        let mut g = Game::new();
+        for event in picking {
+            g.apply(event).unwrap();
+        }
+        assert!(matches!(g.apply(Event::OpenLobby), Err(Error::WrongPhase { .. })));
+        assert!(matches!(g.apply(Event::ClueFullyShown), Err(Error::WrongPhase { .. })));
+    }
 }
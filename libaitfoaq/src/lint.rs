@@ -0,0 +1,196 @@
+//! Checks a board before game night, beyond what [Board::validate] requires.
+//! Only errors would break the game, warnings are things the author might
+//! not have meant. Nothing here stops a board from being loaded.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::matching::normalize;
+use crate::state::{Board, ClueHandle, MAX_FOLLOWUPS};
+use crate::Error;
+
+/// Clues with more characters, markup included, hardly fit on the screen
+const LONG_CLUE: usize = 250;
+/// How boards refer to their media, see `board-assets` of the server
+const ASSETS_PREFIX: &str = "board-assets/";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rule {
+    /// See [Board::validate], or the board couldn't be read at all
+    Invalid,
+    DuplicateResponse,
+    LongClue,
+    MissingMedia,
+    /// Points that don't rise from row to row, or differ between categories
+    UnusualPoints,
+    /// Some clues of a category hide their value and others don't, which
+    /// gives the hidden ones away
+    MixedHiddenValues,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Finding {
+    pub rule: Rule,
+    /// `None` for findings about the whole board
+    pub clue: Option<ClueHandle>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LintReport {
+    pub errors: Vec<Finding>,
+    pub warnings: Vec<Finding>,
+}
+
+impl LintReport {
+    /// For boards that couldn't be parsed, so there's nothing to lint
+    pub fn unreadable(message: String) -> Self {
+        Self {
+            errors: vec![Finding { rule: Rule::Invalid, clue: None, message }],
+            warnings: vec![],
+        }
+    }
+}
+
+/// Media is only looked for in `assets` if given, it's the directory the
+/// server serves as `board-assets`
+pub fn lint(board: &Board, assets: Option<&Path>) -> LintReport {
+    let mut report = LintReport::default();
+    if let Err(error) = board.validate() {
+        let message = match error {
+            Error::EmptyBoard => "The board has no clues".to_owned(),
+            Error::UnevenBoard => "All categories need the same number of clues".to_owned(),
+            Error::EmptyClue => "A clue has no text".to_owned(),
+            Error::InvalidPoints => "A clue is worth nothing, or too much".to_owned(),
+            Error::ChainTooLong => format!("No more than {} parts can follow a clue", MAX_FOLLOWUPS),
+            error => format!("{:?}", error),
+        };
+        report.errors.push(Finding { rule: Rule::Invalid, clue: None, message });
+    }
+
+    let mut responses: Vec<(String, ClueHandle)> = vec![];
+    for (c, category) in board.categories.iter().enumerate() {
+        for (q, clue) in category.clues.iter().enumerate() {
+            let handle = (c, q);
+            let response = normalize(&clue.response);
+            match responses.iter().find(|(r, _)| !response.is_empty() && *r == response) {
+                Some((_, first)) => report.warnings.push(Finding {
+                    rule: Rule::DuplicateResponse,
+                    clue: Some(handle),
+                    message: format!("Same response as clue {:?}: {}", first, clue.response),
+                }),
+                None => responses.push((response, handle)),
+            }
+            let length = clue.clue.chars().count();
+            if length > LONG_CLUE {
+                report.warnings.push(Finding {
+                    rule: Rule::LongClue,
+                    clue: Some(handle),
+                    message: format!("The clue has {} characters", length),
+                });
+            }
+            let Some(assets) = assets else { continue };
+            for text in [&clue.clue, &clue.response, &clue.hint] {
+                for source in sources(text) {
+                    let Some(file) = source.trim_start_matches('/').strip_prefix(ASSETS_PREFIX) else { continue };
+                    if !assets.join(file).is_file() {
+                        report.errors.push(Finding {
+                            rule: Rule::MissingMedia,
+                            clue: Some(handle),
+                            message: format!("{} doesn't exist", source),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    let ladder = |c: usize| -> Vec<_> { board.categories[c].clues.iter().map(|clue| clue.points).collect() };
+    for c in 0..board.categories.len() {
+        let points = ladder(c);
+        let message = if points.windows(2).any(|w| w[0] >= w[1]) {
+            "The points don't rise from row to row"
+        } else if points != ladder(0) {
+            "The points differ from the first category's"
+        } else {
+            continue;
+        };
+        report.warnings.push(Finding {
+            rule: Rule::UnusualPoints,
+            clue: None,
+            message: format!("{}: {}", board.categories[c].title, message),
+        });
+    }
+    for category in &board.categories {
+        let hidden = category.clues.iter().filter(|clue| clue.value_hidden).count();
+        if hidden > 0 && hidden < category.clues.len() {
+            report.warnings.push(Finding {
+                rule: Rule::MixedHiddenValues,
+                clue: None,
+                message: format!("{}: {} of {} clues hide their value", category.title, hidden, category.clues.len()),
+            });
+        }
+    }
+    report
+}
+
+/// The values of `src` attributes, quoted either way
+fn sources(text: &str) -> Vec<&str> {
+    text.match_indices("src=")
+        .filter_map(|(i, _)| {
+            let rest = &text[i + 4..];
+            let quote = rest.chars().next().filter(|q| *q == '"' || *q == '\'')?;
+            rest[1..].split(quote).next()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(findings: &[Finding]) -> Vec<(Rule, Option<ClueHandle>)> {
+        findings.iter().map(|f| (f.rule, f.clue)).collect()
+    }
+
+    #[test]
+    fn demo_is_clean() {
+        assert_eq!(lint(&Board::demo(), Some(Path::new("."))), LintReport::default());
+    }
+
+    #[test]
+    fn every_rule() {
+        let mut board = Board::demo();
+        board.categories[2].clues[1].response = "What is <i>Jupiter</i>?".to_owned();
+        board.categories[0].clues[2].clue = "a very long clue ".repeat(20);
+        board.categories[1].clues[0].clue = r#"<img src="board-assets/lib.rs"> and <img src='/board-assets/missing.png'>"#.to_owned();
+        board.categories[1].clues[1].hint = "<img src='https://example.com/remote.png'>".to_owned();
+        let report = lint(&board, Some(&Path::new(env!("CARGO_MANIFEST_DIR")).join("src")));
+        assert_eq!(rules(&report.errors), [(Rule::MissingMedia, Some((1, 0)))]);
+        assert!(report.errors[0].message.contains("missing.png"));
+        assert_eq!(rules(&report.warnings), [(Rule::LongClue, Some((0, 2))), (Rule::DuplicateResponse, Some((2, 1)))]);
+        // media isn't looked for without a directory
+        assert!(lint(&board, None).errors.is_empty());
+
+        let mut board = Board::demo();
+        board.categories[1].clues[2].points = 400;
+        board.categories[2].clues.swap(0, 1);
+        board.categories[0].clues[1].clue = String::new();
+        let report = lint(&board, None);
+        assert_eq!(rules(&report.errors), [(Rule::Invalid, None)]);
+        assert_eq!(rules(&report.warnings), [(Rule::UnusualPoints, None), (Rule::UnusualPoints, None)]);
+        assert_eq!(report.warnings[0].message, "Programming: The points differ from the first category's");
+        assert_eq!(report.warnings[1].message, "Animals: The points don't rise from row to row");
+
+        let mut board = Board::demo();
+        board.categories[0].clues[0].value_hidden = true;
+        for clue in &mut board.categories[1].clues {
+            clue.value_hidden = true;
+        }
+        let report = lint(&board, None);
+        assert_eq!(rules(&report.warnings), [(Rule::MixedHiddenValues, None)]);
+        assert_eq!(report.warnings[0].message, "Planets: 1 of 3 clues hide their value");
+    }
+}
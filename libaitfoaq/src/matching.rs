@@ -0,0 +1,173 @@
+//! Compares typed answers to a clue's response, so the moderator has a hint
+//! of whether to accept them. Only ever a suggestion, nothing here changes
+//! the game.
+
+use serde::{Deserialize, Serialize};
+
+/// From this similarity on an answer is as good as the response
+const ACCEPT_FROM: f64 = 0.85;
+/// From this similarity on an answer might be right
+const REVIEW_FROM: f64 = 0.6;
+
+/// Stripped from the start of answers and responses, as long as something
+/// is left afterwards
+const LEADING_PHRASES: &[&str] = &[
+    "what is", "what are", "what was", "what were",
+    "who is", "who are", "who was", "who were",
+    "where is", "where are", "when is", "when was",
+    "was ist", "was sind", "wer ist", "wer sind", "wo ist", "wo sind",
+    "the", "a", "an", "der", "die", "das", "ein", "eine",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Suggestion {
+    Accept,
+    Review,
+    Reject,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MatchResult {
+    /// How close the answer is to the response, from 0.0 for nothing in
+    /// common to 1.0 for equal after [normalize]
+    pub similarity: f64,
+    /// How close the answer is to any part of the hint
+    pub hint_similarity: f64,
+    pub suggestion: Suggestion,
+}
+
+/// Only answers close to the response are suggested for accepting, ones
+/// close to something in the hint are only worth a look.
+pub fn score(answer: &str, response: &str, hint: &str) -> MatchResult {
+    let answer = normalize(answer);
+    let similarity = similarity(&answer, &normalize(response));
+    let hint_similarity = best_window(&answer, &normalize(hint));
+    let suggestion = if similarity >= ACCEPT_FROM {
+        Suggestion::Accept
+    } else if similarity >= REVIEW_FROM || hint_similarity >= ACCEPT_FROM {
+        Suggestion::Review
+    } else {
+        Suggestion::Reject
+    };
+    MatchResult { similarity, hint_similarity, suggestion }
+}
+
+/// Lowercase words without markup, punctuation, diacritics or leading
+/// articles, separated by single spaces
+pub fn normalize(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    let mut in_tag = false;
+    for c in text.chars().flat_map(char::to_lowercase) {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => { in_tag = false; folded.push(' '); },
+            _ if in_tag => {},
+            'ä' | 'æ' => folded.push_str("ae"),
+            'ö' | 'œ' => folded.push_str("oe"),
+            'ü' => folded.push_str("ue"),
+            'ß' => folded.push_str("ss"),
+            'à' | 'á' | 'â' | 'ã' | 'å' => folded.push('a'),
+            'è' | 'é' | 'ê' | 'ë' => folded.push('e'),
+            'ì' | 'í' | 'î' | 'ï' => folded.push('i'),
+            'ò' | 'ó' | 'ô' | 'õ' | 'ø' => folded.push('o'),
+            'ù' | 'ú' | 'û' => folded.push('u'),
+            'ý' | 'ÿ' => folded.push('y'),
+            'ç' => folded.push('c'),
+            'ñ' => folded.push('n'),
+            c if c.is_alphanumeric() => folded.push(c),
+            _ => folded.push(' '),
+        }
+    }
+    let mut words: Vec<&str> = folded.split_whitespace().collect();
+    'strip: loop {
+        for phrase in LEADING_PHRASES {
+            let phrase: Vec<&str> = phrase.split(' ').collect();
+            if words.len() > phrase.len() && words.starts_with(&phrase) {
+                words.drain(..phrase.len());
+                continue 'strip;
+            }
+        }
+        break;
+    }
+    words.join(" ")
+}
+
+/// 1.0 minus the Levenshtein distance relative to the longer text, 0.0 if
+/// either is empty
+fn similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    1.0 - previous[b.len()] as f64 / a.len().max(b.len()) as f64
+}
+
+/// The best [similarity] to any run of as many words in `text` as `answer`
+/// has
+fn best_window(answer: &str, text: &str) -> f64 {
+    let length = answer.split(' ').count();
+    let words: Vec<&str> = text.split(' ').collect();
+    words
+        .windows(length.min(words.len()))
+        .map(|window| similarity(answer, &window.join(" ")))
+        .fold(0.0, f64::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tricky_pairs() {
+        let pairs = [
+            ("Paris", "Paris", Suggestion::Accept),
+            ("what is paris?", "Paris", Suggestion::Accept),
+            ("Who are The Beatles", "Beatles", Suggestion::Accept),
+            ("beatles", "The Beatles", Suggestion::Accept),
+            ("Müller", "Mueller", Suggestion::Accept),
+            ("Muller", "Müller", Suggestion::Accept),
+            ("Crème brûlée", "creme brulee", Suggestion::Accept),
+            ("Eifel Tower", "Eiffel-Tower", Suggestion::Accept),
+            ("Paris", "<b>Paris</b>", Suggestion::Accept),
+            ("Was ist der Rhein", "Rhein", Suggestion::Accept),
+            ("Londn", "London", Suggestion::Review),
+            ("the", "The", Suggestion::Accept),
+            ("Berlin", "Paris", Suggestion::Reject),
+            ("", "Paris", Suggestion::Reject),
+            ("Paris", "", Suggestion::Reject),
+        ];
+        for (answer, response, expected) in pairs {
+            let result = score(answer, response, "");
+            assert_eq!(result.suggestion, expected, "{:?} for {:?}: {:?}", answer, response, result);
+        }
+    }
+
+    #[test]
+    fn hints_only_earn_a_review() {
+        let result = score("the Seine", "Paris", "The river flowing through it is the Seine");
+        assert_eq!(result.suggestion, Suggestion::Review);
+        assert_eq!(result.hint_similarity, 1.0);
+        assert_eq!(score("Paris", "Paris", "Seine").suggestion, Suggestion::Accept);
+        assert_eq!(score("Danube", "Paris", "Seine").suggestion, Suggestion::Reject);
+    }
+
+    #[test]
+    fn normalizes() {
+        assert_eq!(normalize("  What is   the Straße?! "), "strasse");
+        assert_eq!(normalize("<i>Ça</i> va"), "ca va");
+        assert_eq!(normalize("A"), "a");
+    }
+}
@@ -0,0 +1,153 @@
+//! The whole board on paper, so the moderator has a backup of every clue,
+//! response and hint. In the order of the board, followups right after
+//! their clue.
+
+use crate::state::{Board, Clue, ClueHandle, Points};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrintedCategory {
+    pub title: String,
+    pub clues: Vec<PrintedClue>,
+}
+
+/// Texts are on a single line, whatever line breaks and indentation the
+/// board had. Markup is kept, it's up to the printer to escape it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrintedClue {
+    pub clue: ClueHandle,
+    /// 0 for the clue itself, see [Board::part]
+    pub followup: usize,
+    pub points: Points,
+    pub can_wager: bool,
+    pub exclusive: bool,
+    pub text: String,
+    pub response: String,
+    /// `None` if the board has none
+    pub hint: Option<String>,
+}
+
+impl PrintedClue {
+    /// Like "wager, exclusive", empty for ordinary clues
+    pub fn markers(&self) -> String {
+        [(self.can_wager, "wager"), (self.exclusive, "exclusive")]
+            .into_iter()
+            .filter_map(|(marked, marker)| marked.then_some(marker))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn new(clue: ClueHandle, followup: usize, part: &Clue) -> Self {
+        let hint = single_line(&part.hint);
+        Self {
+            clue,
+            followup,
+            points: part.points,
+            can_wager: part.can_wager,
+            exclusive: part.exclusive,
+            text: single_line(&part.clue),
+            response: single_line(&part.response),
+            hint: (!hint.is_empty()).then_some(hint),
+        }
+    }
+}
+
+impl Board {
+    pub fn printable(&self) -> Vec<PrintedCategory> {
+        self.categories.iter().enumerate()
+            .map(|(c, category)| PrintedCategory {
+                title: single_line(&category.title),
+                clues: category.clues.iter().enumerate()
+                    .flat_map(|(q, clue)| clue.chain().enumerate().map(move |(followup, part)| PrintedClue::new((c, q), followup, part)))
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// [Board::printable] as plain text, the same for the same board
+    pub fn printable_text(&self) -> String {
+        let mut text = String::new();
+        for category in self.printable() {
+            if !text.is_empty() {
+                text.push('\n');
+            }
+            text.push_str(&format!("== {} ==\n", category.title));
+            for clue in category.clues {
+                let indent = "  ".repeat(clue.followup);
+                let followup = if clue.followup > 0 { "Followup " } else { "" };
+                let markers = match clue.markers() {
+                    markers if markers.is_empty() => markers,
+                    markers => format!(" [{}]", markers),
+                };
+                text.push_str(&format!("{}{}{}{}: {}\n", indent, followup, clue.points, markers, clue.text));
+                text.push_str(&format!("{}  Response: {}\n", indent, clue.response));
+                if let Some(hint) = clue.hint {
+                    text.push_str(&format!("{}  Hint: {}\n", indent, hint));
+                }
+            }
+        }
+        text
+    }
+}
+
+/// Collapses all whitespace, line breaks included, into single spaces
+fn single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::Category;
+
+    fn clue(text: &str, response: &str, hint: &str, points: Points) -> Clue {
+        Clue {
+            id: None,
+            clue: text.to_owned(),
+            response: response.to_owned(),
+            hint: hint.to_owned(),
+            points,
+            can_wager: false,
+            exclusive: false,
+            solved: false,
+            followup: None,
+            value_hidden: false,
+            value_revealed: false,
+        }
+    }
+
+    #[test]
+    fn boards_print_in_reading_order() {
+        let mut double = clue("Two\n   lines", "What is\tspace?", "  ", 200);
+        double.can_wager = true;
+        double.exclusive = true;
+        double.solved = true;
+        let mut chained = clue("The first <b>part</b>", "What is one?", "", 300);
+        chained.followup = Some(Box::new(clue("The second part", "What is two?", "also: 2", 600)));
+        let board = Board {
+            categories: vec![
+                Category { title: " Space ".to_owned(), id: None, clues: vec![clue("Far away", "What is Pluto?", "or a dog", 100), double] },
+                Category { title: "Numbers".to_owned(), id: None, clues: vec![chained] },
+            ],
+        };
+
+        assert_eq!(board.printable_text(), "\
+== Space ==
+100: Far away
+  Response: What is Pluto?
+  Hint: or a dog
+200 [wager, exclusive]: Two lines
+  Response: What is space?
+
+== Numbers ==
+300: The first <b>part</b>
+  Response: What is one?
+  Followup 600: The second part
+    Response: What is two?
+    Hint: also: 2
+");
+        let printed = board.printable();
+        assert_eq!(printed[1].clues[1].clue, (1, 0));
+        assert_eq!(printed[1].clues[1].followup, 1);
+        assert_eq!(Board { categories: vec![] }.printable_text(), "");
+    }
+}
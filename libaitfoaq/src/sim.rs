@@ -0,0 +1,209 @@
+//! Simulated contestants, e.g. to load test a server with many players. A
+//! [Bot] only decides what to do, sending the event after the delay is up to
+//! the caller. With the same seed and the same states a bot always decides
+//! the same.
+
+use std::ops::Range;
+use std::time::Duration;
+
+use crate::events::Event;
+use crate::state::{ContestantHandle, GamePhase, GameState, Points, SplitMix64};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BotConfig {
+    /// Chance to buzz whenever buzzing opens, from 0.0 to 1.0
+    pub buzz_probability: f64,
+    /// Reaction times cluster around the middle of this range
+    pub reaction_time: Range<Duration>,
+    /// Share of its score a bot wagers, kept within the bounds of the clue
+    pub wager_ratio: f64,
+}
+
+impl Default for BotConfig {
+    fn default() -> Self {
+        Self {
+            buzz_probability: 0.5,
+            reaction_time: Duration::from_millis(200)..Duration::from_millis(800),
+            wager_ratio: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Decision {
+    /// How long to wait before sending the event
+    pub after: Duration,
+    pub event: Event,
+}
+
+#[derive(Debug, Clone)]
+pub struct Bot {
+    pub contestant: ContestantHandle,
+    config: BotConfig,
+    rng: SplitMix64,
+    /// The phase of the last decision, so repeated states don't count twice
+    considered: Option<GamePhase>,
+}
+
+impl Bot {
+    pub fn new(contestant: ContestantHandle, config: BotConfig, seed: u64) -> Self {
+        Self {
+            contestant,
+            config,
+            rng: SplitMix64(seed),
+            considered: None,
+        }
+    }
+
+    /// Picks a random unsolved clue on its turn, wagers in proportion to its
+    /// score, and maybe buzzes. Decides only once per phase, so the same
+    /// state can be passed again, e.g. when only the spectators changed.
+    pub fn decide(&mut self, state: &GameState) -> Option<Decision> {
+        if self.considered.as_ref() == Some(&state.phase) {
+            return None;
+        }
+        self.considered = Some(state.phase.clone());
+        let event = match state.phase {
+            GamePhase::Picking { contestant } if contestant == self.contestant => {
+                let unsolved: Vec<_> = state
+                    .board
+                    .categories
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(c, category)| {
+                        category.clues.iter().enumerate().filter(|(_, clue)| !clue.solved).map(move |(q, _)| (c, q))
+                    })
+                    .collect();
+                if unsolved.is_empty() {
+                    return None;
+                }
+                Event::Pick { clue: unsolved[self.rng.below(unsolved.len())].into() }
+            }
+            // bots don't make typos
+            GamePhase::Waging { contestant, pending_wager: Some(_), .. } if contestant == self.contestant => Event::ConfirmWage,
+            GamePhase::Waging { contestant, min_wager, max_wager, .. } if contestant == self.contestant => {
+                let score = state.contestants.get(self.contestant).map_or(0, |c| c.points);
+                let points = (score as f64 * self.config.wager_ratio).round() as Points;
+                Event::SetWage { points: points.clamp(min_wager, max_wager.max(min_wager)) }
+            }
+            GamePhase::Buzzing { .. } => {
+                if self.rng.unit() >= self.config.buzz_probability {
+                    return None;
+                }
+                Event::Buzz { contestant: self.contestant }
+            }
+            _ => return None,
+        };
+        Some(Decision { after: self.reaction_time(), event })
+    }
+
+    /// The mean of two uniform draws, so times near the middle of the range
+    /// are the most likely
+    fn reaction_time(&mut self) -> Duration {
+        let Range { start, end } = self.config.reaction_time;
+        let spread = end.saturating_sub(start);
+        start + spread.mul_f64((self.rng.unit() + self.rng.unit()) / 2.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::Board;
+    use crate::Game;
+
+    /// Two bots on the demo board, the first one is picking
+    fn started() -> Game {
+        let mut game = Game::new();
+        game.apply(Event::LoadBoard(Board::demo())).unwrap();
+        game.apply(Event::OpenLobby).unwrap();
+        for name_hint in ["a", "b"] {
+            game.apply(Event::ConnectContestant { name_hint: name_hint.to_owned() }).unwrap();
+        }
+        game.apply(Event::StartGame).unwrap();
+        let GamePhase::Picking { contestant } = game.get_game_state().phase else { panic!() };
+        if contestant != 0 {
+            game.apply(Event::PassPick { to: Some(0) }).unwrap();
+        }
+        game
+    }
+
+    /// Lets both bots play every clue, with the moderator accepting every
+    /// other answer
+    fn play(seed: u64) -> Vec<String> {
+        let mut game = started();
+        let mut bots = [Bot::new(0, BotConfig::default(), seed), Bot::new(1, BotConfig::default(), seed + 1)];
+        let mut log = vec![];
+        let mut accept = false;
+        for _ in 0..200 {
+            let state = game.get_game_state();
+            let decisions: Vec<_> = bots.iter_mut().filter_map(|bot| bot.decide(&state)).collect();
+            for decision in &decisions {
+                log.push(format!("{:?}", decision));
+            }
+            // a repeated state changes nothing
+            assert!(bots.iter_mut().all(|bot| bot.decide(&state).is_none()));
+            let fastest = decisions.into_iter().min_by_key(|d| d.after).map(|d| d.event);
+            let event = match (&state.phase, fastest) {
+                (GamePhase::Score, _) => break,
+                (_, Some(event)) => event,
+                (GamePhase::Clue { .. }, None) => Event::ClueFullyShown,
+                (GamePhase::Buzzed { .. }, None) => {
+                    accept = !accept;
+                    if accept { Event::AcceptAnswer } else { Event::RejectAnswer }
+                }
+                (_, None) => Event::FinishClue { confirmed: true },
+            };
+            let _ = game.apply(event);
+        }
+        assert!(matches!(game.get_game_state().phase, GamePhase::Score), "{:?}", game.get_game_state().phase);
+        log
+    }
+
+    #[test]
+    fn decisions_are_deterministic() {
+        let log = play(7);
+        assert!(log.iter().any(|d| d.contains("Pick")));
+        assert!(log.iter().any(|d| d.contains("Buzz")));
+        assert!(log.iter().any(|d| d.contains("SetWage")));
+        assert_eq!(log, play(7));
+        assert_ne!(log, play(8));
+    }
+
+    #[test]
+    fn bots_follow_their_config() {
+        let mut game = started();
+        let state = game.get_game_state();
+        let config = BotConfig {
+            buzz_probability: 1.0,
+            reaction_time: Duration::from_millis(100)..Duration::from_millis(300),
+            wager_ratio: 0.5,
+        };
+        // it's not the second bot's turn
+        assert!(Bot::new(1, config.clone(), 1).decide(&state).is_none());
+        let Some(Decision { after, event: Event::Pick { clue } }) = Bot::new(0, config.clone(), 1).decide(&state) else {
+            panic!()
+        };
+        assert!(!state.board.get(&state.board.handle(&clue).unwrap()).unwrap().solved);
+        assert!((100..300).contains(&after.as_millis()));
+
+        game.apply(Event::Pick { clue: (1, 2).into() }).unwrap();
+        game.apply(Event::AwardPoints { contestant: 0, points: 500, reason: None, clue: None }).ok();
+        let state = game.get_game_state();
+        let GamePhase::Waging { min_wager, .. } = state.phase else { panic!("{:?}", state.phase) };
+        let Some(Decision { event: Event::SetWage { points }, .. }) = Bot::new(0, config.clone(), 1).decide(&state) else {
+            panic!()
+        };
+        assert_eq!(points, (state.contestants[0].points / 2).max(min_wager));
+
+        game.apply(Event::SetWage { points }).unwrap();
+        game.apply(Event::FinishClue { confirmed: true }).unwrap();
+        game.apply(Event::FinishClue { confirmed: true }).unwrap();
+        game.apply(Event::Pick { clue: (0, 0).into() }).unwrap();
+        game.apply(Event::ClueFullyShown).unwrap();
+        let state = game.get_game_state();
+        assert!(Bot::new(1, BotConfig { buzz_probability: 0.0, ..config.clone() }, 1).decide(&state).is_none());
+        let buzz = Bot::new(1, config, 1).decide(&state).unwrap();
+        assert!(matches!(buzz.event, Event::Buzz { contestant: 1 }));
+    }
+}
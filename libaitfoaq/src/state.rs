@@ -1,52 +1,435 @@
-use serde::{Deserialize, Serialize};
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
 
-pub type Points = i32; // JS is limited to 32bit
+use crate::events::EventKind;
+
+/// Serialized as a plain JSON number. JS only represents integers up to 2^53
+/// exactly, so scores are kept within [MAX_POINTS].
+pub type Points = i64;
+/// The largest score, or score deficit, a contestant can have
+pub const MAX_POINTS: Points = (1 << 53) - 1;
+/// How many parts can follow a clue, see [Clue::followup]
+pub const MAX_FOLLOWUPS: usize = 3;
 pub type ContestantHandle = usize;
 pub type ClueHandle = (usize, usize);
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+/// A clue by its position, or by its [Clue::id] which stays the same when
+/// the board is edited or shuffled. Resolved when applied, see
+/// [Board::resolve].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+pub enum ClueRef {
+    ByHandle(ClueHandle),
+    ById(String),
+}
+
+impl From<ClueHandle> for ClueRef {
+    fn from(handle: ClueHandle) -> Self {
+        Self::ByHandle(handle)
+    }
+}
+
+/// Serialized with an additional `phase_name`, see [GamePhase::name]. It's
+/// only added, `phase` keeps its shape and `phase_name` is ignored when
+/// deserializing, so older clients and journals keep working.
+#[derive(Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema), schemars(transform = with_phase_name))]
 pub struct GameState {
     pub contestants: Vec<Contestant>,
     pub board: Board,
     pub phase: GamePhase,
     pub options: Options,
+    /// Computed from the board, older states don't have it
+    #[serde(default)]
+    pub progress: BoardSummary,
+    /// Only set in the state right after the last clue of this category was
+    /// finished, so boards can celebrate it exactly once
+    #[serde(default)]
+    pub newly_completed_category: Option<usize>,
+    /// See [Event::StartGame](crate::events::Event::StartGame)
+    #[serde(default)]
+    pub locked: bool,
+    /// If the lock was lifted, see [Event::Unlock](crate::events::Event::Unlock)
+    #[serde(default)]
+    pub unlocked: bool,
+    /// If the hint of the clue is shown in [GamePhase::Clue],
+    /// [GamePhase::Buzzing] and [GamePhase::Buzzed] because of
+    /// [Options::auto_reveal_hint_after]. The [GamePhase::Resolution] of such
+    /// a clue starts with `show_hint`.
+    #[serde(default)]
+    pub hint_revealed: bool,
+    /// Only computed in [GamePhase::Score], see [Standing::rank]
+    #[serde(default)]
+    pub final_standings: Vec<Standing>,
+    /// The contestant of [GamePhase::Waging] disconnected. The phase stays
+    /// as it is for when they return, until the moderator wagers 0 on their
+    /// behalf or abandons the clue.
+    #[serde(default)]
+    pub wager_stalled: bool,
+    /// Which part of a chain of [Clue::followup]s is played, 0 for the
+    /// picked clue itself. See [GameState::current_clue].
+    #[serde(default)]
+    pub followup: usize,
+    /// See [Game::legal_events](crate::Game::legal_events)
+    #[serde(default)]
+    pub legal_events: Vec<EventKind>,
+    /// Every score changed by hand so far, in order
+    #[serde(default)]
+    pub corrections: Vec<Correction>,
+    /// What a correct answer to the clue being played wins, less than its
+    /// points after rejected answers with [Options::decay_on_rejection].
+    /// Only while it's played, in [GamePhase::Clue], [GamePhase::Buzzing]
+    /// and [GamePhase::Buzzed].
+    #[serde(default)]
+    pub current_value: Option<Points>,
+    /// How often each contestant buzzed first in [GamePhase::Practice], by
+    /// [ContestantHandle]. Empty in every other phase, practice never
+    /// counts towards the scores.
+    #[serde(default)]
+    pub practice_wins: Vec<u32>,
+}
+
+impl GameState {
+    /// The clue, or part of a chain, the phase is about
+    pub fn current_clue(&self) -> Option<&Clue> {
+        self.board.part(&self.phase.clue()?, self.followup).ok()
+    }
+
+    /// The state as contestants and the audience may see it. With
+    /// [Options::hide_values] or [Clue::value_hidden] the points of clues
+    /// that weren't picked yet are zeroed. Typed answers are only for the
+    /// moderator.
+    pub fn redacted(&self) -> Self {
+        let mut state = self.clone();
+        if let GamePhase::Buzzed { answer, .. } = &mut state.phase {
+            *answer = None;
+        }
+        let picked = self.phase.clue();
+        for (c, category) in state.board.categories.iter_mut().enumerate() {
+            for (q, clue) in category.clues.iter_mut().enumerate() {
+                if clue.is_value_hidden(&self.options) && picked != Some((c, q)) {
+                    clue.points = 0;
+                }
+            }
+        }
+        state
+    }
+
+    /// The contestants in the order they're shown, the order they connected
+    /// in until they're reordered
+    pub fn display_order(&self) -> Vec<ContestantHandle> {
+        let mut order: Vec<ContestantHandle> = (0..self.contestants.len()).collect();
+        order.sort_by_key(|&c| (self.contestants[c].display_position, c));
+        order
+    }
+}
+
+/// The derived schema only knows the fields that are deserialized
+#[cfg(feature = "schema")]
+fn with_phase_name(schema: &mut schemars::Schema) {
+    if let Some(properties) = schema.get_mut("properties").and_then(|p| p.as_object_mut()) {
+        properties.insert("phase_name".to_owned(), schemars::json_schema!({"type": "string"}).into());
+    }
+    if let Some(required) = schema.get_mut("required").and_then(|r| r.as_array_mut()) {
+        required.push("phase_name".into());
+    }
+}
+
+impl Serialize for GameState {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("GameState", 17)?;
+        state.serialize_field("contestants", &self.contestants)?;
+        state.serialize_field("board", &self.board)?;
+        state.serialize_field("phase", &self.phase)?;
+        state.serialize_field("phase_name", self.phase.name())?;
+        state.serialize_field("options", &self.options)?;
+        state.serialize_field("progress", &self.progress)?;
+        state.serialize_field("newly_completed_category", &self.newly_completed_category)?;
+        state.serialize_field("locked", &self.locked)?;
+        state.serialize_field("unlocked", &self.unlocked)?;
+        state.serialize_field("hint_revealed", &self.hint_revealed)?;
+        state.serialize_field("final_standings", &self.final_standings)?;
+        state.serialize_field("wager_stalled", &self.wager_stalled)?;
+        state.serialize_field("followup", &self.followup)?;
+        state.serialize_field("legal_events", &self.legal_events)?;
+        state.serialize_field("corrections", &self.corrections)?;
+        state.serialize_field("current_value", &self.current_value)?;
+        state.serialize_field("practice_wins", &self.practice_wins)?;
+        state.end()
+    }
+}
+
+/// A score changed by hand, see [Event::AwardPoints](crate::events::Event::AwardPoints)
+/// and [Event::AdjustScores](crate::events::Event::AdjustScores)
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Correction {
+    pub contestant: ContestantHandle,
+    /// Negative when points were revoked
+    pub points: Points,
+    /// The clue the points were for, if the moderator said so
+    pub clue: Option<ClueHandle>,
+    pub reason: Option<String>,
+}
+
+/// A place on the final scoreboard
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Standing {
+    /// 1 for the winner(s), contestants with equal points share a rank and
+    /// the next one is skipped
+    pub rank: usize,
+    pub contestant: ContestantHandle,
+    pub points: Points,
+}
+
+impl Standing {
+    /// Best first. Ties are listed in the order the contestants connected, so
+    /// every screen shows the same order.
+    pub fn rank(contestants: &[Contestant]) -> Vec<Self> {
+        let mut standings: Vec<Self> = contestants
+            .iter()
+            .enumerate()
+            .map(|(contestant, c)| Self {
+                rank: 1 + contestants.iter().filter(|o| o.points > c.points).count(),
+                contestant,
+                points: c.points,
+            })
+            .collect();
+        standings.sort_by_key(|s| (s.rank, s.contestant));
+        standings
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Board {
     pub categories: Vec<Category>,
 }
 
 impl Board {
     pub fn get(&self, clue: &ClueHandle) -> Result<&Clue, super::Error> {
-        Ok(self
+        self
             .categories
             .get(clue.0)
             .ok_or(super::Error::ClueNotFound)?
             .clues
             .get(clue.1)
-            .ok_or(super::Error::ClueNotFound)?)
+            .ok_or(super::Error::ClueNotFound)
     }
 
     pub fn get_mut(&mut self, clue: &ClueHandle) -> Result<&mut Clue, super::Error> {
-        Ok(self
+        self
             .categories
             .get_mut(clue.0)
             .ok_or(super::Error::ClueNotFound)?
             .clues
             .get_mut(clue.1)
-            .ok_or(super::Error::ClueNotFound)?)
+            .ok_or(super::Error::ClueNotFound)
+    }
+
+    /// The clue itself for `followup` 0, otherwise the part of its chain, see
+    /// [Clue::followup]
+    pub fn part(&self, clue: &ClueHandle, followup: usize) -> Result<&Clue, super::Error> {
+        let mut part = self.get(clue)?;
+        for _ in 0..followup {
+            part = part.followup.as_deref().ok_or(super::Error::ClueNotFound)?;
+        }
+        Ok(part)
+    }
+
+    /// Where the clue with this [Clue::id] is right now
+    pub fn resolve(&self, id: &str) -> Option<ClueHandle> {
+        self.categories.iter().enumerate().find_map(|(c, category)| {
+            let q = category.clues.iter().position(|clue| clue.id.as_deref() == Some(id))?;
+            Some((c, q))
+        })
+    }
+
+    pub fn handle(&self, clue: &ClueRef) -> Result<ClueHandle, super::Error> {
+        match clue {
+            ClueRef::ByHandle(handle) => Ok(*handle),
+            ClueRef::ById(id) => self.resolve(id).ok_or(super::Error::ClueNotFound),
+        }
     }
 
     pub fn mark_solved(&mut self, clue: &ClueHandle) -> Result<(), super::Error> {
         self.get_mut(clue)?.solved = true;
         Ok(())
     }
+
+    /// The points of the most valuable clue, played or not
+    pub fn most_valuable(&self) -> Points {
+        self.categories
+            .iter()
+            .flat_map(|c| c.clues.iter())
+            .map(|c| c.points)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// How many clues haven't been played yet
+    pub fn remaining(&self) -> usize {
+        self.categories
+            .iter()
+            .flat_map(|c| c.clues.iter())
+            .filter(|c| !c.solved)
+            .count()
+    }
+
+    /// Reorders the categories with a Fisher-Yates shuffle. The generator is
+    /// spelled out here so the order never changes for a seed, otherwise
+    /// journals wouldn't replay the same way.
+    pub fn shuffle(&mut self, seed: u64) {
+        let mut rng = SplitMix64(seed);
+        for i in (1..self.categories.len()).rev() {
+            let j = rng.below(i + 1);
+            self.categories.swap(i, j);
+        }
+    }
+
+    /// Checks what the game relies on but doesn't enforce while loading:
+    /// at least one category, the same number of clues in every category,
+    /// unique ids and playable clues with no more than [MAX_FOLLOWUPS]
+    pub fn validate(&self) -> Result<(), super::Error> {
+        let Some(first) = self.categories.first() else {
+            return Err(super::Error::EmptyBoard);
+        };
+        if first.clues.is_empty() {
+            return Err(super::Error::EmptyBoard);
+        }
+        if self.categories.iter().any(|c| c.clues.len() != first.clues.len()) {
+            return Err(super::Error::UnevenBoard);
+        }
+        let mut category_ids = std::collections::HashSet::new();
+        let mut clue_ids = std::collections::HashSet::new();
+        let categories = self.categories.iter().filter_map(|c| c.id.as_deref()).all(|id| category_ids.insert(id));
+        let clues = self.categories.iter().flat_map(|c| c.clues.iter()).filter_map(|c| c.id.as_deref()).all(|id| clue_ids.insert(id));
+        if !categories || !clues {
+            return Err(super::Error::DuplicateId);
+        }
+        for clue in self.categories.iter().flat_map(|c| c.clues.iter()) {
+            if clue.chain().count() > MAX_FOLLOWUPS + 1 {
+                return Err(super::Error::ChainTooLong);
+            }
+            for part in clue.chain() {
+                if part.clue.trim().is_empty() {
+                    return Err(super::Error::EmptyClue);
+                }
+                if !(1..=MAX_POINTS).contains(&part.points) {
+                    return Err(super::Error::InvalidPoints);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// A small board to try the game without writing one first. The second
+    /// category's last clue can be wagered on, so every phase can be seen.
+    pub fn demo() -> Self {
+        let clue = |clue: &str, response: &str, hint: &str, points| Clue {
+            id: None,
+            clue: clue.to_owned(),
+            response: response.to_owned(),
+            hint: hint.to_owned(),
+            points,
+            can_wager: false,
+            exclusive: false,
+            solved: false,
+            followup: None,
+            value_hidden: false,
+            value_revealed: false,
+        };
+        let category = |title: &str, clues| Category { title: title.to_owned(), id: None, clues };
+        let mut board = Board {
+            categories: vec![
+                category("Planets", vec![
+                    clue("The largest planet of the solar system", "What is Jupiter?", "It has at least 95 moons", 100),
+                    clue("The planet closest to the sun", "What is Mercury?", "A year there takes 88 days", 200),
+                    clue("This planet rotates on its side", "What is Uranus?", "Its axis is tilted by 98°", 300),
+                ]),
+                category("Programming", vec![
+                    clue("The language this game is written in", "What is Rust?", "Its mascot is a crab named Ferris", 100),
+                    clue("The format boards are written in", "What is JSON?", "JavaScript Object Notation", 200),
+                    clue("Tim Berners-Lee invented this markup language", "What is HTML?", "HyperText Markup Language, 1993", 300),
+                ]),
+                category("Animals", vec![
+                    clue("The only mammals that can truly fly", "What are bats?", "Order Chiroptera", 100),
+                    clue("This bird can't fly, but swims fast", "What is a penguin?", "Up to 36 km/h", 200),
+                    clue("The largest animal that ever lived", "What is the blue whale?", "Up to 30 m long", 300),
+                ]),
+            ],
+        };
+        let daily_double = &mut board.categories[1].clues[2];
+        daily_double.can_wager = true;
+        daily_double.exclusive = true;
+        board
+    }
+
+    pub fn summary(&self) -> BoardSummary {
+        let mut summary = BoardSummary::default();
+        for category in &self.categories {
+            let remaining = category.clues.iter().filter(|c| !c.solved).count();
+            summary.clues_total += category.clues.len();
+            summary.clues_remaining += remaining;
+            summary.remaining_per_category.push(remaining);
+            summary.completed_categories.push(remaining == 0 && !category.clues.is_empty());
+        }
+        summary.clues_solved = summary.clues_total - summary.clues_remaining;
+        summary.percent_solved = match summary.clues_total {
+            0 => 0,
+            total => summary.clues_solved * 100 / total,
+        };
+        summary
+    }
+}
+
+/// splitmix64, enough for shuffling and bots while staying reproducible
+#[derive(Clone, Debug)]
+pub(crate) struct SplitMix64(pub u64);
+
+impl SplitMix64 {
+    pub fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// In `0..n`
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    /// In `0.0..1.0`
+    #[cfg_attr(not(feature = "sim"), allow(dead_code))]
+    pub fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// How far through its [Board] a game is, e.g. for a progress indicator
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BoardSummary {
+    pub clues_total: usize,
+    pub clues_solved: usize,
+    pub clues_remaining: usize,
+    /// Rounded down
+    pub percent_solved: usize,
+    /// In the order of [Board::categories]
+    pub remaining_per_category: Vec<usize>,
+    /// In the order of [Board::categories], empty categories are never
+    /// completed
+    #[serde(default)]
+    pub completed_categories: Vec<bool>,
 }
 
 impl Board {
     pub fn clue_rows(&self) -> Vec<Vec<(ClueHandle, Clue)>> {
-        if self.categories.len() == 0 {
+        if self.categories.is_empty() {
             return vec![];
         }
         (0..self.categories[0].clues.len())
@@ -55,13 +438,22 @@ impl Board {
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Category {
     pub title: String,
+    /// Stays the same when the board is edited, unlike its position
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
     pub clues: Vec<Clue>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Clue {
+    /// Stays the same when the board is edited, unlike a [ClueHandle], see
+    /// [ClueRef]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
     /// The prompt for players, in the form of an answer
     pub clue: String,
     /// The expected answer from players
@@ -81,9 +473,45 @@ pub struct Clue {
     pub exclusive: bool,
     /// If this clue was already played.
     pub solved: bool,
+    /// Shown to the same contestant right after a correct answer, for its
+    /// own points. Wagers, `exclusive` and `solved` only count for the
+    /// picked clue.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub followup: Option<Box<Clue>>,
+    /// Like [Options::hide_values] but for this clue only, so all clues of a
+    /// category can look the same until one is picked
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub value_hidden: bool,
+    /// Set once the clue is picked, its points are shown from then on
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub value_revealed: bool,
 }
 
 impl Clue {
+    /// If the points are kept from contestants, see [GameState::redacted]
+    pub fn is_value_hidden(&self, options: &Options) -> bool {
+        !self.solved && (options.hide_values || (self.value_hidden && !self.value_revealed))
+    }
+}
+
+/// The editable text fields of a [Clue], see
+/// [Event::EditClue](crate::events::Event::EditClue).
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ClueField {
+    Clue,
+    Response,
+    Hint,
+    Points,
+}
+
+impl Clue {
+    /// The clue itself and all followups
+    pub fn chain(&self) -> impl Iterator<Item = &Clue> {
+        std::iter::successors(Some(self), |clue| clue.followup.as_deref())
+    }
+
     pub fn is_long(&self) -> bool {
         let lines: Vec<_> = self.clue.lines().collect();
         // 3+ lines
@@ -99,6 +527,7 @@ impl Clue {
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Contestant {
     /// Can be renamed by the moderator and is the only name that should be
     /// shown during the game. If None, the name_hint can be used instead.
@@ -113,14 +542,67 @@ pub struct Contestant {
     /// More than one player can be indicated at a time, but not during regular
     /// gameplay.
     pub indicate: bool,
-    /// If the controller is still connected to the game
+    /// If the controller is still connected to the game. This is not part of
+    /// the game's history, see [Event::is_presence](crate::events::Event::is_presence),
+    /// and only means something while the game is played live.
     pub connected: bool,
+    /// Derived for [GameState], see [Contestant::display_names]
+    #[serde(default)]
+    pub display_name: String,
+    /// Where they're shown, counting from 0, see
+    /// [Event::ReorderContestants](crate::events::Event::ReorderContestants)
+    /// and [GameState::display_order]
+    #[serde(default)]
+    pub display_position: usize,
+}
+
+impl Contestant {
+    /// The name, or the hint until there is one
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.name_hint)
+    }
+
+    /// Everyone's score after the `changes` of
+    /// [Event::AdjustScores](crate::events::Event::AdjustScores), applied
+    /// in order
+    pub fn adjusted_scores(contestants: &[Contestant], changes: &[(ContestantHandle, Points)]) -> Result<Vec<Points>, crate::Error> {
+        let mut scores: Vec<Points> = contestants.iter().map(|c| c.points).collect();
+        let mut invalid = vec![];
+        for (entry, &(contestant, points)) in changes.iter().enumerate() {
+            match scores.get_mut(contestant) {
+                Some(score) => match crate::add_points(*score, points) {
+                    Ok(new) => *score = new,
+                    Err(_) => invalid.push(entry),
+                },
+                None => invalid.push(entry),
+            }
+        }
+        match invalid.is_empty() {
+            true => Ok(scores),
+            false => Err(crate::Error::InvalidAdjustments { entries: invalid }),
+        }
+    }
+
+    /// What to show for each contestant. Names that are the same, ignoring
+    /// case, get a suffix from the second one on, like "Alex (2)".
+    pub fn display_names(contestants: &[Contestant]) -> Vec<String> {
+        let keys: Vec<String> = contestants.iter().map(|c| c.name().trim().to_lowercase()).collect();
+        contestants
+            .iter()
+            .enumerate()
+            .map(|(i, c)| match keys[..i].iter().filter(|k| **k == keys[i]).count() {
+                0 => c.name().to_owned(),
+                earlier => format!("{} ({})", c.name(), earlier + 1),
+            })
+            .collect()
+    }
 }
 
 /// The phase a [Game](crate::Game) is in. Transitians between states are
 /// documented on [Event](crate::events::Event). Use
 /// [Game::apply](crate::Game::apply) to transition.
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum GamePhase {
     /// Loading questions and configuration. The main screen is likely not
     /// visible yet, and contestants might not be present yet. Regardless, the
@@ -129,12 +611,30 @@ pub enum GamePhase {
     /// Players connecting, introducing themselves, and testing their
     /// controllers. The board is still hidden.
     Connecting,
+    /// Contestants racing each other on their buzzers before the game, see
+    /// [Event::StartPractice](crate::events::Event::StartPractice). Nobody
+    /// can join until it ends.
+    Practice {
+        /// The first to buzz since the last
+        /// [Event::ResetPractice](crate::events::Event::ResetPractice),
+        /// who is indicated
+        winner: Option<ContestantHandle>,
+    },
     /// Contestants picking a question from the board
     Picking { contestant: ContestantHandle },
-    /// Betting points before seeing the clue
+    /// Betting points before seeing the clue. The bounds are fixed when the
+    /// clue is picked.
     Waging {
         clue: ClueHandle,
         contestant: ContestantHandle,
+        min_wager: Points,
+        /// The contestant's score, or the most valuable clue on the board if
+        /// that is more
+        max_wager: Points,
+        /// With [Options::confirm_wagers], the wager waiting for
+        /// [Event::ConfirmWage](crate::events::Event::ConfirmWage)
+        #[serde(default)]
+        pending_wager: Option<Points>,
     },
     /// The clue/prompt is shown or played to the contestants
     Clue {
@@ -149,6 +649,13 @@ pub enum GamePhase {
     Buzzed {
         clue: ClueHandle,
         contestant: ContestantHandle,
+        /// See [Event::SubmitAnswer](crate::events::Event::SubmitAnswer)
+        #[serde(default)]
+        answer: Option<String>,
+        /// The picked clue was exclusive or waged on, so nobody else could
+        /// buzz. See [Options::exclusive_single_attempt].
+        #[serde(default)]
+        exclusive: bool,
     },
     /// A correct answer was provided or all contestants failed
     Resolution {
@@ -164,8 +671,72 @@ pub enum GamePhase {
     Score,
 }
 
+impl GamePhase {
+    /// The variant in snake_case, like `picking`. Unlike the serialized
+    /// phase it doesn't change when fields are added, so UIs can switch on it.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Preparing => "preparing",
+            Self::Connecting => "connecting",
+            Self::Practice { .. } => "practice",
+            Self::Picking { .. } => "picking",
+            Self::Waging { .. } => "waging",
+            Self::Clue { .. } => "clue",
+            Self::Buzzing { .. } => "buzzing",
+            Self::Buzzed { .. } => "buzzed",
+            Self::Resolution { .. } => "resolution",
+            Self::Score => "score",
+        }
+    }
+
+    /// The clue that is being played, if any
+    pub fn clue(&self) -> Option<ClueHandle> {
+        match self {
+            Self::Waging { clue, .. }
+            | Self::Clue { clue, .. }
+            | Self::Buzzing { clue }
+            | Self::Buzzed { clue, .. }
+            | Self::Resolution { clue, .. } => Some(*clue),
+            Self::Preparing | Self::Connecting | Self::Practice { .. } | Self::Picking { .. } | Self::Score => None,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
 pub struct Options {
+    /// If names contestants choose for themselves have to be approved by the
+    /// moderator before they are shown
+    pub approve_names: bool,
+    /// If contestants only learn what a clue is worth once it is picked, see
+    /// [GameState::redacted]. Scoring always uses the real value.
+    pub hide_values: bool,
+    /// If finishing a clue before an answer was accepted, which skips
+    /// scoring it, has to be confirmed. See
+    /// [Event::FinishClue](crate::events::Event::FinishClue).
+    pub confirm_skip: bool,
+    /// Show the hint of a clue once this many answers to it were rejected,
+    /// see [GameState::hint_revealed]. `Some(0)` shows it right away.
+    pub auto_reveal_hint_after: Option<u8>,
+    /// The percentage of a clue's value kept after each rejected answer, so
+    /// later answers are worth less, see [GameState::current_value]. More
+    /// than 100 keeps the whole value, like `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decay_on_rejection: Option<u8>,
+    /// If naming a contestant like another one, ignoring case, is refused,
+    /// see [Error::NameTaken](crate::Error::NameTaken)
+    pub unique_names: bool,
+    /// If wagers have to be confirmed before the clue is shown, see
+    /// [Event::SetWage](crate::events::Event::SetWage)
+    pub confirm_wagers: bool,
+    /// If a rejected answer to an exclusive clue, or to one that was waged
+    /// on, ends the clue like on TV, instead of letting everyone else buzz
+    pub exclusive_single_attempt: bool,
+    /// What points are called, like "stars", shown after them. See
+    /// [Options::format_points].
+    pub points_label: Option<String>,
+    pub points_format: PointsFormat,
     // pub multiple_attempts: bool, allow contestants to buzz in again after providing a wrong answer
     // pub wrong_answer_penalty: bool, deduct points on wrong anwsers
     // pub wait_for_clue: bool, wait for the clue to be finished reading/playing once before opening up for buzzing
@@ -173,6 +744,66 @@ pub struct Options {
 
 impl Default for Options {
     fn default() -> Self {
-        Options {}
+        Self {
+            approve_names: false,
+            hide_values: false,
+            confirm_skip: false,
+            auto_reveal_hint_after: None,
+            decay_on_rejection: None,
+            unique_names: true,
+            confirm_wagers: false,
+            exclusive_single_attempt: true,
+            points_label: None,
+            points_format: PointsFormat::default(),
+        }
     }
 }
+
+impl Options {
+    /// How `points` are shown to people, e.g. "€1,500" or "1500 stars".
+    /// Scores are always sent as plain numbers besides.
+    pub fn format_points(&self, points: Points) -> String {
+        let sign = if points < 0 { "-" } else { "" };
+        let digits = points.unsigned_abs().to_string();
+        let mut grouped = String::new();
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                grouped.push(',');
+            }
+            grouped.push(digit);
+        }
+        let number = match &self.points_format {
+            PointsFormat::PlainNumber => points.to_string(),
+            PointsFormat::Thousands => format!("{}{}", sign, grouped),
+            PointsFormat::Currency { symbol, position: SymbolPosition::Before } => format!("{}{}{}", sign, symbol, grouped),
+            PointsFormat::Currency { symbol, position: SymbolPosition::After } => format!("{}{} {}", sign, grouped, symbol),
+        };
+        match self.points_label.as_deref().map(str::trim) {
+            Some(label) if !label.is_empty() => format!("{} {}", number, label),
+            _ => number,
+        }
+    }
+}
+
+/// How [Options::format_points] writes the number
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum PointsFormat {
+    /// Like `1500`
+    #[default]
+    PlainNumber,
+    /// Like `1,500`
+    Thousands,
+    /// Grouped like [PointsFormat::Thousands], like `€1,500` or `1,500 €`
+    Currency { symbol: String, position: SymbolPosition },
+}
+
+/// Where the symbol of [PointsFormat::Currency] goes
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum SymbolPosition {
+    Before,
+    After,
+}
@@ -13,14 +13,14 @@ prop_compose! {
         w in prop::bool::weighted(0.1),
         e in prop::bool::weighted(0.1),
     ) -> Clue {
-        Clue { clue: c, response: a, hint: h, points: p, can_wager: w, exclusive: e, solved: false }
+        Clue { id: None, clue: c, response: a, hint: h, points: p, can_wager: w, exclusive: e, solved: false, followup: None, value_hidden: false, value_revealed: false }
     }
 }
 prop_compose! {
     fn arb_category(nclues: usize)
                 (title in ".*", clues in prop::collection::vec(arb_clue(), prop::collection::size_range(nclues)))
                 -> Category {
-        Category {title, clues}
+        Category {title, id: None, clues}
     }
 }
 prop_compose! {
@@ -40,3 +40,108 @@ proptest! {
         assert_eq!(r.board, board);
     }
 }
+
+/// A copy to try events on, without the observer
+fn fork(game: &Game) -> Game {
+    Game {
+        phase: game.phase.clone(),
+        board: game.board.clone(),
+        contestants: game.contestants.clone(),
+        options: game.options.clone(),
+        newly_completed_category: game.newly_completed_category,
+        rejected_answers: game.rejected_answers,
+        followup: game.followup,
+        locked: game.locked,
+        unlocked: game.unlocked,
+        missing_reasons: game.missing_reasons,
+        control: game.control,
+        corrections: game.corrections.clone(),
+        decayed_value: game.decayed_value,
+        practice_wins: game.practice_wins.clone(),
+        observer: None,
+    }
+}
+
+/// Every kind of event, with every parameter that could make a difference
+fn candidates(game: &Game) -> Vec<Event> {
+    let reason = || Some("correction".to_owned());
+    let mut events = vec![
+        Event::Settings(game.options.clone()),
+        Event::LoadBoard(game.board.clone()),
+        Event::ShuffleBoard { seed: 1 },
+        Event::OpenLobby,
+        Event::ConnectContestant { name_hint: "late".to_owned() },
+        Event::ImportContestants { names: vec![format!("imported {}", game.contestants.len())] },
+        Event::ReorderContestants { order: (0..game.contestants.len()).rev().collect() },
+        Event::StartPractice,
+        Event::ResetPractice,
+        Event::EndPractice,
+        Event::StartGame,
+        Event::Unlock,
+        Event::PassPick { to: None },
+        Event::SetWage { points: 0 },
+        Event::ConfirmWage,
+        Event::CancelWage,
+        Event::ClueFullyShown,
+        Event::AcceptAnswer,
+        Event::WithdrawBuzz,
+        Event::RejectAnswer,
+        Event::RevealHint,
+        Event::FinishClue { confirmed: true },
+        Event::AdjustScores { changes: vec![], reason: "bonus".to_owned() },
+    ];
+    for contestant in 0..game.contestants.len() {
+        events.extend([
+            Event::DisconnectContestant { contestant },
+            Event::ReconnectContestant { contestant },
+            Event::NameContestant { index: contestant, name: format!("renamed {}", contestant) },
+            Event::AwardPoints { contestant, points: 1, reason: reason(), clue: None },
+            Event::RevokePoints { contestant, points: 1, reason: reason(), clue: None },
+            Event::PassPick { to: Some(contestant) },
+            Event::Buzz { contestant },
+            Event::SubmitAnswer { contestant, text: "answer".to_owned() },
+        ]);
+    }
+    for (c, category) in game.board.categories.iter().enumerate() {
+        events.push(Event::EditCategory { category: c, title: "title".to_owned() });
+        for q in 0..category.clues.len() {
+            events.push(Event::EditClue { clue: (c, q), field: ClueField::Hint, value: "hint".to_owned() });
+            events.push(Event::Pick { clue: (c, q).into() });
+        }
+    }
+    events
+}
+
+proptest! {
+    #[test]
+    fn legal_events_are_the_ones_that_succeed(
+        (ncats, nclues) in (1..3usize, 1..3usize),
+        wager in any::<prop::sample::Index>(),
+        exclusive in any::<prop::sample::Index>(),
+        confirm_wagers in any::<bool>(),
+        confirm_skip in any::<bool>(),
+        steps in prop::collection::vec(any::<prop::sample::Index>(), 0..80),
+    ) {
+        let mut board = Board::demo();
+        board.categories.truncate(ncats);
+        for category in &mut board.categories {
+            category.clues.truncate(nclues);
+        }
+        board.categories[wager.index(ncats)].clues[0].can_wager = true;
+        board.categories[exclusive.index(ncats)].clues[nclues - 1].exclusive = true;
+        let mut game = Game::new();
+        game.apply(Event::Settings(Options { confirm_wagers, confirm_skip, ..Options::default() })).unwrap();
+        game.apply(Event::LoadBoard(board)).unwrap();
+        for step in steps {
+            let succeeding: Vec<Event> = candidates(&game)
+                .into_iter()
+                .filter(|event| fork(&game).apply(event.clone()).is_ok())
+                .collect();
+            let mut kinds: Vec<EventKind> = succeeding.iter().map(Event::kind).collect();
+            kinds.sort();
+            kinds.dedup();
+            prop_assert_eq!(game.legal_events(), kinds, "in {:?}", game.phase);
+            game.apply(step.get(&succeeding).clone()).unwrap();
+        }
+    }
+}
@@ -0,0 +1,250 @@
+//! Which [Event]s move the game from which [GamePhase] to which, as data.
+//! The state machine is tested against it, and the diagram of [Event] is
+//! generated from it. After changing the table regenerate the diagram with
+//! `UPDATE_TRANSITIONS=1 cargo test -p libaitfoaq transitions`.
+//!
+//! Phases are named by [GamePhase::name], events by [Event::name].
+
+#[cfg(doc)]
+use crate::{events::Event, state::GamePhase};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transition {
+    pub from: &'static str,
+    pub event: &'static str,
+    pub to: &'static str,
+}
+
+const fn t(from: &'static str, event: &'static str, to: &'static str) -> Transition {
+    Transition { from, event, to }
+}
+
+/// Events that are allowed in every phase and stay in it, left out of
+/// [TRANSITIONS] unless they can also change it. Some have other conditions, like [Event::Unlock] needing a
+/// locked game.
+pub const EVERY_PHASE: &[&str] = &[
+    "EditClue",
+    "DisconnectContestant",
+    "ReconnectContestant",
+    "NameContestant",
+    "AwardPoints",
+    "RevokePoints",
+    "AdjustScores",
+    "Unlock",
+];
+
+/// Where an event can lead depends on the clue or the board, e.g.
+/// [Event::Pick] leads to waging only for some clues
+pub const TRANSITIONS: &[Transition] = &[
+    t("preparing", "Settings", "preparing"),
+    t("preparing", "LoadBoard", "preparing"),
+    t("preparing", "ShuffleBoard", "preparing"),
+    t("preparing", "EditCategory", "preparing"),
+    t("preparing", "ImportContestants", "preparing"),
+    t("preparing", "OpenLobby", "connecting"),
+    t("connecting", "LoadBoard", "connecting"),
+    t("connecting", "OpenLobby", "connecting"),
+    t("connecting", "ConnectContestant", "connecting"),
+    t("connecting", "ImportContestants", "connecting"),
+    t("connecting", "ReorderContestants", "connecting"),
+    t("connecting", "Buzz", "connecting"),
+    t("connecting", "StartPractice", "practice"),
+    t("practice", "StartPractice", "practice"),
+    t("practice", "Buzz", "practice"),
+    t("practice", "ResetPractice", "practice"),
+    t("practice", "EndPractice", "connecting"),
+    t("connecting", "StartGame", "picking"),
+    t("picking", "PassPick", "picking"),
+    t("picking", "Pick", "waging"),
+    t("picking", "Pick", "clue"),
+    t("waging", "SetWage", "clue"),
+    t("waging", "SetWage", "waging"),
+    t("waging", "CancelWage", "waging"),
+    t("waging", "ConfirmWage", "clue"),
+    t("waging", "FinishClue", "resolution"),
+    t("clue", "ClueFullyShown", "buzzing"),
+    t("clue", "ClueFullyShown", "buzzed"),
+    t("clue", "FinishClue", "resolution"),
+    t("buzzing", "ClueFullyShown", "buzzing"),
+    t("buzzing", "Buzz", "buzzed"),
+    t("buzzing", "FinishClue", "resolution"),
+    t("buzzed", "SubmitAnswer", "buzzed"),
+    t("buzzed", "AcceptAnswer", "resolution"),
+    t("buzzed", "AcceptAnswer", "clue"),
+    t("buzzed", "WithdrawBuzz", "buzzing"),
+    t("buzzed", "DisconnectContestant", "buzzing"),
+    t("buzzed", "RejectAnswer", "buzzing"),
+    t("buzzed", "RejectAnswer", "resolution"),
+    t("buzzed", "FinishClue", "resolution"),
+    t("resolution", "RevealHint", "resolution"),
+    t("resolution", "FinishClue", "picking"),
+    t("resolution", "FinishClue", "score"),
+    t("score", "Buzz", "score"),
+];
+
+/// If `event` may lead from `from` to `to`
+pub fn allowed(from: &str, event: &str, to: &str) -> bool {
+    (from == to && EVERY_PHASE.contains(&event))
+        || TRANSITIONS.iter().any(|t| t.from == from && t.event == event && t.to == to)
+}
+
+/// A mermaid state diagram of [TRANSITIONS], events between the same two
+/// phases share an arrow
+pub fn mermaid() -> String {
+    let state = |phase: &str| {
+        let mut chars = phase.chars();
+        chars.next().map(|first| first.to_uppercase().chain(chars).collect::<String>()).unwrap_or_default()
+    };
+    let mut arrows: Vec<(&str, &str, Vec<&str>)> = vec![];
+    for transition in TRANSITIONS {
+        match arrows.iter_mut().find(|(from, to, _)| *from == transition.from && *to == transition.to) {
+            Some((_, _, events)) => events.push(transition.event),
+            None => arrows.push((transition.from, transition.to, vec![transition.event])),
+        }
+    }
+    let mut diagram = String::from("stateDiagram-v2\n    [*] --> Preparing\n");
+    for (from, to, events) in arrows {
+        diagram += &format!("    {} --> {}: {}\n", state(from), state(to), events.join(", "));
+    }
+    diagram += "    Score --> [*]\n";
+    diagram += &format!("    note left of Preparing: In every phase {}\n", EVERY_PHASE.join(", "));
+    diagram
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::Event;
+    use crate::state::{Board, ClueField, GamePhase, Options, SplitMix64};
+    use crate::Game;
+
+    const DIAGRAM: &str = "doc/transitions.mmd";
+
+    /// A plain clue, one to wager on, an exclusive one, and one with a
+    /// followup
+    fn board() -> Board {
+        let mut board = Board::demo();
+        board.categories.truncate(2);
+        for category in &mut board.categories {
+            category.clues.truncate(2);
+        }
+        board.categories[0].clues[1].can_wager = true;
+        board.categories[1].clues[0].exclusive = true;
+        let followup = board.categories[1].clues[0].clone();
+        board.categories[1].clues[1].followup = Some(Box::new(followup));
+        board
+    }
+
+    /// At least one of each kind, with parameters that fit some state
+    fn candidates(board: &Board) -> Vec<Event> {
+        let reason = || Some("correction".to_owned());
+        let mut events = vec![
+            Event::Settings(Options::default()),
+            Event::LoadBoard(board.clone()),
+            Event::ShuffleBoard { seed: 1 },
+            Event::EditClue { clue: (0, 0), field: ClueField::Hint, value: "hint".to_owned() },
+            Event::EditCategory { category: 0, title: "title".to_owned() },
+            Event::OpenLobby,
+            Event::ConnectContestant { name_hint: "late".to_owned() },
+            Event::ImportContestants { names: vec!["imported".to_owned()] },
+            Event::DisconnectContestant { contestant: 1 },
+            Event::ReconnectContestant { contestant: 1 },
+            Event::ReorderContestants { order: vec![1, 0] },
+            Event::NameContestant { index: 0, name: "name".to_owned() },
+            Event::AwardPoints { contestant: 0, points: 10, reason: reason(), clue: None },
+            Event::RevokePoints { contestant: 0, points: 10, reason: reason(), clue: None },
+            Event::AdjustScores { changes: vec![(0, 10), (1, -10)], reason: "bonus".to_owned() },
+            Event::StartPractice,
+            Event::ResetPractice,
+            Event::EndPractice,
+            Event::StartGame,
+            Event::Unlock,
+            Event::PassPick { to: None },
+            Event::SetWage { points: 0 },
+            Event::SetWage { points: 5 },
+            Event::ConfirmWage,
+            Event::CancelWage,
+            Event::ClueFullyShown,
+            Event::AcceptAnswer,
+            Event::WithdrawBuzz,
+            Event::RejectAnswer,
+            Event::RevealHint,
+            Event::FinishClue { confirmed: true },
+        ];
+        for contestant in [0, 1] {
+            events.push(Event::Buzz { contestant });
+            events.push(Event::SubmitAnswer { contestant, text: "answer".to_owned() });
+        }
+        for (c, category) in board.categories.iter().enumerate() {
+            events.extend((0..category.clues.len()).map(|q| Event::Pick { clue: (c, q).into() }));
+        }
+        events
+    }
+
+    fn replay(events: &[Event]) -> Game {
+        let mut game = Game::new();
+        for event in events {
+            game.apply(event.clone()).expect("replays the same");
+        }
+        game
+    }
+
+    #[test]
+    fn the_game_follows_the_transitions() {
+        let board = board();
+        let candidates = candidates(&board);
+        let mut seen = vec![];
+        for seed in 0..16 {
+            let mut rng = SplitMix64(seed);
+            let mut script = vec![Event::LoadBoard(board.clone())];
+            if seed % 2 == 1 {
+                script.push(Event::Settings(Options { confirm_wagers: true, ..Options::default() }));
+            }
+            // taken from the back
+            let mut setup = vec![
+                Event::ConnectContestant { name_hint: "b".to_owned() },
+                Event::ConnectContestant { name_hint: "a".to_owned() },
+                Event::OpenLobby,
+            ];
+            for _ in 0..100 {
+                let pending = |phase: &GamePhase| matches!(phase, GamePhase::Waging { pending_wager: Some(_), .. });
+                let phase = replay(&script).get_game_state().phase;
+                let from = phase.name();
+                let mut advancing = vec![];
+                for event in &candidates {
+                    let Ok(state) = replay(&script).apply(event.clone()) else { continue };
+                    let to = state.phase.name();
+                    assert!(allowed(from, event.name(), to), "{} --{}--> {} isn't listed", from, event.name(), to);
+                    seen.push((from, event.name(), to));
+                    // so are wagers waiting to be confirmed
+                    if to != from || pending(&state.phase) != pending(&phase) {
+                        advancing.push(event.clone());
+                    }
+                }
+                let next = match (setup.pop(), from) {
+                    (_, "score") => break,
+                    (Some(event), _) => event,
+                    (None, _) => advancing.swap_remove(rng.below(advancing.len())),
+                };
+                script.push(next);
+            }
+            assert_eq!(replay(&script).get_game_state().phase.name(), "score");
+        }
+        for transition in TRANSITIONS {
+            assert!(seen.contains(&(transition.from, transition.event, transition.to)), "{:?} never happened", transition);
+        }
+        for event in EVERY_PHASE {
+            assert!(seen.iter().any(|(_, e, _)| e == event), "{} never happened", event);
+        }
+    }
+
+    #[test]
+    fn the_diagram_is_up_to_date() {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(DIAGRAM);
+        if std::env::var_os("UPDATE_TRANSITIONS").is_some() {
+            std::fs::write(&path, mermaid()).unwrap();
+        }
+        let checked_in = std::fs::read_to_string(&path).unwrap_or_default();
+        assert!(checked_in == mermaid(), "{} is outdated, see the docs of this module", DIAGRAM);
+    }
+}
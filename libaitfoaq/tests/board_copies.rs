@@ -0,0 +1,95 @@
+//! Counts how often applying [Event::LoadBoard] copies the board. It has its
+//! own test binary, since the counting allocator replaces the global one.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use libaitfoaq::events::Event;
+use libaitfoaq::state::{Board, Category, Clue, Points};
+use libaitfoaq::Game;
+
+/// Counts the allocations of the current thread, each test runs on its own
+struct Counting;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|a| a.set(a.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(|a| a.get());
+    f();
+    ALLOCATIONS.with(|a| a.get()) - before
+}
+
+fn board(categories: usize, clues: usize) -> Board {
+    Board {
+        categories: (1..=categories)
+            .map(|c| Category {
+                title: format!("Category {}", c),
+                id: None,
+                clues: (1..=clues)
+                    .map(|q| Clue {
+                        id: None,
+                        clue: format!("clue {}", q),
+                        response: format!("response {}", q),
+                        hint: format!("hint {}", q),
+                        points: 100 * q as Points,
+                        can_wager: false,
+                        exclusive: false,
+                        solved: false,
+                        followup: None,
+                        value_hidden: false,
+                        value_revealed: false,
+                    })
+                    .collect(),
+            })
+            .collect(),
+    }
+}
+
+/// [Game::get_game_state] copies the board into every returned state, which
+/// is the one copy left when applying an owned board. What's avoided is the
+/// game's own copy: [Game::apply] moves the board in, [Game::apply_ref]
+/// clones it only once the event is allowed.
+#[test]
+fn boards_are_copied_once_to_be_kept() {
+    let event = Event::LoadBoard(board(8, 10));
+    let Event::LoadBoard(board) = &event else { unreachable!() };
+    let copy = allocations(|| drop(std::hint::black_box(board.clone())));
+
+    let mut g = Game::default();
+    g.set_observer(Box::new(|_, _| {}));
+    let applied = allocations(|| drop(g.apply_ref(&event).unwrap()));
+    // once to keep it and once for the returned state
+    assert!(applied < 2 * copy + copy / 10, "{} allocations, a board takes {}", applied, copy);
+    assert_eq!(g.get_game_state().board, *board);
+
+    // owned boards are moved, only the returned state is a copy
+    let mut g = Game::default();
+    g.set_observer(Box::new(|_, _| {}));
+    let owned = Event::LoadBoard(board.clone());
+    let applied = allocations(|| drop(g.apply(owned).unwrap()));
+    assert!(applied < copy + copy / 10, "{} allocations, a board takes {}", applied, copy);
+    assert_eq!(g.get_game_state().board, *board);
+
+    // rejected events copy nothing
+    g.apply(Event::OpenLobby).unwrap();
+    g.apply(Event::ConnectContestant { name_hint: "pi-1".to_owned() }).unwrap();
+    g.apply(Event::StartGame).unwrap();
+    let rejected = allocations(|| assert!(g.apply_ref(&event).is_err()));
+    assert!(rejected < copy / 10, "{} allocations", rejected);
+}
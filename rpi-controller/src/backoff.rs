@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+/// Delays between reconnection attempts, doubling from `min` up to `max`
+#[derive(Debug)]
+pub struct Backoff {
+    min: Duration,
+    max: Duration,
+    current: Duration,
+    attempts: u32,
+}
+
+impl Backoff {
+    pub fn new(min: Duration, max: Duration) -> Self {
+        Self { min, max, current: min, attempts: 0 }
+    }
+
+    /// The delay before the next attempt, without jitter
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.current;
+        self.current = (self.current * 2).min(self.max);
+        self.attempts += 1;
+        delay
+    }
+
+    /// Starts over at the minimum, e.g. after a successful connection
+    pub fn reset(&mut self) {
+        self.current = self.min;
+        self.attempts = 0;
+    }
+
+    /// How many delays were handed out since the last reset
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// The delay before the next attempt, randomly shortened by up to half
+    /// so handsets that lost the server at the same time don't all come back
+    /// at the same time
+    pub fn next_jittered(&mut self) -> Duration {
+        jitter(self.next_delay(), rand::random())
+    }
+}
+
+/// Scales `delay` into `[delay/2, delay)` with `fraction` in `[0, 1)`
+fn jitter(delay: Duration, fraction: f64) -> Duration {
+    delay.mul_f64(0.5 + fraction / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backoff() -> Backoff {
+        Backoff::new(Duration::from_millis(500), Duration::from_secs(30))
+    }
+
+    #[test]
+    fn doubles_up_to_the_cap() {
+        let mut backoff = backoff();
+        let delays: Vec<_> = (0..9).map(|_| backoff.next_delay().as_millis()).collect();
+        assert_eq!(delays, [500, 1000, 2000, 4000, 8000, 16000, 30000, 30000, 30000]);
+        assert_eq!(backoff.attempts(), 9);
+        backoff.reset();
+        assert_eq!(backoff.attempts(), 0);
+        assert_eq!(backoff.next_delay(), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds() {
+        let delay = Duration::from_secs(4);
+        assert_eq!(jitter(delay, 0.0), Duration::from_secs(2));
+        assert!(jitter(delay, 0.999) < delay);
+        for _ in 0..100 {
+            let delay = backoff().next_jittered();
+            assert!(delay >= Duration::from_millis(250) && delay < Duration::from_millis(500));
+        }
+    }
+}
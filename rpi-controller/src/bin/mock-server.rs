@@ -0,0 +1,55 @@
+//! Runs the mock server on its own, to develop the controller without the
+//! real server. Every line on stdin is the new phase, as the server encodes
+//! it, e.g. `"Score"` or `{"Picking":{"contestant":0}}`.
+
+#[allow(dead_code)]
+#[path = "../mock_server.rs"]
+mod mock_server;
+
+use mock_server::{Game, MockServer};
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let address = std::env::args().nth(1).unwrap_or_else(|| "127.0.0.1:3000".to_owned());
+    let address = match address.parse() {
+        Ok(address) => address,
+        Err(e) => {
+            eprintln!("invalid address {:?}: {}", address, e);
+            std::process::exit(2);
+        },
+    };
+    let server = match MockServer::bind(address, Game::default()).await {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("can't listen on {}: {}", address, e);
+            std::process::exit(1);
+        },
+    };
+    println!("listening on {}", server.uri());
+
+    let (lines_tx, mut lines) = tokio::sync::mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lines().map_while(Result::ok) {
+            if lines_tx.send(line).is_err() {
+                return;
+            }
+        }
+    });
+    let mut seen = 0;
+    let mut poll = tokio::time::interval(std::time::Duration::from_millis(100));
+    loop {
+        tokio::select! {
+            line = lines.recv() => match line.as_deref().map(serde_json::from_str) {
+                Some(Ok(phase)) => server.update(|game| game.phase = phase),
+                Some(Err(e)) => eprintln!("not a phase: {}", e),
+                None => return,
+            },
+            _ = poll.tick() => server.read(|game| {
+                for input in &game.inputs[seen..] {
+                    println!("received {}", input);
+                }
+                seen = game.inputs.len();
+            }),
+        }
+    }
+}
@@ -0,0 +1,295 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::handset::{Flash, PinTiples};
+
+pub const DEFAULT_PATH: &str = "/etc/aitfoaq-controller.toml";
+
+/// The controller configuration, read from a TOML file. Every value has a
+/// default, so an empty file (or none at all) is a valid configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// how often the handset pins are read
+    pub update_hertz: u64,
+    /// switch changes within this window after a change are ignored
+    pub debounce_ms: u64,
+    /// the presence line has to be stable this long, plugging in a handset
+    /// bounces
+    pub presence_settle_ms: u64,
+    /// send buzzes in every phase instead of only when the game accepts
+    /// them, for debugging
+    pub always_send: bool,
+    /// a press while the connection is lost is sent once it's back, if that
+    /// takes no longer than this
+    pub buzz_buffer_ms: u64,
+    /// the LEDs flash when buzzing opens, before they blink until someone
+    /// buzzed
+    pub buzzing_flash: Flash,
+    pub server: Server,
+    pub identity: Identity,
+    pub handsets: Vec<HandsetConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Server {
+    /// websocket address like `ws://gamehost:3000/websocket`
+    pub uri: Option<String>,
+    /// together with `ping_misses` this defines how long it takes until a
+    /// lost connection is noticed
+    pub ping_interval_ms: u64,
+    /// reconnect once this many pings within `ping_window_ms` weren't
+    /// answered, like the server does
+    pub ping_misses: usize,
+    pub ping_window_ms: u64,
+    /// a PEM file with the CA of a `wss://` server that isn't trusted by
+    /// the web roots, like a self-signed one at the venue
+    pub ca_cert: Option<PathBuf>,
+    /// accept any certificate, only for testing
+    pub insecure: bool,
+    pub reconnect: Reconnect,
+}
+
+/// Bounds of the exponential backoff between reconnection attempts
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Reconnect {
+    pub min_ms: u64,
+    pub max_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Identity {
+    /// used instead of the machine id, handsets are `<id>-1`, `<id>-2`, ...
+    pub id: Option<String>,
+    /// contestants are suggested as `<prefix>1`, `<prefix>2`, ... instead of
+    /// their handset id
+    pub name_hint_prefix: Option<String>,
+    /// where handsets remember which contestant they are across restarts
+    pub state_directory: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HandsetConfig {
+    /// the line to enter on stdin to press this handset when simulating,
+    /// defaults to its number
+    pub key: Option<String>,
+    /// required unless simulating
+    pub pins: Option<PinTiples>,
+    /// shows the contestant's name, points and the phase of the game
+    pub display: Option<DisplayConfig>,
+}
+
+/// A SSD1306 display on an I2C bus, needs the `display` feature
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayConfig {
+    pub bus: u8,
+    pub address: u16,
+    /// pixel rows, 32 or 64
+    pub height: u8,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            bus: 1,
+            address: 0x3C,
+            height: 64,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            update_hertz: 20,
+            debounce_ms: 30,
+            presence_settle_ms: 250,
+            always_send: false,
+            buzz_buffer_ms: 1000,
+            buzzing_flash: Flash::default(),
+            server: Server::default(),
+            identity: Identity::default(),
+            handsets: [
+                PinTiples::new(21, 20, 26),
+                PinTiples::new(13, 19, 16),
+                PinTiples::new(5, 6, 12),
+                PinTiples::new(0, 1, 7),
+            ]
+                .into_iter()
+                .map(|pins| HandsetConfig { pins: Some(pins), ..HandsetConfig::default() })
+                .collect(),
+        }
+    }
+}
+
+impl Default for Identity {
+    fn default() -> Self {
+        Self {
+            id: None,
+            name_hint_prefix: None,
+            state_directory: PathBuf::from("/var/lib/aitfoaq-controller"),
+        }
+    }
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Self {
+            uri: None,
+            ping_interval_ms: 1000,
+            ping_misses: 3,
+            ping_window_ms: 5000,
+            ca_cert: None,
+            insecure: false,
+            reconnect: Reconnect::default(),
+        }
+    }
+}
+
+impl Default for Reconnect {
+    fn default() -> Self {
+        Self {
+            min_ms: 500,
+            max_ms: 30_000,
+        }
+    }
+}
+
+impl Config {
+    /// Reads the configuration from `path`, or [DEFAULT_PATH] if none is
+    /// given. Only a missing default file falls back to the defaults.
+    pub fn load(path: Option<&Path>) -> Result<Self, String> {
+        let explicit = path.is_some();
+        let path = path.unwrap_or(Path::new(DEFAULT_PATH));
+        match std::fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && !explicit => Ok(Self::default()),
+            Err(e) => Err(format!("{}: {}", path.display(), e)),
+        }
+    }
+
+    /// Parses a configuration, errors name the offending key
+    pub fn parse(text: &str) -> Result<Self, String> {
+        basic_toml::from_str(text).map_err(|e| e.to_string())
+    }
+
+    pub fn update_interval(&self) -> Duration {
+        Duration::from_millis(1000 / self.update_hertz.max(1))
+    }
+
+    pub fn debounce(&self) -> Duration {
+        Duration::from_millis(self.debounce_ms)
+    }
+
+    pub fn presence_settle(&self) -> Duration {
+        Duration::from_millis(self.presence_settle_ms)
+    }
+
+    pub fn buzz_buffer(&self) -> Duration {
+        Duration::from_millis(self.buzz_buffer_ms)
+    }
+
+    /// The key to press each handset when simulating
+    pub fn keys(&self) -> Vec<String> {
+        self.handsets.iter()
+            .enumerate()
+            .map(|(i, h)| h.key.clone().unwrap_or_else(|| (i + 1).to_string()))
+            .collect()
+    }
+}
+
+impl Server {
+    /// At least a millisecond, an interval can't tick without a period
+    pub fn ping_interval(&self) -> Duration {
+        Duration::from_millis(self.ping_interval_ms.max(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"
+update_hertz = 50
+debounce_ms = 20
+buzz_buffer_ms = 500
+buzzing_flash = { times = 2, on_ms = 50 }
+
+[server]
+uri = "wss://gamehost:3000/websocket"
+ca_cert = "/etc/aitfoaq/venue-ca.pem"
+ping_interval_ms = 2000
+ping_misses = 5
+reconnect = { min_ms = 250, max_ms = 10000 }
+
+[identity]
+id = "stage-left"
+name_hint_prefix = "Podium "
+state_directory = "/tmp/aitfoaq"
+
+[[handsets]]
+pins = { switch = 21, presence = 20, led = 26 }
+key = "a"
+
+[[handsets]]
+pins = { switch = 13, presence = 19, led = 16 }
+display = { address = 61 }
+"#;
+
+    #[test]
+    fn sample_round_trips() {
+        let config = Config::parse(SAMPLE).unwrap();
+        assert_eq!(config.server.uri.as_deref(), Some("wss://gamehost:3000/websocket"));
+        assert_eq!(config.server.ca_cert, Some(PathBuf::from("/etc/aitfoaq/venue-ca.pem")));
+        assert_eq!(config.server.ping_misses, 5);
+        assert_eq!(config.buzzing_flash, Flash { times: 2, on_ms: 50, off_ms: 80 });
+        assert_eq!(config.server.reconnect, Reconnect { min_ms: 250, max_ms: 10_000 });
+        assert_eq!(config.identity.id.as_deref(), Some("stage-left"));
+        assert_eq!(config.handsets.len(), 2);
+        assert_eq!(config.keys(), ["a", "2"]);
+        assert_eq!(config.handsets[1].display, Some(DisplayConfig { address: 0x3D, ..DisplayConfig::default() }));
+        assert_eq!(config.update_interval(), Duration::from_millis(20));
+        assert_eq!(config.buzz_buffer(), Duration::from_millis(500));
+
+        let written = basic_toml::to_string(&config).unwrap();
+        assert_eq!(Config::parse(&written).unwrap(), config);
+    }
+
+    #[test]
+    fn missing_values_are_defaults() {
+        let config = Config::parse("[server]\nuri = \"ws://gamehost/websocket\"").unwrap();
+        assert_eq!(config.handsets, Config::default().handsets);
+        assert_eq!(config.server.ping_interval_ms, 1000);
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn pings_have_a_period() {
+        let config = Config::parse("[server]\nping_interval_ms = 0").unwrap();
+        assert_eq!(config.server.ping_interval(), Duration::from_millis(1));
+        assert_eq!(Config::default().server.ping_interval(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn errors_name_the_key() {
+        let error = Config::parse("[server]\nping_interval_ms = \"soon\"").unwrap_err();
+        assert!(error.contains("server.ping_interval_ms"), "{}", error);
+        let error = Config::parse("[[handsets]]\npins = { switch = 1, presence = 2 }").unwrap_err();
+        assert!(error.contains("handsets.pins"), "{}", error);
+        let error = Config::parse("[identity]\nname = \"x\"").unwrap_err();
+        assert!(error.contains("identity"), "{}", error);
+    }
+
+    #[test]
+    fn only_the_default_path_may_be_missing() {
+        let missing = Path::new("/nonexistent/aitfoaq-controller.toml");
+        assert!(Config::load(Some(missing)).is_err());
+    }
+}
@@ -0,0 +1,259 @@
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+/// Characters per line, with a blank column between the 5 pixel wide glyphs
+pub const COLUMNS: usize = 128 / 6;
+
+/// The text a handset display shows, one entry per line
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Screen {
+    pub lines: Vec<String>,
+}
+
+impl std::fmt::Display for Screen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.lines.join(" | "))
+    }
+}
+
+/// A display of a handset, like a small OLED on the podium
+pub trait Display: Send {
+    fn show(&mut self, screen: &Screen) -> Result<(), String>;
+}
+
+/// Prints the screen instead, e.g. when simulating
+pub struct PrintedDisplay {
+    pub name: String,
+}
+
+impl Display for PrintedDisplay {
+    fn show(&mut self, screen: &Screen) -> Result<(), String> {
+        println!("handset {}: display: {}", self.name, screen);
+        Ok(())
+    }
+}
+
+/// Redraws the display whenever the screen changes. Failures are only
+/// logged, the next change tries again.
+pub async fn drive(
+    mut display: Box<dyn Display>,
+    mut screen_rx: watch::Receiver<Screen>,
+    name: String,
+    cancellation_token: CancellationToken,
+) {
+    loop {
+        tokio::select! {
+            _ = cancellation_token.cancelled() => return,
+            changed = screen_rx.changed() => if changed.is_err() { return },
+        }
+        let screen = screen_rx.borrow_and_update().clone();
+        // talking to the display is slow, keep it away from the websocket
+        let drawn = tokio::task::spawn_blocking(move || {
+            let result = display.show(&screen);
+            (display, result)
+        }).await;
+        match drawn {
+            Ok((returned, result)) => {
+                display = returned;
+                if let Err(e) = result {
+                    eprintln!("handset {}: failed to update the display: {}", name, e);
+                }
+            },
+            Err(e) => {
+                eprintln!("handset {}: display crashed, not updating it anymore: {}", name, e);
+                return;
+            },
+        }
+    }
+}
+
+/// Renders the lines into SSD1306 pages: one line per page of 8 pixel rows,
+/// every byte is a column with the top pixel in the lowest bit
+#[cfg_attr(not(feature = "display"), allow(dead_code))]
+fn framebuffer(screen: &Screen, pages: usize) -> Vec<u8> {
+    let mut buffer = vec![0; 128 * pages];
+    for (page, line) in screen.lines.iter().take(pages).enumerate() {
+        for (column, c) in line.chars().take(COLUMNS).enumerate() {
+            let glyph = FONT.get((c as usize).wrapping_sub(0x20)).unwrap_or(&FONT[b'?' as usize - 0x20]);
+            let start = page * 128 + column * 6;
+            buffer[start..start + 5].copy_from_slice(glyph);
+        }
+    }
+    buffer
+}
+
+#[cfg(feature = "display")]
+mod ssd1306 {
+    use super::*;
+    use rppal::i2c::{Error, I2c};
+
+    /// A SSD1306 OLED on an I2C bus
+    pub struct Ssd1306 {
+        i2c: I2c,
+        pages: usize,
+    }
+
+    const COMMAND: u8 = 0x00;
+    const DATA: u8 = 0x40;
+
+    impl Ssd1306 {
+        /// Initializes a display with 32 or 64 pixel rows
+        pub fn new(bus: u8, address: u16, height: u8) -> Result<Self, Error> {
+            let mut i2c = I2c::with_bus(bus)?;
+            i2c.set_slave_address(address)?;
+            let com_pins = if height > 32 { 0x12 } else { 0x02 };
+            i2c.write(&[
+                COMMAND,
+                0xAE, // off
+                0xD5, 0x80, // clock divider
+                0xA8, height - 1, // multiplex ratio
+                0xD3, 0x00, // no display offset
+                0x40, // start at line 0
+                0x8D, 0x14, // charge pump on
+                0x20, 0x00, // horizontal addressing
+                0xA1, 0xC8, // not mirrored
+                0xDA, com_pins,
+                0x81, 0xCF, // contrast
+                0xD9, 0xF1, // precharge
+                0xDB, 0x40, // VCOMH deselect level
+                0xA4, // show the RAM
+                0xA6, // not inverted
+                0xAF, // on
+            ])?;
+            Ok(Self { i2c, pages: height as usize / 8 })
+        }
+    }
+
+    impl Display for Ssd1306 {
+        fn show(&mut self, screen: &Screen) -> Result<(), String> {
+            let write = |i2c: &mut I2c, bytes: &[u8]| i2c.write(bytes).map(|_| ()).map_err(|e| e.to_string());
+            write(&mut self.i2c, &[COMMAND, 0x21, 0, 127, 0x22, 0, self.pages as u8 - 1])?;
+            for chunk in framebuffer(screen, self.pages).chunks(32) {
+                write(&mut self.i2c, &[&[DATA], chunk].concat())?;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "display")]
+pub use ssd1306::Ssd1306;
+
+/// 5x7 glyphs for ASCII 0x20 to 0x7E, column by column
+const FONT: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x00, 0x00, 0x5F, 0x00, 0x00], // !
+    [0x00, 0x07, 0x00, 0x07, 0x00], // "
+    [0x14, 0x7F, 0x14, 0x7F, 0x14], // #
+    [0x24, 0x2A, 0x7F, 0x2A, 0x12], // $
+    [0x23, 0x13, 0x08, 0x64, 0x62], // %
+    [0x36, 0x49, 0x55, 0x22, 0x50], // &
+    [0x00, 0x05, 0x03, 0x00, 0x00], // '
+    [0x00, 0x1C, 0x22, 0x41, 0x00], // (
+    [0x00, 0x41, 0x22, 0x1C, 0x00], // )
+    [0x08, 0x2A, 0x1C, 0x2A, 0x08], // *
+    [0x08, 0x08, 0x3E, 0x08, 0x08], // +
+    [0x00, 0x50, 0x30, 0x00, 0x00], // ,
+    [0x08, 0x08, 0x08, 0x08, 0x08], // -
+    [0x00, 0x60, 0x60, 0x00, 0x00], // .
+    [0x20, 0x10, 0x08, 0x04, 0x02], // /
+    [0x3E, 0x51, 0x49, 0x45, 0x3E], // 0
+    [0x00, 0x42, 0x7F, 0x40, 0x00], // 1
+    [0x42, 0x61, 0x51, 0x49, 0x46], // 2
+    [0x21, 0x41, 0x45, 0x4B, 0x31], // 3
+    [0x18, 0x14, 0x12, 0x7F, 0x10], // 4
+    [0x27, 0x45, 0x45, 0x45, 0x39], // 5
+    [0x3C, 0x4A, 0x49, 0x49, 0x30], // 6
+    [0x01, 0x71, 0x09, 0x05, 0x03], // 7
+    [0x36, 0x49, 0x49, 0x49, 0x36], // 8
+    [0x06, 0x49, 0x49, 0x29, 0x1E], // 9
+    [0x00, 0x36, 0x36, 0x00, 0x00], // :
+    [0x00, 0x56, 0x36, 0x00, 0x00], // ;
+    [0x08, 0x14, 0x22, 0x41, 0x00], // <
+    [0x14, 0x14, 0x14, 0x14, 0x14], // =
+    [0x00, 0x41, 0x22, 0x14, 0x08], // >
+    [0x02, 0x01, 0x51, 0x09, 0x06], // ?
+    [0x32, 0x49, 0x79, 0x41, 0x3E], // @
+    [0x7E, 0x11, 0x11, 0x11, 0x7E], // A
+    [0x7F, 0x49, 0x49, 0x49, 0x36], // B
+    [0x3E, 0x41, 0x41, 0x41, 0x22], // C
+    [0x7F, 0x41, 0x41, 0x22, 0x1C], // D
+    [0x7F, 0x49, 0x49, 0x49, 0x41], // E
+    [0x7F, 0x09, 0x09, 0x01, 0x01], // F
+    [0x3E, 0x41, 0x41, 0x51, 0x32], // G
+    [0x7F, 0x08, 0x08, 0x08, 0x7F], // H
+    [0x00, 0x41, 0x7F, 0x41, 0x00], // I
+    [0x20, 0x40, 0x41, 0x3F, 0x01], // J
+    [0x7F, 0x08, 0x14, 0x22, 0x41], // K
+    [0x7F, 0x40, 0x40, 0x40, 0x40], // L
+    [0x7F, 0x02, 0x04, 0x02, 0x7F], // M
+    [0x7F, 0x04, 0x08, 0x10, 0x7F], // N
+    [0x3E, 0x41, 0x41, 0x41, 0x3E], // O
+    [0x7F, 0x09, 0x09, 0x09, 0x06], // P
+    [0x3E, 0x41, 0x51, 0x21, 0x5E], // Q
+    [0x7F, 0x09, 0x19, 0x29, 0x46], // R
+    [0x46, 0x49, 0x49, 0x49, 0x31], // S
+    [0x01, 0x01, 0x7F, 0x01, 0x01], // T
+    [0x3F, 0x40, 0x40, 0x40, 0x3F], // U
+    [0x1F, 0x20, 0x40, 0x20, 0x1F], // V
+    [0x7F, 0x20, 0x18, 0x20, 0x7F], // W
+    [0x63, 0x14, 0x08, 0x14, 0x63], // X
+    [0x03, 0x04, 0x78, 0x04, 0x03], // Y
+    [0x61, 0x51, 0x49, 0x45, 0x43], // Z
+    [0x00, 0x7F, 0x41, 0x41, 0x00], // [
+    [0x02, 0x04, 0x08, 0x10, 0x20], // \
+    [0x00, 0x41, 0x41, 0x7F, 0x00], // ]
+    [0x04, 0x02, 0x01, 0x02, 0x04], // ^
+    [0x40, 0x40, 0x40, 0x40, 0x40], // _
+    [0x00, 0x01, 0x02, 0x04, 0x00], // `
+    [0x20, 0x54, 0x54, 0x54, 0x78], // a
+    [0x7F, 0x48, 0x44, 0x44, 0x38], // b
+    [0x38, 0x44, 0x44, 0x44, 0x20], // c
+    [0x38, 0x44, 0x44, 0x48, 0x7F], // d
+    [0x38, 0x54, 0x54, 0x54, 0x18], // e
+    [0x08, 0x7E, 0x09, 0x01, 0x02], // f
+    [0x08, 0x14, 0x54, 0x54, 0x3C], // g
+    [0x7F, 0x08, 0x04, 0x04, 0x78], // h
+    [0x00, 0x44, 0x7D, 0x40, 0x00], // i
+    [0x20, 0x40, 0x44, 0x3D, 0x00], // j
+    [0x00, 0x7F, 0x10, 0x28, 0x44], // k
+    [0x00, 0x41, 0x7F, 0x40, 0x00], // l
+    [0x7C, 0x04, 0x18, 0x04, 0x78], // m
+    [0x7C, 0x08, 0x04, 0x04, 0x78], // n
+    [0x38, 0x44, 0x44, 0x44, 0x38], // o
+    [0x7C, 0x14, 0x14, 0x14, 0x08], // p
+    [0x08, 0x14, 0x14, 0x18, 0x7C], // q
+    [0x7C, 0x08, 0x04, 0x04, 0x08], // r
+    [0x48, 0x54, 0x54, 0x54, 0x20], // s
+    [0x04, 0x3F, 0x44, 0x40, 0x20], // t
+    [0x3C, 0x40, 0x40, 0x20, 0x7C], // u
+    [0x1C, 0x20, 0x40, 0x20, 0x1C], // v
+    [0x3C, 0x40, 0x30, 0x40, 0x3C], // w
+    [0x44, 0x28, 0x10, 0x28, 0x44], // x
+    [0x0C, 0x50, 0x50, 0x50, 0x3C], // y
+    [0x44, 0x64, 0x54, 0x4C, 0x44], // z
+    [0x00, 0x08, 0x36, 0x41, 0x00], // {
+    [0x00, 0x00, 0x7F, 0x00, 0x00], // |
+    [0x00, 0x41, 0x36, 0x08, 0x00], // }
+    [0x08, 0x04, 0x08, 0x10, 0x08], // ~
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_are_rendered_into_pages() {
+        let screen = Screen { lines: vec!["A".to_owned(), "x".repeat(30), "é".to_owned()] };
+        let buffer = framebuffer(&screen, 4);
+        assert_eq!(buffer.len(), 512);
+        assert_eq!(buffer[..6], [0x7E, 0x11, 0x11, 0x11, 0x7E, 0x00]);
+        // long lines are cut off instead of wrapping into the next page
+        let x = &buffer[128..256];
+        assert_eq!(x[(COLUMNS - 1) * 6..COLUMNS * 6 - 1], FONT[(b'x' - 0x20) as usize]);
+        assert!(x[COLUMNS * 6..].iter().all(|b| *b == 0));
+        // unknown characters are shown as ?
+        assert_eq!(buffer[256..261], FONT[(b'?' - 0x20) as usize]);
+        assert!(buffer[384..].iter().all(|b| *b == 0));
+    }
+}
@@ -0,0 +1,485 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::display::{Display, Screen};
+
+/// How long the switch is ignored after it changed, to swallow contact bounce
+pub const DEBOUNCE_WINDOW: Duration = Duration::from_millis(30);
+/// How long the presence line has to be stable, plugging in a jack bounces
+pub const PRESENCE_SETTLE: Duration = Duration::from_millis(250);
+
+/// An input line of a handset, like the switch or the presence detection
+pub trait Input: Send {
+    /// If the line is active, e.g. the switch is pressed
+    fn is_active(&mut self) -> bool;
+}
+
+/// An output line of a handset, like the LED
+pub trait Output: Send {
+    /// Shows the pattern, `elapsed` is the time since it was switched to
+    fn render(&mut self, pattern: LedPattern, elapsed: Duration);
+}
+
+/// What the LED of a handset shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LedPattern {
+    #[default]
+    Off,
+    Solid,
+    /// e.g. buzzing is open
+    FastBlink,
+    /// a short flash every now and then, e.g. while disconnected
+    Heartbeat,
+    /// a quick flicker, e.g. when a press was ignored
+    Flicker,
+    /// a few flashes, then off, e.g. when buzzing opens
+    Flash(Flash),
+}
+
+/// How the LED flashes when buzzing opens
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Flash {
+    /// no flashing at all with 0
+    pub times: u32,
+    pub on_ms: u64,
+    pub off_ms: u64,
+}
+
+impl Default for Flash {
+    fn default() -> Self {
+        Self {
+            times: 3,
+            on_ms: 80,
+            off_ms: 80,
+        }
+    }
+}
+
+impl Flash {
+    /// How long it takes to flash every time
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis((self.on_ms + self.off_ms) * self.times as u64)
+    }
+}
+
+impl LedPattern {
+    #[cfg_attr(not(feature = "rppal"), allow(dead_code))]
+    pub fn is_on(&self, elapsed: Duration) -> bool {
+        let millis = elapsed.as_millis();
+        match self {
+            Self::Off => false,
+            Self::Solid => true,
+            Self::FastBlink => millis % 200 < 100,
+            Self::Heartbeat => millis % 1500 < 100,
+            Self::Flicker => millis % 60 < 30,
+            Self::Flash(flash) => {
+                let period = (flash.on_ms + flash.off_ms).max(1) as u128;
+                millis < period * flash.times as u128 && millis % period < flash.on_ms as u128
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PinTiples {
+    pub switch: u8,
+    pub presence: u8,
+    pub led: u8,
+}
+
+impl PinTiples {
+    pub fn new(switch: u8, presence: u8, led: u8) -> Self {
+        Self {
+            switch,
+            presence,
+            led,
+        }
+    }
+}
+
+/// Only lets a change of the switch through if the last accepted change was
+/// at least a window ago, and reports presses but not releases.
+#[derive(Debug)]
+pub struct Debouncer {
+    window: Duration,
+    pressed: bool,
+    last_change: Option<Instant>,
+}
+
+impl Debouncer {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            pressed: false,
+            last_change: None,
+        }
+    }
+
+    /// Feeds a raw reading, returns true on a debounced press
+    pub fn update(&mut self, raw: bool, now: Instant) -> bool {
+        if raw == self.pressed {
+            return false;
+        }
+        if self.last_change.is_some_and(|l| now.duration_since(l) < self.window) {
+            return false;
+        }
+        self.pressed = raw;
+        self.last_change = Some(now);
+        raw
+    }
+}
+
+/// Only lets a reading through once it stayed the same for a window
+#[derive(Debug)]
+pub struct Settling {
+    window: Duration,
+    settled: bool,
+    /// when the readings started to differ from the settled value
+    since: Option<Instant>,
+}
+
+impl Settling {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            settled: false,
+            since: None,
+        }
+    }
+
+    /// Feeds a raw reading, returns the settled value
+    pub fn update(&mut self, raw: bool, now: Instant) -> bool {
+        if raw == self.settled {
+            self.since = None;
+        } else if now.duration_since(*self.since.get_or_insert(now)) >= self.window {
+            self.settled = raw;
+            self.since = None;
+        }
+        self.settled
+    }
+}
+
+pub struct Handset {
+    /// Used to tell handsets apart, e.g. its position on the podium
+    pub name: String,
+    switch: Box<dyn Input>,
+    presence: Box<dyn Input>,
+    led: Box<dyn Output>,
+    /// driven in its own task, see [Handset::take_display]
+    display: Option<Box<dyn Display>>,
+    debouncer: Debouncer,
+    presence_settling: Settling,
+
+    /// only sends clicks, not when the switch is released
+    pub switch_tx: watch::Sender<()>,
+    /// sends true when a handset is connected, and false on disconnect
+    pub presence_tx: watch::Sender<bool>,
+    /// sends the wanted pattern of the led, not necessarrily bound to any events
+    pub led_tx: watch::Sender<LedPattern>,
+    led_rx: watch::Receiver<LedPattern>,
+    /// what the display should show
+    pub screen_tx: watch::Sender<Screen>,
+    /// when the current pattern started, to render it from the beginning
+    pattern_since: Instant,
+}
+
+impl Handset {
+    pub fn new(name: String, switch: Box<dyn Input>, presence: Box<dyn Input>, led: Box<dyn Output>) -> Self {
+        let (led_tx, led_rx) = watch::channel(LedPattern::default());
+        Self {
+            name,
+            switch,
+            presence,
+            led,
+            display: None,
+            debouncer: Debouncer::new(DEBOUNCE_WINDOW),
+            presence_settling: Settling::new(PRESENCE_SETTLE),
+            switch_tx: watch::Sender::new(()),
+            presence_tx: watch::Sender::new(false),
+            led_tx,
+            led_rx,
+            screen_tx: watch::Sender::new(Screen::default()),
+            pattern_since: Instant::now(),
+        }
+    }
+
+    /// Replaces the default [DEBOUNCE_WINDOW]
+    pub fn with_debounce(mut self, window: Duration) -> Self {
+        self.debouncer = Debouncer::new(window);
+        self
+    }
+
+    /// Replaces the default [PRESENCE_SETTLE]
+    pub fn with_presence_settle(mut self, window: Duration) -> Self {
+        self.presence_settling = Settling::new(window);
+        self
+    }
+
+    pub fn with_display(mut self, display: Box<dyn Display>) -> Self {
+        self.display = Some(display);
+        self
+    }
+
+    /// The display is slow to update, so it's not updated with the pins
+    pub fn take_display(&mut self) -> Option<Box<dyn Display>> {
+        self.display.take()
+    }
+
+    pub fn update(&mut self) {
+        let now = Instant::now();
+        let switch = self.switch.is_active();
+        if self.debouncer.update(switch, now) {
+            self.switch_tx.send_replace(());
+        }
+
+        let presence = self.presence_settling.update(self.presence.is_active(), now);
+        if *self.presence_tx.borrow() != presence {
+            self.presence_tx.send_replace(presence);
+        }
+
+        if self.led_rx.has_changed().unwrap_or(false) {
+            self.pattern_since = now;
+        }
+        let pattern = *self.led_rx.borrow_and_update();
+        self.led.render(pattern, now.duration_since(self.pattern_since));
+    }
+}
+
+#[cfg(feature = "rppal")]
+mod gpio {
+    use super::*;
+    use rppal::gpio::{Error, Gpio, InputPin, OutputPin};
+
+    // the handsets pull their lines low when active
+    impl Input for InputPin {
+        fn is_active(&mut self) -> bool {
+            self.is_low()
+        }
+    }
+
+    impl Output for OutputPin {
+        fn render(&mut self, pattern: LedPattern, elapsed: Duration) {
+            self.write((pattern.is_on(elapsed) as u8).into())
+        }
+    }
+
+    impl Handset {
+        pub fn gpio(name: String, pins: &PinTiples) -> Result<Self, Error> {
+            let gpio = Gpio::new()?;
+            Ok(Self::new(
+                name,
+                Box::new(gpio.get(pins.switch)?.into_input_pullup()),
+                Box::new(gpio.get(pins.presence)?.into_input_pullup()),
+                Box::new(gpio.get(pins.led)?.into_output_high()),
+            ))
+        }
+    }
+}
+
+/// Presses simulated switches, shared between all of them
+#[derive(Clone)]
+pub struct Presses(Arc<[AtomicUsize]>);
+
+impl Presses {
+    /// Presses the switch with the given number, starting at 1
+    pub fn press(&self, number: usize) -> bool {
+        let Some(presses) = number.checked_sub(1).and_then(|i| self.0.get(i)) else {
+            return false;
+        };
+        presses.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    /// Presses switches from stdin: a line with one of the `keys` presses
+    /// the corresponding switch, an empty line the first one
+    pub fn read_stdin(self, keys: Vec<String>) {
+        std::thread::spawn(move || {
+            for line in std::io::stdin().lines().map_while(Result::ok) {
+                let number = match line.trim() {
+                    "" => Some(1),
+                    key => keys.iter().position(|k| k == key).map(|i| i + 1),
+                };
+                if !number.is_some_and(|n| self.press(n)) {
+                    println!("no handset {:?}, expected one of {:?}", line.trim(), keys);
+                }
+            }
+        });
+    }
+}
+
+/// A switch that is pressed through [Presses] instead of a GPIO pin
+pub struct SimulatedSwitch {
+    presses: Presses,
+    index: usize,
+}
+
+impl Input for SimulatedSwitch {
+    fn is_active(&mut self) -> bool {
+        // every press is active for a single reading
+        self.presses.0[self.index]
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |p| p.checked_sub(1))
+            .is_ok()
+    }
+}
+
+/// Creates `count` switches and the handle to press them
+pub fn simulated_switches(count: usize) -> (Presses, Vec<SimulatedSwitch>) {
+    let presses = Presses((0..count).map(|_| AtomicUsize::new(0)).collect());
+    let switches = (0..count)
+        .map(|index| SimulatedSwitch { presses: presses.clone(), index })
+        .collect();
+    (presses, switches)
+}
+
+/// Toggles the presence of simulated handsets, all start out present
+#[derive(Clone)]
+pub struct Presence(Arc<[AtomicBool]>);
+
+impl Presence {
+    /// Toggles the handset with the given number, starting at 1, and returns
+    /// its new presence
+    pub fn toggle(&self, number: usize) -> Option<bool> {
+        let present = self.0.get(number.checked_sub(1)?)?;
+        Some(!present.fetch_xor(true, Ordering::Relaxed))
+    }
+}
+
+/// A presence line that is toggled through [Presence]
+pub struct SimulatedPresence {
+    presence: Presence,
+    index: usize,
+}
+
+impl Input for SimulatedPresence {
+    fn is_active(&mut self) -> bool {
+        self.presence.0[self.index].load(Ordering::Relaxed)
+    }
+}
+
+/// Creates `count` presence lines and the handle to toggle them
+pub fn simulated_presence(count: usize) -> (Presence, Vec<SimulatedPresence>) {
+    let presence = Presence((0..count).map(|_| AtomicBool::new(true)).collect());
+    let lines = (0..count)
+        .map(|index| SimulatedPresence { presence: presence.clone(), index })
+        .collect();
+    (presence, lines)
+}
+
+/// Prints the pattern of the LED whenever it changes
+pub struct PrintedLed {
+    pub name: String,
+    pub pattern: Option<LedPattern>,
+}
+
+impl Output for PrintedLed {
+    fn render(&mut self, pattern: LedPattern, _elapsed: Duration) {
+        if self.pattern != Some(pattern) {
+            println!("handset {}: LED: {:?}", self.name, pattern);
+            self.pattern = Some(pattern);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Replays a fixed list of readings
+    struct MockPin(std::vec::IntoIter<bool>);
+
+    impl Input for MockPin {
+        fn is_active(&mut self) -> bool {
+            self.0.next().unwrap_or(false)
+        }
+    }
+
+    fn presses(readings: &[(u64, bool)]) -> usize {
+        presses_with_window(DEBOUNCE_WINDOW, readings)
+    }
+
+    fn presses_with_window(window: Duration, readings: &[(u64, bool)]) -> usize {
+        let start = Instant::now();
+        let mut pin = MockPin(readings.iter().map(|(_, r)| *r).collect::<Vec<_>>().into_iter());
+        let mut debouncer = Debouncer::new(window);
+        readings.iter()
+            .filter(|(ms, _)| debouncer.update(pin.is_active(), start + Duration::from_millis(*ms)))
+            .count()
+    }
+
+    #[test]
+    fn bouncing_press_is_one_press() {
+        assert_eq!(presses(&[(0, true), (2, false), (4, true), (6, false), (8, true), (100, false)]), 1);
+    }
+
+    #[test]
+    fn release_is_not_a_press() {
+        assert_eq!(presses(&[(0, true), (40, true), (80, false), (120, false)]), 1);
+    }
+
+    #[test]
+    fn separate_presses_are_counted() {
+        assert_eq!(presses(&[(0, true), (50, false), (100, true), (150, false), (200, true)]), 3);
+    }
+
+    #[test]
+    fn window_is_configurable() {
+        let readings = [(0, true), (50, false), (100, true), (200, false), (300, true)];
+        assert_eq!(presses_with_window(Duration::from_millis(80), &readings), 2);
+        assert_eq!(presses_with_window(Duration::ZERO, &readings), 3);
+    }
+
+    #[test]
+    fn presence_settles() {
+        let start = Instant::now();
+        let mut settling = Settling::new(PRESENCE_SETTLE);
+        let readings: Vec<_> = [(0, true), (10, false), (20, true), (100, true), (270, true), (300, false), (400, true), (600, false)]
+            .into_iter()
+            .map(|(ms, raw)| settling.update(raw, start + Duration::from_millis(ms)))
+            .collect();
+        assert_eq!(readings, [false, false, false, false, true, true, true, true]);
+    }
+
+    #[test]
+    fn patterns_blink() {
+        let on = |pattern: LedPattern, ms| pattern.is_on(Duration::from_millis(ms));
+        assert!(!on(LedPattern::Off, 0));
+        assert!(on(LedPattern::Solid, 1234));
+        assert!(on(LedPattern::FastBlink, 0) && !on(LedPattern::FastBlink, 150) && on(LedPattern::FastBlink, 250));
+        assert!(on(LedPattern::Heartbeat, 50) && !on(LedPattern::Heartbeat, 750) && on(LedPattern::Heartbeat, 1550));
+        assert!(on(LedPattern::Flicker, 0) && !on(LedPattern::Flicker, 45) && on(LedPattern::Flicker, 70));
+        let flash = LedPattern::Flash(Flash::default());
+        assert!(on(flash, 0) && !on(flash, 100) && on(flash, 330) && !on(flash, 490));
+        assert_eq!(Flash::default().duration(), Duration::from_millis(480));
+    }
+
+    #[test]
+    fn simulated_presence_toggles() {
+        let (presence, mut lines) = simulated_presence(2);
+        assert!(lines[1].is_active());
+        assert_eq!(presence.toggle(2), Some(false));
+        assert!(!lines[1].is_active());
+        assert!(lines[0].is_active());
+        assert_eq!(presence.toggle(2), Some(true));
+        assert_eq!(presence.toggle(3), None);
+        assert_eq!(presence.toggle(0), None);
+    }
+
+    #[test]
+    fn simulated_presses_are_momentary() {
+        let (presses, mut switches) = simulated_switches(2);
+        assert!(presses.press(2));
+        assert!(presses.press(2));
+        assert!(!presses.press(3));
+        assert!(!presses.press(0));
+        assert!(!switches[0].is_active());
+        assert!(switches[1].is_active());
+        assert!(switches[1].is_active());
+        assert!(!switches[1].is_active());
+    }
+}
@@ -0,0 +1,209 @@
+use std::io::Read;
+
+use tokio_util::sync::CancellationToken;
+
+use crate::handset::{Presence, Presses};
+
+/// Ends the simulation
+pub const QUIT: char = 'q';
+
+/// The keys of one simulated handset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub buzz: char,
+    /// toggles whether the handset is present
+    pub presence: char,
+}
+
+/// Parses bindings like `1,2,3,4` or `a:A,b:B`. Without an explicit
+/// presence key it's the shifted buzz key on a US layout.
+pub fn parse_bindings(bindings: &str) -> Result<Vec<KeyBinding>, String> {
+    let single = |key: &str| {
+        let mut chars = key.trim().chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(format!("expected a single key, got {:?}", key)),
+        }
+    };
+    let parsed = bindings.split(',')
+        .map(|binding| {
+            let (buzz, presence) = match binding.split_once(':') {
+                Some((buzz, presence)) => (single(buzz)?, single(presence)?),
+                None => {
+                    let buzz = single(binding)?;
+                    let presence = shifted(buzz).ok_or(format!("no presence key for {:?}, use {}:<key>", buzz, buzz))?;
+                    (buzz, presence)
+                },
+            };
+            Ok(KeyBinding { buzz, presence })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let mut keys: Vec<char> = parsed.iter().flat_map(|b| [b.buzz, b.presence]).collect();
+    if keys.contains(&QUIT) {
+        return Err(format!("{:?} is reserved for quitting", QUIT));
+    }
+    keys.sort();
+    if let Some(duplicate) = keys.windows(2).find(|w| w[0] == w[1]) {
+        return Err(format!("{:?} is bound twice", duplicate[0]));
+    }
+    Ok(parsed)
+}
+
+fn shifted(key: char) -> Option<char> {
+    const DIGITS: &str = ")!@#$%^&*(";
+    match key {
+        '0'..='9' => DIGITS.chars().nth(key.to_digit(10)? as usize),
+        'a'..='z' => Some(key.to_ascii_uppercase()),
+        _ => None,
+    }
+}
+
+/// What a key press did
+#[derive(Debug, PartialEq, Eq)]
+pub enum Action {
+    /// the handset with this number buzzed
+    Buzz(usize),
+    /// the handset with this number is now present, or not
+    Presence(usize, bool),
+    Quit,
+    Unbound(char),
+}
+
+/// Maps key presses to the simulated handsets
+pub struct Keyboard {
+    pub bindings: Vec<KeyBinding>,
+    pub presses: Presses,
+    pub presence: Presence,
+}
+
+impl Keyboard {
+    pub fn dispatch(&self, key: char) -> Action {
+        if key == QUIT {
+            return Action::Quit;
+        }
+        for (i, binding) in self.bindings.iter().enumerate() {
+            let number = i + 1;
+            if key == binding.buzz && self.presses.press(number) {
+                return Action::Buzz(number);
+            }
+            if key == binding.presence {
+                if let Some(present) = self.presence.toggle(number) {
+                    return Action::Presence(number, present);
+                }
+            }
+        }
+        Action::Unbound(key)
+    }
+
+    /// Dispatches key presses from stdin until `q` is pressed, which cancels
+    /// the token
+    pub fn read_stdin(self, cancellation_token: CancellationToken) {
+        std::thread::spawn(move || {
+            for byte in std::io::stdin().lock().bytes().map_while(Result::ok) {
+                match self.dispatch(byte as char) {
+                    Action::Buzz(n) => println!("handset {}: buzz", n),
+                    Action::Presence(n, present) => println!("handset {}: present: {}", n, present),
+                    Action::Quit => {
+                        println!("Stopping");
+                        cancellation_token.cancel();
+                        return;
+                    },
+                    Action::Unbound('\n') => {},
+                    Action::Unbound(key) => println!("{:?} is not bound to a handset", key),
+                }
+            }
+        });
+    }
+}
+
+/// Switches the terminal to unbuffered input without echo, so single key
+/// presses arrive immediately. Restores the terminal when dropped.
+#[cfg(unix)]
+pub struct RawMode {
+    original: libc::termios,
+}
+
+#[cfg(unix)]
+impl RawMode {
+    pub fn enable() -> std::io::Result<Self> {
+        // SAFETY: termios is plain data and only accessed through libc
+        unsafe {
+            let mut termios = std::mem::zeroed::<libc::termios>();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut termios) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            let original = termios;
+            termios.c_lflag &= !(libc::ICANON | libc::ECHO);
+            termios.c_cc[libc::VMIN] = 1;
+            termios.c_cc[libc::VTIME] = 0;
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(Self { original })
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        // SAFETY: restores the settings read in enable
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub struct RawMode;
+
+#[cfg(not(unix))]
+impl RawMode {
+    pub fn enable() -> std::io::Result<Self> {
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handset::{simulated_presence, simulated_switches, Input};
+
+    #[test]
+    fn parses_bindings() {
+        let bindings = parse_bindings("1,2,3,4").unwrap();
+        let presence: String = bindings.iter().map(|b| b.presence).collect();
+        assert_eq!(presence, "!@#$");
+        assert_eq!(parse_bindings("a, x:y").unwrap(), [
+            KeyBinding { buzz: 'a', presence: 'A' },
+            KeyBinding { buzz: 'x', presence: 'y' },
+        ]);
+        assert!(parse_bindings("1,1").is_err());
+        assert!(parse_bindings("1,a:!").is_err());
+        assert!(parse_bindings("q").is_err());
+        assert!(parse_bindings("12").is_err());
+        assert!(parse_bindings("-").is_err());
+        assert!(parse_bindings("").is_err());
+    }
+
+    #[test]
+    fn dispatches_keys_to_handsets() {
+        let bindings = parse_bindings("1,2").unwrap();
+        let (presses, mut switches) = simulated_switches(2);
+        let (presence, mut lines) = simulated_presence(2);
+        let keyboard = Keyboard { bindings, presses, presence };
+
+        assert_eq!(keyboard.dispatch('2'), Action::Buzz(2));
+        assert!(!switches[0].is_active());
+        assert!(switches[1].is_active());
+
+        assert_eq!(keyboard.dispatch('!'), Action::Presence(1, false));
+        assert!(!lines[0].is_active());
+        assert_eq!(keyboard.dispatch('!'), Action::Presence(1, true));
+        assert!(lines[0].is_active());
+
+        assert_eq!(keyboard.dispatch('3'), Action::Unbound('3'));
+        assert_eq!(keyboard.dispatch('q'), Action::Quit);
+    }
+}
@@ -0,0 +1,97 @@
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+/// Press-to-acknowledgment latencies of buzzes
+#[derive(Debug, Default)]
+pub struct Latencies {
+    samples: Vec<Duration>,
+}
+
+/// Latencies in milliseconds
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Summary {
+    pub count: usize,
+    pub min_ms: f64,
+    pub p50_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+impl Latencies {
+    pub fn record(&mut self, latency: Duration) {
+        self.samples.push(latency);
+    }
+
+    pub fn summary(&self) -> Option<Summary> {
+        let mut sorted = self.samples.clone();
+        sorted.sort();
+        // nearest-rank percentile
+        let percentile = |p: f64| sorted[((p * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len()) - 1];
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        Some(Summary {
+            count: sorted.len(),
+            min_ms: ms(*sorted.first()?),
+            p50_ms: ms(percentile(0.5)),
+            p99_ms: ms(percentile(0.99)),
+            max_ms: ms(*sorted.last()?),
+        })
+    }
+}
+
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} buzzes, min {:.1}ms, p50 {:.1}ms, p99 {:.1}ms, max {:.1}ms",
+            self.count, self.min_ms, self.p50_ms, self.p99_ms, self.max_ms,
+        )
+    }
+}
+
+/// The last buzz sent, until the server acknowledged it by indicating the
+/// contestant or answering with an error
+#[derive(Debug, Default)]
+pub struct PendingBuzz {
+    pressed: Option<Instant>,
+}
+
+impl PendingBuzz {
+    /// A new press replaces one that was never acknowledged
+    pub fn pressed(&mut self, at: Instant) {
+        self.pressed = Some(at);
+    }
+
+    /// The latency of the pending buzz, if there is one
+    pub fn acknowledged(&mut self, at: Instant) -> Option<Duration> {
+        self.pressed.take().map(|pressed| at.duration_since(pressed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarizes_percentiles() {
+        let mut latencies = Latencies::default();
+        assert_eq!(latencies.summary(), None);
+        for ms in (1..=100).rev() {
+            latencies.record(Duration::from_millis(ms));
+        }
+        let summary = latencies.summary().unwrap();
+        assert_eq!(summary, Summary { count: 100, min_ms: 1.0, p50_ms: 50.0, p99_ms: 99.0, max_ms: 100.0 });
+        assert_eq!(summary.to_string(), "100 buzzes, min 1.0ms, p50 50.0ms, p99 99.0ms, max 100.0ms");
+    }
+
+    #[test]
+    fn only_pending_buzzes_are_measured() {
+        let start = Instant::now();
+        let mut pending = PendingBuzz::default();
+        assert_eq!(pending.acknowledged(start), None);
+        pending.pressed(start);
+        pending.pressed(start + Duration::from_millis(10));
+        assert_eq!(pending.acknowledged(start + Duration::from_millis(25)), Some(Duration::from_millis(15)));
+        assert_eq!(pending.acknowledged(start + Duration::from_millis(30)), None);
+    }
+}
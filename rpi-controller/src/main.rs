@@ -1,8 +1,9 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
-use rppal::gpio::{Gpio, InputPin, OutputPin, Error as GPIOError};
 use tokio::sync::watch;
-use tokio::time::{sleep, Interval, MissedTickBehavior, interval};
+use tokio::time::{sleep, sleep_until, Instant, Interval, MissedTickBehavior, interval};
 use tokio_tungstenite::tungstenite::handshake::client::{generate_key, Request};
 use tokio_tungstenite::tungstenite::Message;
 use tokio_tungstenite::tungstenite::http::Uri;
@@ -11,90 +12,124 @@ use futures_util::{SinkExt, StreamExt};
 use machineid_rs::{IdBuilder, Encryption, HWIDComponent};
 use serde::Deserialize;
 
-const UPDATE_HERTZ: u64 = 20;
-const RECONNECT_HERTZ: u64 = 2;
-const PING_HERTZ: u64 = 1; // this also defines the max latency
+use backoff::Backoff;
+use config::{Config, HandsetConfig};
+use display::Screen;
+use handset::{Flash, Handset, LedPattern, PinTiples, Presence, Presses};
+use latency::{Latencies, PendingBuzz};
+use pinger::Pinger;
+use registration::Registration;
+use systemd::Notifier;
 
-type Websocket = tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+mod backoff;
+mod config;
+mod display;
+mod handset;
+mod keyboard;
+mod latency;
+#[cfg(test)]
+mod mock_server;
+mod pinger;
+mod registration;
+mod systemd;
+mod tls;
 
-struct PinTiples {
-    switch: u8,
-    presence: u8,
-    led: u8,
-}
+const LATENCY_REPORT_INTERVAL: Duration = Duration::from_secs(30);
+/// The server's `PROTOCOL_VERSION` this controller was written against
+const PROTOCOL_VERSION: u32 = 8;
+/// Servers before this lack inputs the controller relies on
+const MIN_PROTOCOL_VERSION: u32 = 1;
+/// Sent by the server when upgrading the connection
+const PROTOCOL_HEADER: &str = "x-aitfoaq-protocol";
+/// How long the LED flickers after a press the game wouldn't accept
+const IGNORED_PRESS_FLICKER: Duration = Duration::from_millis(300);
 
-impl PinTiples {
-    fn new(switch: u8, presence: u8, led: u8) -> Self {
-        Self {
-            switch,
-            presence,
-            led,
-        }
-    }
-}
-
-struct Handset {
-    switch: InputPin,
-    presence: InputPin,
-    led: OutputPin,
-    switch_flank: bool,
-
-    /// only sends clicks, not when the switch is released
-    switch_tx: watch::Sender<()>,
-    /// sends true when a handset is connected, and false on disconnect
-    presence_tx: watch::Sender<bool>,
-    /// sends the wanted state of the led, not necessarrily bound to any events
-    led_tx: watch::Sender<bool>,
-    led_rx: watch::Receiver<bool>,
-}
-
-impl TryFrom<&PinTiples> for Handset {
-    type Error = GPIOError;
-    fn try_from(pins: &PinTiples) -> Result<Self, Self::Error> {
-        let gpio = Gpio::new()?;
-        let (led_tx, led_rx) = watch::channel(false);
-        Ok(Self {
-            switch: gpio.get(pins.switch)?.into_input_pullup(),
-            presence: gpio.get(pins.presence)?.into_input_pullup(),
-            led: gpio.get(pins.led)?.into_output_high(),
-            switch_flank: true,
-
-            switch_tx: watch::Sender::new(()),
-            presence_tx: watch::Sender::new(false),
-            led_tx,
-            led_rx,
-        })
-    }
-}
-
-impl Handset {
-    fn update(&mut self) {
-        let switch = self.switch.is_low();
-        if self.switch_flank ^ switch {
-            self.switch_flank = switch;
-            if switch {
-                self.switch_tx.send_replace(());
-            }
-        }
-
-        let presence = self.presence.is_low();
-        if *self.presence_tx.borrow() != presence {
-            self.presence_tx.send_replace(presence);
-        }
-
-        self.led.write((*self.led_rx.borrow() as u8).into());
-    }
-}
+type Websocket = tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
 
 struct HandsetCommunicator {
     id: String,
+    /// the name hint to register with, and to find our contestant again
+    name_hint: String,
     switch_rx: watch::Receiver<()>,
     presence_rx: watch::Receiver<bool>,
-    led_tx: watch::Sender<bool>,
+    led_tx: watch::Sender<LedPattern>,
+    screen_tx: watch::Sender<Screen>,
+    /// the pattern from the last state, shown again after an overlay
+    led_pattern: LedPattern,
+    /// shown instead of `led_pattern` until it ends, like a flicker
+    led_overlay: Option<(LedPattern, Instant)>,
+    /// shown when buzzing opens
+    buzzing_flash: Flash,
+    /// from the last state, buzzes are only sent when the game accepts them
+    phase: Option<GamePhase>,
+    /// send buzzes regardless of the phase
+    always_send: bool,
+    /// presses while the connection is lost are sent if it's back this soon
+    buzz_buffer: Duration,
+    /// a press while the connection was lost, sent once registered again
+    buffered_press: Option<BufferedPress>,
+    registration: Registration,
+    /// the stored contestant we asked to reconnect as, until the server
+    /// accepts or rejects it
+    stored_attempt: Option<usize>,
+    /// from the last state, to pick a fallback if the stored contestant is
+    /// rejected
+    contestants: Vec<Contestant>,
+    /// no registration attempts before this, e.g. because the lobby is full
+    retry_registration: Option<Instant>,
+    /// only set when measuring latencies
+    latencies: Option<Arc<Mutex<Latencies>>>,
+    pending_buzz: PendingBuzz,
+    notifier: Notifier,
     connection: Connection,
     ping_interval: Interval,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ServerMessage {
+    State(ServerState),
+    Error { error: String },
+    /// Sent after each of our inputs, what they changed is in the state
+    Ack { ack: Ack },
+}
+
+#[derive(Debug, Deserialize)]
+struct Ack {
+    input: String,
+    accepted: bool,
+    /// Like `too_late`, see the server's `ack.json` schema
+    reason: Option<String>,
+}
+
+/// The error frames the controller acts on, classified by the variant names
+/// in the error text, like `State(Game(ContestantNotFound))`
+#[derive(Debug, PartialEq)]
+enum ServerError {
+    /// the contestant we reconnected or buzzed as doesn't exist
+    ContestantNotFound,
+    /// no more contestants can connect
+    LobbyFull,
+    /// someone else controls the contestant we reconnected as
+    Taken,
+    Other,
+}
+
+impl From<&str> for ServerError {
+    fn from(error: &str) -> Self {
+        let is = |variant: &str| error.split(|c: char| !c.is_alphanumeric()).any(|word| word == variant);
+        if is("ContestantNotFound") {
+            Self::ContestantNotFound
+        } else if is("LobbyFull") {
+            Self::LobbyFull
+        } else if is("AlreadyControlled") || is("AlreadyClaimed") {
+            Self::Taken
+        } else {
+            Self::Other
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct ServerState {
     game: GameState,
@@ -104,12 +139,84 @@ struct ServerState {
 #[derive(Debug, Deserialize)]
 struct GameState {
     contestants: Vec<Contestant>,
+    phase: GamePhase,
+}
+
+type ClueHandle = (usize, usize);
+
+/// Mirrors the serde representation of `libaitfoaq::state::GamePhase`
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[allow(dead_code)]
+enum GamePhase {
+    Preparing,
+    Connecting,
+    Practice { winner: Option<usize> },
+    Picking { contestant: usize },
+    Waging { clue: ClueHandle, contestant: usize, min_wager: i64, max_wager: i64 },
+    Clue { clue: ClueHandle, exclusive: Option<usize> },
+    Buzzing { clue: ClueHandle },
+    Buzzed { clue: ClueHandle, contestant: usize },
+    Resolution { clue: ClueHandle, contestant: usize, show_hint: bool },
+    Score,
+}
+
+/// If the game accepts buzzes, in `Connecting` and `Score` they toggle the
+/// indicator, in `Practice` the first one wins
+fn accepts_buzz(phase: &GamePhase) -> bool {
+    matches!(phase, GamePhase::Buzzing { .. } | GamePhase::Practice { .. } | GamePhase::Connecting | GamePhase::Score)
+}
+
+/// The LED pattern of a registered handset
+fn led_pattern(phase: &GamePhase, me: &Contestant) -> LedPattern {
+    match phase {
+        _ if me.indicate => LedPattern::Solid,
+        GamePhase::Buzzing { .. } | GamePhase::Practice { winner: None } => LedPattern::FastBlink,
+        _ => LedPattern::Off,
+    }
+}
+
+/// What the LED shows before the pattern of a new phase. Only entering
+/// `Buzzing` from another phase flashes, not e.g. reconnecting during it.
+fn led_transition(previous: Option<&GamePhase>, phase: &GamePhase, flash: Flash) -> Option<LedPattern> {
+    let is_buzzing = |phase: &GamePhase| matches!(phase, GamePhase::Buzzing { .. });
+    let opened = is_buzzing(phase) && previous.is_some_and(|previous| !is_buzzing(previous));
+    (opened && flash.times > 0).then_some(LedPattern::Flash(flash))
+}
+
+/// What the display of a registered handset shows
+fn screen(game: &GameState, me_index: usize) -> Screen {
+    let name = |contestant: &Contestant| contestant.name.clone().unwrap_or_else(|| contestant.name_hint.clone());
+    let Some(me) = game.contestants.get(me_index) else { return Screen::default() };
+    let who = |contestant: &usize| match game.contestants.get(*contestant) {
+        _ if *contestant == me_index => "you".to_owned(),
+        Some(other) => name(other),
+        None => "?".to_owned(),
+    };
+    let phase = match &game.phase {
+        GamePhase::Preparing => "Preparing".to_owned(),
+        GamePhase::Connecting => "Waiting for players".to_owned(),
+        GamePhase::Practice { winner: None } => "Practice: buzz!".to_owned(),
+        GamePhase::Practice { winner: Some(winner) } => format!("Practice: {} first", who(winner)),
+        GamePhase::Picking { contestant } => format!("Picking: {}", who(contestant)),
+        GamePhase::Waging { contestant, .. } => format!("Waging: {}", who(contestant)),
+        GamePhase::Clue { .. } => "Clue".to_owned(),
+        GamePhase::Buzzing { .. } => "Buzz now!".to_owned(),
+        GamePhase::Buzzed { contestant, .. } => format!("Buzzed: {}", who(contestant)),
+        GamePhase::Resolution { .. } => "Resolution".to_owned(),
+        GamePhase::Score => "Final score".to_owned(),
+    };
+    Screen { lines: vec![name(me), format!("{} points", me.points), phase] }
 }
 
 #[derive(Debug, Deserialize, Clone)]
 struct Contestant {
+    /// set by the moderator, otherwise the name hint is shown
+    name: Option<String>,
+    points: i64,
     indicate: bool,
     name_hint: String,
+    /// false once the server processed our disconnect
+    connected: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -118,85 +225,333 @@ struct ConnectionState {
 }
 
 impl HandsetCommunicator {
-    fn from_handset_with_request(machine_id: String, handset: &Handset, socket_address: Uri) -> Self {
-        let mut reconnect_interval = interval(Duration::from_millis(1000/RECONNECT_HERTZ));
-        reconnect_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
-        let mut ping_interval = interval(Duration::from_millis(1000/PING_HERTZ));
+    fn from_handset_with_request(id: String, name_hint: String, registration: Registration, handset: &Handset, socket_address: Uri, server: &config::Server, tls: Arc<rustls::ClientConfig>) -> Self {
+        let mut ping_interval = interval(server.ping_interval());
         ping_interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
-        let id = format!("{}-{}", machine_id, handset.switch.pin());
 
         Self {
             id: id.to_owned(),
+            name_hint,
             switch_rx: handset.switch_tx.subscribe(),
             presence_rx: handset.presence_tx.subscribe(),
             led_tx: handset.led_tx.clone(),
+            screen_tx: handset.screen_tx.clone(),
+            led_pattern: LedPattern::default(),
+            led_overlay: None,
+            buzzing_flash: Flash::default(),
+            phase: None,
+            always_send: false,
+            buzz_buffer: Duration::ZERO,
+            buffered_press: None,
+            registration,
+            stored_attempt: None,
+            contestants: vec![],
+            retry_registration: None,
+            latencies: None,
+            pending_buzz: PendingBuzz::default(),
+            notifier: Notifier::default(),
             connection: Connection{
                 id,
                 uri: socket_address,
-                reconnect_interval,
+                tls,
+                backoff: Backoff::new(
+                    Duration::from_millis(server.reconnect.min_ms),
+                    Duration::from_millis(server.reconnect.max_ms),
+                ),
+                next_attempt: Instant::now(),
                 inner: SocketState::Unconnected,
-                ping_in_transit: false,
+                outage: None,
+                pinger: Pinger::new(Duration::from_millis(server.ping_window_ms), server.ping_misses),
+                slow_pong: server.ping_interval(),
             },
             ping_interval,
         }
     }
+    /// Records the time from each press until the server acknowledges it
+    fn measuring(mut self, latencies: Option<Arc<Mutex<Latencies>>>) -> Self {
+        self.latencies = latencies;
+        self
+    }
+    /// Reports the connection state to systemd
+    fn notifying(mut self, notifier: Notifier) -> Self {
+        self.notifier = notifier;
+        self
+    }
+    /// Flashes the LED like this when buzzing opens
+    fn flashing(mut self, flash: Flash) -> Self {
+        self.buzzing_flash = flash;
+        self
+    }
+    /// Sends buzzes even when the game wouldn't accept them
+    fn always_sending(mut self, always_send: bool) -> Self {
+        self.always_send = always_send;
+        self
+    }
+    /// Remembers presses while the connection is lost for up to `window`
+    fn buffering(mut self, window: Duration) -> Self {
+        self.buzz_buffer = window;
+        self
+    }
     async fn communicate(&mut self, cancellation_token: CancellationToken) {
         let mut switch_rx = self.switch_rx.to_owned();
         let mut presence_rx = self.presence_rx.to_owned();
 
         loop {
+            if self.connection.me().is_none() {
+                self.set_led(LedPattern::Heartbeat);
+            }
             let auto_reconnect = *presence_rx.borrow();
+            self.notifier.status(&self.id, self.status(auto_reconnect));
+            if self.connection.me().is_none() {
+                self.set_screen(Screen { lines: vec![self.name_hint.clone(), self.status(auto_reconnect)] });
+            }
             tokio::select! {
                 _ = cancellation_token.cancelled() => { return; },
                 Ok(_) = switch_rx.changed() => {
-                    if self.connection.is_disconnected() {
-                        let new = !*self.led_tx.borrow();
-                        self.led_tx.send_replace(new);
-                    } else {
-                        if let Some(me) = self.connection.me_index() {
-                            let response = serde_json::json!({
-                                "type": "buzz",
-                                "contestant": me,
-                            }).to_string();
-                            self.connection.send(&response).await;
-                        }
+                    let accepted = self.always_send || self.phase.as_ref().is_some_and(accepts_buzz);
+                    let recently_lost = self.connection.outage.as_ref().is_some_and(|o| o.since.elapsed() < self.buzz_buffer);
+                    match self.connection.me_index() {
+                        None if recently_lost && accepted && *presence_rx.borrow() => {
+                            println!("{}: switch pressed while the connection is lost, sending it once back", self.id);
+                            let phase = self.phase.clone();
+                            self.buffered_press.get_or_insert(BufferedPress { at: Instant::now(), phase });
+                        },
+                        None => println!("{}: switch pressed while not registered", self.id),
+                        Some(_) if !*presence_rx.borrow() => println!("{}: switch pressed while absent", self.id),
+                        Some(_) if !accepted => {
+                            println!("{}: switch pressed during {:?}, not buzzing", self.id, self.phase);
+                            self.overlay(LedPattern::Flicker, IGNORED_PRESS_FLICKER);
+                        },
+                        Some(me) => self.buzz(me, Instant::now()).await,
                     }
                 },
                 Ok(_) = presence_rx.changed() => {
                     let presence = *presence_rx.borrow();
                     println!("{}: presence: {presence}", self.id);
-                    if !presence { self.connection.disconnect() }
+                    match (presence, self.connection.me_index()) {
+                        // the server marks us as disconnected and stops controlling us
+                        (false, Some(me)) => {
+                            let request = serde_json::json!({
+                                "type": "disconnect_contestant",
+                                "contestant": me,
+                            });
+                            self.connection.send(&request.to_string()).await;
+                        },
+                        (true, None) if self.connection.is_connected() => {
+                            let contestants = self.contestants.clone();
+                            self.register(&contestants).await;
+                        },
+                        // an unconnected socket reconnects by itself once present
+                        _ => {},
+                    }
                 },
                 msg = self.connection.receive(auto_reconnect) => {
-                    let Some(msg) = msg else { continue };
-                    let Some(me) = self.connection.me() else {
-                        // register a new contestant by default
-                        let mut response = serde_json::json!({
-                            "type": "connect_contestant",
-                            "name_hint": self.id,
-                        }).to_string();
-                        if let Some(index) = msg.game.contestants.iter()
-                            .position(|c| c.name_hint == self.id)
-                        {
-                            // contestant with our name found, reconnect instead
-                            response = serde_json::json!({
-                                "type": "reconnect_contestant",
-                                "contestant": index,
-                            }).to_string();
+                    let msg = match msg {
+                        None => continue,
+                        Some(ServerMessage::Error { error }) => {
+                            eprintln!("{}: server error: {}", self.id, error);
+                            self.buzz_acknowledged();
+                            match ServerError::from(error.as_str()) {
+                                ServerError::ContestantNotFound => {
+                                    println!("{}: server doesn't know contestant {:?}, forgetting it", self.id, self.registration.contestant());
+                                    self.stored_attempt = None;
+                                    self.registration.clear();
+                                    if self.connection.me_index().is_none() {
+                                        let contestants = std::mem::take(&mut self.contestants);
+                                        self.register(&contestants).await;
+                                    }
+                                },
+                                ServerError::Taken if self.stored_attempt.is_some() => {
+                                    let taken = self.stored_attempt.take();
+                                    println!("{}: someone else controls contestant {:?}, forgetting it", self.id, taken);
+                                    self.registration.clear();
+                                    if self.connection.me_index().is_none() {
+                                        let contestants = self.contestants.clone();
+                                        self.register_except(&contestants, taken).await;
+                                    }
+                                },
+                                ServerError::LobbyFull => {
+                                    let delay = self.connection.backoff.next_jittered();
+                                    println!("{}: lobby is full, registering again in {:?}", self.id, delay);
+                                    self.retry_registration = Some(Instant::now() + delay);
+                                },
+                                ServerError::Taken | ServerError::Other => {},
+                            }
+                            continue;
+                        },
+                        Some(ServerMessage::Ack { ack }) => {
+                            if !ack.accepted {
+                                println!("{}: server didn't accept {}: {:?}", self.id, ack.input, ack.reason);
+                            }
+                            self.buzz_acknowledged();
+                            continue;
+                        },
+                        Some(ServerMessage::State(msg)) => msg,
+                    };
+                    let previous = self.phase.replace(msg.game.phase.clone());
+                    let (Some(me_index), Some(me)) = (self.connection.me_index(), self.connection.me().cloned()) else {
+                        self.contestants = msg.game.contestants;
+                        if !*presence_rx.borrow() {
+                            let remembered = self.registration.contestant().and_then(|c| self.contestants.get(c));
+                            if remembered.is_some_and(|c| c.connected) {
+                                println!("{}: absent, but the server still considers us connected", self.id);
+                            }
+                        } else if self.retry_registration.is_none() {
+                            let contestants = self.contestants.clone();
+                            self.register(&contestants).await;
+                            sleep(Duration::from_secs(1)).await;
                         }
-                        self.connection.send(&response).await;
-                        sleep(Duration::from_secs(1)).await;
                         continue;
                     };
-                    let my_led = *self.led_tx.borrow();
-                    if my_led != me.indicate {
-                        self.led_tx.send_replace(!my_led);
+                    self.stored_attempt = None;
+                    self.registration.store(me_index);
+                    let outage = self.connection.outage.take();
+                    if let Some(press) = self.buffered_press.take() {
+                        self.send_buffered(press, outage, me_index).await;
+                    }
+                    self.notifier.status(&self.id, self.status(true));
+                    self.notifier.ready();
+                    if me.indicate {
+                        self.buzz_acknowledged();
                     }
+                    if let Some(transition) = led_transition(previous.as_ref(), &msg.game.phase, self.buzzing_flash) {
+                        self.overlay(transition, self.buzzing_flash.duration());
+                    } else if matches!(self.led_overlay, Some((LedPattern::Flash(_), _))) && previous != self.phase {
+                        // buzzing closed before the flash was over
+                        self.led_overlay = None;
+                    }
+                    self.set_led(led_pattern(&msg.game.phase, &me));
+                    self.set_screen(screen(&msg.game, me_index));
+                },
+                _ = sleep_until(self.retry_registration.unwrap_or_else(Instant::now)), if self.retry_registration.is_some() => {
+                    self.retry_registration = None;
+                    let contestants = self.contestants.clone();
+                    self.register(&contestants).await;
+                },
+                _ = sleep_until(self.led_overlay.map_or_else(Instant::now, |(_, until)| until)), if self.led_overlay.is_some() => {
+                    self.led_overlay = None;
+                    self.show_led(self.led_pattern);
                 },
                 _ = self.ping_interval.tick() => { self.connection.ping().await; },
             }
         }
     }
+    async fn buzz(&mut self, me: usize, pressed: Instant) {
+        self.pending_buzz.pressed(pressed.into_std());
+        let response = serde_json::json!({
+            "type": "buzz",
+            "contestant": me,
+        }).to_string();
+        self.connection.send(&response).await;
+    }
+    /// Sends a press from while the connection was lost, unless it took too
+    /// long to come back or the game moved on since
+    async fn send_buffered(&mut self, press: BufferedPress, outage: Option<Outage>, me: usize) {
+        let back = outage.and_then(|o| o.until).unwrap_or_else(Instant::now);
+        let waited = back.saturating_duration_since(press.at);
+        if waited > self.buzz_buffer {
+            println!("{}: dropping a press, the connection was back {:?} after it", self.id, waited);
+        } else if self.phase != press.phase {
+            println!("{}: dropping a press during {:?}, the game moved on to {:?}", self.id, press.phase, self.phase);
+        } else {
+            println!("{}: sending a press from {:?} ago", self.id, press.at.elapsed());
+            self.buzz(me, press.at).await;
+        }
+    }
+    /// Reconnects as the stored contestant, then as the one with our name
+    /// hint, and registers a new contestant if neither exists
+    async fn register(&mut self, contestants: &[Contestant]) {
+        self.register_except(contestants, None).await;
+    }
+    /// Like [HandsetCommunicator::register], but never as `taken`. A stored
+    /// contestant with another name hint is from an earlier game and
+    /// forgotten.
+    async fn register_except(&mut self, contestants: &[Contestant], taken: Option<usize>) {
+        let stored = self.registration.contestant();
+        if stored.is_some_and(|stored| contestants.get(stored).is_some_and(|c| c.name_hint != self.name_hint)) {
+            println!("{}: contestant {:?} is someone else now, forgetting it", self.id, stored);
+            self.registration.clear();
+        }
+        let mine = |(index, c): &(usize, &Contestant)| c.name_hint == self.name_hint && Some(*index) != taken;
+        let response = if let Some(contestant) = self.registration.contestant() {
+            self.stored_attempt = Some(contestant);
+            serde_json::json!({
+                "type": "reconnect_contestant",
+                "contestant": contestant,
+            })
+        } else if let Some((index, _)) = contestants.iter().enumerate().find(mine) {
+            serde_json::json!({
+                "type": "reconnect_contestant",
+                "contestant": index,
+            })
+        } else {
+            serde_json::json!({
+                "type": "connect_contestant",
+                "name_hint": self.name_hint,
+            })
+        };
+        self.connection.send(&response.to_string()).await;
+    }
+    /// For the systemd status line
+    fn status(&self, present: bool) -> String {
+        match &self.connection.inner {
+            SocketState::Registered { me: (me_index, _), .. } => format!("controlling contestant {}", me_index),
+            _ if !present => "handset absent".to_owned(),
+            SocketState::Connected { .. } => "connected, registering".to_owned(),
+            SocketState::Unconnected => match self.connection.backoff.attempts() {
+                0 => "connecting".to_owned(),
+                attempt => format!("reconnecting, attempt {}", attempt),
+            },
+        }
+    }
+    fn buzz_acknowledged(&mut self) {
+        let Some(latencies) = &self.latencies else { return };
+        if let Some(latency) = self.pending_buzz.acknowledged(std::time::Instant::now()) {
+            latencies.lock().expect("poisoned latencies").record(latency);
+        }
+    }
+    /// Shows `pattern`, or after the overlay if one is going on
+    fn set_led(&mut self, pattern: LedPattern) {
+        self.led_pattern = pattern;
+        if self.led_overlay.is_none() {
+            self.show_led(pattern);
+        }
+    }
+    /// Shows `pattern` for a while, e.g. a flicker to tell the contestant
+    /// that their press was noticed, but ignored
+    fn overlay(&mut self, pattern: LedPattern, duration: Duration) {
+        self.led_overlay = Some((pattern, Instant::now() + duration));
+        self.show_led(pattern);
+    }
+    fn set_screen(&self, screen: Screen) {
+        self.screen_tx.send_if_modified(|current| {
+            let modified = *current != screen;
+            *current = screen;
+            modified
+        });
+    }
+    fn show_led(&self, pattern: LedPattern) {
+        self.led_tx.send_if_modified(|current| {
+            let modified = *current != pattern;
+            *current = pattern;
+            modified
+        });
+    }
+}
+
+/// A press while the connection was lost
+struct BufferedPress {
+    at: Instant,
+    /// the game has to be in the same phase once we're registered again
+    phase: Option<GamePhase>,
+}
+
+/// From losing the connection as a registered contestant until the server
+/// registers us again
+struct Outage {
+    since: Instant,
+    /// when we were connected again, the registration comes later
+    until: Option<Instant>,
 }
 
 enum SocketState {
@@ -215,14 +570,31 @@ impl SocketState {
             Self::Registered { socket, .. } => Self::Registered { socket, me },
         };
     }
+    /// The server stopped considering us the controller of a contestant
+    fn unregister(&mut self) {
+        let old = std::mem::replace(self, Self::Unconnected);
+        *self = match old {
+            Self::Registered { socket, .. } => Self::Connected { socket },
+            other => other,
+        };
+    }
 }
 
 struct Connection {
     id: String, // for convenience, matches id from HandsetCommunicator
     inner: SocketState,
+    /// used for `wss://` addresses, see [tls::client_config]
+    tls: Arc<rustls::ClientConfig>,
+    /// taken by the communicator once registered again
+    outage: Option<Outage>,
     uri: Uri,
-    reconnect_interval: Interval,
-    ping_in_transit: bool,
+    backoff: Backoff,
+    /// no reconnection attempts before this, survives the receive future being
+    /// dropped by other events
+    next_attempt: Instant,
+    pinger: Pinger,
+    /// pongs that take longer than this are logged
+    slow_pong: Duration,
 }
 
 impl Connection {
@@ -243,6 +615,9 @@ impl Connection {
     fn is_disconnected(&self) -> bool {
         matches!(self.inner, SocketState::Unconnected)
     }
+    fn is_connected(&self) -> bool {
+        matches!(self.inner, SocketState::Connected { .. })
+    }
     async fn send(&mut self, msg: &str) {
         let s = match self.inner {
             SocketState::Unconnected => { return; },
@@ -254,14 +629,20 @@ impl Connection {
             self.disconnect();
         }
     }
-    async fn receive(&mut self, reconnect: bool) -> Option<ServerState> {
-        // try to reconnect
-        if self.is_disconnected() && reconnect {
+    async fn receive(&mut self, reconnect: bool) -> Option<ServerMessage> {
+        if self.is_disconnected() {
+            if !reconnect {
+                // wait for the handset to come back
+                return std::future::pending().await;
+            }
+            sleep_until(self.next_attempt).await;
             self.connect().await;
         }
         let socket = match self.inner {
-            SocketState::Unconnected => { 
-                self.reconnect_interval.tick().await;
+            SocketState::Unconnected => {
+                let delay = self.backoff.next_jittered();
+                println!("{}: reconnecting in {:?}", self.id, delay);
+                self.next_attempt = Instant::now() + delay;
                 return None;
             },
             SocketState::Connected { socket: ref mut s } => s,
@@ -287,8 +668,12 @@ impl Connection {
                 let _ = socket.send(Message::Pong(payload)).await;
                 return None;
             },
-            Message::Pong(_) => {
-                self.ping_in_transit = false;
+            Message::Pong(payload) => {
+                if let Some(rtt) = self.pinger.received(&payload, Instant::now()) {
+                    if rtt >= self.slow_pong {
+                        println!("{}: slow pong, round trip took {:?}", self.id, rtt);
+                    }
+                }
                 return None;
             },
             Message::Close(..) => {
@@ -298,17 +683,18 @@ impl Connection {
             Message::Text(msg) => msg,
             _ => { return None; },
         };
-        let msg = match serde_json::from_str::<ServerState>(&msg) {
+        let msg = match serde_json::from_str::<ServerMessage>(&msg) {
             Err(e) => {
                 println!("{}: error parsing server message: {:?}: {}", self.id, e, msg);
                 return None;
             },
-            Ok(msg) => msg,
+            Ok(ServerMessage::State(msg)) => msg,
+            Ok(error) => return Some(error),
         };
         // println!("{}: received {:?}", self.id, &msg);
 
         match msg.connection.controlling {
-            None => {},
+            None => self.inner.unregister(),
             Some(i) => {
                 let Some(me) = msg.game.contestants.get(i) else {
                     println!("{}: server thought this was controlling contestant {}, but there are only {} contestants connected", self.id, i, msg.game.contestants.len());
@@ -316,47 +702,158 @@ impl Connection {
                     return None;
                 };
                 self.inner.register(i, me.clone());
+                self.backoff.reset();
             },
         }
 
-        Some(msg)
+        Some(ServerMessage::State(msg))
     }
     async fn connect(&mut self) {
         if matches!(self.inner, SocketState::Connected { .. } | SocketState::Registered { .. }) {
             return;
         }
-        match tokio_tungstenite::connect_async(build_request(self.uri.to_owned())).await {
-            Err(e) => { println!("{}: failure to connect: {}", self.id, e); },
-            Ok((s,_)) => self.inner = SocketState::Connected { socket: s },
+        match open(&self.uri, &self.tls).await {
+            Err(e) => { println!("{}: failure to connect: {}", self.id, ConnectFailure::from(e)); },
+            Ok((s, response)) => match check_protocol(response.headers()) {
+                Err(e) => { println!("{}: failure to connect: {}", self.id, e); },
+                Ok(warning) => {
+                    if let Some(warning) = warning {
+                        println!("{}: {}", self.id, warning);
+                    }
+                    self.inner = SocketState::Connected { socket: s };
+                    if let Some(outage) = &mut self.outage {
+                        outage.until = Some(Instant::now());
+                    }
+                },
+            },
         }
     }
     fn disconnect(&mut self) {
+        if matches!(self.inner, SocketState::Registered { .. }) {
+            self.outage = Some(Outage { since: Instant::now(), until: None });
+        } else if let Some(outage) = &mut self.outage {
+            // lost again before the registration
+            outage.until = None;
+        }
         self.inner = SocketState::Unconnected;
+        self.pinger.reset();
     }
     async fn ping(&mut self) {
-        // we are supposed to ping again, but the previos one hasn't come back yet...
-        if self.ping_in_transit {
-            self.ping_in_transit = false;
-            self.disconnect();
-            println!("{}: missing Pong! reconnecting", self.id);
-            return;
-        }
         let socket = match self.inner {
             SocketState::Unconnected => { return; },
             SocketState::Connected { socket: ref mut s } => s,
             SocketState::Registered { socket: ref mut s, .. } => s,
         };
-        if let Err(e) = socket.send(Message::Ping("ping".into())).await {
+        let payload = match self.pinger.next(Instant::now()) {
+            Ok(payload) => payload,
+            Err(missed) => {
+                println!("{}: {} pings weren't answered ({:?} ago), reconnecting", self.id, missed.len(), missed);
+                self.disconnect();
+                return;
+            },
+        };
+        if let Err(e) = socket.send(Message::Ping(payload)).await {
             self.disconnect();
             println!("{}: error while sending ping: {:?}", self.id, e);
         };
-        self.ping_in_transit = true;
     }
 }
 
+/// Connects to `uri`, over TLS for `wss://` addresses
+async fn open(uri: &Uri, tls: &Arc<rustls::ClientConfig>) -> Result<(Websocket, tokio_tungstenite::tungstenite::handshake::client::Response), tokio_tungstenite::tungstenite::Error> {
+    let connector = tokio_tungstenite::Connector::Rustls(tls.clone());
+    tokio_tungstenite::connect_async_tls_with_config(build_request(uri.to_owned()), None, false, Some(connector)).await
+}
+
+/// Why connecting to the server failed, so that an unreachable server can be
+/// told apart from TLS problems in the logs
+#[derive(Debug, PartialEq)]
+enum ConnectFailure {
+    /// nothing is listening, or the network is down
+    Unreachable(String),
+    /// the TLS handshake failed, like when the certificate isn't trusted,
+    /// see `--ca-cert` and `--insecure`
+    Tls(String),
+    /// the server answered, but refused the websocket upgrade
+    Rejected(u16),
+    /// the server's protocol is older than [MIN_PROTOCOL_VERSION]
+    Incompatible(u32),
+    Other(String),
+}
+
+impl From<tokio_tungstenite::tungstenite::Error> for ConnectFailure {
+    fn from(error: tokio_tungstenite::tungstenite::Error) -> Self {
+        use tokio_tungstenite::tungstenite::Error;
+        match error {
+            // the handshake errors of rustls are wrapped in IO errors
+            Error::Io(e) if e.get_ref().is_some_and(|inner| inner.is::<rustls::Error>()) => Self::Tls(e.to_string()),
+            Error::Io(e) => Self::Unreachable(e.to_string()),
+            Error::Tls(e) => Self::Tls(e.to_string()),
+            Error::Http(response) => Self::Rejected(response.status().as_u16()),
+            e => Self::Other(e.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for ConnectFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unreachable(e) => write!(f, "server unreachable: {}", e),
+            Self::Tls(e) => write!(f, "TLS error: {}", e),
+            Self::Rejected(status) => write!(f, "server refused the websocket with status {}", status),
+            Self::Incompatible(version) => {
+                write!(f, "server speaks protocol {}, but this controller needs at least {}, update the server", version, MIN_PROTOCOL_VERSION)
+            },
+            Self::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Compares the [PROTOCOL_HEADER] of the upgrade with what this controller
+/// speaks, returns a warning for mismatches that might still work. Servers
+/// without it predate it.
+fn check_protocol(headers: &tokio_tungstenite::tungstenite::http::HeaderMap) -> Result<Option<String>, ConnectFailure> {
+    let announced = headers.get(PROTOCOL_HEADER).and_then(|v| v.to_str().ok()).and_then(|v| v.trim().parse::<u32>().ok());
+    match announced {
+        None => Ok(Some(format!("server doesn't announce its protocol, it's older than this controller's {}", PROTOCOL_VERSION))),
+        Some(version) if version < MIN_PROTOCOL_VERSION => Err(ConnectFailure::Incompatible(version)),
+        Some(version) if version != PROTOCOL_VERSION => {
+            Ok(Some(format!("server speaks protocol {}, this controller {}, some inputs might be rejected", version, PROTOCOL_VERSION)))
+        },
+        Some(_) => Ok(None),
+    }
+}
+
+/// The `Host` header for `uri`, with the port unless it's the default one
+fn host_header(uri: &Uri) -> String {
+    let host = uri.host().expect("no host in websocket URL");
+    let default_port = match uri.scheme_str() {
+        Some("wss") => 443,
+        _ => 80,
+    };
+    match uri.port_u16() {
+        Some(port) if port != default_port => format!("{}:{}", host, port),
+        _ => host.to_owned(),
+    }
+}
+
+/// The controller doesn't need the board, so the server is asked to leave it
+/// out of every state. Servers that don't know the parameter ignore it.
+fn with_scope(uri: Uri) -> Uri {
+    let Some(path_and_query) = uri.path_and_query() else { return uri };
+    let joined = match path_and_query.query() {
+        Some(query) if query.split('&').any(|p| p.starts_with("scope=")) => return uri,
+        Some(query) => format!("{}?{}&scope=contestants", path_and_query.path(), query),
+        None => format!("{}?scope=contestants", path_and_query.path()),
+    };
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = joined.parse().ok();
+    Uri::from_parts(parts).unwrap_or(uri)
+}
+
 fn build_request(uri: tokio_tungstenite::tungstenite::http::Uri) -> Request {
-    Request::get(uri.to_owned())
-        .header("Host", uri.host().expect("no host in websocket URL"))
+    Request::get(with_scope(uri.to_owned()))
+        .header("Host", host_header(&uri))
         .header("User-Agent", format!("{} ({})", env!("CARGO_BIN_NAME"), env!("CARGO_PKG_VERSION")))
         .header("Accept", "application/json")
         .header("Connection", "Upgrade")
@@ -367,67 +864,1030 @@ fn build_request(uri: tokio_tungstenite::tungstenite::http::Uri) -> Request {
         .expect("failed to build connection request")
 }
 
+/// Command line arguments, they override values from the config file
+#[derive(Default)]
+struct Args {
+    config: Option<PathBuf>,
+    uri: Option<String>,
+    /// read buzzes from stdin instead of GPIO pins
+    simulate: bool,
+    /// one entry per handset
+    pins: Vec<PinTiples>,
+    /// number of simulated handsets
+    handsets: Option<usize>,
+    debounce_ms: Option<u64>,
+    ping_interval_ms: Option<u64>,
+    ping_misses: Option<usize>,
+    id: Option<String>,
+    /// measure the time from a press until the server acknowledged it
+    measure_latency: bool,
+    /// send buzzes in every phase
+    always_send: bool,
+    /// simulate one handset per binding, driven by single key presses
+    simulate_keys: Option<Vec<keyboard::KeyBinding>>,
+    ca_cert: Option<PathBuf>,
+    /// accept any certificate of `wss://` servers
+    insecure: bool,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().ok_or(format!("{} needs a value", name));
+            match arg.as_str() {
+                "--simulate" => parsed.simulate = true,
+                "--measure-latency" => parsed.measure_latency = true,
+                "--always-send" => parsed.always_send = true,
+                "--insecure" => parsed.insecure = true,
+                "--ca-cert" => parsed.ca_cert = Some(value("--ca-cert")?.into()),
+                "--simulate-keys" => parsed.simulate_keys = Some(keyboard::parse_bindings(&value("--simulate-keys")?)?),
+                "--config" => parsed.config = Some(value("--config")?.into()),
+                "--id" => parsed.id = Some(value("--id")?),
+                "--pins" => {
+                    let value = value("--pins")?;
+                    let numbers = value.split(',')
+                        .map(|p| p.trim().parse::<u8>())
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|e| format!("invalid pin in {:?}: {}", value, e))?;
+                    let [switch, presence, led] = numbers[..] else {
+                        return Err(format!("expected <switch>,<presence>,<led>, got {:?}", value));
+                    };
+                    parsed.pins.push(PinTiples::new(switch, presence, led));
+                },
+                "--handsets" => {
+                    let value = value("--handsets")?;
+                    parsed.handsets = Some(value.parse().map_err(|e| format!("invalid handset count {:?}: {}", value, e))?);
+                },
+                "--debounce" => {
+                    let value = value("--debounce")?;
+                    parsed.debounce_ms = Some(value.parse().map_err(|e| format!("invalid debounce window {:?}: {}", value, e))?);
+                },
+                "--ping-interval" => {
+                    let value = value("--ping-interval")?;
+                    parsed.ping_interval_ms = Some(value.parse().map_err(|e| format!("invalid ping interval {:?}: {}", value, e))?);
+                },
+                "--ping-misses" => {
+                    let value = value("--ping-misses")?;
+                    parsed.ping_misses = Some(value.parse().map_err(|e| format!("invalid number of pings {:?}: {}", value, e))?);
+                },
+                _ if parsed.uri.is_none() && !arg.starts_with("--") => parsed.uri = Some(arg),
+                _ => return Err(format!("unexpected argument {:?}", arg)),
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn apply(self, config: &mut Config) {
+        if let Some(uri) = self.uri {
+            config.server.uri = Some(uri);
+        }
+        if let Some(id) = self.id {
+            config.identity.id = Some(id);
+        }
+        if let Some(debounce_ms) = self.debounce_ms {
+            config.debounce_ms = debounce_ms;
+        }
+        if let Some(ping_interval_ms) = self.ping_interval_ms {
+            config.server.ping_interval_ms = ping_interval_ms;
+        }
+        if let Some(ping_misses) = self.ping_misses {
+            config.server.ping_misses = ping_misses;
+        }
+        if self.always_send {
+            config.always_send = true;
+        }
+        if let Some(ca_cert) = self.ca_cert {
+            config.server.ca_cert = Some(ca_cert);
+        }
+        if self.insecure {
+            config.server.insecure = true;
+        }
+        if !self.pins.is_empty() {
+            config.handsets = self.pins.into_iter()
+                .map(|pins| HandsetConfig { pins: Some(pins), ..HandsetConfig::default() })
+                .collect();
+        }
+        if let Some(count) = self.handsets {
+            config.handsets.resize(count, HandsetConfig::default());
+        }
+    }
+}
+
+#[cfg(feature = "rppal")]
+fn gpio_handsets(handsets: &[HandsetConfig]) -> Result<Vec<Handset>, String> {
+    handsets.iter()
+        .enumerate()
+        .map(|(i, h)| {
+            let pins = h.pins.as_ref().ok_or(format!("handset {} has no `pins`", i + 1))?;
+            let handset = Handset::gpio((i + 1).to_string(), pins).map_err(|e| format!("handset {}: {}", i + 1, e))?;
+            Ok(attach_display(handset, h))
+        })
+        .collect()
+}
+
+/// A display that doesn't work is left out, the handset works without it
+#[cfg(feature = "display")]
+fn attach_display(handset: Handset, config: &HandsetConfig) -> Handset {
+    let Some(display) = &config.display else { return handset };
+    match display::Ssd1306::new(display.bus, display.address, display.height) {
+        Ok(ssd1306) => handset.with_display(Box::new(ssd1306)),
+        Err(e) => {
+            eprintln!("handset {}: no display: {}", handset.name, e);
+            handset
+        },
+    }
+}
+
+#[cfg(not(feature = "display"))]
+#[cfg_attr(not(feature = "rppal"), allow(dead_code))]
+fn attach_display(handset: Handset, config: &HandsetConfig) -> Handset {
+    if config.display.is_some() {
+        eprintln!("handset {}: built without display support, ignoring `display`", handset.name);
+    }
+    handset
+}
+
+#[cfg(not(feature = "rppal"))]
+fn gpio_handsets(_handsets: &[HandsetConfig]) -> Result<Vec<Handset>, String> {
+    Err("built without GPIO support, use --simulate".to_owned())
+}
+
+/// Handsets numbered from 1, pressed through the returned [Presses] and
+/// [Presence]
+fn simulated_handsets(count: usize) -> (Presses, Presence, Vec<Handset>) {
+    let (presses, switches) = handset::simulated_switches(count);
+    let (presence, lines) = handset::simulated_presence(count);
+    let handsets = switches.into_iter()
+        .zip(lines)
+        .enumerate()
+        .map(|(i, (switch, line))| {
+            let name = (i + 1).to_string();
+            Handset::new(
+                name.clone(),
+                Box::new(switch),
+                Box::new(line),
+                Box::new(handset::PrintedLed { name: name.clone(), pattern: None }),
+            ).with_display(Box::new(display::PrintedDisplay { name }))
+        })
+        .collect();
+    (presses, presence, handsets)
+}
+
+/// Runs every handset in its own task until cancelled
+#[allow(clippy::too_many_arguments)] // shared by every handset
+async fn run(
+    config: Config,
+    machine_id: String,
+    uri: Uri,
+    handsets: Vec<Handset>,
+    tls: Arc<rustls::ClientConfig>,
+    latencies: Option<Arc<Mutex<Latencies>>>,
+    notifier: Notifier,
+    cancellation_token: CancellationToken,
+) {
+    let mut tasks = tokio::task::JoinSet::new();
+    for handset in handsets {
+        let mut handset = handset
+            .with_debounce(config.debounce())
+            .with_presence_settle(config.presence_settle());
+        let id = format!("{}-{}", machine_id, handset.name);
+        let name_hint = match &config.identity.name_hint_prefix {
+            Some(prefix) => format!("{}{}", prefix, handset.name),
+            None => id.clone(),
+        };
+        let registration = Registration::load(&config.identity.state_directory, &id);
+        let mut communicator = HandsetCommunicator::from_handset_with_request(id, name_hint, registration, &handset, uri.to_owned(), &config.server, tls.clone())
+            .measuring(latencies.clone())
+            .notifying(notifier.clone())
+            .always_sending(config.always_send)
+            .buffering(config.buzz_buffer())
+            .flashing(config.buzzing_flash);
+        if let Some(display) = handset.take_display() {
+            tasks.spawn(display::drive(display, handset.screen_tx.subscribe(), handset.name.clone(), cancellation_token.clone()));
+        }
+        let update_interval = config.update_interval();
+        let cancellation_token = cancellation_token.clone();
+        tasks.spawn(async move {
+            tokio::join!(
+                // handle websocket communication
+                communicator.communicate(cancellation_token.clone()),
+                async {
+                    // handle hardware pins
+                    let mut interval = interval(update_interval);
+                    while !cancellation_token.is_cancelled() {
+                        handset.update();
+                        tokio::select! {
+                            _ = cancellation_token.cancelled() => {},
+                            _ = interval.tick() => {},
+                        }
+                    }
+                },
+            );
+        });
+    }
+    while (tasks.join_next().await).is_some() { }
+}
+
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> std::result::Result<(), std::boxed::Box<dyn std::error::Error>> {
-    let mut machine_id = IdBuilder::new(Encryption::SHA256)
-        .add_component(HWIDComponent::SystemID)
-        .add_component(HWIDComponent::CPUID)
-        .add_component(HWIDComponent::MachineName)
-        .build("nonceorsomminidunno")
-        .expect("Can't build a machine id");
-    machine_id.truncate(16);
-
-    let uri: Uri = std::env::args()
-        .nth(1)
-        .expect(&format!("usage: {} <ws-address>", env!("CARGO_BIN_NAME")))
+    let args = Args::parse(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        eprintln!(
+            "usage: {} [--config <file>] [--simulate [--handsets <n>] | --simulate-keys <keys>] [--measure-latency] [--always-send] [--ca-cert <file>] [--insecure] [--id <id>] [--debounce <ms>] [--ping-interval <ms>] [--ping-misses <n>] [--pins <switch>,<presence>,<led>]... [<ws-address>]",
+            env!("CARGO_BIN_NAME"),
+        );
+        std::process::exit(2);
+    });
+    let mut config = Config::load(args.config.as_deref())?;
+    let simulate = args.simulate;
+    let simulate_keys = args.simulate_keys.clone();
+    let latencies = args.measure_latency.then(|| Arc::new(Mutex::new(Latencies::default())));
+    args.apply(&mut config);
+
+    let uri: Uri = config.server.uri.as_deref()
+        .ok_or("no ws-address given, pass one or set `server.uri`")?
         .parse()
-        .expect("Could not parse ws-address");
+        .map_err(|e| format!("invalid ws-address: {}", e))?;
     println!("connecting to {:?}", uri);
+    let tls = tls::client_config(config.server.ca_cert.as_deref(), config.server.insecure)?;
+    if config.server.insecure {
+        println!("accepting any certificate of the server, don't use --insecure at a game");
+    }
 
-    let mut handsets: Vec<Handset> = [
-        PinTiples::new(21, 20, 26),
-        PinTiples::new(13, 19, 16),
-        PinTiples::new(5, 6, 12),
-        PinTiples::new(0, 1, 7),
-    ]
-        .iter()
-        .map(|p| p.try_into())
-        .collect::<Result<Vec<_>,_>>()?;
-
-    let communicators: Vec<_> = handsets.iter()
-        .map(|h| HandsetCommunicator::from_handset_with_request(machine_id.to_owned(), h, uri.to_owned()))
-        .collect();
+    let machine_id = match config.identity.id.clone() {
+        Some(id) => id,
+        None => {
+            let mut machine_id = IdBuilder::new(Encryption::SHA256)
+                .add_component(HWIDComponent::SystemID)
+                .add_component(HWIDComponent::CPUID)
+                .add_component(HWIDComponent::MachineName)
+                .build("nonceorsomminidunno")
+                .expect("Can't build a machine id");
+            machine_id.truncate(16);
+            machine_id
+        },
+    };
 
     let cancellation_token = CancellationToken::new();
+    let notifier = Notifier::from_env().unwrap_or_else(|e| {
+        eprintln!("can't notify systemd: {}", e);
+        Notifier::default()
+    });
+    let mut _raw_mode = None;
+
+    let handsets: Vec<Handset> = if let Some(bindings) = simulate_keys {
+        let (presses, presence, handsets) = simulated_handsets(bindings.len());
+        for (i, binding) in bindings.iter().enumerate() {
+            println!("handset {}: {:?} buzzes, {:?} toggles presence", i + 1, binding.buzz, binding.presence);
+        }
+        println!("{:?} quits", keyboard::QUIT);
+        match keyboard::RawMode::enable() {
+            Ok(raw_mode) => _raw_mode = Some(raw_mode),
+            Err(e) => println!("can't read single key presses, end each with enter: {}", e),
+        }
+        keyboard::Keyboard { bindings, presses, presence }.read_stdin(cancellation_token.clone());
+        handsets
+    } else if simulate {
+        let (presses, _, handsets) = simulated_handsets(config.handsets.len());
+        println!("simulating {} handsets, enter one of {:?} to buzz (an empty line buzzes the first)", handsets.len(), config.keys());
+        presses.read_stdin(config.keys());
+        handsets
+    } else {
+        gpio_handsets(&config.handsets)?
+    };
 
     tokio::join!(
+        run(config, machine_id, uri, handsets, tls, latencies.clone(), notifier.clone(), cancellation_token.clone()),
         async {
-            // handle websocket communication
-            let mut tasks = tokio::task::JoinSet::new();
-            for mut communicator in communicators {
-                let cancellation_token = cancellation_token.clone();
-                tasks.spawn(async move { communicator.communicate(cancellation_token).await });
+            // keep the systemd watchdog happy while the runtime is alive
+            let Some(period) = systemd::watchdog_interval().filter(|_| notifier.is_enabled()) else { return };
+            let mut watchdog = interval(period);
+            loop {
+                tokio::select! {
+                    _ = cancellation_token.cancelled() => return,
+                    _ = watchdog.tick() => notifier.watchdog(),
+                }
             }
-            while (tasks.join_next().await).is_some() { }
         },
         async {
-            // handle hardware pins
-            let mut interval = interval(Duration::from_millis(1000/UPDATE_HERTZ));
-            while !cancellation_token.is_cancelled() {
-                for handset in &mut handsets {
-                    handset.update();
+            // report latencies
+            let Some(latencies) = &latencies else { return };
+            let mut report = interval(LATENCY_REPORT_INTERVAL);
+            report.tick().await;
+            loop {
+                tokio::select! {
+                    _ = cancellation_token.cancelled() => return,
+                    _ = report.tick() => {},
+                }
+                match latencies.lock().expect("poisoned latencies").summary() {
+                    Some(summary) => println!("latency: {}", summary),
+                    None => println!("latency: no buzzes yet"),
                 }
-                interval.tick().await;
             }
         },
         async {
             // handle termination
-            if let Err(sigint_error) = tokio::signal::ctrl_c().await {
-                dbg!(sigint_error);
+            tokio::select! {
+                _ = cancellation_token.cancelled() => {},
+                sigint = tokio::signal::ctrl_c() => {
+                    if let Err(sigint_error) = sigint {
+                        dbg!(sigint_error);
+                    }
+                    println!("Stopping");
+                    cancellation_token.cancel();
+                },
             }
-            println!("Stopping");
-            cancellation_token.cancel();
         },
     );
+    notifier.stopping();
+    if let Some(latencies) = latencies {
+        let summary = latencies.lock().expect("poisoned latencies").summary();
+        println!("{}", serde_json::to_string(&summary)?);
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mock_server::{Game, MockServer, Reply};
+    use tokio::net::TcpListener;
+
+    /// Keeps registrations in a temporary directory instead of the system one
+    fn config(state_directory: &tempfile::TempDir) -> Config {
+        let mut config = Config::default();
+        config.identity.state_directory = state_directory.path().to_owned();
+        config
+    }
+
+    fn default_tls() -> Arc<rustls::ClientConfig> {
+        tls::client_config(None, false).unwrap()
+    }
+
+    /// A controller running against a [MockServer] until stopped
+    struct Controller {
+        cancellation_token: CancellationToken,
+        task: tokio::task::JoinHandle<()>,
+        statuses: Statuses,
+    }
+
+    impl Controller {
+        fn start(server: &MockServer, config: Config, handsets: Vec<Handset>) -> Self {
+            let cancellation_token = CancellationToken::new();
+            let uri = server.uri().parse().unwrap();
+            let (statuses, notifier) = Statuses::listen();
+            let task = tokio::spawn(run(config, "machine".to_owned(), uri, handsets, default_tls(), None, notifier, cancellation_token.clone()));
+            Self { cancellation_token, task, statuses }
+        }
+
+        async fn registered(&mut self) {
+            self.statuses.registered().await;
+        }
+
+        async fn stop(self) {
+            self.cancellation_token.cancel();
+            self.task.await.unwrap();
+        }
+    }
+
+    /// The status lines a controller reports to systemd. They are read on
+    /// their own thread, the notifier blocks once too many are unread.
+    struct Statuses {
+        lines: tokio::sync::mpsc::UnboundedReceiver<String>,
+        _directory: tempfile::TempDir,
+    }
+
+    impl Statuses {
+        fn listen() -> (Self, Notifier) {
+            let directory = tempfile::tempdir().unwrap();
+            let path = directory.path().join("notify");
+            let socket = std::os::unix::net::UnixDatagram::bind(&path).unwrap();
+            socket.set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+            let (tx, lines) = tokio::sync::mpsc::unbounded_channel();
+            std::thread::spawn(move || {
+                let mut buffer = [0; 1024];
+                while !tx.is_closed() {
+                    if let Ok(length) = socket.recv(&mut buffer) {
+                        let _ = tx.send(String::from_utf8_lossy(&buffer[..length]).into_owned());
+                    }
+                }
+            });
+            let notifier = Notifier::connect(path.to_str().unwrap()).unwrap();
+            (Self { lines, _directory: directory }, notifier)
+        }
+
+        /// Waits for the next status in which the handset controls its
+        /// contestant. Presses buffered until then were sent before it.
+        async fn registered(&mut self) {
+            tokio::time::timeout(Duration::from_secs(10), async {
+                while !self.lines.recv().await.unwrap().contains("controlling contestant") {}
+            }).await.expect("the handset never registered");
+        }
+    }
+
+    #[test]
+    fn phases_map_to_led_patterns() {
+        let pattern = |phase: &str, indicate: bool| {
+            let phase: GamePhase = serde_json::from_str(phase).unwrap();
+            led_pattern(&phase, &Contestant { name: None, points: 0, indicate, name_hint: "me".to_owned(), connected: true })
+        };
+        assert_eq!(pattern(r#""Connecting""#, false), LedPattern::Off);
+        assert_eq!(pattern(r#""Connecting""#, true), LedPattern::Solid);
+        assert_eq!(pattern(r#"{"Picking":{"contestant":0}}"#, true), LedPattern::Solid);
+        assert_eq!(pattern(r#"{"Clue":{"clue":[0,1],"exclusive":null}}"#, false), LedPattern::Off);
+        assert_eq!(pattern(r#"{"Buzzing":{"clue":[0,1]}}"#, false), LedPattern::FastBlink);
+        assert_eq!(pattern(r#"{"Buzzed":{"clue":[0,1],"contestant":1}}"#, false), LedPattern::Off);
+        assert_eq!(pattern(r#"{"Buzzed":{"clue":[0,1],"contestant":0}}"#, true), LedPattern::Solid);
+        assert_eq!(pattern(r#""Score""#, false), LedPattern::Off);
+        assert_eq!(pattern(r#"{"Practice":{"winner":null}}"#, false), LedPattern::FastBlink);
+        assert_eq!(pattern(r#"{"Practice":{"winner":1}}"#, false), LedPattern::Off);
+        assert_eq!(pattern(r#"{"Practice":{"winner":0}}"#, true), LedPattern::Solid);
+    }
+
+    #[test]
+    fn only_opening_buzzing_flashes() {
+        let phase = |phase: &str| serde_json::from_str::<GamePhase>(phase).unwrap();
+        let (clue, buzzing, buzzed) = (
+            phase(r#"{"Clue":{"clue":[0,1],"exclusive":null}}"#),
+            phase(r#"{"Buzzing":{"clue":[0,1]}}"#),
+            phase(r#"{"Buzzed":{"clue":[0,1],"contestant":1}}"#),
+        );
+        let flash = Flash::default();
+        assert_eq!(led_transition(Some(&clue), &buzzing, flash), Some(LedPattern::Flash(flash)));
+        assert_eq!(led_transition(Some(&buzzed), &buzzing, flash), Some(LedPattern::Flash(flash)));
+        assert_eq!(led_transition(Some(&buzzing), &buzzing, flash), None);
+        assert_eq!(led_transition(None, &buzzing, flash), None);
+        assert_eq!(led_transition(Some(&buzzing), &buzzed, flash), None);
+        assert_eq!(led_transition(Some(&clue), &buzzing, Flash { times: 0, ..flash }), None);
+    }
+
+    #[test]
+    fn parses_state_and_error_frames() {
+        let state = r#"{"game":{"contestants":[],"phase":"Score","phase_name":"score"},"connection":{"controlling":null}}"#;
+        let Ok(ServerMessage::State(state)) = serde_json::from_str(state) else { panic!("not a state") };
+        assert_eq!(state.game.phase, GamePhase::Score);
+        let state = r#"{"game":{"contestants":[],"phase":{"Buzzing":{"clue":[0,1]}},"phase_name":"buzzing"},"connection":{"controlling":0}}"#;
+        let Ok(ServerMessage::State(state)) = serde_json::from_str(state) else { panic!("not a state") };
+        assert_eq!(state.game.phase, GamePhase::Buzzing { clue: (0, 1) });
+        // newer servers say why the state changed
+        let state = r#"{"game":{"contestants":[],"phase":"Score","phase_name":"score"},"connection":{"controlling":null},"caused_by":{"type":"FinishClue"}}"#;
+        assert!(matches!(serde_json::from_str(state), Ok(ServerMessage::State(_))));
+        let ack = r#"{"ack":{"input":"buzz","accepted":false,"reason":"too_late"}}"#;
+        let Ok(ServerMessage::Ack { ack }) = serde_json::from_str(ack) else { panic!("not an ack") };
+        assert!(!ack.accepted);
+        assert_eq!(ack.reason.as_deref(), Some("too_late"));
+
+        let error = |text: &str| {
+            let frame = serde_json::json!({ "error": text }).to_string();
+            let Ok(ServerMessage::Error { error }) = serde_json::from_str(&frame) else { panic!("not an error: {}", frame) };
+            ServerError::from(error.as_str())
+        };
+        assert_eq!(error("State(Game(ContestantNotFound))"), ServerError::ContestantNotFound);
+        assert_eq!(error("Game(ContestantNotFound)"), ServerError::ContestantNotFound);
+        assert_eq!(error("State(LobbyFull)"), ServerError::LobbyFull);
+        assert_eq!(error("State(AlreadyControlled)"), ServerError::Taken);
+        assert_eq!(error("State(AlreadyClaimed)"), ServerError::Taken);
+        assert_eq!(error("Forbidden"), ServerError::Other);
+        assert_eq!(error("Game(WrongPhase { is: Picking { contestant: 0 } })"), ServerError::Other);
+    }
+
+    #[test]
+    fn screens_show_name_points_and_phase() {
+        let game = |phase: &str| -> GameState {
+            serde_json::from_str(&format!(r#"{{
+                "contestants": [
+                    {{"name": "Alice", "name_hint": "pi-1", "points": 300, "indicate": false, "connected": true}},
+                    {{"name": null, "name_hint": "pi-2", "points": -100, "indicate": true, "connected": true}}
+                ],
+                "phase": {}
+            }}"#, phase)).unwrap()
+        };
+        let shown = |phase: &str, me| screen(&game(phase), me).to_string();
+        assert_eq!(shown(r#""Connecting""#, 0), "Alice | 300 points | Waiting for players");
+        assert_eq!(shown(r#"{"Picking":{"contestant":0}}"#, 1), "pi-2 | -100 points | Picking: Alice");
+        assert_eq!(shown(r#"{"Picking":{"contestant":0}}"#, 0), "Alice | 300 points | Picking: you");
+        assert_eq!(shown(r#"{"Buzzing":{"clue":[0,1]}}"#, 0), "Alice | 300 points | Buzz now!");
+        assert_eq!(shown(r#"{"Buzzed":{"clue":[0,1],"contestant":1}}"#, 0), "Alice | 300 points | Buzzed: pi-2");
+        assert_eq!(shown(r#"{"Practice":{"winner":null}}"#, 1), "pi-2 | -100 points | Practice: buzz!");
+        assert_eq!(shown(r#"{"Practice":{"winner":1}}"#, 0), "Alice | 300 points | Practice: pi-2 first");
+        assert_eq!(screen(&game(r#""Score""#), 2), Screen::default());
+    }
+
+    fn parse(args: &str) -> Result<Args, String> {
+        Args::parse(args.split_whitespace().map(str::to_owned))
+    }
+
+    #[test]
+    fn arguments_override_the_config() {
+        let mut config = Config::parse(r#"
+            debounce_ms = 20
+            [server]
+            uri = "ws://file/websocket"
+            [identity]
+            id = "file"
+        "#).unwrap();
+        parse("--debounce 50 --pins 1,2,3 ws://argument/websocket").unwrap().apply(&mut config);
+        assert_eq!(config.server.uri.as_deref(), Some("ws://argument/websocket"));
+        assert_eq!(config.debounce_ms, 50);
+        assert_eq!(config.identity.id.as_deref(), Some("file"));
+        assert_eq!(config.handsets, [HandsetConfig { pins: Some(PinTiples::new(1, 2, 3)), ..HandsetConfig::default() }]);
+
+        let mut config = Config::default();
+        let args = parse("--simulate --handsets 2 --config /tmp/controller.toml").unwrap();
+        assert!(args.simulate);
+        assert_eq!(args.config, Some(PathBuf::from("/tmp/controller.toml")));
+        args.apply(&mut config);
+        assert_eq!(config.server.uri, None);
+        assert_eq!(config.debounce(), handset::DEBOUNCE_WINDOW);
+        assert_eq!(config.keys(), ["1", "2"]);
+        assert!(!config.always_send);
+        parse("--always-send").unwrap().apply(&mut config);
+        assert!(config.always_send);
+        parse("--ping-interval 500 --ping-misses 4").unwrap().apply(&mut config);
+        assert_eq!((config.server.ping_interval_ms, config.server.ping_misses), (500, 4));
+        parse("--ca-cert /etc/venue-ca.pem --insecure").unwrap().apply(&mut config);
+        assert_eq!(config.server.ca_cert, Some(PathBuf::from("/etc/venue-ca.pem")));
+        assert!(config.server.insecure);
+
+        assert!(parse("--debounce soon").is_err());
+        assert!(parse("--ping-misses -1").is_err());
+        assert!(parse("--pins 1,2").is_err());
+        assert!(parse("--config").is_err());
+        assert!(parse("--ca-cert").is_err());
+        assert!(parse("--unknown").is_err());
+        assert!(parse("--simulate-keys 1,1").is_err());
+        assert_eq!(parse("--simulate-keys 1,2,3").unwrap().simulate_keys.map(|k| k.len()), Some(3));
+    }
+
+    #[tokio::test]
+    async fn registers_every_handset() {
+        let server = MockServer::start(Game::default()).await;
+        let (presses, _, handsets) = simulated_handsets(4);
+        let state_directory = tempfile::tempdir().unwrap();
+        let controller = Controller::start(&server, config(&state_directory), handsets);
+
+        server.wait_for(|game| game.contestants.len() == 4).await;
+        let mut names = server.read(|game| game.names());
+        names.sort();
+        assert_eq!(names, ["machine-1", "machine-2", "machine-3", "machine-4"]);
+        assert!(server.read(|game| game.requested.iter().all(|path| path == "/websocket?scope=contestants")));
+
+        // registration replies might still be in flight, keep pressing
+        let second = server.read(|game| game.names().iter().position(|c| *c == "machine-2").unwrap());
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while !server.read(|game| game.received("buzz").contains(&second)) {
+                presses.press(2);
+                sleep(Duration::from_millis(200)).await;
+            }
+        }).await.expect("handset 2 never buzzed");
+        assert!(server.read(|game| game.received("buzz").iter().all(|b| *b == second)));
+
+        controller.stop().await;
+    }
+
+    #[tokio::test]
+    async fn notifies_systemd_once_registered() {
+        let server = MockServer::start(Game::default()).await;
+        let uri: Uri = server.uri().parse().unwrap();
+
+        let state_directory = tempfile::tempdir().unwrap();
+        let path = state_directory.path().join("notify");
+        let systemd = tokio::net::UnixDatagram::bind(&path).unwrap();
+        let notifier = Notifier::connect(path.to_str().unwrap()).unwrap();
+
+        let (_, _, handsets) = simulated_handsets(1);
+        let cancellation_token = CancellationToken::new();
+        let controller = tokio::spawn(run(config(&state_directory), "machine".to_owned(), uri, handsets, default_tls(), None, notifier, cancellation_token.clone()));
+
+        let mut notifications = vec![];
+        tokio::time::timeout(Duration::from_secs(10), async {
+            let mut buffer = [0; 1024];
+            while !notifications.iter().any(|n| n == "READY=1") {
+                let length = systemd.recv(&mut buffer).await.unwrap();
+                notifications.push(String::from_utf8_lossy(&buffer[..length]).into_owned());
+            }
+        }).await.expect("never notified readiness");
+        cancellation_token.cancel();
+        controller.await.unwrap();
+
+        assert!(notifications.contains(&"STATUS=machine-1: controlling contestant 0".to_owned()), "{:?}", notifications);
+    }
+
+    #[test]
+    fn host_header_has_the_port() {
+        let host = |uri: &str| host_header(&uri.parse().unwrap());
+        assert_eq!(host("ws://gamehost:3000/websocket"), "gamehost:3000");
+        assert_eq!(host("ws://gamehost/websocket"), "gamehost");
+        assert_eq!(host("ws://gamehost:80/websocket"), "gamehost");
+        assert_eq!(host("wss://gamehost:80/websocket"), "gamehost:80");
+        assert_eq!(host("wss://gamehost:443/websocket"), "gamehost");
+    }
+
+    #[test]
+    fn asks_for_the_contestants_only() {
+        let scoped = |uri: &str| with_scope(uri.parse().unwrap()).to_string();
+        assert_eq!(scoped("ws://gamehost:3000/websocket"), "ws://gamehost:3000/websocket?scope=contestants");
+        assert_eq!(scoped("ws://gamehost/websocket?lang=de"), "ws://gamehost/websocket?lang=de&scope=contestants");
+        assert_eq!(scoped("ws://gamehost/websocket?scope=full"), "ws://gamehost/websocket?scope=full");
+    }
+
+    #[tokio::test]
+    async fn connect_failures_are_distinguishable() {
+        let connect = |uri: String| async move {
+            let uri: Uri = uri.parse().unwrap();
+            ConnectFailure::from(open(&uri, &default_tls()).await.unwrap_err())
+        };
+
+        // nothing listens on a port that was just freed
+        let address = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        let failure = connect(format!("ws://{}/websocket", address)).await;
+        assert!(matches!(failure, ConnectFailure::Unreachable(_)), "{:?}", failure);
+
+        // answers every upgrade with a 403
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            use tokio::io::{AsyncReadExt, AsyncWriteExt};
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = [0; 1024];
+                if let Ok(1..) = stream.read(&mut request).await {
+                    let _ = stream.write_all(b"HTTP/1.1 403 Forbidden\r\ncontent-length: 0\r\n\r\n").await;
+                }
+            }
+        });
+        // the plain HTTP answer isn't a TLS handshake
+        let failure = connect(format!("wss://{}/websocket", address)).await;
+        assert!(matches!(failure, ConnectFailure::Tls(_)), "{:?}", failure);
+        assert_eq!(connect(format!("ws://{}/websocket", address)).await, ConnectFailure::Rejected(403));
+    }
+
+    #[tokio::test]
+    async fn connects_to_self_signed_servers_when_told_to() {
+        let certificate = rcgen::generate_simple_self_signed(vec!["localhost".to_owned()]).unwrap();
+        let server_config = rustls::ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(
+                vec![certificate.serialize_der().unwrap().into()],
+                rustls::pki_types::PrivatePkcs8KeyDer::from(certificate.serialize_private_key_der()).into(),
+            )
+            .unwrap();
+        let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(server_config));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let Ok(stream) = acceptor.accept(stream).await else { return };
+                    let Ok(mut socket) = tokio_tungstenite::accept_async(stream).await else { return };
+                    let _ = socket.send(Message::Text("hello".to_owned())).await;
+                });
+            }
+        });
+
+        let state_directory = tempfile::tempdir().unwrap();
+        let ca_cert = state_directory.path().join("ca.pem");
+        std::fs::write(&ca_cert, certificate.serialize_pem().unwrap()).unwrap();
+        let uri: Uri = format!("wss://localhost:{}/websocket", port).parse().unwrap();
+        let hello = |tls: Arc<rustls::ClientConfig>| {
+            let uri = uri.clone();
+            async move {
+                let (mut socket, _) = open(&uri, &tls).await.map_err(ConnectFailure::from)?;
+                Ok::<_, ConnectFailure>(socket.next().await.unwrap().unwrap().into_text().unwrap())
+            }
+        };
+
+        let failure = hello(default_tls()).await.unwrap_err();
+        assert!(matches!(&failure, ConnectFailure::Tls(e) if e.contains("UnknownIssuer")), "{:?}", failure);
+        assert_eq!(hello(tls::client_config(Some(&ca_cert), false).unwrap()).await.unwrap(), "hello");
+        assert_eq!(hello(tls::client_config(None, true).unwrap()).await.unwrap(), "hello");
+
+        let empty = state_directory.path().join("empty.pem");
+        std::fs::write(&empty, "").unwrap();
+        assert!(tls::client_config(Some(&empty), false).is_err());
+        assert!(tls::client_config(Some(&state_directory.path().join("missing.pem")), false).is_err());
+    }
+
+    #[test]
+    fn checks_the_protocol_of_the_server() {
+        use tokio_tungstenite::tungstenite::http::HeaderMap;
+        let check = |announced: Option<&str>| {
+            let mut headers = HeaderMap::new();
+            if let Some(announced) = announced {
+                headers.insert(PROTOCOL_HEADER, announced.parse().unwrap());
+            }
+            check_protocol(&headers)
+        };
+        assert_eq!(check(Some(&PROTOCOL_VERSION.to_string())), Ok(None));
+        assert!(check(Some(&(PROTOCOL_VERSION + 1).to_string())).unwrap().unwrap().contains("might be rejected"));
+        assert!(check(None).unwrap().is_some());
+        assert!(check(Some("garbage")).unwrap().is_some());
+        assert_eq!(check(Some("0")), Err(ConnectFailure::Incompatible(0)));
+    }
+
+    #[tokio::test]
+    async fn refuses_incompatible_servers() {
+        let server = MockServer::start(Game { protocol: Some(0), ..Game::default() }).await;
+        let (_, _, handsets) = simulated_handsets(1);
+        let state_directory = tempfile::tempdir().unwrap();
+        let controller = Controller::start(&server, config(&state_directory), handsets);
+
+        server.wait_for(|game| !game.requested.is_empty()).await;
+        sleep(Duration::from_millis(500)).await;
+        assert!(server.read(|game| game.inputs.is_empty()));
+        // servers before the header are still tried
+        server.update(|game| game.protocol = None);
+        server.wait_for(|game| game.names() == ["machine-1"]).await;
+        controller.stop().await;
+    }
+
+    #[tokio::test]
+    async fn connects_to_a_late_server() {
+        // reserve a port, but don't listen on it yet
+        let address = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        let uri: Uri = format!("ws://{}/websocket", address).parse().unwrap();
+        let (_, _, handsets) = simulated_handsets(1);
+        let cancellation_token = CancellationToken::new();
+        let state_directory = tempfile::tempdir().unwrap();
+        let controller = tokio::spawn(run(config(&state_directory), "machine".to_owned(), uri, handsets, default_tls(), None, Notifier::default(), cancellation_token.clone()));
+
+        sleep(Duration::from_secs(2)).await;
+        let server = MockServer::bind(address, Game::default()).await.unwrap();
+
+        server.wait_for(|game| game.names() == ["machine-1"]).await;
+        cancellation_token.cancel();
+        controller.await.unwrap();
+    }
+
+    /// Runs a single handset against a server that already knows `contestants`
+    /// until `condition` holds, returns the inputs and the stored registration
+    async fn restart(
+        contestants: &[&str],
+        stored: Option<usize>,
+        condition: impl Fn(&Game) -> bool,
+    ) -> (MockServer, Option<usize>) {
+        let server = MockServer::start(Game::with_contestants(contestants)).await;
+        restart_with(server, stored, condition).await
+    }
+
+    /// Like [restart], with a server that was set up further
+    async fn restart_with(
+        server: MockServer,
+        stored: Option<usize>,
+        condition: impl Fn(&Game) -> bool,
+    ) -> (MockServer, Option<usize>) {
+        let state_directory = tempfile::tempdir().unwrap();
+        if let Some(contestant) = stored {
+            Registration::load(state_directory.path(), "machine-1").store(contestant);
+        }
+        let (_, _, handsets) = simulated_handsets(1);
+        let controller = Controller::start(&server, config(&state_directory), handsets);
+        server.wait_for(condition).await;
+        // the reply is only handled after the controller waited for it
+        let stored = || Registration::load(state_directory.path(), "machine-1").contestant();
+        server.wait_for(|game| stored().is_some_and(|c| c < game.contestants.len())).await;
+        controller.stop().await;
+
+        let stored = stored();
+        (server, stored)
+    }
+
+    #[tokio::test]
+    async fn reconnects_as_the_stored_contestant() {
+        // both have our name hint, the stored one wins
+        let (server, stored) = restart(&["machine-1", "machine-1"], Some(1), |game| !game.received("reconnect_contestant").is_empty()).await;
+        assert_eq!(server.read(|game| game.inputs.clone()), ["reconnect_contestant 1"]);
+        assert_eq!(server.read(|game| game.contestants.len()), 2);
+        assert_eq!(stored, Some(1));
+    }
+
+    #[tokio::test]
+    async fn ignores_a_stored_contestant_of_someone_else() {
+        // stored in an earlier game
+        let (server, stored) = restart(&["someone", "machine-1"], Some(0), |game| !game.received("reconnect_contestant").is_empty()).await;
+        assert_eq!(server.read(|game| game.inputs.clone()), ["reconnect_contestant 1"]);
+        assert_eq!(stored, Some(1));
+    }
+
+    #[tokio::test]
+    async fn forgets_a_contestant_someone_else_controls() {
+        for error in ["State(AlreadyControlled)", "State(AlreadyClaimed)"] {
+            let server = MockServer::start(Game::with_contestants(&["machine-1"])).await;
+            server.on("reconnect_contestant", move |_, _, _| Reply::Error(error.to_owned()));
+            let (server, stored) = restart_with(server, Some(0), |game| game.contestants.len() == 2).await;
+            assert_eq!(server.read(|game| game.inputs.clone()), ["reconnect_contestant 0", "connect_contestant"], "{}", error);
+            assert_eq!(stored, Some(1));
+        }
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_the_name_hint() {
+        let (server, stored) = restart(&["someone", "machine-1"], None, |game| !game.received("reconnect_contestant").is_empty()).await;
+        assert_eq!(server.read(|game| game.inputs.clone()), ["reconnect_contestant 1"]);
+        assert_eq!(server.read(|game| game.contestants.len()), 2);
+        assert_eq!(stored, Some(1));
+    }
+
+    #[tokio::test]
+    async fn forgets_a_rejected_contestant() {
+        let (server, stored) = restart(&["someone"], Some(5), |game| game.contestants.len() == 2).await;
+        assert_eq!(server.read(|game| game.inputs.clone()), ["reconnect_contestant 5", "connect_contestant"]);
+        assert_eq!(server.read(|game| game.names()), ["someone", "machine-1"]);
+        assert_eq!(stored, Some(1));
+    }
+
+    #[tokio::test]
+    async fn measures_buzz_latency() {
+        let server = MockServer::start(Game { buzz_delay: Duration::from_millis(200), ..Game::default() }).await;
+        let uri: Uri = server.uri().parse().unwrap();
+
+        let (presses, _, handsets) = simulated_handsets(1);
+        let latencies = Arc::new(Mutex::new(Latencies::default()));
+        let state_directory = tempfile::tempdir().unwrap();
+        let cancellation_token = CancellationToken::new();
+        let (mut statuses, notifier) = Statuses::listen();
+        let controller = tokio::spawn(run(
+            config(&state_directory),
+            "machine".to_owned(),
+            uri,
+            handsets,
+            default_tls(),
+            Some(latencies.clone()),
+            notifier,
+            cancellation_token.clone(),
+        ));
+
+        statuses.registered().await;
+        presses.press(1);
+        server.wait_for(|game| game.indicated.is_some()).await;
+        sleep(Duration::from_millis(100)).await;
+        cancellation_token.cancel();
+        controller.await.unwrap();
+
+        let summary = latencies.lock().unwrap().summary().unwrap();
+        assert_eq!(summary.count, 1);
+        assert!(summary.min_ms >= 200.0 && summary.max_ms < 500.0, "{}", summary);
+    }
+
+    #[tokio::test]
+    async fn backs_off_while_the_lobby_is_full() {
+        let server = MockServer::start(Game { capacity: Some(0), ..Game::default() }).await;
+        let (_, _, handsets) = simulated_handsets(1);
+        let state_directory = tempfile::tempdir().unwrap();
+        let controller = Controller::start(&server, config(&state_directory), handsets);
+
+        server.wait_for(|game| game.lobby_full == 2).await;
+        server.update(|game| game.capacity = None);
+        server.wait_for(|game| game.names() == ["machine-1"]).await;
+        controller.stop().await;
+    }
+
+    #[tokio::test]
+    async fn follows_the_presence_of_the_handset() {
+        let server = MockServer::start(Game::default()).await;
+        let (presses, presence, handsets) = simulated_handsets(1);
+        let state_directory = tempfile::tempdir().unwrap();
+        let mut controller = Controller::start(&server, config(&state_directory), handsets);
+
+        controller.registered().await;
+
+        // a bounce shorter than the settle time goes unnoticed
+        presence.toggle(1);
+        sleep(Duration::from_millis(100)).await;
+        presence.toggle(1);
+        sleep(Duration::from_millis(500)).await;
+
+        presence.toggle(1);
+        server.wait_for(|game| !game.contestants[0].connected).await;
+        presses.press(1);
+        sleep(Duration::from_millis(500)).await;
+
+        presence.toggle(1);
+        server.wait_for(|game| game.contestants[0].connected).await;
+        sleep(Duration::from_millis(200)).await;
+        controller.stop().await;
+
+        assert_eq!(server.read(|game| game.inputs.clone()), ["connect_contestant", "disconnect_contestant 0", "reconnect_contestant 0"]);
+    }
+
+    #[tokio::test]
+    async fn flashes_when_buzzing_opens() {
+        let server = MockServer::start(Game::default()).await;
+        let (_, presence, handsets) = simulated_handsets(1);
+        let mut led_rx = handsets[0].led_tx.subscribe();
+        let recording = tokio::spawn(async move {
+            let mut timeline = vec![];
+            while led_rx.changed().await.is_ok() {
+                timeline.push(*led_rx.borrow_and_update());
+            }
+            timeline
+        });
+        let state_directory = tempfile::tempdir().unwrap();
+        let mut controller = Controller::start(&server, config(&state_directory), handsets);
+
+        controller.registered().await;
+        let buzzing = serde_json::json!({"Buzzing": {"clue": [0, 1]}});
+        for phase in [
+            serde_json::json!({"Clue": {"clue": [0, 1], "exclusive": null}}),
+            buzzing.clone(),
+            serde_json::json!({"Buzzed": {"clue": [0, 1], "contestant": 1}}),
+            buzzing,
+        ] {
+            server.update(|game| game.phase = phase);
+            sleep(Duration::from_millis(700)).await;
+        }
+
+        // reconnecting while buzzing is open doesn't flash again
+        presence.toggle(1);
+        server.wait_for(|game| !game.contestants[0].connected).await;
+        sleep(Duration::from_millis(500)).await;
+        presence.toggle(1);
+        server.wait_for(|game| game.contestants[0].connected).await;
+        sleep(Duration::from_millis(500)).await;
+        controller.stop().await;
+
+        let flash = LedPattern::Flash(Flash::default());
+        assert_eq!(recording.await.unwrap(), [
+            LedPattern::Heartbeat,
+            LedPattern::Off,
+            flash,
+            LedPattern::FastBlink,
+            LedPattern::Off,
+            flash,
+            LedPattern::FastBlink,
+            LedPattern::Heartbeat,
+            LedPattern::FastBlink,
+        ]);
+    }
+
+    /// Presses a registered handset a few times during `phase`, returns the
+    /// buzzes the server received
+    async fn buzzes_during(phase: serde_json::Value, always_send: bool) -> Vec<usize> {
+        let server = MockServer::start(Game { phase, ..Game::default() }).await;
+        let (presses, _, handsets) = simulated_handsets(1);
+        let state_directory = tempfile::tempdir().unwrap();
+        let mut config = config(&state_directory);
+        config.always_send = always_send;
+        let mut controller = Controller::start(&server, config, handsets);
+
+        controller.registered().await;
+        for _ in 0..3 {
+            presses.press(1);
+            sleep(Duration::from_millis(200)).await;
+        }
+        controller.stop().await;
+
+        server.read(|game| game.received("buzz"))
+    }
+
+    #[tokio::test]
+    async fn buzzes_only_when_the_game_accepts_them() {
+        let picking = serde_json::json!({"Picking": {"contestant": 0}});
+        assert!(buzzes_during(picking.clone(), false).await.is_empty());
+        assert_eq!(buzzes_during(serde_json::json!({"Buzzing": {"clue": [0, 1]}}), false).await, [0, 0, 0]);
+        assert_eq!(buzzes_during("Score".into(), false).await, [0, 0, 0]);
+        assert_eq!(buzzes_during(picking, true).await, [0, 0, 0]);
+    }
+
+    /// Presses a registered handset during `Buzzing` shortly after the
+    /// connection was lost, which comes back after `outage`. Returns the
+    /// buzzes the server received.
+    async fn buzzes_after_an_outage(buzz_buffer_ms: u64, outage: Duration) -> Vec<usize> {
+        let server = MockServer::start(Game { phase: serde_json::json!({"Buzzing": {"clue": [0, 1]}}), ..Game::default() }).await;
+        let (presses, presence, handsets) = simulated_handsets(1);
+        let state_directory = tempfile::tempdir().unwrap();
+        let mut config = config(&state_directory);
+        config.buzz_buffer_ms = buzz_buffer_ms;
+        config.server.reconnect = config::Reconnect { min_ms: 100, max_ms: 100 };
+        let mut controller = Controller::start(&server, config, handsets);
+
+        controller.registered().await;
+        server.update(|game| game.unreachable = true);
+        server.drop_connections();
+        server.wait_for(|game| !game.contestants[0].connected).await;
+        sleep(Duration::from_millis(100)).await;
+        presses.press(1);
+        sleep(outage).await;
+        server.update(|game| game.unreachable = false);
+        controller.registered().await;
+        // leaving follows a buffered buzz on the same connection
+        presence.toggle(1);
+        server.wait_for(|game| !game.contestants[0].connected).await;
+        controller.stop().await;
+
+        server.read(|game| game.received("buzz"))
+    }
+
+    #[tokio::test]
+    async fn buzzes_once_the_connection_is_back() {
+        assert_eq!(buzzes_after_an_outage(1000, Duration::from_millis(300)).await, [0]);
+    }
+
+    #[tokio::test]
+    async fn drops_presses_when_the_connection_took_too_long() {
+        assert!(buzzes_after_an_outage(300, Duration::from_millis(700)).await.is_empty());
+    }
+}
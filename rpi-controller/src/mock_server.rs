@@ -0,0 +1,387 @@
+//! A stand-in for the game server that speaks just enough of its websocket
+//! protocol to develop and test the controller against: it sends states like
+//! the server does, records every input and can be scripted per input type.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::Value;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+use tokio_tungstenite::tungstenite::Message;
+
+/// What the mock server knows about the game
+#[derive(Debug)]
+pub struct Game {
+    pub contestants: Vec<Contestant>,
+    pub phase: Value,
+    /// the contestant that buzzed last
+    pub indicated: Option<usize>,
+    /// contestants beyond this are rejected with `LobbyFull`
+    pub capacity: Option<usize>,
+    /// how often a contestant was rejected because of the capacity
+    pub lobby_full: usize,
+    /// waited for before a buzz is handled
+    pub buzz_delay: Duration,
+    /// every input in the order they arrived, like `buzz 0`
+    pub inputs: Vec<String>,
+    /// the path and query of every websocket upgrade
+    pub requested: Vec<String>,
+    /// announced when upgrading like the server does, `None` like servers
+    /// before it
+    pub protocol: Option<u32>,
+    /// websocket upgrades are refused, like when the network is down
+    pub unreachable: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contestant {
+    pub name_hint: String,
+    pub points: i64,
+    pub connected: bool,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self {
+            contestants: vec![],
+            phase: "Connecting".into(),
+            indicated: None,
+            capacity: None,
+            lobby_full: 0,
+            buzz_delay: Duration::ZERO,
+            inputs: vec![],
+            requested: vec![],
+            protocol: Some(8),
+            unreachable: false,
+        }
+    }
+}
+
+impl Game {
+    /// Starts with contestants that aren't connected yet
+    pub fn with_contestants(names: &[&str]) -> Self {
+        Self {
+            contestants: names.iter()
+                .map(|name| Contestant { name_hint: name.to_string(), points: 0, connected: false })
+                .collect(),
+            ..Self::default()
+        }
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.contestants.iter().map(|c| c.name_hint.clone()).collect()
+    }
+
+    /// The contestants of every input of one type, like all buzzes
+    pub fn received(&self, input_type: &str) -> Vec<usize> {
+        self.inputs.iter()
+            .filter_map(|input| input.strip_prefix(input_type)?.strip_prefix(' ')?.parse().ok())
+            .collect()
+    }
+
+    /// Like the server sends it to a connection controlling `controlling`
+    pub fn state(&self, controlling: Option<usize>) -> Value {
+        let contestants: Vec<_> = self.contestants.iter()
+            .enumerate()
+            .map(|(i, c)| serde_json::json!({
+                "name": null,
+                "name_hint": c.name_hint,
+                "points": c.points,
+                "indicate": self.indicated == Some(i),
+                "connected": c.connected,
+            }))
+            .collect();
+        // the variant in snake_case, all of them are a single word
+        let phase_name = match &self.phase {
+            Value::Object(phase) => phase.keys().next().cloned().unwrap_or_default(),
+            phase => phase.as_str().unwrap_or_default().to_owned(),
+        };
+        serde_json::json!({
+            "game": {"contestants": contestants, "phase": self.phase, "phase_name": phase_name.to_lowercase()},
+            "connection": {"controlling": controlling},
+        })
+    }
+}
+
+/// How the server reacts to an input
+#[derive(Debug, PartialEq)]
+pub enum Reply {
+    /// the game changed, every connection gets the new state
+    Broadcast,
+    /// only the sender gets an error frame
+    Error(String),
+    Nothing,
+}
+
+/// Handles one type of input, gets the game, the input and which contestant
+/// the connection controls
+pub type Handler = Box<dyn FnMut(&mut Game, &Value, &mut Option<usize>) -> Reply + Send>;
+
+struct Shared {
+    game: Mutex<Game>,
+    handlers: Mutex<HashMap<String, Handler>>,
+    changed: watch::Sender<()>,
+    /// every connection closes when this changes
+    dropped: watch::Sender<()>,
+}
+
+pub struct MockServer {
+    address: SocketAddr,
+    shared: Arc<Shared>,
+    accepting: JoinHandle<()>,
+}
+
+impl MockServer {
+    /// Listens on a free local port
+    pub async fn start(game: Game) -> Self {
+        Self::bind("127.0.0.1:0".parse().unwrap(), game).await.expect("can't listen on a local port")
+    }
+
+    pub async fn bind(address: SocketAddr, game: Game) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(address).await?;
+        let address = listener.local_addr()?;
+        let shared = Arc::new(Shared {
+            game: Mutex::new(game),
+            handlers: Mutex::new(HashMap::new()),
+            changed: watch::Sender::new(()),
+            dropped: watch::Sender::new(()),
+        });
+        let accepting = tokio::spawn(accept(listener, shared.clone()));
+        Ok(Self { address, shared, accepting })
+    }
+
+    pub fn uri(&self) -> String {
+        format!("ws://{}/websocket", self.address)
+    }
+
+    /// Replaces the default handling of an input type, e.g. `buzz`
+    pub fn on(&self, input_type: &str, handler: impl FnMut(&mut Game, &Value, &mut Option<usize>) -> Reply + Send + 'static) {
+        self.shared.handlers.lock().unwrap().insert(input_type.to_owned(), Box::new(handler));
+    }
+
+    pub fn read<R>(&self, f: impl FnOnce(&Game) -> R) -> R {
+        f(&self.shared.game.lock().unwrap())
+    }
+
+    /// Changes the game and sends the new state to every connection
+    pub fn update<R>(&self, f: impl FnOnce(&mut Game) -> R) -> R {
+        let result = f(&mut self.shared.game.lock().unwrap());
+        self.shared.changed.send_replace(());
+        result
+    }
+
+    /// Closes every connection, like a network outage
+    pub fn drop_connections(&self) {
+        self.shared.dropped.send_replace(());
+    }
+
+    /// Waits up to 10 seconds for the game to reach a condition
+    pub async fn wait_for(&self, condition: impl Fn(&Game) -> bool) {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while !self.read(&condition) {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        }).await.expect("mock server never reached the expected state");
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.accepting.abort();
+    }
+}
+
+async fn accept(listener: TcpListener, shared: Arc<Shared>) {
+    loop {
+        let Ok((stream, _)) = listener.accept().await else { continue };
+        tokio::spawn(connection(stream, shared.clone()));
+    }
+}
+
+async fn connection(stream: TcpStream, shared: Arc<Shared>) {
+    // pings are answered by tungstenite while reading
+    #[allow(clippy::result_large_err)] // the error type is tungstenite's
+    let record = |request: &Request, mut response: Response| {
+        let path = request.uri().path_and_query().map(|p| p.to_string()).unwrap_or_default();
+        let mut game = shared.game.lock().unwrap();
+        if game.unreachable {
+            let mut refused = tokio_tungstenite::tungstenite::handshake::server::ErrorResponse::new(None);
+            *refused.status_mut() = tokio_tungstenite::tungstenite::http::StatusCode::SERVICE_UNAVAILABLE;
+            return Err(refused);
+        }
+        game.requested.push(path);
+        if let Some(protocol) = game.protocol {
+            response.headers_mut().insert("x-aitfoaq-protocol", protocol.into());
+        }
+        Ok(response)
+    };
+    let Ok(mut socket) = tokio_tungstenite::accept_hdr_async(stream, record).await else { return };
+    let mut controlling = None;
+    let mut changed = shared.changed.subscribe();
+    let mut dropped = shared.dropped.subscribe();
+    let mut reply = Reply::Broadcast;
+    loop {
+        let frame = match reply {
+            Reply::Broadcast => {
+                changed.mark_unchanged();
+                shared.game.lock().unwrap().state(controlling)
+            },
+            Reply::Error(error) => serde_json::json!({ "error": error }),
+            Reply::Nothing => Value::Null,
+        };
+        if !frame.is_null() && socket.send(frame.to_string().into()).await.is_err() {
+            break;
+        }
+        reply = tokio::select! {
+            Ok(()) = changed.changed() => Reply::Broadcast,
+            Ok(()) = dropped.changed() => break,
+            msg = socket.next() => match msg {
+                Some(Ok(Message::Text(msg))) => match serde_json::from_str(&msg) {
+                    Ok(input) => {
+                        let reply = handle(&shared, &input, &mut controlling).await;
+                        if reply == Reply::Broadcast {
+                            shared.changed.send_replace(());
+                        }
+                        reply
+                    },
+                    Err(e) => Reply::Error(format!("Parsing({})", e)),
+                },
+                Some(Ok(_)) => Reply::Nothing,
+                _ => break,
+            },
+        };
+    }
+    // the server disconnects the contestant of a closed connection
+    if let Some(contestant) = controlling {
+        let mut game = shared.game.lock().unwrap();
+        if let Some(c) = game.contestants.get_mut(contestant) {
+            c.connected = false;
+        }
+        shared.changed.send_replace(());
+    }
+}
+
+async fn handle(shared: &Shared, input: &Value, controlling: &mut Option<usize>) -> Reply {
+    let input_type = input["type"].as_str().unwrap_or_default();
+    if input_type == "buzz" {
+        let delay = shared.game.lock().unwrap().buzz_delay;
+        tokio::time::sleep(delay).await;
+    }
+    let mut game = shared.game.lock().unwrap();
+    game.inputs.push(match input["contestant"].as_u64() {
+        Some(contestant) => format!("{} {}", input_type, contestant),
+        None => input_type.to_owned(),
+    });
+    if let Some(handler) = shared.handlers.lock().unwrap().get_mut(input_type) {
+        return handler(&mut game, input, controlling);
+    }
+    let contestant = input["contestant"].as_u64().map(|c| c as usize);
+    match (input_type, contestant) {
+        ("connect_contestant", _) if controlling.is_none() => {
+            if game.capacity.is_some_and(|c| game.contestants.len() >= c) {
+                game.lobby_full += 1;
+                return Reply::Error("State(LobbyFull)".to_owned());
+            }
+            let name_hint = input["name_hint"].as_str().unwrap_or_default().to_owned();
+            game.contestants.push(Contestant { name_hint, points: 0, connected: true });
+            *controlling = Some(game.contestants.len() - 1);
+            Reply::Broadcast
+        },
+        ("reconnect_contestant", Some(contestant)) if controlling.is_none() => {
+            let Some(c) = game.contestants.get_mut(contestant) else {
+                return Reply::Error("State(Game(ContestantNotFound))".to_owned());
+            };
+            c.connected = true;
+            *controlling = Some(contestant);
+            Reply::Broadcast
+        },
+        ("disconnect_contestant", Some(contestant)) if controlling.is_some() => {
+            let Some(c) = game.contestants.get_mut(contestant) else {
+                return Reply::Error("State(Game(ContestantNotFound))".to_owned());
+            };
+            c.connected = false;
+            *controlling = None;
+            Reply::Broadcast
+        },
+        ("buzz", Some(contestant)) => {
+            game.indicated = Some(contestant);
+            Reply::Broadcast
+        },
+        _ => Reply::Nothing,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Client = tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<TcpStream>>;
+
+    async fn connect(server: &MockServer) -> Client {
+        tokio_tungstenite::connect_async(server.uri()).await.unwrap().0
+    }
+
+    async fn send(client: &mut Client, input: Value) {
+        client.send(input.to_string().into()).await.unwrap();
+    }
+
+    async fn receive(client: &mut Client) -> Value {
+        loop {
+            if let Message::Text(frame) = client.next().await.unwrap().unwrap() {
+                return serde_json::from_str(&frame).unwrap();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn registers_and_broadcasts() {
+        let server = MockServer::start(Game::default()).await;
+        let mut first = connect(&server).await;
+        let mut second = connect(&server).await;
+        assert_eq!(receive(&mut first).await["connection"]["controlling"], Value::Null);
+        receive(&mut second).await;
+
+        send(&mut first, serde_json::json!({"type": "connect_contestant", "name_hint": "pi-1"})).await;
+        let state = receive(&mut first).await;
+        assert_eq!(state["connection"]["controlling"], 0);
+        assert_eq!(state["game"]["contestants"][0]["name_hint"], "pi-1");
+        let state = receive(&mut second).await;
+        assert_eq!(state["connection"]["controlling"], Value::Null);
+        assert_eq!(state["game"]["contestants"][0]["connected"], true);
+
+        send(&mut second, serde_json::json!({"type": "reconnect_contestant", "contestant": 3})).await;
+        assert_eq!(receive(&mut second).await["error"], "State(Game(ContestantNotFound))");
+
+        server.update(|game| game.phase = "Score".into());
+        let state = receive(&mut first).await;
+        assert_eq!(state["game"]["phase"], "Score");
+        assert_eq!(state["game"]["phase_name"], "score");
+        assert_eq!(server.read(|game| game.inputs.clone()), ["connect_contestant", "reconnect_contestant 3"]);
+    }
+
+    #[tokio::test]
+    async fn inputs_can_be_scripted() {
+        let server = MockServer::start(Game::with_contestants(&["someone"])).await;
+        // hands everyone the first contestant
+        server.on("connect_contestant", |_, _, controlling| {
+            *controlling = Some(0);
+            Reply::Broadcast
+        });
+        let mut client = connect(&server).await;
+        receive(&mut client).await;
+        send(&mut client, serde_json::json!({"type": "connect_contestant", "name_hint": "pi-1"})).await;
+        assert_eq!(receive(&mut client).await["connection"]["controlling"], 0);
+        assert_eq!(server.read(|game| game.names()), ["someone"]);
+
+        send(&mut client, serde_json::json!({"type": "buzz", "contestant": 0})).await;
+        drop(client);
+        server.wait_for(|game| !game.received("buzz").is_empty()).await;
+        server.wait_for(|game| !game.contestants[0].connected).await;
+    }
+}
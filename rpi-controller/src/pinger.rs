@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// Keeps track of pings that haven't been answered yet, like the server's
+/// pinger does. A single late pong isn't fatal, only `misses` unanswered
+/// pings within `window` are.
+#[derive(Debug)]
+pub struct Pinger {
+    counter: u64,
+    outstanding: HashMap<Vec<u8>, Instant>,
+    window: Duration,
+    misses: usize,
+}
+
+impl Pinger {
+    pub fn new(window: Duration, misses: usize) -> Self {
+        Self {
+            counter: 0,
+            outstanding: HashMap::with_capacity(misses),
+            window,
+            misses: misses.max(1),
+        }
+    }
+
+    /// The payload of the next ping, or how long ago the unanswered pings
+    /// were sent if there are too many of them
+    pub fn next(&mut self, now: Instant) -> Result<Vec<u8>, Vec<Duration>> {
+        self.outstanding.retain(|_, sent| now.duration_since(*sent) < self.window);
+        if self.outstanding.len() >= self.misses {
+            let mut ages: Vec<_> = self.outstanding.values().map(|sent| now.duration_since(*sent)).collect();
+            ages.sort();
+            return Err(ages);
+        }
+
+        let payload: Vec<u8> = format!("ping:{}", self.counter).into();
+        self.counter += 1;
+        self.outstanding.insert(payload.clone(), now);
+        Ok(payload)
+    }
+
+    /// The round trip time of the ping this pong answers, if it was ours
+    pub fn received(&mut self, payload: &[u8], now: Instant) -> Option<Duration> {
+        self.outstanding.remove(payload).map(|sent| now.duration_since(sent))
+    }
+
+    /// Forgets every ping, they were sent over a connection that's gone
+    pub fn reset(&mut self) {
+        self.outstanding.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn tolerates_fewer_misses_than_allowed() {
+        let start = Instant::now();
+        let mut pinger = Pinger::new(5 * SECOND, 3);
+        let first = pinger.next(start).unwrap();
+        pinger.next(start + SECOND).unwrap();
+        pinger.next(start + 2 * SECOND).unwrap();
+        assert_eq!(pinger.next(start + 3 * SECOND), Err(vec![SECOND, 2 * SECOND, 3 * SECOND]));
+
+        // a late pong still counts
+        assert_eq!(pinger.received(&first, start + 3 * SECOND), Some(3 * SECOND));
+        assert!(pinger.next(start + 3 * SECOND).is_ok());
+        assert_eq!(pinger.received(&first, start + 4 * SECOND), None);
+        assert_eq!(pinger.received(b"someone else's", start + 4 * SECOND), None);
+    }
+
+    #[test]
+    fn misses_expire_after_the_window() {
+        let start = Instant::now();
+        let mut pinger = Pinger::new(5 * SECOND, 2);
+        pinger.next(start).unwrap();
+        pinger.next(start + 4 * SECOND).unwrap();
+        assert!(pinger.next(start + 5 * SECOND).is_ok(), "the first miss is too old to count");
+        assert_eq!(pinger.next(start + 6 * SECOND), Err(vec![SECOND, 2 * SECOND]));
+
+        pinger.reset();
+        assert!(pinger.next(start + 6 * SECOND).is_ok());
+    }
+}
@@ -0,0 +1,77 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// The contestant a handset registered as, remembered in a small file so a
+/// restarted controller can take it over again
+#[derive(Debug)]
+pub struct Registration {
+    path: PathBuf,
+    stored: Option<Stored>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+struct Stored {
+    contestant: usize,
+}
+
+impl Registration {
+    /// Reads the registration of the handset `id` from `directory`, a
+    /// missing or unreadable file means there is none
+    pub fn load(directory: &Path, id: &str) -> Self {
+        let path = directory.join(format!("{}.json", id));
+        let stored = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok());
+        Self { path, stored }
+    }
+
+    pub fn contestant(&self) -> Option<usize> {
+        self.stored.as_ref().map(|s| s.contestant)
+    }
+
+    /// Remembers the contestant, failing to write is only logged
+    pub fn store(&mut self, contestant: usize) {
+        if self.contestant() == Some(contestant) {
+            return;
+        }
+        let stored = Stored { contestant };
+        let written = self.path.parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&self.path, serde_json::to_string(&stored).expect("always serializable")));
+        if let Err(e) = written {
+            println!("could not remember registration in {}: {}", self.path.display(), e);
+        }
+        self.stored = Some(stored);
+    }
+
+    /// Forgets the contestant, e.g. after the server rejected it
+    pub fn clear(&mut self) {
+        self.stored = None;
+        match std::fs::remove_file(&self.path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                println!("could not forget registration in {}: {}", self.path.display(), e);
+            },
+            _ => {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn survives_a_restart() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut registration = Registration::load(directory.path(), "machine-1");
+        assert_eq!(registration.contestant(), None);
+
+        registration.store(2);
+        assert_eq!(Registration::load(directory.path(), "machine-1").contestant(), Some(2));
+        assert_eq!(Registration::load(directory.path(), "machine-2").contestant(), None);
+
+        registration.clear();
+        assert_eq!(Registration::load(directory.path(), "machine-1").contestant(), None);
+    }
+}
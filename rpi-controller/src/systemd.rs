@@ -0,0 +1,166 @@
+use std::collections::BTreeMap;
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Tells systemd about the state of the service, see sd_notify(3). Does
+/// nothing unless started by systemd with `Type=notify`.
+#[derive(Clone, Default)]
+pub struct Notifier(Option<Arc<Inner>>);
+
+struct Inner {
+    socket: UnixDatagram,
+    ready: AtomicBool,
+    /// the last status of every handset, systemd only keeps a single line
+    statuses: Mutex<BTreeMap<String, String>>,
+}
+
+impl Notifier {
+    /// Connects to `$NOTIFY_SOCKET` if it is set
+    pub fn from_env() -> std::io::Result<Self> {
+        match std::env::var("NOTIFY_SOCKET") {
+            Ok(path) => Self::connect(&path),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    /// Connects to a socket path, or an abstract socket if it starts with `@`
+    pub fn connect(path: &str) -> std::io::Result<Self> {
+        let address = match path.strip_prefix('@') {
+            #[cfg(target_os = "linux")]
+            Some(name) => {
+                use std::os::linux::net::SocketAddrExt;
+                SocketAddr::from_abstract_name(name)?
+            },
+            _ => SocketAddr::from_pathname(path)?,
+        };
+        let socket = UnixDatagram::unbound()?;
+        socket.connect_addr(&address)?;
+        Ok(Self(Some(Arc::new(Inner {
+            socket,
+            ready: AtomicBool::new(false),
+            statuses: Mutex::new(BTreeMap::new()),
+        }))))
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// Reports the service as started, only the first call is sent
+    pub fn ready(&self) {
+        let Some(inner) = &self.0 else { return };
+        if !inner.ready.swap(true, Ordering::Relaxed) {
+            self.send("READY=1");
+        }
+    }
+
+    /// Updates the status line with the status of one handset, unchanged
+    /// statuses aren't sent again
+    pub fn status(&self, handset: &str, status: String) {
+        let Some(inner) = &self.0 else { return };
+        let line = {
+            let mut statuses = inner.statuses.lock().expect("poisoned statuses");
+            if statuses.get(handset) == Some(&status) {
+                return;
+            }
+            statuses.insert(handset.to_owned(), status);
+            statuses.iter()
+                .map(|(handset, status)| format!("{}: {}", handset, status))
+                .collect::<Vec<_>>()
+                .join("; ")
+        };
+        self.send(&format!("STATUS={}", line));
+    }
+
+    /// Tells the watchdog that the service is still alive
+    pub fn watchdog(&self) {
+        self.send("WATCHDOG=1");
+    }
+
+    pub fn stopping(&self) {
+        self.send("STOPPING=1");
+    }
+
+    fn send(&self, state: &str) {
+        let Some(inner) = &self.0 else { return };
+        if let Err(e) = inner.socket.send(state.as_bytes()) {
+            eprintln!("failed to notify systemd: {}", e);
+        }
+    }
+}
+
+/// How often to ping the watchdog, half of `$WATCHDOG_USEC` if it is meant
+/// for this process
+pub fn watchdog_interval() -> Option<Duration> {
+    let usec = std::env::var("WATCHDOG_USEC").ok()?;
+    if let Ok(pid) = std::env::var("WATCHDOG_PID") {
+        if pid.parse() != Ok(std::process::id()) {
+            return None;
+        }
+    }
+    half_of_usec(&usec)
+}
+
+fn half_of_usec(usec: &str) -> Option<Duration> {
+    match usec.parse() {
+        Ok(0) | Err(_) => None,
+        Ok(usec) => Some(Duration::from_micros(usec) / 2),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Binds a socket in a temporary directory, like systemd does
+    fn listen(directory: &tempfile::TempDir) -> (UnixDatagram, Notifier) {
+        let path = directory.path().join("notify");
+        let socket = UnixDatagram::bind(&path).unwrap();
+        socket.set_nonblocking(true).unwrap();
+        (socket, Notifier::connect(path.to_str().unwrap()).unwrap())
+    }
+
+    fn received(socket: &UnixDatagram) -> Vec<String> {
+        let mut buffer = [0; 1024];
+        std::iter::from_fn(|| {
+            let length = socket.recv(&mut buffer).ok()?;
+            Some(String::from_utf8_lossy(&buffer[..length]).into_owned())
+        }).collect()
+    }
+
+    #[test]
+    fn sends_notifications() {
+        let directory = tempfile::tempdir().unwrap();
+        let (socket, notifier) = listen(&directory);
+
+        notifier.ready();
+        notifier.ready();
+        notifier.watchdog();
+        assert_eq!(received(&socket), ["READY=1", "WATCHDOG=1"]);
+
+        notifier.status("pi-2", "reconnecting, attempt 5".to_owned());
+        notifier.status("pi-1", "controlling contestant 0".to_owned());
+        notifier.status("pi-1", "controlling contestant 0".to_owned());
+        assert_eq!(received(&socket), [
+            "STATUS=pi-2: reconnecting, attempt 5",
+            "STATUS=pi-1: controlling contestant 0; pi-2: reconnecting, attempt 5",
+        ]);
+    }
+
+    #[test]
+    fn disabled_without_a_socket() {
+        let notifier = Notifier::default();
+        assert!(!notifier.is_enabled());
+        notifier.ready();
+        notifier.status("pi-1", "connecting".to_owned());
+    }
+
+    #[test]
+    fn watchdog_pings_twice_per_timeout() {
+        assert_eq!(half_of_usec("30000000"), Some(Duration::from_secs(15)));
+        assert_eq!(half_of_usec("0"), None);
+        assert_eq!(half_of_usec("soon"), None);
+    }
+}
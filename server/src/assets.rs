@@ -0,0 +1,188 @@
+use std::{collections::BTreeMap, sync::OnceLock};
+
+use axum::{
+    extract::{Path, Query},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Json},
+    routing::get,
+    Router,
+};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// Embeds files from the templates directory. Adding an asset only requires
+/// listing it here.
+macro_rules! assets {
+    ($($file:literal),* $(,)?) => {
+        &[$(($file, include_bytes!(concat!("../templates/", $file)) as &[u8])),*]
+    };
+}
+
+static FILES: &[(&str, &[u8])] = assets![
+    "favicon.ico",
+    "style.css",
+    "Mallanna-Regular.ttf",
+    "htmx.min.js",
+    "htmx.ws.js",
+    "confetti.min.js",
+];
+
+pub struct Asset {
+    pub path: &'static str,
+    pub content_type: &'static str,
+    pub bytes: &'static [u8],
+    /// Shortened SHA-256 of the content, used as ETag and for cache-busting
+    pub hash: String,
+}
+
+/// All embedded assets, hashed on first use
+pub fn manifest() -> &'static [Asset] {
+    static MANIFEST: OnceLock<Vec<Asset>> = OnceLock::new();
+    MANIFEST.get_or_init(|| {
+        FILES.iter()
+            .map(|(path, bytes)| Asset {
+                path,
+                content_type: content_type(path),
+                bytes,
+                hash: hex::encode(&Sha256::digest(bytes)[..8]),
+            })
+            .collect()
+    })
+}
+
+/// Guessed from the extension, also for [crate::board_assets]
+pub fn content_type(path: &str) -> &'static str {
+    match path.rsplit_once('.').map(|(_, extension)| extension.to_lowercase()).as_deref() {
+        Some("css") => "text/css",
+        Some("js") => "application/javascript",
+        Some("ico") => "image/x-icon",
+        Some("ttf") => "font/ttf",
+        Some("json") => "application/json",
+        Some("html") => "text/html",
+        Some("png") => "image/png",
+        Some("svg") => "image/svg+xml",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("mp3") => "audio/mpeg",
+        Some("ogg") => "audio/ogg",
+        Some("wav") => "audio/wav",
+        Some("mp4") => "video/mp4",
+        Some("webm") => "video/webm",
+        _ => "application/octet-stream",
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CacheBust {
+    v: Option<String>,
+}
+
+/// Serves an embedded asset. Requests with `?v=<hash>` matching the current
+/// content can be cached forever, everything else has to be revalidated.
+pub async fn serve(
+    Path(path): Path<String>,
+    Query(CacheBust { v }): Query<CacheBust>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let Some(asset) = manifest().iter().find(|a| a.path == path) else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+    let etag = format!("\"{}\"", asset.hash);
+    let cache_control = match v {
+        Some(v) if v == asset.hash => "public, max-age=31536000, immutable",
+        _ => "no-cache",
+    };
+    let headers_out = [
+        (header::CONTENT_TYPE, asset.content_type.to_owned()),
+        (header::ETAG, etag.clone()),
+        (header::CACHE_CONTROL, cache_control.to_owned()),
+    ];
+    if headers.get(header::IF_NONE_MATCH).is_some_and(|h| h.as_bytes() == etag.as_bytes()) {
+        return Ok((StatusCode::NOT_MODIFIED, headers_out, &[][..]));
+    }
+    Ok((StatusCode::OK, headers_out, asset.bytes))
+}
+
+/// Maps asset paths to their hashes
+pub async fn serve_manifest() -> Json<BTreeMap<&'static str, &'static str>> {
+    Json(manifest().iter().map(|a| (a.path, a.hash.as_str())).collect())
+}
+
+/// `/manifest.json` and every asset next to it
+pub fn routes<S: Clone + Send + Sync + 'static>() -> Router<S> {
+    Router::new()
+        .route("/manifest.json", get(serve_manifest))
+        .route("/:asset", get(serve))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    async fn get(path: &str, v: Option<&str>, etag: Option<&str>) -> axum::response::Response {
+        let mut headers = HeaderMap::new();
+        if let Some(etag) = etag {
+            headers.insert(header::IF_NONE_MATCH, HeaderValue::from_str(etag).unwrap());
+        }
+        let query = Query(CacheBust { v: v.map(str::to_owned) });
+        serve(Path(path.to_owned()), query, headers).await.into_response()
+    }
+
+    #[tokio::test]
+    async fn serves_every_asset() {
+        for asset in manifest() {
+            let response = get(asset.path, None, None).await;
+            assert_eq!(response.status(), StatusCode::OK, "{}", asset.path);
+            assert_eq!(response.headers()[header::CONTENT_TYPE], asset.content_type);
+            assert_ne!(asset.content_type, "application/octet-stream", "{}", asset.path);
+            assert_eq!(response.headers()[header::CACHE_CONTROL], "no-cache");
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            assert_eq!(&body[..], asset.bytes, "{}", asset.path);
+
+            let response = get(asset.path, Some(&asset.hash), None).await;
+            assert!(response.headers()[header::CACHE_CONTROL].to_str().unwrap().contains("immutable"));
+
+            let etag = format!("\"{}\"", asset.hash);
+            let response = get(asset.path, None, Some(&etag)).await;
+            assert_eq!(response.status(), StatusCode::NOT_MODIFIED, "{}", asset.path);
+        }
+        assert_eq!(get("journal.jsonl", None, None).await.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn manifest_lists_every_asset() {
+        let Json(listed) = serve_manifest().await;
+        assert_eq!(listed.len(), FILES.len());
+        assert_eq!(listed["style.css"].len(), 16);
+    }
+
+    #[tokio::test]
+    async fn routes_find_the_manifest_and_assets() {
+        use tower::ServiceExt;
+        let get = |uri: &str| {
+            let request = axum::http::Request::get(uri).body(axum::body::Body::empty()).unwrap();
+            routes::<()>().oneshot(request)
+        };
+        let body = |response: axum::response::Response| async {
+            axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap()
+        };
+
+        // not taken by the `/:asset` route
+        let response = get("/manifest.json").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+        let listed: BTreeMap<String, String> = serde_json::from_slice(&body(response).await).unwrap();
+        assert_eq!(listed.len(), FILES.len());
+
+        let response = get(&format!("/style.css?v={}", listed["style.css"])).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/css");
+        assert!(response.headers()[header::CACHE_CONTROL].to_str().unwrap().contains("immutable"));
+        assert_eq!(&body(response).await[..], manifest().iter().find(|a| a.path == "style.css").unwrap().bytes);
+
+        assert_eq!(get("/journal.jsonl").await.unwrap().status(), StatusCode::NOT_FOUND);
+        assert_eq!(get("/templates/style.css").await.unwrap().status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,178 @@
+//! Connects simulated contestants, see [libaitfoaq::sim], to a running server
+//! and reports how many state updates reach them and how long they take.
+//! The game has to be in the lobby, the moderator starts and runs it as usual.
+//!
+//! `load-test <ws://host:port/websocket> [--bots <n>] [--seed <n>] [--seconds <n>]`
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures_util::{SinkExt, StreamExt};
+use libaitfoaq::sim::{Bot, BotConfig, Decision};
+use libaitfoaq::state::{ContestantHandle, GameState};
+use serde::Deserialize;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::Message;
+
+/// The parts of the server's JSON state frames the bots need
+#[derive(Deserialize)]
+struct Frame {
+    game: GameState,
+    connection: Connection,
+}
+
+#[derive(Deserialize)]
+struct Connection {
+    controlling: Option<ContestantHandle>,
+}
+
+#[derive(Default)]
+struct Stats {
+    connected: usize,
+    frames: usize,
+    /// Frames that weren't a state, like rejected inputs
+    errors: usize,
+    /// From sending an input to the next state arriving
+    latencies: Vec<Duration>,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(url) = args.get(1).filter(|a| !a.starts_with("--")).cloned() else {
+        eprintln!("usage: load-test <ws://host:port/websocket> [--bots <n>] [--seed <n>] [--seconds <n>]");
+        std::process::exit(2);
+    };
+    let number = |flag: &str, default: u64| -> u64 {
+        match args.iter().position(|a| a == flag).map(|i| args.get(i + 1).and_then(|v| v.parse().ok())) {
+            None => default,
+            Some(Some(value)) => value,
+            Some(None) => {
+                eprintln!("{} needs a number", flag);
+                std::process::exit(2);
+            },
+        }
+    };
+    let bots = number("--bots", 10);
+    let seed = number("--seed", 0);
+    let seconds = number("--seconds", 60);
+
+    let stats = Arc::new(Mutex::new(Stats::default()));
+    for n in 0..bots {
+        tokio::spawn(run_bot(url.clone(), n, seed.wrapping_add(n), stats.clone()));
+    }
+    println!("{} bots connecting to {}, stopping after {}s or on ctrl-c", bots, url, seconds);
+    let started = Instant::now();
+    tokio::select! {
+        _ = tokio::time::sleep(Duration::from_secs(seconds)) => {},
+        _ = tokio::signal::ctrl_c() => {},
+    }
+    report(&stats.lock().unwrap(), started.elapsed());
+}
+
+async fn run_bot(url: String, n: u64, seed: u64, stats: Arc<Mutex<Stats>>) {
+    let request = url.as_str().into_client_request().map(|mut request| {
+        let headers = request.headers_mut();
+        headers.insert("user-agent", HeaderValue::from_static("libaitfoaq load-test"));
+        headers.insert("accept", HeaderValue::from_static("application/json"));
+        request
+    });
+    let socket = match request {
+        Ok(request) => tokio_tungstenite::connect_async(request).await,
+        Err(e) => Err(e),
+    };
+    let (mut sink, mut stream) = match socket {
+        Ok((socket, _)) => socket.split(),
+        Err(e) => {
+            eprintln!("bot {} can't connect: {}", n, e);
+            return;
+        },
+    };
+    stats.lock().unwrap().connected += 1;
+
+    let join = serde_json::json!({"type": "connect_contestant", "name_hint": format!("bot {}", n)});
+    if sink.send(Message::text(join.to_string())).await.is_err() {
+        return;
+    }
+    let mut sent = Some(Instant::now());
+    let mut bot: Option<Bot> = None;
+    let mut pending: Option<(tokio::time::Instant, Decision)> = None;
+    loop {
+        let deadline = pending.as_ref().map(|(at, _)| *at);
+        tokio::select! {
+            message = stream.next() => {
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => {
+                        eprintln!("bot {} disconnected: {}", n, e);
+                        break;
+                    },
+                    None => break,
+                };
+                let Ok(frame) = serde_json::from_str::<Frame>(&text) else {
+                    stats.lock().unwrap().errors += 1;
+                    continue;
+                };
+                {
+                    let mut stats = stats.lock().unwrap();
+                    stats.frames += 1;
+                    if let Some(sent) = sent.take() {
+                        stats.latencies.push(sent.elapsed());
+                    }
+                }
+                if let (None, Some(contestant)) = (&bot, frame.connection.controlling) {
+                    bot = Some(Bot::new(contestant, BotConfig::default(), seed));
+                }
+                if let Some(decision) = bot.as_mut().and_then(|bot| bot.decide(&frame.game)) {
+                    pending = Some((tokio::time::Instant::now() + decision.after, decision));
+                }
+            },
+            _ = tokio::time::sleep_until(deadline.unwrap_or_else(tokio::time::Instant::now)), if deadline.is_some() => {
+                let Some((_, decision)) = pending.take() else { continue };
+                let Some(input) = input(&decision) else { continue };
+                if sink.send(Message::text(input.to_string())).await.is_err() {
+                    break;
+                }
+                sent = Some(Instant::now());
+            },
+        }
+    }
+    stats.lock().unwrap().connected -= 1;
+}
+
+/// What the websocket expects for the events bots decide on
+fn input(decision: &Decision) -> Option<serde_json::Value> {
+    use libaitfoaq::events::Event;
+    Some(match &decision.event {
+        Event::Buzz { contestant } => serde_json::json!({"type": "buzz", "contestant": contestant}),
+        Event::Pick { clue } => serde_json::json!({"type": "pick", "clue": clue}),
+        Event::SetWage { points } => serde_json::json!({"type": "set_wage", "points": points.to_string()}),
+        Event::ConfirmWage => serde_json::json!({"type": "confirm_wage"}),
+        _ => return None,
+    })
+}
+
+fn report(stats: &Stats, elapsed: Duration) {
+    let seconds = elapsed.as_secs_f64();
+    println!("connected bots: {}", stats.connected);
+    println!("state frames:   {} ({:.1}/s)", stats.frames, stats.frames as f64 / seconds);
+    println!("error frames:   {}", stats.errors);
+    let mut latencies = stats.latencies.clone();
+    latencies.sort();
+    if latencies.is_empty() {
+        println!("no inputs were answered");
+        return;
+    }
+    // nearest-rank percentile
+    let percentile = |p: f64| latencies[((p * latencies.len() as f64).ceil() as usize).clamp(1, latencies.len()) - 1];
+    println!(
+        "latency:        p50 {:?}, p95 {:?}, p99 {:?}, max {:?} ({} inputs)",
+        percentile(0.5),
+        percentile(0.95),
+        percentile(0.99),
+        latencies[latencies.len() - 1],
+        latencies.len(),
+    );
+}
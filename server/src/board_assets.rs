@@ -0,0 +1,239 @@
+//! The media boards refer to, served as `/board-assets`. The moderator can
+//! see what's there and add to it from the browser, without shell access to
+//! the server.
+
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+/// How far below the directory files are listed
+const MAX_DEPTH: usize = 4;
+/// Larger uploads are rejected
+pub const MAX_UPLOAD: usize = 32 * 1024 * 1024;
+/// Media a browser can show or play, uploads with other extensions are
+/// rejected
+const EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "svg", "mp3", "ogg", "wav", "mp4", "webm"];
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct AssetFile {
+    /// Relative to the directory, separated by `/`
+    pub path: String,
+    pub size: u64,
+    /// Guessed from the extension
+    pub content_type: &'static str,
+    /// Seconds since the epoch
+    pub modified: u64,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum UploadError {
+    #[error("invalid file name {0:?}")]
+    InvalidName(String),
+    #[error("only {} files can be uploaded", EXTENSIONS.join(", "))]
+    NotAllowed,
+    #[error("{0} already exists")]
+    Exists(String),
+    #[error("files can't be larger than {} bytes", MAX_UPLOAD)]
+    TooLarge,
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Directories and when they were modified, which changes with the files
+/// directly in them, and the files found in them. The files too, because
+/// the times can be too coarse to tell two changes apart.
+type Snapshot = (Vec<(PathBuf, SystemTime)>, Vec<PathBuf>);
+
+#[derive(Debug)]
+pub struct BoardAssets {
+    directory: PathBuf,
+    /// Sizes and times of the files aren't read again until a directory
+    /// changed. Files changed in place are listed like they were before.
+    cache: Mutex<Option<(Snapshot, Vec<AssetFile>)>>,
+}
+
+impl Default for BoardAssets {
+    fn default() -> Self {
+        Self::new(PathBuf::from(crate::BOARD_ASSETS))
+    }
+}
+
+impl BoardAssets {
+    pub fn new(directory: PathBuf) -> Self {
+        Self { directory, cache: Mutex::new(None) }
+    }
+
+    /// Files by path, without dotfiles, symlinks, or anything in hidden
+    /// directories. Empty if the directory doesn't exist.
+    pub async fn list(&self) -> std::io::Result<Vec<AssetFile>> {
+        let mut directories = vec![];
+        let mut files = vec![];
+        let mut pending = vec![(self.directory.clone(), 0)];
+        while let Some((directory, depth)) = pending.pop() {
+            let mut entries = match tokio::fs::read_dir(&directory).await {
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound && depth == 0 => return Ok(vec![]),
+                entries => entries?,
+            };
+            directories.push((directory.clone(), tokio::fs::metadata(&directory).await?.modified()?));
+            while let Some(entry) = entries.next_entry().await? {
+                if entry.file_name().to_string_lossy().starts_with('.') {
+                    continue;
+                }
+                let file_type = entry.file_type().await?;
+                if file_type.is_dir() && depth < MAX_DEPTH {
+                    pending.push((entry.path(), depth + 1));
+                } else if file_type.is_file() {
+                    files.push(entry.path());
+                }
+            }
+        }
+        directories.sort();
+        files.sort();
+        let snapshot = (directories, files);
+
+        let mut cache = self.cache.lock().await;
+        if let Some((cached, listing)) = &*cache {
+            if *cached == snapshot {
+                return Ok(listing.clone());
+            }
+        }
+        let mut listing = vec![];
+        for path in &snapshot.1 {
+            let metadata = match tokio::fs::metadata(path).await {
+                // removed since it was found
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                metadata => metadata?,
+            };
+            listing.push(self.describe(path, &metadata));
+        }
+        listing.sort_by(|a, b| a.path.cmp(&b.path));
+        *cache = Some((snapshot, listing.clone()));
+        Ok(listing)
+    }
+
+    /// Adds a file directly to the directory, never replacing one
+    pub async fn store(&self, name: &str, bytes: &[u8]) -> Result<AssetFile, UploadError> {
+        let invalid = name.is_empty()
+            || name.len() > 255
+            || name.starts_with('.')
+            || name.chars().any(|c| c.is_control() || matches!(c, '/' | '\\' | ':'));
+        if invalid {
+            return Err(UploadError::InvalidName(name.to_owned()));
+        }
+        let extension = name.rsplit_once('.').map(|(_, extension)| extension.to_lowercase());
+        if !extension.is_some_and(|extension| EXTENSIONS.contains(&extension.as_str())) {
+            return Err(UploadError::NotAllowed);
+        }
+        if bytes.len() > MAX_UPLOAD {
+            return Err(UploadError::TooLarge);
+        }
+
+        tokio::fs::create_dir_all(&self.directory).await?;
+        let path = self.directory.join(name);
+        let mut file = match tokio::fs::OpenOptions::new().write(true).create_new(true).open(&path).await {
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => return Err(UploadError::Exists(name.to_owned())),
+            file => file?,
+        };
+        let written = async {
+            file.write_all(bytes).await?;
+            file.sync_all().await
+        };
+        if let Err(e) = written.await {
+            let _ = tokio::fs::remove_file(&path).await;
+            return Err(e.into());
+        }
+        *self.cache.lock().await = None;
+        Ok(self.describe(&path, &tokio::fs::metadata(&path).await?))
+    }
+
+    fn describe(&self, path: &Path, metadata: &std::fs::Metadata) -> AssetFile {
+        let relative = path.strip_prefix(&self.directory).unwrap_or(path);
+        let relative: Vec<_> = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+        let relative = relative.join("/");
+        AssetFile {
+            content_type: crate::assets::content_type(&relative),
+            path: relative,
+            size: metadata.len(),
+            modified: metadata.modified()
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |since| since.as_secs()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(listing: &[AssetFile]) -> Vec<&str> {
+        listing.iter().map(|file| file.path.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn assets_are_listed_and_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let assets = BoardAssets::new(dir.path().join("board-assets"));
+        assert_eq!(assets.list().await.unwrap(), []);
+
+        let root = dir.path().join("board-assets");
+        let deep = root.join("a/b/c/d/e");
+        std::fs::create_dir_all(&deep).unwrap();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::write(root.join("logo.png"), b"png").unwrap();
+        std::fs::write(root.join(".DS_Store"), b"").unwrap();
+        std::fs::write(root.join(".git/config"), b"").unwrap();
+        std::fs::write(root.join("a/b/tune.MP3"), b"mp3").unwrap();
+        std::fs::write(root.join("a/b/c/d/deepest.svg"), b"").unwrap();
+        std::fs::write(deep.join("too-deep.png"), b"").unwrap();
+
+        let listing = assets.list().await.unwrap();
+        assert_eq!(paths(&listing), ["a/b/c/d/deepest.svg", "a/b/tune.MP3", "logo.png"]);
+        assert_eq!(listing[2].size, 3);
+        assert_eq!(listing[2].content_type, "image/png");
+        assert!(listing[2].modified > 0);
+
+        // unchanged directories are taken from the cache
+        std::fs::write(root.join("logo.png"), b"a larger png").unwrap();
+        assert_eq!(assets.list().await.unwrap(), listing);
+        std::fs::write(root.join("a/b/new.gif"), b"").unwrap();
+        let listing = assets.list().await.unwrap();
+        assert_eq!(paths(&listing), ["a/b/c/d/deepest.svg", "a/b/new.gif", "a/b/tune.MP3", "logo.png"]);
+        assert_eq!(listing[3].size, 12);
+    }
+
+    #[tokio::test]
+    async fn uploads_are_stored_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let assets = BoardAssets::new(dir.path().join("board-assets"));
+        assert_eq!(assets.list().await.unwrap(), []);
+
+        let stored = assets.store("Photo 1.JPG", b"jpeg").await.unwrap();
+        assert_eq!(stored.path, "Photo 1.JPG");
+        assert_eq!(stored.size, 4);
+        assert_eq!(std::fs::read(dir.path().join("board-assets/Photo 1.JPG")).unwrap(), b"jpeg");
+        assert_eq!(assets.list().await.unwrap(), [stored]);
+
+        assert!(matches!(assets.store("Photo 1.JPG", b"other").await, Err(UploadError::Exists(_))));
+        assert_eq!(std::fs::read(dir.path().join("board-assets/Photo 1.JPG")).unwrap(), b"jpeg");
+        let too_large = vec![0; MAX_UPLOAD + 1];
+        assert!(matches!(assets.store("large.png", &too_large).await, Err(UploadError::TooLarge)));
+    }
+
+    #[tokio::test]
+    async fn unsafe_names_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let assets = BoardAssets::new(dir.path().join("board-assets"));
+        for name in ["", "../escape.png", "sub/dir.png", "..\\escape.png", ".hidden.png", "..", "C:evil.png", "line\nbreak.png"] {
+            assert!(matches!(assets.store(name, b"").await, Err(UploadError::InvalidName(_))), "{:?}", name);
+        }
+        for name in ["script.js", "page.html", "noextension", "archive.png.exe"] {
+            assert!(matches!(assets.store(name, b"").await, Err(UploadError::NotAllowed)), "{:?}", name);
+        }
+        assert!(!dir.path().join("escape.png").exists());
+        assert_eq!(assets.list().await.unwrap(), []);
+    }
+}
@@ -1,90 +1,461 @@
-use std::{collections::HashMap, net::SocketAddr, num::ParseIntError, time::Duration};
+use std::{collections::{BTreeMap, HashMap, VecDeque}, future::Future, net::SocketAddr, num::ParseIntError, sync::{atomic::{AtomicU64, AtomicUsize, Ordering}, Arc, Mutex, OnceLock}, time::Duration};
 
-use crate::state::{State, StateChannelsAndToken};
+use crate::i18n::Locale;
+use crate::replay::ReplayStep;
+use crate::state::{Actor, Attempt, ConnectionId, Out, ScheduledStart, SendError, State, StateChannelsAndToken, Verdict};
 use axum::extract::ws::{Message, WebSocket};
 use askama::Template;
-use libaitfoaq::{events::Event, state::{ClueHandle, ContestantHandle, GameState, GamePhase}};
-use tokio::{select, time::{interval, Instant, Interval}};
+use libaitfoaq::{events::{Event, EventKind}, matching::MatchResult, state::{ClueField, ClueHandle, ClueRef, Contestant, ContestantHandle, GameState, GamePhase, Options, Points, PointsFormat, SymbolPosition}};
+use futures_util::{stream::{SplitSink, SplitStream}, SinkExt, StreamExt};
+use tokio::{select, sync::{mpsc, watch}, task::JoinHandle, time::{interval, sleep_until, Instant, Interval}};
 use serde::Deserialize;
 use thiserror::Error;
 
-const PING_INTERVAL: Duration= Duration::from_millis(1000);
-const PING_WINDOW: Duration = Duration::from_millis(5000);
-const PING_MISSES: usize = 3;
+/// How often connections are pinged, and how many unanswered pings within a
+/// window close them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pinging {
+    pub interval: Duration,
+    pub window: Duration,
+    pub misses: usize,
+}
+
+impl Default for Pinging {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_millis(1000),
+            window: Duration::from_millis(5000),
+            misses: 3,
+        }
+    }
+}
+
+impl Pinging {
+    /// Overrides the defaults with `PING_INTERVAL_MS`, `PING_WINDOW_MS` and
+    /// `PING_MISSES`
+    pub fn from_env() -> Result<Self, String> {
+        let var = |name: &str| -> Result<Option<u64>, String> {
+            match std::env::var(name) {
+                Ok(value) => value.trim().parse()
+                    .map(Some)
+                    .map_err(|e| format!("invalid {} {:?}: {}", name, value, e)),
+                Err(_) => Ok(None),
+            }
+        };
+        let mut pinging = Self::default();
+        if let Some(ms) = var("PING_INTERVAL_MS")? {
+            pinging.interval = Duration::from_millis(ms.max(1));
+        }
+        if let Some(ms) = var("PING_WINDOW_MS")? {
+            pinging.window = Duration::from_millis(ms);
+        }
+        if let Some(misses) = var("PING_MISSES")? {
+            pinging.misses = misses.max(1) as usize;
+        }
+        Ok(pinging)
+    }
+}
+
+/// Counts the connections that neither control a contestant nor are the
+/// moderator. Only published once it moved by more than the hysteresis, so
+/// not every join and leave resends the state to everyone.
+#[derive(Debug, Clone)]
+pub struct Spectators(Arc<SpectatorsInner>);
+
+#[derive(Debug)]
+struct SpectatorsInner {
+    count: Mutex<usize>,
+    published: watch::Sender<usize>,
+    /// Can be changed by a [Profile](crate::profile::Profile)
+    hysteresis: AtomicUsize,
+}
+
+impl Default for Spectators {
+    fn default() -> Self { Self::new(0) }
+}
+
+impl Spectators {
+    pub fn new(hysteresis: usize) -> Self {
+        Self(Arc::new(SpectatorsInner {
+            count: Mutex::new(0),
+            published: watch::Sender::new(0),
+            hysteresis: AtomicUsize::new(hysteresis),
+        }))
+    }
+
+    /// For the next change of the count
+    pub fn set_hysteresis(&self, hysteresis: usize) {
+        self.0.hysteresis.store(hysteresis, Ordering::Relaxed);
+    }
+
+    /// Reads the hysteresis from `SPECTATOR_HYSTERESIS`, 0 publishes every
+    /// change
+    pub fn from_env() -> Result<Self, String> {
+        match std::env::var("SPECTATOR_HYSTERESIS") {
+            Ok(value) => value.trim().parse()
+                .map(Self::new)
+                .map_err(|e| format!("invalid SPECTATOR_HYSTERESIS {:?}: {}", value, e)),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<usize> {
+        self.0.published.subscribe()
+    }
+
+    fn join(&self) {
+        self.update(|count| count + 1);
+    }
+
+    fn leave(&self) {
+        self.update(|count| count.saturating_sub(1));
+    }
 
-#[tracing::instrument(skip(socket, rx, tx))]
+    fn update(&self, change: impl FnOnce(usize) -> usize) {
+        let mut count = self.0.count.lock().unwrap();
+        *count = change(*count);
+        let hysteresis = self.0.hysteresis.load(Ordering::Relaxed);
+        self.0.published.send_if_modified(|published| {
+            // nobody watching anymore is always worth telling
+            let publish = count.abs_diff(*published) > hysteresis || (*count == 0 && *published != 0);
+            if publish {
+                *published = *count;
+            }
+            publish
+        });
+    }
+}
+
+/// Counts the moderators' connections, see [State::with_host_watch]
+#[derive(Debug, Clone)]
+pub struct Hosts(Arc<watch::Sender<usize>>);
+
+impl Default for Hosts {
+    fn default() -> Self { Self(Arc::new(watch::Sender::new(0))) }
+}
+
+impl Hosts {
+    pub fn subscribe(&self) -> watch::Receiver<usize> {
+        self.0.subscribe()
+    }
+
+    pub fn connected(&self) -> usize {
+        *self.0.borrow()
+    }
+
+    fn join(&self) {
+        self.0.send_modify(|count| *count += 1);
+    }
+
+    fn leave(&self) {
+        self.0.send_modify(|count| *count = count.saturating_sub(1));
+    }
+}
+
+/// Bumped whenever what is sent over the websocket changes in a way older
+/// clients could trip over, which [schemas] keep track of. Sent with every
+/// state and as [PROTOCOL_HEADER] when upgrading the connection.
+pub const PROTOCOL_VERSION: u32 = 8;
+pub const PROTOCOL_HEADER: &str = "x-aitfoaq-protocol";
+
+#[tracing::instrument(skip(socket, channels))]
+#[allow(clippy::too_many_arguments)] // one per negotiated setting
 pub async fn player_handler(
     socket: WebSocket,
     peer_address: SocketAddr,
-    StateChannelsAndToken{rx, tx, ..}: StateChannelsAndToken,
+    channels: StateChannelsAndToken,
     is_admin: bool,
     serializer: Serializer,
+    scope: Scope,
+    locale: Locale,
+    preferences: DisplayPreferences,
 ) {
-    let name = format!("{}", &peer_address);
-    let mut connection = Connection {
-        should_disconnect: false,
-        socket,
-        tx,
-        rx,
-        serializer,
-        state: ConnectionState {
-            is_admin,
-            name: name.to_owned(),
-            controlling: None,
-        },
-        pinger: Pinger::from(name),
-    };
-    let state = connection.rx.borrow().clone();
+    let mut connection = Connection::new(socket, format!("{}", &peer_address), channels, is_admin, serializer, locale);
+    connection.state.scope = scope;
+    connection.state.display = preferences;
+    connection.run().await;
+}
+
+/// How many messages besides state updates may wait for a slow client
+const OUTBOX_SIZE: usize = 16;
+/// How long the outbox may stay full without the client catching up before
+/// it is given up
+const LAGGING_DEADLINE: Duration = Duration::from_secs(5);
+
+/// The websocket as far as a [Connection] needs it, so it can be driven
+/// without a real one. Split so a slow client only stalls the writer task.
+trait Socket {
+    type Reader: SocketReader;
+    type Writer: SocketWriter;
+    fn split(self) -> (Self::Reader, Self::Writer);
+}
+
+trait SocketReader {
+    async fn recv(&mut self) -> Option<Result<Message, axum::Error>>;
+}
 
-    // send initial state
-    if connection.socket
-        .send(Message::Text(connection.serializer.game_state(&state, &connection.state)))
-        .await
-        .is_err()
-    {
-        tracing::error!(%connection.state.name, "socket was prematuerely closed");
-        return;
+trait SocketWriter: Send + 'static {
+    fn send(&mut self, msg: Message) -> impl Future<Output = Result<(), axum::Error>> + Send;
+}
+
+impl Socket for WebSocket {
+    type Reader = SplitStream<WebSocket>;
+    type Writer = SplitSink<WebSocket, Message>;
+    fn split(self) -> (Self::Reader, Self::Writer) {
+        let (writer, reader) = StreamExt::split(self);
+        (reader, writer)
     }
+}
 
-    // put his whole thing in a struct and put the body of the loop in a function
-    // implement Drop to send Event::DisconnectPlayer on connection close
+impl SocketReader for SplitStream<WebSocket> {
+    async fn recv(&mut self) -> Option<Result<Message, axum::Error>> { self.next().await }
+}
 
-    while !connection.should_disconnect {
-        select! {
-            msg = connection.socket.recv() => { connection.handle_message(msg).await; },
-            Ok(_) = connection.rx.changed() => { connection.handle_new_game_state().await; }
-            _ = connection.pinger.tick() => {
-                match connection.pinger.next() {
-                    Ok(payload) => { connection.send_msg(Message::Ping(payload)).await; },
-                    Err(e) => { connection.disconnect(e, "Too many missed pings").await; }
-                }
+impl SocketWriter for SplitSink<WebSocket, Message> {
+    fn send(&mut self, msg: Message) -> impl Future<Output = Result<(), axum::Error>> + Send {
+        SinkExt::send(self, msg)
+    }
+}
+
+/// Messages waiting for the writer task. State updates replace each other
+/// since only the latest matters, everything else is sent in order and never
+/// dropped.
+struct Outbox {
+    messages: mpsc::Sender<Message>,
+    state: watch::Sender<Option<String>>,
+    /// Messages that didn't fit, moved over as the writer catches up
+    overflow: VecDeque<Message>,
+    /// Since when the writer hasn't caught up with a full outbox
+    full_since: Option<Instant>,
+    writer: JoinHandle<()>,
+}
+
+impl Outbox {
+    fn new(name: String, writer: impl SocketWriter) -> Self {
+        let (messages, messages_rx) = mpsc::channel(OUTBOX_SIZE);
+        let (state, state_rx) = watch::channel(None);
+        Self {
+            messages,
+            state,
+            overflow: VecDeque::new(),
+            full_since: None,
+            writer: tokio::spawn(write(name, writer, messages_rx, state_rx)),
+        }
+    }
+    fn state(&mut self, frame: String) {
+        self.state.send_replace(Some(frame));
+    }
+    fn push(&mut self, msg: Message) {
+        let msg = match self.overflow.is_empty() {
+            true => match self.messages.try_send(msg) {
+                Ok(()) => return,
+                Err(mpsc::error::TrySendError::Full(msg)) => msg,
+                // the writer is gone and the connection about to end
+                Err(mpsc::error::TrySendError::Closed(_)) => return,
             },
+            false => msg,
+        };
+        self.full_since.get_or_insert_with(Instant::now);
+        self.overflow.push_back(msg);
+    }
+    /// Moves the oldest overflowing message over, once there's room
+    fn catch_up(&mut self, permit: mpsc::OwnedPermit<Message>) {
+        if let Some(msg) = self.overflow.pop_front() {
+            permit.send(msg);
         }
+        self.full_since = (!self.overflow.is_empty()).then(Instant::now);
+    }
+    fn lagging_deadline(&self) -> Instant {
+        self.full_since.unwrap_or_else(Instant::now) + LAGGING_DEADLINE
+    }
+}
+
+impl Drop for Outbox {
+    fn drop(&mut self) {
+        // a client that stopped reading can't hold on to the socket
+        self.writer.abort();
     }
+}
 
-    if let Some(c) = connection.state.controlling {
-        let _ = State::send(Event::DisconnectContestant { contestant: c }, &connection.tx).await;
+/// Writes queued messages to the socket until it fails
+async fn write(
+    name: String,
+    mut writer: impl SocketWriter,
+    mut messages: mpsc::Receiver<Message>,
+    mut state: watch::Receiver<Option<String>>,
+) {
+    loop {
+        let msg = select! {
+            biased;
+            Some(msg) = messages.recv() => msg,
+            Ok(()) = state.changed() => match state.borrow_and_update().clone() {
+                Some(frame) => Message::Text(frame),
+                None => continue,
+            },
+            else => return,
+        };
+        if let Err(error) = writer.send(msg).await {
+            tracing::warn!(%name, ?error, "failed to send message");
+            return;
+        }
     }
 }
 
-struct Connection {
+/// For the next [Connection]
+static NEXT_CONNECTION: AtomicU64 = AtomicU64::new(0);
+
+/// A single client. Releases the contestant it controls when it ends, or
+/// when it is dropped before that, see [State::release].
+struct Connection<R: SocketReader> {
+    id: ConnectionId,
     should_disconnect: bool,
-    socket: WebSocket,
+    socket: R,
+    outbox: Outbox,
     tx: tokio::sync::mpsc::Sender<crate::state::In>,
     rx: tokio::sync::watch::Receiver<crate::state::Out>,
     serializer: Serializer,
     state: ConnectionState,
     pinger: Pinger,
+    spectators: Spectators,
+    spectator_count: watch::Receiver<usize>,
+    /// If this connection is counted as a spectator right now
+    spectating: bool,
+    /// The input of a contestant that still has to be acknowledged, and
+    /// in which format, see [Ack]
+    acking: Option<(String, Serializer)>,
+    hosts: Hosts,
+    /// If this connection is counted as a moderator right now
+    hosting: bool,
+}
+
+impl<R: SocketReader> Drop for Connection<R> {
+    fn drop(&mut self) {
+        if self.spectating {
+            self.spectators.leave();
+        }
+        if self.hosting {
+            self.hosts.leave();
+        }
+        // `run` was cancelled, nobody can wait for this anymore
+        if let Some(contestant) = self.state.controlling.take() {
+            let (tx, id) = (self.tx.clone(), self.id);
+            tokio::spawn(async move {
+                let _ = State::release(contestant, id, &tx).await;
+            });
+        }
+    }
 }
 
-impl Connection {
-    async fn handle_new_game_state(&mut self) {
-        let new = self.rx.borrow_and_update().clone();
-        if let Err(error) = self.socket.send(
-            Message::Text(self.serializer.game_state(&new, &self.state))
-        ).await {
-            self.disconnect(error.into(), "failed to send state update").await;
+impl<R: SocketReader> Connection<R> {
+    fn new<S: Socket<Reader = R>>(
+        socket: S,
+        name: String,
+        StateChannelsAndToken{rx, tx, replaying, pinging, spectators, hosts, ..}: StateChannelsAndToken,
+        is_admin: bool,
+        serializer: Serializer,
+        locale: Locale,
+    ) -> Self {
+        let (socket, writer) = socket.split();
+        Self {
+            id: NEXT_CONNECTION.fetch_add(1, Ordering::Relaxed),
+            should_disconnect: false,
+            socket,
+            outbox: Outbox::new(name.clone(), writer),
+            tx,
+            rx,
+            serializer,
+            state: ConnectionState {
+                is_admin,
+                name: name.clone(),
+                controlling: None,
+                replaying,
+                locale,
+                scope: Scope::default(),
+                display: DisplayPreferences::default(),
+                may: Capabilities::default(),
+                legal_events: vec![],
+                protocol_version: PROTOCOL_VERSION,
+                server_version: env!("CARGO_PKG_VERSION").to_owned(),
+            },
+            pinger: Pinger::new(name, *pinging.borrow()),
+            spectator_count: spectators.subscribe(),
+            spectators,
+            spectating: false,
+            acking: None,
+            hosts,
+            hosting: false,
+        }
+    }
+
+    async fn run(mut self) {
+        self.count_spectator();
+        if self.state.is_admin {
+            self.hosts.join();
+            self.hosting = true;
         }
+        // send initial state
+        self.handle_new_game_state();
+
+        while !self.should_disconnect {
+            self.step().await;
+            self.count_spectator();
+        }
+
+        if let Some(contestant) = self.state.controlling.take() {
+            let _ = State::release(contestant, self.id, &self.tx).await;
+        }
+    }
+
+    /// Handles whatever happens first: a message, a new state, a ping, or the
+    /// writer catching up or falling behind. Never waits for the client.
+    async fn step(&mut self) {
+        select! {
+            msg = self.socket.recv() => { self.handle_message(msg).await; },
+            Ok(_) = self.rx.changed() => { self.handle_changed_game_state(); }
+            Ok(_) = self.spectator_count.changed() => { self.handle_new_game_state(); }
+            _ = self.pinger.tick() => {
+                match self.pinger.next(Instant::now()) {
+                    Ok(payload) => { self.send_msg(Message::Ping(payload)); },
+                    Err(e) => { self.disconnect(e, "Too many missed pings").await; }
+                }
+            },
+            Ok(permit) = self.outbox.messages.clone().reserve_owned(), if !self.outbox.overflow.is_empty() => {
+                self.outbox.catch_up(permit);
+            },
+            _ = sleep_until(self.outbox.lagging_deadline()), if self.outbox.full_since.is_some() => {
+                self.disconnect(Error::Lagging(self.outbox.overflow.len()), "client stopped reading").await;
+            },
+            _ = self.outbox.messages.closed() => {
+                self.disconnect_without_error("failed to send messages").await;
+            },
+        }
+    }
+
+    /// Resends the state without a cause, e.g. when the client connects
+    fn handle_new_game_state(&mut self) {
+        self.send_game_state(false);
+    }
+    fn handle_changed_game_state(&mut self) {
+        self.send_game_state(true);
+    }
+    fn send_game_state(&mut self, changed: bool) {
+        let mut new = self.rx.borrow_and_update().clone();
+        if !changed {
+            new.caused_by = None;
+        }
+        let spectators = *self.spectator_count.borrow_and_update();
+        self.outbox.state(self.serializer.game_state(&new, &self.state, spectators));
+    }
+    /// Keeps [Spectators] up to date as this connection starts or stops
+    /// controlling a contestant
+    fn count_spectator(&mut self) {
+        let spectating = !self.state.is_admin && self.state.controlling.is_none();
+        match (self.spectating, spectating) {
+            (false, true) => self.spectators.join(),
+            (true, false) => self.spectators.leave(),
+            _ => {},
+        }
+        self.spectating = spectating;
     }
     async fn handle_message(&mut self, msg: Option<Result<Message, axum::Error>>) {
         let msg = match msg {
@@ -102,11 +473,11 @@ impl Connection {
             },
             Message::Ping(payload) => {
                 // tracing::trace!(%self.state.name, "received ping");
-                self.send_msg(Message::Pong(payload)).await;
+                self.send_msg(Message::Pong(payload));
             },
             Message::Pong(payload) => {
-                if let Some(rtt_latency) = self.pinger.received(payload) {
-                    // tracing::trace!(%self.state.name, ?rtt_latency);
+                if let Some(rtt_latency) = self.pinger.received(payload, Instant::now()) {
+                    tracing::trace!(%self.state.name, ?rtt_latency);
                 }
             },
             Message::Binary(_) => {
@@ -114,68 +485,291 @@ impl Connection {
                 return;
             },
             Message::Text(msg) => {
-                match serde_json::from_str::<Input>(&msg) {
-                    Err(error) => {
-                        tracing::warn!(%self.state.name, %msg, ?error, "received unrecognized msg from client");
+                let parsed = msg.parse::<InputMessage>();
+                if let (Ok(_), Some(contestant)) = (&parsed, self.state.controlling) {
+                    // whatever they sent, someone is there, see [Idleness](crate::state::Idleness)
+                    if let Err(rejection) = State::active(contestant, &self.tx).await {
+                        tracing::debug!(%self.state.name, ?rejection, "could not refresh activity");
+                    }
+                }
+                self.acking = match &parsed {
+                    Ok(InputMessage { input: Input::Subscribe { .. }, .. }) | Err(_) => None,
+                    Ok(InputMessage { kind, format, .. }) => (self.state.controlling.is_some() && !self.state.is_admin)
+                        .then(|| (kind.clone(), format.unwrap_or(self.serializer))),
+                };
+                self.handle_input_message(parsed, &msg).await;
+                if let Some((input, format)) = self.acking.take() {
+                    self.send_ack(Ack { input, accepted: true, reason: None }, None, format);
+                }
+            },
+        } {
+        }
+    }
+    async fn handle_input_message(&mut self, parsed: Result<InputMessage, serde_json::Error>, msg: &str) {
+        match parsed {
+            Err(error) => {
+                tracing::warn!(%self.state.name, %msg, ?error, "received unrecognized msg from client");
+            },
+            Ok(InputMessage { input: Input::Subscribe { format, scope }, .. }) => {
+                tracing::trace!(%self.state.name, ?format, ?scope, "switching state format");
+                self.serializer = format.unwrap_or(self.serializer);
+                self.state.scope = scope.unwrap_or(self.state.scope);
+                self.handle_new_game_state();
+            },
+            Ok(InputMessage { input: input @ (Input::ReplayNext | Input::ReplayPrev | Input::ReplaySeek { .. }), format, .. }) => {
+                let format = format.unwrap_or(self.serializer);
+                if !self.state.is_admin {
+                    return self.send_error(Error::Forbidden, format);
+                }
+                let step = match input {
+                    Input::ReplayPrev => ReplayStep::Prev,
+                    Input::ReplaySeek { index } => ReplayStep::Seek(index),
+                    _ => ReplayStep::Next,
+                };
+                if let Err(e) = State::replay(step, &self.tx).await {
+                    self.send_error(e.into(), format);
+                }
+            },
+            Ok(InputMessage { input: Input::SuggestName { name }, format, .. }) => {
+                let format = format.unwrap_or(self.serializer);
+                let Some(contestant) = self.state.controlling else {
+                    return self.send_error(Error::Forbidden, format);
+                };
+                if let Err(e) = State::suggest_name(contestant, name, &self.tx).await {
+                    self.send_error(e.into(), format);
+                }
+            },
+            Ok(InputMessage { input: Input::SubmitAnswerText { text }, format, .. }) => {
+                let format = format.unwrap_or(self.serializer);
+                let Some(contestant) = self.state.controlling else {
+                    return self.send_error(Error::Forbidden, format);
+                };
+                if let Err(e) = State::send(Event::SubmitAnswer { contestant, text }, &self.tx).await {
+                    self.send_error(e.into(), format);
+                }
+            },
+            Ok(InputMessage { input: input @ (Input::ApproveName { .. } | Input::RejectName { .. }), format, .. }) => {
+                let format = format.unwrap_or(self.serializer);
+                if !self.state.is_admin {
+                    return self.send_error(Error::Forbidden, format);
+                }
+                let (contestant, approved) = match input {
+                    Input::ApproveName { contestant } => (contestant, true),
+                    Input::RejectName { contestant } => (contestant, false),
+                    _ => unreachable!(),
+                };
+                if let Err(e) = State::approve_name(contestant, approved, &self.tx).await {
+                    self.send_error(e.into(), format);
+                }
+            },
+            Ok(InputMessage { input: Input::BuzzFor { contestant }, format, .. }) => {
+                let format = format.unwrap_or(self.serializer);
+                if !self.state.is_admin {
+                    return self.send_error(Error::Forbidden, format);
+                }
+                let event = Event::Buzz { contestant };
+                if let Err(e) = State::send_by(event, Actor::Moderator, &self.tx).await {
+                    self.send_error(e.into(), format);
+                }
+            },
+            Ok(InputMessage { input: Input::ClearIdle { contestant }, format, .. }) => {
+                let format = format.unwrap_or(self.serializer);
+                if !self.state.is_admin {
+                    return self.send_error(Error::Forbidden, format);
+                }
+                if let Err(e) = State::clear_idle(contestant, &self.tx).await {
+                    self.send_error(e.into(), format);
+                }
+            },
+            Ok(InputMessage { input: Input::AdjustScores { changes, reason, dry_run }, format, .. }) => {
+                let format = format.unwrap_or(self.serializer);
+                if !self.state.is_admin {
+                    return self.send_error(Error::Forbidden, format);
+                }
+                if dry_run {
+                    let preview = ScorePreview::new(&self.rx.borrow().game.contestants, &changes);
+                    return match preview {
+                        Ok(preview) => self.send_msg(Message::Text(format.score_preview(&preview, self.state.locale))),
+                        Err(e) => self.send_error(e.into(), format),
+                    };
+                }
+                if let Err(e) = State::send(Event::AdjustScores { changes, reason }, &self.tx).await {
+                    self.send_error(e.into(), format);
+                }
+            },
+            Ok(InputMessage { input: Input::ApplyProfile { name }, format, .. }) => {
+                let format = format.unwrap_or(self.serializer);
+                if !self.state.is_admin {
+                    return self.send_error(Error::Forbidden, format);
+                }
+                if let Err(e) = State::apply_profile(name, &self.tx).await {
+                    self.send_error(e.into(), format);
+                }
+            },
+            Ok(InputMessage { input: input @ (Input::ScheduleStart { .. } | Input::CancelScheduledStart), format, .. }) => {
+                let format = format.unwrap_or(self.serializer);
+                if !self.state.is_admin {
+                    return self.send_error(Error::Forbidden, format);
+                }
+                let after = match input {
+                    Input::ScheduleStart { seconds } => Some(Duration::from_secs(seconds)),
+                    _ => None,
+                };
+                if let Err(e) = State::schedule_start(after, &self.tx).await {
+                    self.send_error(e.into(), format);
+                }
+            },
+            Ok(InputMessage { input: input @ (Input::SetLobbyDeadline { .. } | Input::ExtendLobbyDeadline { .. }), format, .. }) => {
+                let format = format.unwrap_or(self.serializer);
+                if !self.state.is_admin {
+                    return self.send_error(Error::Forbidden, format);
+                }
+                let result = match input {
+                    Input::SetLobbyDeadline { seconds } => State::set_lobby_deadline(Duration::from_secs(seconds), &self.tx).await,
+                    Input::ExtendLobbyDeadline { seconds } => State::extend_lobby_deadline(Duration::from_secs(seconds), &self.tx).await,
+                    _ => return,
+                };
+                if let Err(e) = result {
+                    self.send_error(e.into(), format);
+                }
+            },
+            Ok(InputMessage { input: Input::ClaimContestant { contestant }, format, .. }) => {
+                let format = format.unwrap_or(self.serializer);
+                if self.state.controlling.is_some() { return };
+                match State::claim_seat(contestant, self.id, &self.tx).await {
+                    Err(e) => { self.send_error(e.into(), format); }
+                    Ok(_) => {
+                        self.state.controlling = Some(contestant);
                     },
-                    Ok(input) => {
-                        tracing::trace!(%self.state.name, ?input, "received msg from client");
-                        match handle_input(input).await {
-                            Ok(Some(Event::ConnectContestant { name_hint })) => {
-                                if self.state.controlling.is_some() { return };
-                                let event = Event::ConnectContestant { name_hint };
-                                match State::send(event, &self.tx).await {
-                                    Err(e) => { self.send_error(e.into()).await; }
-                                    Ok(state) => {
-                                        self.state.controlling = Some(state.contestants.len());
-                                    },
-                                }
-                            },
-                            Ok(Some(Event::DisconnectContestant { contestant })) => {
-                                if self.state.controlling.is_none() { return };
-                                let event = Event::DisconnectContestant { contestant };
-                                match State::send(event, &self.tx).await {
-                                    Err(e) => { self.send_error(e.into()).await; }
-                                    Ok(_) => {
-                                        self.state.controlling = None;
-                                    },
-                                }
-                            },
-                            Ok(Some(Event::ReconnectContestant { contestant })) => {
-                                if self.state.controlling.is_some() { return };
-                                let event = Event::ReconnectContestant { contestant };
-                                match State::send(event, &self.tx).await {
-                                    Err(e) => { self.send_error(e.into()).await; }
-                                    Ok(_) => {
-                                        self.state.controlling = Some(contestant);
-                                    },
-                                }
+                }
+            },
+            Ok(InputMessage { input: Input::Resume, format, .. }) => {
+                let format = format.unwrap_or(self.serializer);
+                if !self.state.is_admin {
+                    return self.send_error(Error::Forbidden, format);
+                }
+                if let Err(e) = State::resume(&self.tx).await {
+                    self.send_error(e.into(), format);
+                }
+            },
+            Ok(InputMessage {
+                input: Input::Settings { .. }
+                    | Input::LoadBoard { .. }
+                    | Input::ShuffleBoard { .. }
+                    | Input::EditClue { .. }
+                    | Input::EditCategoryTitle { .. }
+                    | Input::AwardPoints { .. }
+                    | Input::RevokePoints { .. }
+                    | Input::Unlock,
+                format,
+                ..
+            }) if !self.state.is_admin => {
+                // the board, the settings and the scores are up to the moderator
+                self.send_error(Error::Forbidden, format.unwrap_or(self.serializer));
+            },
+            Ok(InputMessage { input, format, .. }) => {
+                tracing::trace!(%self.state.name, ?input, "received msg from client");
+                let format = format.unwrap_or(self.serializer);
+                match handle_input(input).await {
+                    Ok(Some(Event::ConnectContestant { name_hint })) => {
+                        if self.state.controlling.is_some() { return };
+                        match State::join(name_hint, self.id, &self.tx).await {
+                            Err(e) => { self.send_error(e.into(), format); }
+                            Ok(state) => {
+                                // the new contestant is the last one
+                                self.state.controlling = Some(state.contestants.len() - 1);
                             },
-                            Ok(Some(event)) => {
-                                if let Err(e) = State::send(event, &self.tx).await {
-                                    self.send_error(e.into()).await;
-                                }
+                        }
+                    },
+                    Ok(Some(Event::DisconnectContestant { contestant })) => {
+                        // only the contestant this connection controls
+                        if self.state.controlling != Some(contestant) { return };
+                        match State::release(contestant, self.id, &self.tx).await {
+                            Err(e) => { self.send_error(e.into(), format); }
+                            Ok(_) => {
+                                self.state.controlling = None;
                             },
-                            Ok(None) => {},
-                            Err(error) => {
-                                tracing::error!(%self.state.name, ?error, "encountered error while handling input");
-                                self.send_error(error).await;
+                        }
+                    },
+                    Ok(Some(Event::ReconnectContestant { contestant })) => {
+                        if self.state.controlling.is_some() { return };
+                        match State::claim(contestant, self.id, &self.tx).await {
+                            Err(e) => { self.send_error(e.into(), format); }
+                            Ok(_) => {
+                                self.state.controlling = Some(contestant);
                             },
                         }
                     },
+                    Ok(Some(Event::SetWage { points: 0 })) if !self.state.is_admin => {
+                        // skipping the wager is up to the moderator
+                        self.send_error(Error::Forbidden, format);
+                    },
+                    Ok(Some(event @ (Event::SetWage { .. } | Event::ConfirmWage | Event::CancelWage))) if !self.state.is_admin => {
+                        // only the waging contestant
+                        let may = Capabilities::of(&self.rx.borrow().game.phase, self.state.controlling, false);
+                        if !may.can_wage {
+                            return self.send_error(Error::Forbidden, format);
+                        }
+                        if let Err(e) = State::send(event, &self.tx).await {
+                            self.send_error(e.into(), format);
+                        }
+                    },
+                    Ok(Some(Event::PassPick { to })) => {
+                        // only the moderator or the picking contestant
+                        let picking = match self.rx.borrow().game.phase {
+                            GamePhase::Picking { contestant } => Some(contestant),
+                            _ => None,
+                        };
+                        if !self.state.is_admin && (picking.is_none() || self.state.controlling != picking) {
+                            return self.send_error(Error::Forbidden, format);
+                        }
+                        if let Err(e) = State::send(Event::PassPick { to }, &self.tx).await {
+                            self.send_error(e.into(), format);
+                        }
+                    },
+                    Ok(Some(Event::WithdrawBuzz)) => {
+                        // only the moderator or the contestant who buzzed
+                        let buzzed = match self.rx.borrow().game.phase {
+                            GamePhase::Buzzed { contestant, .. } => Some(contestant),
+                            _ => None,
+                        };
+                        if !self.state.is_admin && (buzzed.is_none() || self.state.controlling != buzzed) {
+                            return self.send_error(Error::Forbidden, format);
+                        }
+                        if let Err(e) = State::send(Event::WithdrawBuzz, &self.tx).await {
+                            self.send_error(e.into(), format);
+                        }
+                    },
+                    Ok(Some(event)) => {
+                        if let Err(e) = State::send(event, &self.tx).await {
+                            self.send_error(e.into(), format);
+                        }
+                    },
+                    Ok(None) => {},
+                    Err(error) => {
+                        tracing::error!(%self.state.name, ?error, "encountered error while handling input");
+                        self.send_error(error, format);
+                    },
                 }
             },
-        } {
         }
     }
-    async fn send_error(&mut self, err: Error) {
-        self.send_msg(Message::Text(self.serializer.error(err))).await;
-    }
-    async fn send_state(&mut self, state: GameState) {}
-    async fn send_msg(&mut self, msg: Message) {
-        if let Err(error) = self.socket.send(msg).await {
-            self.disconnect(error.into(), "failed to send message").await;
+    fn send_error(&mut self, err: Error, format: Serializer) {
+        if let Some((input, _)) = self.acking.take() {
+            let reason = Reason::of(&err, self.state.controlling);
+            self.send_ack(Ack { input, accepted: false, reason: Some(reason) }, Some(&err), format);
+            if format == Serializer::Html {
+                // the toast already says why
+                return;
+            }
         }
+        self.send_msg(Message::Text(format.error(err, self.state.locale)));
+    }
+    fn send_ack(&mut self, ack: Ack, error: Option<&Error>, format: Serializer) {
+        self.send_msg(Message::Text(format.ack(ack, error, self.state.locale)));
+    }
+    fn send_msg(&mut self, msg: Message) {
+        self.outbox.push(msg);
     }
     async fn disconnect(&mut self, cause: Error, reason: &str) {
         tracing::warn!(%self.state.name, ?cause, %reason, "disconnecting");
@@ -191,179 +785,3484 @@ struct Pinger {
     name: String,
     counter: u64,
     interval: Interval,
+    pinging: Pinging,
     outstanding: HashMap<Vec<u8>, Instant>,
 }
 
 impl Pinger {
-    fn from(name: String) -> Self {
-        let mut interval = interval(PING_INTERVAL);
+    fn new(name: String, pinging: Pinging) -> Self {
+        let mut interval = interval(pinging.interval);
         interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
         Self {
             name: format!("{}-{:?}", name, Instant::now()),
             counter: 0,
             interval,
-            outstanding: HashMap::with_capacity(5),
+            pinging,
+            outstanding: HashMap::with_capacity(pinging.misses),
         }
     }
     async fn tick(&mut self) { self.interval.tick().await; }
-    fn next(&mut self) -> Result<Vec<u8>, Error> {
-        self.outstanding.retain(|_, t| t.elapsed() < PING_WINDOW);
-        if self.outstanding.len() >= PING_MISSES {
-            return Err(Error::MissedPings(
-                self.outstanding.values()
-                    .map(|i| i.elapsed())
-                    .collect()
-            ));
+    fn next(&mut self, now: Instant) -> Result<Vec<u8>, Error> {
+        self.outstanding.retain(|_, t| now.duration_since(*t) < self.pinging.window);
+        if self.outstanding.len() >= self.pinging.misses {
+            let mut ages: Vec<_> = self.outstanding.values()
+                .map(|t| now.duration_since(*t))
+                .collect();
+            ages.sort();
+            return Err(Error::MissedPings(ages));
         }
 
         let payload: Vec<u8> = format!("{}:{}", self.name, self.counter).into();
         self.counter += 1;
-        self.outstanding.insert(payload.to_owned(), Instant::now());
+        self.outstanding.insert(payload.to_owned(), now);
         Ok(payload)
     }
-    fn received(&mut self, payload: Vec<u8>) -> Option<Duration> {
-        // tracing::trace!(
-        //     received = %String::from_utf8_lossy(&payload),
-        //     outstanding = ?self.outstanding.keys().map(|v| String::from_utf8_lossy(&v)).collect::<Vec<_>>(),
-        //     "outstanding pings"
-        // );
-        self.outstanding.remove(&payload).map(|t| t.elapsed())
+    fn received(&mut self, payload: Vec<u8>, now: Instant) -> Option<Duration> {
+        self.outstanding.remove(&payload).map(|t| now.duration_since(t))
     }
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 struct ConnectionState {
     is_admin: bool,
     name: String,
     controlling: Option<ContestantHandle>,
+    replaying: bool,
+    /// Only for the server's own strings, see [Locale::negotiate]
+    #[serde(default)]
+    locale: Locale,
+    #[serde(default)]
+    scope: Scope,
+    /// Only for the HTML, see [DisplayPreferences::from_query]
+    #[serde(default)]
+    display: DisplayPreferences,
+    /// Derived from the phase whenever the state is sent
+    #[serde(flatten)]
+    may: Capabilities,
+    /// The [GameState::legal_events] this connection is meant to send, all
+    /// of them for the moderator
+    #[serde(default)]
+    legal_events: Vec<EventKind>,
+    /// See [PROTOCOL_VERSION], missing from servers before it
+    #[serde(default)]
+    protocol_version: u32,
+    /// The server's crate version, for the logs of clients
+    #[serde(default)]
+    server_version: String,
 }
 
-#[derive(Template, serde::Serialize, serde::Deserialize)]
-#[template(path = "state.html")]
-struct StateTemplate {
-    game: GameState,
-    connection: ConnectionState,
-}
-
-impl StateTemplate {
-    fn is_winner(&self, c: ContestantHandle) -> Result<bool, Error> {
-        if !matches!(self.game.phase, GamePhase::Score) { return Ok(false); }
-        let highscore = self.game.contestants
+impl ConnectionState {
+    /// Works out [Capabilities] and the legal events for `game`
+    fn with(&self, game: &GameState) -> Self {
+        let mut connection = self.clone();
+        connection.may = Capabilities::of(&game.phase, self.controlling, self.is_admin);
+        connection.legal_events = game
+            .legal_events
             .iter()
-            .max_by_key(|c| c.points)
-            .ok_or(libaitfoaq::Error::ContestantNotFound)?
-            .points;
-        let score = self.game.contestants
-            .get(c)
-            .ok_or(libaitfoaq::Error::ContestantNotFound)?
-            .points;
-        Ok(score == highscore)
+            .copied()
+            .filter(|kind| self.is_admin || connection.may.allow(*kind, self.controlling.is_some()))
+            .collect();
+        connection
     }
 }
 
-#[derive(Debug)]
-pub enum Serializer {
-    HTML,
-    JSON,
+/// What a connection may do in the current phase, so clients don't have to
+/// work it out from the phase themselves
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+struct Capabilities {
+    can_buzz: bool,
+    can_pick: bool,
+    can_wage: bool,
+    /// Typing an answer, see [Input::SubmitAnswerText]
+    can_answer: bool,
 }
 
-impl Serializer {
-    #[tracing::instrument]
-    fn game_state(&self, game: &GameState, connection: &ConnectionState) -> String {
-        let state = StateTemplate {
-            game: game.clone(),
-            connection: connection.clone(),
-        };
-        match self {
-            Self::HTML => {
-                state.render().unwrap_or_else(|e| self.error(e.into()))
-            },
-            Self::JSON => {
-                serde_json::to_string(&state).unwrap_or_else(|e| self.error(e.into()))
-            },
+impl Capabilities {
+    /// The moderator picks and wages on behalf of contestants, but only
+    /// contestants buzz. Nobody is locked out of buzzing again after a wrong
+    /// answer.
+    fn of(phase: &GamePhase, controlling: Option<ContestantHandle>, is_admin: bool) -> Self {
+        let is = |contestant: &ContestantHandle| is_admin || controlling == Some(*contestant);
+        Self {
+            can_buzz: controlling.is_some() && matches!(phase, GamePhase::Buzzing { .. } | GamePhase::Practice { .. }),
+            can_pick: matches!(phase, GamePhase::Picking { contestant } if is(contestant)),
+            can_wage: matches!(phase, GamePhase::Waging { contestant, .. } if is(contestant)),
+            can_answer: matches!(phase, GamePhase::Buzzed { contestant, .. } if controlling == Some(*contestant)),
         }
     }
-    #[tracing::instrument]
-    fn error(&self, error: Error) -> String {
-        match self {
-            Self::HTML => {
-                error.render().unwrap_or("unrenderable error".to_string())
-            },
-            Self::JSON => {
-                serde_json::json!({
-                    "error": format!("{:?}", error)
-                }).to_string()
-            },
+
+    /// If a contestant's controller, or a spectator who could become one, is
+    /// meant to send events of this kind
+    fn allow(self, kind: EventKind, controlling: bool) -> bool {
+        match kind {
+            EventKind::ConnectContestant | EventKind::ReconnectContestant => !controlling,
+            // toggling the lights in the lobby and at the end, too
+            EventKind::DisconnectContestant | EventKind::Buzz => controlling,
+            EventKind::Pick | EventKind::PassPick => self.can_pick,
+            EventKind::SetWage | EventKind::ConfirmWage | EventKind::CancelWage => self.can_wage,
+            EventKind::SubmitAnswer => self.can_answer,
+            _ => false,
         }
     }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "snake_case", tag = "type")]
-enum Input {
-    LoadBoard{board: String},
-    OpenLobby,
-    StartGame,
-    ConnectContestant { name_hint: String },
-    ReconnectContestant { contestant: ContestantHandle },
-    Buzz { contestant: ContestantHandle },
-    Pick { clue: ClueHandle },
-    ClueFullyShown,
-    AcceptAnswer,
-    RejectAnswer,
-    RevealHint,
-    FinishClue,
-    NameContestant { contestant: ContestantHandle, name: String },
-    AwardPoints { contestant: ContestantHandle, points: String },
-    RevokePoints { contestant: ContestantHandle, points: String },
+/// What is sent as JSON, see [StateView] for what the HTML is rendered from
+#[derive(serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+struct StateFrame {
+    game: GameState,
+    connection: ConnectionState,
+    /// Only sent to admins
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pending_names: Option<BTreeMap<ContestantHandle, String>>,
+    /// See [Spectators], it might lag behind a little
+    #[serde(default)]
+    spectators: usize,
+    /// Only sent to admins
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    answer_match: Option<AnswerMatch>,
+    /// Until the game starts by itself, see [Input::ScheduleStart]
+    #[serde(default)]
+    starting_in_ms: Option<u64>,
+    /// Until the lobby should be over, see [Input::SetLobbyDeadline]
+    #[serde(default)]
+    lobby_remaining_ms: Option<u64>,
+    /// Only sent to admins, the last reminder of the lobby deadline as
+    /// seconds before it, zero once it passed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    lobby_reminder_s: Option<u64>,
+    /// Only sent to admins, see [ScheduledStart::Failed]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    start_failed: bool,
+    /// Only sent to admins, see [Input::ApplyProfile]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    profiles: Option<Vec<String>>,
+    /// Contestants without input for a while, see [Out::idle]
+    #[serde(default)]
+    idle: Vec<ContestantHandle>,
+    /// Waiting for a moderator, see [Out::paused] and [Input::Resume]
+    #[serde(default)]
+    paused: bool,
+    /// Only sent to admins, see [Out::controllers]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    controllers: Option<BTreeMap<ContestantHandle, usize>>,
+    /// See [Out::caused_by] and [caused_by]. `null` for frames that weren't
+    /// sent because of a new state, like the first one.
+    #[serde(default)]
+    caused_by: Option<serde_json::Value>,
+    /// Only sent to admins
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    admin: Option<AdminStateExtras>,
+    /// The HTML for everyone but the moderator leaves out the clue until it
+    /// was fully shown, see [Out::obscure_clues]
+    #[serde(default)]
+    obscure_clues: bool,
 }
 
-async fn handle_input(input: Input) -> Result<Option<libaitfoaq::events::Event>, Error> {
-    match input {
-        Input::LoadBoard{board: board_path} => {
-            // todo: load from uploaded json or zipfile instead of path
-            let board = tokio::fs::read(board_path).await?;
-            let board: libaitfoaq::state::Board = serde_json::from_slice(&board)?;
-            Ok(Some(Event::LoadBoard(board)))
-        }
-        Input::OpenLobby => Ok(Some(Event::OpenLobby)),
-        Input::StartGame => Ok(Some(Event::StartGame)),
-        Input::ConnectContestant { name_hint } => Ok(Some(Event::ConnectContestant { name_hint })),
-        Input::ReconnectContestant { contestant } => Ok(Some(Event::ReconnectContestant { contestant })),
-        Input::Buzz { contestant } => Ok(Some(Event::Buzz { contestant })),
-        Input::Pick { clue } => Ok(Some(Event::Pick { clue })),
-        Input::ClueFullyShown => Ok(Some(Event::ClueFullyShown)),
-        Input::AcceptAnswer => Ok(Some(Event::AcceptAnswer)),
-        Input::RejectAnswer => Ok(Some(Event::RejectAnswer)),
-        Input::RevealHint => Ok(Some(Event::RevealHint)),
-        Input::FinishClue => Ok(Some(Event::FinishClue)),
-        Input::NameContestant { contestant, name } => Ok(Some(Event::NameContestant { index: contestant, name })),
-        Input::AwardPoints { contestant, points } => {
-            Ok(Some(Event::AwardPoints{
-                contestant,
-                points: points.parse()?,
-            }))
-        },
-        Input::RevokePoints { contestant, points } => {
-            Ok(Some(Event::RevokePoints{
-                contestant,
-                points: points.parse()?,
-            }))
-        },
+/// How much of the state a connection gets, see [Input::Subscribe]. Only
+/// JSON frames are trimmed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    #[default]
+    Full,
+    /// Just the contestants and the phase, see [ContestantsFrame]. Enough
+    /// for buzzer controllers, which would otherwise parse the whole board
+    /// with every update.
+    Contestants,
+}
+
+/// How the HTML of a connection looks, for venue projectors and low-vision
+/// players. Asked for like `?theme=high-contrast&scale=125`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct DisplayPreferences {
+    pub theme: Theme,
+    /// Text size in percent, between [MIN_SCALE] and [MAX_SCALE]
+    pub scale: u16,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum Theme {
+    /// The colors of the stylesheet, light or dark like the browser
+    #[default]
+    Standard,
+    /// White on black, with bolder text
+    HighContrast,
+}
+
+pub const MIN_SCALE: u16 = 50;
+pub const MAX_SCALE: u16 = 300;
+
+impl Default for DisplayPreferences {
+    fn default() -> Self {
+        Self { theme: Theme::Standard, scale: 100 }
     }
 }
 
-#[derive(Debug, Error, Template)]
-#[template(path = "error.html")]
-enum Error {
-    IO(#[from] std::io::Error),
-    Network(#[from] axum::Error),
-    MissedPings(Vec<Duration>),
-    Parsing(#[from] serde_json::Error),
-    NumberParsing(#[from] ParseIntError),
-    Rendering(#[from] askama::Error),
-    Game(libaitfoaq::Error),
+impl DisplayPreferences {
+    /// The `theme` and `scale` parameters of a query string. Unknown themes
+    /// are ignored and scales clamped, so a typo still shows the game.
+    pub fn from_query(query: Option<&str>) -> Self {
+        let mut display = Self::default();
+        for (key, value) in query.into_iter().flat_map(|q| q.split('&')).filter_map(|pair| pair.split_once('=')) {
+            match key {
+                "theme" if value == "high-contrast" => display.theme = Theme::HighContrast,
+                "theme" => display.theme = Theme::Standard,
+                "scale" => if let Ok(scale) = value.parse::<u16>() {
+                    display.scale = scale.clamp(MIN_SCALE, MAX_SCALE);
+                },
+                _ => {},
+            }
+        }
+        display
+    }
+
+    /// The parameters [DisplayPreferences::from_query] reads, only the ones
+    /// that differ from the defaults
+    pub fn query(&self) -> Vec<String> {
+        let default = Self::default();
+        let theme = (self.theme != default.theme).then(|| format!("theme={}", self.class()));
+        let scale = (self.scale != default.scale).then(|| format!("scale={}", self.scale));
+        theme.into_iter().chain(scale).collect()
+    }
+
+    /// Of the fragments in the HTML, see `style.css`
+    fn class(&self) -> &'static str {
+        match self.theme {
+            Theme::Standard => "",
+            Theme::HighContrast => "high-contrast",
+        }
+    }
+
+    /// The `--scale` the stylesheet multiplies text sizes with
+    fn factor(&self) -> f32 {
+        f32::from(self.scale) / 100.0
+    }
 }
-impl From<libaitfoaq::Error> for Error {
-    fn from(other: libaitfoaq::Error) -> Self { Self::Game(other) }
+
+/// A [StateFrame] trimmed to [Scope::Contestants]
+#[derive(serde::Serialize, schemars::JsonSchema)]
+struct ContestantsFrame<'a> {
+    game: ContestantsOnly<'a>,
+    connection: ConnectionState,
+    caused_by: Option<serde_json::Value>,
+}
+
+#[derive(serde::Serialize, schemars::JsonSchema)]
+struct ContestantsOnly<'a> {
+    contestants: &'a [libaitfoaq::state::Contestant],
+    phase: GamePhase,
+    phase_name: &'static str,
+    legal_events: &'a [EventKind],
+}
+
+impl<'a> ContestantsFrame<'a> {
+    fn new(out: &'a Out, connection: &ConnectionState) -> Self {
+        let connection = connection.with(&out.game);
+        let mut phase = out.game.phase.clone();
+        // typed answers are only for the moderator, like in [GameState::redacted]
+        if let (GamePhase::Buzzed { answer, .. }, false) = (&mut phase, connection.is_admin) {
+            *answer = None;
+        }
+        Self {
+            game: ContestantsOnly {
+                contestants: &out.game.contestants,
+                phase_name: phase.name(),
+                phase,
+                legal_events: &out.game.legal_events,
+            },
+            caused_by: out.caused_by.as_ref().and_then(|event| caused_by(event, connection.is_admin)),
+            connection,
+        }
+    }
+}
+
+/// The parameters of an event anyone may see, the `type` names the event
+const PUBLIC_CAUSE_FIELDS: &[&str] = &["type", "contestant", "clue", "to", "points", "index", "name", "category"];
+
+/// The event as it is journaled, but without boards, which are too big to
+/// send with every state. Others than the moderator only see the parameters
+/// in [PUBLIC_CAUSE_FIELDS], so e.g. typed answers and clue edits stay hidden.
+fn caused_by(event: &Event, is_admin: bool) -> Option<serde_json::Value> {
+    let serde_json::Value::Object(mut fields) = serde_json::to_value(event).ok()? else { return None };
+    if matches!(event, Event::LoadBoard(_)) {
+        fields.retain(|key, _| key == "type");
+    }
+    if !is_admin {
+        fields.retain(|key, _| PUBLIC_CAUSE_FIELDS.contains(&key.as_str()));
+    }
+    Some(fields.into())
+}
+
+/// A typed answer and how close it is to the response, only a suggestion for
+/// the moderator
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+struct AnswerMatch {
+    contestant: ContestantHandle,
+    text: String,
+    #[serde(flatten)]
+    result: MatchResult,
+}
+
+impl AnswerMatch {
+    fn of(game: &GameState) -> Option<Self> {
+        let GamePhase::Buzzed { contestant, answer: Some(text), .. } = &game.phase else { return None };
+        let (contestant, text) = (*contestant, text.clone());
+        let clue = game.current_clue()?;
+        let result = libaitfoaq::matching::score(&text, &clue.response, &clue.hint);
+        Some(Self { contestant, text, result })
+    }
+
+    fn verdict(&self) -> &'static str {
+        match self.result.suggestion {
+            libaitfoaq::matching::Suggestion::Accept => "looks right",
+            libaitfoaq::matching::Suggestion::Review => "have a look",
+            libaitfoaq::matching::Suggestion::Reject => "looks wrong",
+        }
+    }
+}
+
+/// What only the moderator needs to follow the game, mostly for debugging.
+/// Assembled from [Out] for every admin frame, so it only copies what's
+/// already there.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+struct AdminStateExtras {
+    /// What a correct answer wins now, after wagers and
+    /// [Options::decay_on_rejection]. The pending wager while waging.
+    clue_value: Option<Points>,
+    /// See [Out::attempts]
+    attempts: Vec<Attempt>,
+    /// Until idle contestants are disconnected, see [Out::disconnect_at]
+    disconnect_in_ms: BTreeMap<ContestantHandle, u64>,
+    /// Until the game pauses without a moderator, see [Out::pause_at]
+    pause_in_ms: Option<u64>,
+    /// See [Out::recent], shown like [StateFrame::caused_by]
+    recent_events: Vec<RecentEvent>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+struct RecentEvent {
+    /// RFC 3339, older journals don't have it
+    at: Option<String>,
+    event: serde_json::Value,
+}
+
+impl AdminStateExtras {
+    fn of(out: &Out) -> Self {
+        let remaining = |at: Instant| at.saturating_duration_since(Instant::now()).as_millis() as u64;
+        Self {
+            clue_value: match out.game.phase {
+                GamePhase::Waging { pending_wager, .. } => pending_wager,
+                _ => out.game.current_value,
+            },
+            attempts: out.attempts.clone(),
+            disconnect_in_ms: out.disconnect_at.iter().map(|(&contestant, &at)| (contestant, remaining(at))).collect(),
+            pause_in_ms: out.pause_at.map(remaining),
+            recent_events: out.recent.iter()
+                .filter_map(|moment| Some(RecentEvent {
+                    at: moment.at.map(|at| humantime::format_rfc3339_millis(at).to_string()),
+                    event: caused_by(&moment.event, true)?,
+                }))
+                .collect(),
+        }
+    }
+}
+
+impl StateFrame {
+    fn new(out: &Out, connection: &ConnectionState, spectators: usize) -> Self {
+        let connection = connection.with(&out.game);
+        Self {
+            game: match connection.is_admin {
+                true => out.game.clone(),
+                false => {
+                    let mut game = out.game.redacted();
+                    if let GamePhase::Clue { clue, .. } = game.phase {
+                        if out.obscure_clues {
+                            // every part, as followups are shown the same way
+                            let mut part = game.board.get_mut(&clue).ok();
+                            while let Some(clue) = part {
+                                clue.clue.clear();
+                                part = clue.followup.as_deref_mut();
+                            }
+                        }
+                    }
+                    game
+                },
+            },
+            pending_names: connection.is_admin.then(|| out.pending_names.clone()),
+            answer_match: connection.is_admin.then(|| AnswerMatch::of(&out.game)).flatten(),
+            // recomputed for every frame, so it's never stale
+            starting_in_ms: match out.scheduled_start {
+                Some(ScheduledStart::At(at)) => Some(at.saturating_duration_since(Instant::now()).as_millis() as u64),
+                _ => None,
+            },
+            lobby_remaining_ms: out.lobby_deadline
+                .map(|deadline| deadline.at.saturating_duration_since(Instant::now()).as_millis() as u64),
+            lobby_reminder_s: out.lobby_deadline
+                .filter(|_| connection.is_admin)
+                .and_then(|deadline| deadline.reminder)
+                .map(|reminder| reminder.as_secs()),
+            start_failed: connection.is_admin && out.scheduled_start == Some(ScheduledStart::Failed),
+            profiles: connection.is_admin.then(|| out.profiles.clone()),
+            idle: out.idle.iter().copied().collect(),
+            paused: out.paused,
+            controllers: connection.is_admin.then(|| out.controllers.clone()),
+            caused_by: out.caused_by.as_ref().and_then(|event| caused_by(event, connection.is_admin)),
+            admin: connection.is_admin.then(|| AdminStateExtras::of(out)),
+            obscure_clues: out.obscure_clues,
+            connection,
+            spectators,
+        }
+    }
+}
+
+/// The `hx-vals` of every settings input, they are all sent together
+const SETTINGS: &str = r##"js:{"type": "settings", "options": {
+    "approve_names": htmx.find("#approve-names").checked,
+    "hide_values": htmx.find("#hide-values").checked,
+    "confirm_skip": htmx.find("#confirm-skip").checked,
+    "unique_names": htmx.find("#unique-names").checked,
+    "confirm_wagers": htmx.find("#confirm-wagers").checked,
+    "exclusive_single_attempt": htmx.find("#exclusive-single-attempt").checked,
+    "auto_reveal_hint_after": htmx.find("#auto-reveal-hint-after").value === "" ? null : parseInt(htmx.find("#auto-reveal-hint-after").value),
+    "decay_on_rejection": htmx.find("#decay-on-rejection").value === "" ? null : parseInt(htmx.find("#decay-on-rejection").value),
+    "points_label": htmx.find("#points-label").value.trim() === "" ? null : htmx.find("#points-label").value.trim(),
+    "points_format": {
+        "plain_number": "plain_number",
+        "thousands": "thousands",
+        "currency": {"currency": {"symbol": htmx.find("#currency-symbol").value, "position": htmx.find("#currency-position").value}}
+    }[htmx.find("#points-format").value]
+}}"##;
+
+/// Exactly what `state.html` needs, worked out from a [StateFrame]. Changes
+/// to the library's types break this conversion and its tests instead of
+/// rendering an error to every client.
+#[derive(Template)]
+#[template(path = "state.html")]
+struct StateView {
+    locale: Locale,
+    display: DisplayPreferences,
+    phase: &'static str,
+    /// The `type` of [StateFrame::caused_by], empty without one
+    cause: String,
+    may: Capabilities,
+    /// How many clues are left, without a board there's none
+    caption: Option<String>,
+    categories: Vec<CategoryHeader>,
+    rows: Vec<Vec<ClueCell>>,
+    /// Shown instead of the board's contents
+    banner: Option<Banner>,
+    clue: Option<ShownClue>,
+    /// Most wins first, only in [GamePhase::Practice]
+    practice: Option<Vec<PracticeEntry>>,
+    contestants: Vec<ContestantCard>,
+    confetti: bool,
+    admin: Option<AdminView>,
+}
+
+struct CategoryHeader {
+    title: String,
+    /// Greys out categories without clues left, and animates the one that
+    /// just lost its last clue
+    class: &'static str,
+}
+
+struct ClueCell {
+    handle: ClueHandle,
+    /// The points, `None` once solved
+    label: Option<String>,
+    /// The clue itself, only for the moderator
+    title: Option<String>,
+}
+
+/// Like `2:05`, rounded up to the second
+fn minutes(ms: u64) -> String {
+    let seconds = ms.div_ceil(1000);
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+struct Banner {
+    id: &'static str,
+    text: String,
+}
+
+/// See [GameState::practice_wins]
+struct PracticeEntry {
+    name: String,
+    wins: u32,
+    /// Buzzed first since the last reset
+    first: bool,
+}
+
+struct ShownClue {
+    /// HTML from the board, the clue or its response
+    text: String,
+    hint: Option<String>,
+    /// What it's still worth, while [Options::decay_on_rejection] lowers it
+    value: Option<String>,
+    smaller: bool,
+    buzzing: bool,
+    /// Left out until it was fully shown, see [StateFrame::obscure_clues]
+    obscured: bool,
+}
+
+struct ContestantCard {
+    /// The name, or the hint until they have one
+    name: String,
+    /// See [Options::format_points]
+    points: String,
+    indicated: bool,
+    connected: bool,
+    /// See [StateFrame::idle]
+    idle: bool,
+    /// See [libaitfoaq::state::Standing], only once the game is over
+    rank: Option<usize>,
+}
+
+/// The part of the HTML that differs between connections with the same
+/// [ViewKey], rendered for each after the shared [StateView]
+#[derive(Template)]
+#[template(path = "connection.html")]
+struct ConnectionView {
+    locale: Locale,
+    may: Capabilities,
+    /// Gets the buzzer and inputs for them
+    controlling: Option<ContestantHandle>,
+    /// The smallest and largest wager and the one waiting to be confirmed,
+    /// only for the waging contestant
+    wager: Option<(Points, Points, Option<Points>)>,
+    /// Imported contestants nobody claimed yet, for spectators in the lobby
+    seats: Vec<(ContestantHandle, String)>,
+    spectators: usize,
+}
+
+impl ConnectionView {
+    fn new(out: &Out, connection: &ConnectionState, spectators: usize) -> Self {
+        let may = Capabilities::of(&out.game.phase, connection.controlling, connection.is_admin);
+        Self {
+            locale: connection.locale,
+            may,
+            controlling: connection.controlling,
+            wager: match out.game.phase {
+                GamePhase::Waging { min_wager, max_wager, pending_wager, .. } if may.can_wage => {
+                    Some((min_wager, max_wager, pending_wager))
+                },
+                _ => None,
+            },
+            seats: match out.game.phase {
+                GamePhase::Connecting if connection.controlling.is_none() && !connection.is_admin => out.game.contestants.iter()
+                    .enumerate()
+                    .filter(|(_, c)| !c.connected)
+                    .map(|(index, c)| (index, c.display_name.clone()))
+                    .collect(),
+                _ => vec![],
+            },
+            spectators,
+        }
+    }
+
+    fn t<'a>(&self, text: &'a str) -> &'a str {
+        self.locale.translate(text)
+    }
+
+    fn tf<T: std::fmt::Display>(&self, text: &str, values: &[T]) -> String {
+        self.locale.format(text, values)
+    }
+}
+
+struct AdminView {
+    controls: Controls,
+    /// The whole phase, for debugging
+    phase: String,
+    replaying: bool,
+    /// See [Event::StartGame]
+    locked: bool,
+    options: Options,
+    /// The variant of [Options::points_format] for the settings, as serde
+    /// names it
+    points_format: &'static str,
+    /// The symbol of [PointsFormat::Currency] and if it goes after the
+    /// number, "€" before by default
+    currency: (String, bool),
+    /// Response and hint of the current clue
+    notes: Option<(String, String)>,
+    answer_match: Option<AnswerMatch>,
+    countdown_running: bool,
+    start_failed: bool,
+    /// See [Input::SetLobbyDeadline]
+    lobby_deadline: bool,
+    /// The last reminder of the lobby deadline, see
+    /// [State::with_lobby_reminders]
+    lobby_reminder: Option<String>,
+    /// See [Input::Resume]
+    paused: bool,
+    /// The clue being played as JSON, which score corrections can be for
+    correcting: Option<String>,
+    players: Vec<PlayerControls>,
+    /// From [StateFrame::admin], for the collapsed debug panel
+    debug: DebugPanel,
+}
+
+/// [AdminStateExtras] as lines of text
+#[derive(Default)]
+struct DebugPanel {
+    clue_value: Option<String>,
+    attempts: Vec<String>,
+    timers: Vec<String>,
+    recent_events: Vec<String>,
+}
+
+impl DebugPanel {
+    fn new(extras: &AdminStateExtras, game: &GameState, locale: Locale) -> Self {
+        let name = |contestant: &ContestantHandle| game.contestants.get(*contestant)
+            .map_or_else(|| contestant.to_string(), |c| c.display_name.clone());
+        Self {
+            clue_value: extras.clue_value.map(|points| game.options.format_points(points)),
+            attempts: extras.attempts.iter()
+                .map(|attempt| format!(
+                    "{}: {} ({})",
+                    name(&attempt.contestant),
+                    attempt.answer.as_deref().unwrap_or("-"),
+                    locale.translate(match attempt.verdict {
+                        Verdict::Accepted => "accepted",
+                        Verdict::Rejected => "rejected",
+                        Verdict::Withdrawn => "withdrawn",
+                    }),
+                ))
+                .collect(),
+            timers: extras.pause_in_ms.map(|ms| locale.format("The game pauses in {}", &[minutes(ms)]))
+                .into_iter()
+                .chain(extras.disconnect_in_ms.iter().map(|(contestant, &ms)| {
+                    locale.format("{} is disconnected in {}", &[name(contestant), minutes(ms)])
+                }))
+                .collect(),
+            recent_events: extras.recent_events.iter()
+                .map(|recent| format!("{} {}", recent.at.as_deref().unwrap_or("-"), recent.event))
+                .collect(),
+        }
+    }
+}
+
+/// The moderator's controls for the phase
+enum Controls {
+    /// `profiles` to choose from, see [Input::ApplyProfile]
+    Preparing { editor: Vec<EditableCategory>, can_shuffle: bool, profiles: Vec<String> },
+    Connecting { can_start: bool },
+    /// Who buzzed first since the last reset
+    Practice { winner: Option<String> },
+    Picking,
+    /// `stalled` if the contestant disconnected, see
+    /// [GameState::wager_stalled]
+    Waging { min_wager: Points, max_wager: Points, pending_wager: Option<Points>, stalled: bool },
+    Clue,
+    Buzzing,
+    /// Only clues open to everyone can be withdrawn, see [Event::WithdrawBuzz]
+    Buzzed { exclusive: bool },
+    Resolution { show_hint: bool },
+    Score,
+}
+
+struct EditableCategory {
+    title: String,
+    /// Each [ClueField] with its value
+    clues: Vec<[(&'static str, String); 4]>,
+}
+
+struct PlayerControls {
+    index: ContestantHandle,
+    name: String,
+    name_hint: String,
+    /// See [Contestant::display_name](libaitfoaq::state::Contestant::display_name)
+    display_name: String,
+    /// See [Options::approve_names]
+    pending_name: Option<String>,
+    /// Can be cleared, see [Input::ClearIdle]
+    idle: bool,
+    /// How many connections control them, see [Out::controllers]
+    controllers: usize,
+    /// The order with this player shown one earlier, as JSON for
+    /// [Input::ReorderContestants]. Only in the lobby and not for the first.
+    move_earlier: Option<String>,
+}
+
+impl From<&StateFrame> for StateView {
+    fn from(frame: &StateFrame) -> Self {
+        let (game, connection) = (&frame.game, &frame.connection);
+        let locale = connection.locale;
+        let progress = &game.progress;
+        let is_admin = connection.is_admin;
+
+        let categories = game.board.categories.iter().enumerate()
+            .map(|(i, category)| CategoryHeader {
+                title: category.title.clone(),
+                class: if game.newly_completed_category == Some(i) {
+                    "completed newly-completed"
+                } else if progress.completed_categories.get(i).copied().unwrap_or(false) {
+                    "completed"
+                } else {
+                    ""
+                },
+            })
+            .collect();
+        let rows = game.board.clue_rows().into_iter()
+            .map(|row| row.into_iter().map(|(handle, clue)| ClueCell {
+                handle,
+                label: (!clue.solved).then(|| match clue.is_value_hidden(&game.options) && !is_admin {
+                    true => "?".to_owned(),
+                    false => game.options.format_points(clue.points),
+                }),
+                title: is_admin.then_some(clue.clue),
+            }).collect())
+            .collect();
+
+        let banner = match game.phase {
+            _ if frame.paused => Some(Banner {
+                id: "paused",
+                text: locale.translate("Waiting for the host to reconnect").to_owned(),
+            }),
+            GamePhase::Preparing if game.board.categories.is_empty() => Some(Banner {
+                id: "loading",
+                text: locale.translate("Loading...").to_owned(),
+            }),
+            GamePhase::Connecting => frame.starting_in_ms.map(|ms| Banner {
+                id: "countdown",
+                text: locale.format("The game starts in {}", &[minutes(ms)]),
+            }).or_else(|| frame.lobby_remaining_ms.map(|ms| Banner {
+                id: "lobby-deadline",
+                text: match ms {
+                    0 => locale.translate("The game starts soon").to_owned(),
+                    _ => locale.format("The lobby closes in {}", &[minutes(ms)]),
+                },
+            })),
+            _ => None,
+        };
+        let current = game.current_clue();
+        let clue = current.and_then(|clue| match game.phase {
+            GamePhase::Clue { .. } if frame.obscure_clues && !is_admin => Some(ShownClue {
+                text: String::new(),
+                hint: None,
+                value: None,
+                smaller: false,
+                buzzing: false,
+                obscured: true,
+            }),
+            GamePhase::Clue { .. } | GamePhase::Buzzing { .. } | GamePhase::Buzzed { .. } => Some(ShownClue {
+                text: clue.clue.clone(),
+                hint: game.hint_revealed.then(|| clue.hint.clone()),
+                value: game.options.decay_on_rejection
+                    .and(game.current_value)
+                    .map(|points| game.options.format_points(points)),
+                smaller: clue.is_long(),
+                buzzing: matches!(game.phase, GamePhase::Buzzing { .. }),
+                obscured: false,
+            }),
+            GamePhase::Resolution { show_hint, .. } => Some(ShownClue {
+                text: clue.response.clone(),
+                hint: show_hint.then(|| clue.hint.clone()),
+                value: None,
+                smaller: false,
+                buzzing: false,
+                obscured: false,
+            }),
+            _ => None,
+        });
+
+        let card = |index: ContestantHandle, rank: Option<usize>| {
+            let c = &game.contestants[index];
+            ContestantCard {
+                name: c.display_name.clone(),
+                points: game.options.format_points(c.points),
+                indicated: c.indicate,
+                connected: c.connected,
+                idle: frame.idle.contains(&index),
+                rank,
+            }
+        };
+        // in podium order once the game is over
+        let order = game.display_order();
+        let practice = match game.phase {
+            GamePhase::Practice { winner } => {
+                let mut entries: Vec<PracticeEntry> = order.iter()
+                    .map(|&index| PracticeEntry {
+                        name: game.contestants[index].display_name.clone(),
+                        wins: game.practice_wins.get(index).copied().unwrap_or_default(),
+                        first: winner == Some(index),
+                    })
+                    .collect();
+                // stable, ties stay in podium order
+                entries.sort_by_key(|entry| std::cmp::Reverse(entry.wins));
+                Some(entries)
+            },
+            _ => None,
+        };
+        let contestants: Vec<ContestantCard> = match game.phase {
+            GamePhase::Score => game.final_standings.iter().map(|s| card(s.contestant, Some(s.rank))).collect(),
+            _ => order.iter().map(|&index| card(index, None)).collect(),
+        };
+
+        let admin = is_admin.then(|| AdminView {
+            controls: match game.phase {
+                GamePhase::Preparing => Controls::Preparing {
+                    editor: game.board.categories.iter()
+                        .map(|category| EditableCategory {
+                            title: category.title.clone(),
+                            clues: category.clues.iter()
+                                .map(|clue| [
+                                    ("points", clue.points.to_string()),
+                                    ("clue", clue.clue.clone()),
+                                    ("response", clue.response.clone()),
+                                    ("hint", clue.hint.clone()),
+                                ])
+                                .collect(),
+                        })
+                        .collect(),
+                    can_shuffle: game.board.categories.len() > 1,
+                    profiles: frame.profiles.clone().unwrap_or_default(),
+                },
+                GamePhase::Connecting => Controls::Connecting { can_start: !game.contestants.is_empty() },
+                GamePhase::Practice { winner } => Controls::Practice {
+                    winner: winner.and_then(|w| game.contestants.get(w)).map(|c| c.display_name.clone()),
+                },
+                GamePhase::Picking { .. } => Controls::Picking,
+                GamePhase::Waging { min_wager, max_wager, pending_wager, .. } => {
+                    Controls::Waging { min_wager, max_wager, pending_wager, stalled: game.wager_stalled }
+                },
+                GamePhase::Clue { .. } => Controls::Clue,
+                GamePhase::Buzzing { .. } => Controls::Buzzing,
+                GamePhase::Buzzed { exclusive, .. } => Controls::Buzzed { exclusive },
+                GamePhase::Resolution { show_hint, .. } => Controls::Resolution { show_hint },
+                GamePhase::Score => Controls::Score,
+            },
+            phase: format!("{:?}", game.phase),
+            replaying: connection.replaying,
+            locked: game.locked,
+            options: game.options.clone(),
+            points_format: match game.options.points_format {
+                PointsFormat::PlainNumber => "plain_number",
+                PointsFormat::Thousands => "thousands",
+                PointsFormat::Currency { .. } => "currency",
+            },
+            currency: match &game.options.points_format {
+                PointsFormat::Currency { symbol, position } => (symbol.clone(), *position == SymbolPosition::After),
+                _ => ("€".to_owned(), false),
+            },
+            notes: current.map(|clue| (clue.response.clone(), clue.hint.clone())),
+            answer_match: frame.answer_match.clone(),
+            countdown_running: frame.starting_in_ms.is_some(),
+            start_failed: frame.start_failed,
+            lobby_deadline: frame.lobby_remaining_ms.is_some(),
+            lobby_reminder: frame.lobby_reminder_s.map(|seconds| match seconds {
+                0 => locale.translate("The lobby should be over by now").to_owned(),
+                _ => locale.format("{} left in the lobby", &[minutes(seconds * 1000)]),
+            }),
+            paused: frame.paused,
+            correcting: game.phase.clue().map(|clue| serde_json::to_string(&clue).unwrap_or_default()),
+            debug: frame.admin.as_ref().map(|extras| DebugPanel::new(extras, game, locale)).unwrap_or_default(),
+            players: order.iter().enumerate()
+                .map(|(position, &index)| (position, index, &game.contestants[index]))
+                .map(|(position, index, c)| PlayerControls {
+                    index,
+                    name: c.name.clone().unwrap_or_default(),
+                    name_hint: c.name_hint.clone(),
+                    display_name: c.display_name.clone(),
+                    pending_name: frame.pending_names.as_ref().and_then(|p| p.get(&index)).cloned(),
+                    idle: frame.idle.contains(&index),
+                    controllers: frame.controllers.as_ref().and_then(|c| c.get(&index)).copied().unwrap_or_default(),
+                    move_earlier: (matches!(game.phase, GamePhase::Connecting) && position > 0).then(|| {
+                        let mut order = order.clone();
+                        order.swap(position - 1, position);
+                        serde_json::to_string(&order).unwrap_or_default()
+                    }),
+                })
+                .collect(),
+        });
+
+        Self {
+            locale,
+            display: connection.display,
+            phase: game.phase.name(),
+            cause: frame.caused_by.as_ref().and_then(|c| c["type"].as_str()).unwrap_or_default().to_owned(),
+            may: connection.may,
+            caption: (progress.clues_total > 0).then(|| {
+                locale.format("{} of {} clues remaining", &[progress.clues_remaining, progress.clues_total])
+            }),
+            categories,
+            rows,
+            banner,
+            clue,
+            practice,
+            confetti: contestants.iter().any(ContestantCard::is_winner),
+            contestants,
+            admin,
+        }
+    }
+}
+
+impl ContestantCard {
+    fn is_winner(&self) -> bool {
+        self.rank == Some(1)
+    }
+}
+
+impl StateView {
+    fn t<'a>(&self, text: &'a str) -> &'a str {
+        self.locale.translate(text)
+    }
+
+    fn tf<T: std::fmt::Display>(&self, text: &str, values: &[T]) -> String {
+        self.locale.format(text, values)
+    }
+}
+
+/// What the [StateView] of a connection depends on, connections with the same
+/// key are sent the same HTML
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct ViewKey {
+    locale: Locale,
+    display: DisplayPreferences,
+    is_admin: bool,
+    can_pick: bool,
+    /// See [StateFrame::caused_by], the first frame has none
+    caused: bool,
+}
+
+impl ViewKey {
+    fn of(out: &Out, connection: &ConnectionState) -> Self {
+        Self {
+            locale: connection.locale,
+            display: connection.display,
+            is_admin: connection.is_admin,
+            can_pick: Capabilities::of(&out.game.phase, connection.controlling, connection.is_admin).can_pick,
+            caused: out.caused_by.is_some(),
+        }
+    }
+}
+
+/// The rendered [StateView]s of one [Out], by [ViewKey]. Rendering is by
+/// far the most expensive part of sending a state, so it happens once for
+/// everyone with the same key instead of once per connection.
+#[derive(Debug, Clone, Default)]
+pub struct Fragments(Arc<FragmentsInner>);
+
+#[derive(Debug, Default)]
+struct FragmentsInner {
+    rendered: Mutex<HashMap<ViewKey, Arc<OnceLock<Arc<str>>>>>,
+    /// How often `render` was called, to check the sharing
+    renders: AtomicUsize,
+}
+
+impl Fragments {
+    /// Connections asking for the same key at once wait for the first one
+    fn get_or_render(&self, key: ViewKey, render: impl FnOnce() -> String) -> Arc<str> {
+        // not locked while rendering, other keys don't have to wait
+        let cell = self.0.rendered.lock().unwrap().entry(key).or_default().clone();
+        cell.get_or_init(|| {
+            self.0.renders.fetch_add(1, Ordering::Relaxed);
+            render().into()
+        }).clone()
+    }
+
+    #[cfg(test)]
+    fn renders(&self) -> usize {
+        self.0.renders.load(Ordering::Relaxed)
+    }
+}
+
+/// The format frames are sent in. The one negotiated during the upgrade is only
+/// the default for state updates, see [Input::Subscribe] and
+/// [InputMessage::format].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Serializer {
+    Html,
+    Json,
+}
+
+impl Serializer {
+    #[tracing::instrument]
+    fn game_state(&self, out: &Out, connection: &ConnectionState, spectators: usize) -> String {
+        if *self == Self::Json && connection.scope == Scope::Contestants {
+            let frame = ContestantsFrame::new(out, connection);
+            return serde_json::to_string(&frame).unwrap_or_else(|e| self.error(e.into(), connection.locale));
+        }
+        match self {
+            Self::Html => {
+                let shared = out.fragments.get_or_render(ViewKey::of(out, connection), || {
+                    let state = StateFrame::new(out, connection, spectators);
+                    StateView::from(&state).render().unwrap_or_else(|e| self.error(e.into(), connection.locale))
+                });
+                let own = ConnectionView::new(out, connection, spectators);
+                let own = own.render().unwrap_or_else(|e| self.error(e.into(), connection.locale));
+                format!("{}{}", shared, own)
+            },
+            Self::Json => {
+                let state = StateFrame::new(out, connection, spectators);
+                serde_json::to_string(&state).unwrap_or_else(|e| self.error(e.into(), state.connection.locale))
+            },
+        }
+    }
+    #[tracing::instrument]
+    fn score_preview(&self, preview: &ScorePreview, locale: Locale) -> String {
+        match self {
+            Self::Html => {
+                ScorePreviewTemplate { preview, locale }.render().unwrap_or_else(|e| self.error(e.into(), locale))
+            },
+            Self::Json => serde_json::to_string(preview).unwrap_or_else(|e| self.error(e.into(), locale)),
+        }
+    }
+    /// `error` is what rejected the input, for the HTML to say why
+    fn ack(&self, ack: Ack, error: Option<&Error>, locale: Locale) -> String {
+        match self {
+            Self::Html => {
+                let message = match (ack.reason, error) {
+                    (None, _) => locale.translate("Got it").to_owned(),
+                    (Some(Reason::Invalid), Some(error)) => error.message(locale),
+                    (Some(reason), _) => reason.message(locale).to_owned(),
+                };
+                AckTemplate { accepted: ack.accepted, message, locale }
+                    .render()
+                    .unwrap_or_else(|e| self.error(e.into(), locale))
+            },
+            Self::Json => serde_json::to_string(&AckFrame { ack }).unwrap_or_else(|e| self.error(e.into(), locale)),
+        }
+    }
+    #[tracing::instrument]
+    fn error(&self, error: Error, locale: Locale) -> String {
+        match self {
+            Self::Html => {
+                ErrorTemplate { error: &error, locale }.render().unwrap_or("unrenderable error".to_string())
+            },
+            Self::Json => {
+                let retryable = error.is_retryable();
+                serde_json::to_string(&ErrorFrame { error: format!("{:?}", error), retryable }).unwrap_or_default()
+            },
+        }
+    }
+}
+
+/// What a contestant's connection is sent after each of their inputs, next
+/// to the state everyone gets
+#[derive(serde::Serialize, schemars::JsonSchema)]
+struct AckFrame {
+    ack: Ack,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+struct Ack {
+    /// The `type` of the input
+    input: String,
+    accepted: bool,
+    /// Why it wasn't accepted
+    reason: Option<Reason>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum Reason {
+    /// Not possible in this phase of the game
+    WrongPhase,
+    /// Someone else's turn, like an exclusive clue or the pick
+    LockedOut,
+    /// Somebody else buzzed first
+    TooLate,
+    /// The server was too busy, the input can be sent again
+    Busy,
+    /// Anything else, the error frame tells what
+    Invalid,
+}
+
+impl Reason {
+    /// `me` is the contestant who sent the input. Wrong phases carry the
+    /// phase the game is in, which tells who got there first.
+    fn of(error: &Error, me: Option<ContestantHandle>) -> Self {
+        let error = match error {
+            Error::Forbidden => return Self::LockedOut,
+            _ if error.is_retryable() => return Self::Busy,
+            Error::State(crate::state::Rejection::Replaying) => return Self::WrongPhase,
+            Error::Game(error) | Error::State(crate::state::Rejection::Game(error)) => error,
+            _ => return Self::Invalid,
+        };
+        let is = match error {
+            libaitfoaq::Error::NotAnswering => return Self::LockedOut,
+            libaitfoaq::Error::WrongPhase { is } => is,
+            _ => return Self::Invalid,
+        };
+        match *is {
+            GamePhase::Clue { exclusive: Some(contestant), .. } if Some(contestant) != me => Self::LockedOut,
+            GamePhase::Buzzed { contestant, exclusive: true, .. } if Some(contestant) != me => Self::LockedOut,
+            GamePhase::Buzzed { contestant, .. } | GamePhase::Resolution { contestant, .. } if Some(contestant) != me => {
+                Self::TooLate
+            },
+            _ => Self::WrongPhase,
+        }
+    }
+
+    fn message(self, locale: Locale) -> &'static str {
+        match self {
+            Self::WrongPhase | Self::Invalid => locale.translate("Not possible right now"),
+            Self::LockedOut => locale.translate("It's not your turn"),
+            Self::TooLate => locale.translate("Too late, someone else is answering"),
+            Self::Busy => locale.translate("The server is busy, try again"),
+        }
+    }
+}
+
+/// What is sent as JSON instead of a state when an input fails
+#[derive(serde::Serialize, schemars::JsonSchema)]
+struct ErrorFrame {
+    /// The [Error] as Rust prints it for debugging, e.g.
+    /// `Game(WrongPhase { .. })`
+    error: String,
+    /// The same input can be sent again, the server was too busy for it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    retryable: bool,
+}
+
+/// What is sent instead of applying [Input::AdjustScores] with `dry_run`
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+struct ScorePreview {
+    /// Every contestant, changed or not
+    preview: Vec<ScoreChange>,
+}
+
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+struct ScoreChange {
+    contestant: ContestantHandle,
+    name: String,
+    before: Points,
+    after: Points,
+}
+
+impl ScorePreview {
+    fn new(contestants: &[Contestant], changes: &[(ContestantHandle, Points)]) -> Result<Self, libaitfoaq::Error> {
+        let scores = Contestant::adjusted_scores(contestants, changes)?;
+        let preview = contestants
+            .iter()
+            .zip(Contestant::display_names(contestants))
+            .zip(scores)
+            .enumerate()
+            .map(|(contestant, ((c, name), after))| ScoreChange { contestant, name, before: c.points, after })
+            .collect();
+        Ok(Self { preview })
+    }
+}
+
+/// JSON schemas of everything sent over the websocket, by file name. Inputs
+/// can also have a `format`, see [InputMessage::format].
+pub fn schemas() -> Vec<(&'static str, schemars::Schema)> {
+    vec![
+        ("input.json", schemars::schema_for!(Input)),
+        ("state.json", schemars::schema_for!(StateFrame)),
+        ("contestants.json", schemars::schema_for!(ContestantsFrame)),
+        ("error.json", schemars::schema_for!(ErrorFrame)),
+        ("ack.json", schemars::schema_for!(AckFrame)),
+        ("score_preview.json", schemars::schema_for!(ScorePreview)),
+        ("event.json", schemars::schema_for!(Event)),
+    ]
+}
+
+/// Writes [schemas] to `directory`, pretty-printed so changes can be
+/// reviewed
+pub fn dump_schemas(directory: &std::path::Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(directory)?;
+    for (name, schema) in schemas() {
+        let json = serde_json::to_string_pretty(&schema).map_err(std::io::Error::other)?;
+        std::fs::write(directory.join(name), json + "\n")?;
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+struct InputMessage {
+    input: Input,
+    /// Overrides the format of the response to this message only
+    format: Option<Serializer>,
+    /// The `type` of the input, as it was sent
+    kind: String,
+}
+
+impl std::str::FromStr for InputMessage {
+    type Err = serde_json::Error;
+    fn from_str(msg: &str) -> Result<Self, Self::Err> {
+        // parsed separately because `Input::Subscribe` uses the same field
+        #[derive(Deserialize)]
+        struct Format {
+            format: Option<Serializer>,
+            #[serde(rename = "type")]
+            kind: String,
+        }
+        let Format { format, kind } = serde_json::from_str(msg)?;
+        Ok(Self { input: serde_json::from_str(msg)?, format, kind })
+    }
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case", tag = "type")]
+enum Input {
+    /// Switch the format of all following state updates on this connection
+    /// Either can be left out to keep it as it is
+    Subscribe {
+        #[serde(default)]
+        format: Option<Serializer>,
+        #[serde(default)]
+        scope: Option<Scope>,
+    },
+    ReplayNext,
+    ReplayPrev,
+    ReplaySeek { index: usize },
+    /// A contestant naming themselves, see [libaitfoaq::state::Options::approve_names]
+    SuggestName { name: String },
+    /// The contestant who buzzed typing their answer, see
+    /// [Event::SubmitAnswer]
+    SubmitAnswerText { text: String },
+    ApproveName { contestant: ContestantHandle },
+    RejectName { contestant: ContestantHandle },
+    /// A countdown everyone sees, [Event::StartGame] is sent when it runs out
+    ScheduleStart { seconds: u64 },
+    CancelScheduledStart,
+    /// Only shows everyone how long the lobby should still be open, nothing
+    /// happens once it passed, see [crate::state::LobbyDeadline]
+    SetLobbyDeadline { seconds: u64 },
+    ExtendLobbyDeadline { seconds: u64 },
+    /// Continues the game after it paused without a moderator, see
+    /// [State::with_host_watch]
+    Resume,
+    /// Options and server settings from a file, see [crate::profile]
+    ApplyProfile { name: String },
+    /// The moderator knows an idle contestant is still there, see
+    /// [Idleness](crate::state::Idleness)
+    ClearIdle { contestant: ContestantHandle },
+    Settings { options: Options },
+    LoadBoard{board: String},
+    /// Without a seed the server picks one
+    ShuffleBoard { seed: Option<u64> },
+    EditClue { clue: ClueHandle, field: ClueField, value: String },
+    EditCategoryTitle { category: usize, title: String },
+    OpenLobby,
+    /// See [Event::StartPractice]
+    StartPractice,
+    ResetPractice,
+    EndPractice,
+    StartGame,
+    ConnectContestant { name_hint: String },
+    /// One contestant per name, see [Event::ImportContestants]
+    ImportContestants { names: Vec<String> },
+    /// Takes a seat of an imported contestant, see [State::claim_seat]
+    ClaimContestant { contestant: ContestantHandle },
+    /// See [Event::ReorderContestants]
+    ReorderContestants { order: Vec<ContestantHandle> },
+    ReconnectContestant { contestant: ContestantHandle },
+    /// A controller whose handset was unplugged
+    DisconnectContestant { contestant: ContestantHandle },
+    Buzz { contestant: ContestantHandle },
+    /// The moderator buzzing for a contestant who can't, e.g. when everyone
+    /// just shouts. Journaled with [Actor::Moderator].
+    BuzzFor { contestant: ContestantHandle },
+    /// By position like `[0, 1]` or by [Clue::id](libaitfoaq::state::Clue::id)
+    Pick { clue: ClueRef },
+    /// Defer the pick, see [Event::PassPick]
+    PassPick { to: Option<ContestantHandle> },
+    SetWage { points: String },
+    /// See [Options::confirm_wagers]
+    ConfirmWage,
+    CancelWage,
+    ClueFullyShown,
+    AcceptAnswer,
+    /// Only from the moderator or the contestant who buzzed, see
+    /// [Event::WithdrawBuzz]
+    WithdrawBuzz,
+    RejectAnswer,
+    RevealHint,
+    /// `confirmed` is only needed to skip a clue, see
+    /// [Options::confirm_skip]
+    FinishClue {
+        #[serde(default)]
+        confirmed: bool,
+    },
+    NameContestant { contestant: ContestantHandle, name: String },
+    /// The `reason` is needed once the game started, see [Event::StartGame].
+    /// The `clue` the points are for is optional.
+    AwardPoints {
+        contestant: ContestantHandle,
+        points: String,
+        #[serde(default)]
+        reason: String,
+        #[serde(default)]
+        clue: Option<ClueHandle>,
+    },
+    RevokePoints {
+        contestant: ContestantHandle,
+        points: String,
+        #[serde(default)]
+        reason: String,
+        #[serde(default)]
+        clue: Option<ClueHandle>,
+    },
+    /// See [Event::AdjustScores]. With `dry_run` nothing changes, a
+    /// [ScorePreview] is sent back instead.
+    AdjustScores {
+        changes: Vec<(ContestantHandle, Points)>,
+        #[serde(default)]
+        reason: String,
+        #[serde(default)]
+        dry_run: bool,
+    },
+    Unlock,
+}
+
+async fn handle_input(input: Input) -> Result<Option<libaitfoaq::events::Event>, Error> {
+    match input {
+        // handled by the connection itself
+        Input::Subscribe { .. } | Input::ReplayNext | Input::ReplayPrev | Input::ReplaySeek { .. } => Ok(None),
+        Input::SuggestName { .. } | Input::ApproveName { .. } | Input::RejectName { .. } => Ok(None),
+        Input::SubmitAnswerText { .. } => Ok(None),
+        Input::ScheduleStart { .. } | Input::CancelScheduledStart | Input::Resume => Ok(None),
+        Input::SetLobbyDeadline { .. } | Input::ExtendLobbyDeadline { .. } => Ok(None),
+        Input::ApplyProfile { .. } | Input::ClearIdle { .. } | Input::ClaimContestant { .. } => Ok(None),
+        Input::BuzzFor { .. } | Input::AdjustScores { .. } => Ok(None),
+        Input::Settings { options } => Ok(Some(Event::Settings(options))),
+        Input::LoadBoard{board: board_path} => {
+            // todo: load from uploaded json or zipfile instead of path
+            let board = tokio::fs::read(board_path).await?;
+            let board: libaitfoaq::state::Board = serde_json::from_slice(&board)?;
+            Ok(Some(Event::LoadBoard(board)))
+        }
+        Input::ShuffleBoard { seed } => {
+            let seed = seed.unwrap_or_else(|| {
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(0, |d| d.as_nanos() as u64)
+            });
+            Ok(Some(Event::ShuffleBoard { seed }))
+        },
+        Input::EditClue { clue, field, value } => Ok(Some(Event::EditClue { clue, field, value })),
+        Input::EditCategoryTitle { category, title } => Ok(Some(Event::EditCategory { category, title })),
+        Input::OpenLobby => Ok(Some(Event::OpenLobby)),
+        Input::StartPractice => Ok(Some(Event::StartPractice)),
+        Input::ResetPractice => Ok(Some(Event::ResetPractice)),
+        Input::EndPractice => Ok(Some(Event::EndPractice)),
+        Input::StartGame => Ok(Some(Event::StartGame)),
+        Input::ConnectContestant { name_hint } => Ok(Some(Event::ConnectContestant { name_hint })),
+        Input::ImportContestants { names } => Ok(Some(Event::ImportContestants { names })),
+        Input::ReorderContestants { order } => Ok(Some(Event::ReorderContestants { order })),
+        Input::ReconnectContestant { contestant } => Ok(Some(Event::ReconnectContestant { contestant })),
+        Input::DisconnectContestant { contestant } => Ok(Some(Event::DisconnectContestant { contestant })),
+        Input::Buzz { contestant } => Ok(Some(Event::Buzz { contestant })),
+        Input::Pick { clue } => Ok(Some(Event::Pick { clue })),
+        Input::PassPick { to } => Ok(Some(Event::PassPick { to })),
+        Input::SetWage { points } => Ok(Some(Event::SetWage { points: points.trim().parse()? })),
+        Input::ConfirmWage => Ok(Some(Event::ConfirmWage)),
+        Input::CancelWage => Ok(Some(Event::CancelWage)),
+        Input::ClueFullyShown => Ok(Some(Event::ClueFullyShown)),
+        Input::AcceptAnswer => Ok(Some(Event::AcceptAnswer)),
+        Input::WithdrawBuzz => Ok(Some(Event::WithdrawBuzz)),
+        Input::RejectAnswer => Ok(Some(Event::RejectAnswer)),
+        Input::RevealHint => Ok(Some(Event::RevealHint)),
+        Input::FinishClue { confirmed } => Ok(Some(Event::FinishClue { confirmed })),
+        Input::NameContestant { contestant, name } => Ok(Some(Event::NameContestant { index: contestant, name })),
+        Input::AwardPoints { contestant, points, reason, clue } => {
+            Ok(Some(Event::AwardPoints{
+                contestant,
+                points: points.parse()?,
+                reason: Some(reason),
+                clue,
+            }))
+        },
+        Input::RevokePoints { contestant, points, reason, clue } => {
+            Ok(Some(Event::RevokePoints{
+                contestant,
+                points: points.parse()?,
+                reason: Some(reason),
+                clue,
+            }))
+        },
+        Input::Unlock => Ok(Some(Event::Unlock)),
+    }
+}
+
+#[derive(Debug, Error)]
+#[allow(dead_code)] // fields are only read through Debug when rendering
+enum Error {
+    IO(#[from] std::io::Error),
+    Network(#[from] axum::Error),
+    MissedPings(Vec<Duration>),
+    /// How many messages were waiting
+    Lagging(usize),
+    Parsing(#[from] serde_json::Error),
+    NumberParsing(#[from] ParseIntError),
+    Rendering(#[from] askama::Error),
+    Game(libaitfoaq::Error),
+    State(#[from] crate::state::Rejection),
+    Forbidden,
+}
+impl From<libaitfoaq::Error> for Error {
+    fn from(other: libaitfoaq::Error) -> Self { Self::Game(other) }
+}
+
+impl Error {
+    /// See [SendError::QueueFull]
+    fn is_retryable(&self) -> bool {
+        matches!(self, Self::State(crate::state::Rejection::Send(SendError::QueueFull)))
+    }
+
+    /// What's shown to people, the [Debug] form is for logs and programs
+    fn message(&self, locale: Locale) -> String {
+        let game = |error: &libaitfoaq::Error| match error {
+            libaitfoaq::Error::WrongPhase { .. } => locale.translate("Not possible right now").to_owned(),
+            libaitfoaq::Error::ContestantNotFound => locale.translate("No such contestant").to_owned(),
+            libaitfoaq::Error::ContestantDisconnected => locale.translate("That contestant is disconnected").to_owned(),
+            libaitfoaq::Error::NotAnswering => locale.translate("Only the contestant who buzzed can answer").to_owned(),
+            libaitfoaq::Error::NoContestants => locale.translate("Nobody is playing").to_owned(),
+            libaitfoaq::Error::ClueNotFound => locale.translate("No such clue").to_owned(),
+            libaitfoaq::Error::CategoryNotFound => locale.translate("No such category").to_owned(),
+            libaitfoaq::Error::EmptyBoard => locale.translate("The board has no clues").to_owned(),
+            libaitfoaq::Error::UnevenBoard => locale.translate("All categories need the same number of clues").to_owned(),
+            libaitfoaq::Error::ChainTooLong => {
+                locale.format("No more than {} parts can follow a clue", &[libaitfoaq::state::MAX_FOLLOWUPS])
+            },
+            libaitfoaq::Error::EmptyClue => locale.translate("A clue can't be empty").to_owned(),
+            libaitfoaq::Error::InvalidPoints => locale.translate("Invalid points").to_owned(),
+            libaitfoaq::Error::InvalidWager { min, max } => {
+                locale.format("The wager has to be between {} and {}", &[min, max])
+            },
+            libaitfoaq::Error::NoPendingWager => locale.translate("There is no wager to confirm").to_owned(),
+            libaitfoaq::Error::GameLocked => locale.translate("The game has started, unlock it first").to_owned(),
+            libaitfoaq::Error::NotLocked => locale.translate("The game isn't locked").to_owned(),
+            libaitfoaq::Error::ReasonRequired => locale.translate("Say why the score changes").to_owned(),
+            libaitfoaq::Error::InvalidAdjustments { entries } => {
+                let entries = entries.iter().map(|entry| (entry + 1).to_string()).collect::<Vec<_>>().join(", ");
+                locale.format("These score changes aren't possible: {}", &[entries])
+            },
+            libaitfoaq::Error::NameTaken => locale.translate("Someone else already goes by that name").to_owned(),
+            libaitfoaq::Error::InvalidOrder => locale.translate("Every contestant has to appear exactly once").to_owned(),
+            libaitfoaq::Error::DuplicateId => locale.translate("Two categories or clues have the same id").to_owned(),
+            libaitfoaq::Error::ConfirmationRequired { .. } => {
+                locale.translate("This skips the clue without scoring it").to_owned()
+            },
+        };
+        match self {
+            Self::Game(error) | Self::State(crate::state::Rejection::Game(error)) => game(error),
+            Self::State(crate::state::Rejection::InvalidProfile(error)) => locale.format("Invalid profile: {}", &[error]),
+            Self::State(rejection) => locale.translate(&rejection.to_string()).to_owned(),
+            Self::Forbidden => locale.translate("You aren't allowed to do that").to_owned(),
+            Self::Parsing(_) | Self::NumberParsing(_) => locale.translate("Could not understand the input").to_owned(),
+            Self::IO(_) => locale.translate("Could not read the file").to_owned(),
+            Self::Network(_) | Self::MissedPings(_) | Self::Lagging(_) | Self::Rendering(_) => {
+                locale.translate("Something went wrong").to_owned()
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message(Locale::default()))
+    }
+}
+
+#[derive(Template)]
+#[template(path = "score_preview.html")]
+struct ScorePreviewTemplate<'a> {
+    preview: &'a ScorePreview,
+    locale: Locale,
+}
+
+impl ScorePreviewTemplate<'_> {
+    fn t<'a>(&self, text: &'a str) -> &'a str {
+        self.locale.translate(text)
+    }
+}
+
+#[derive(Template)]
+#[template(path = "ack.html")]
+struct AckTemplate {
+    accepted: bool,
+    message: String,
+    locale: Locale,
+}
+
+impl AckTemplate {
+    fn t<'a>(&self, text: &'a str) -> &'a str {
+        self.locale.translate(text)
+    }
+}
+
+#[derive(Template)]
+#[template(path = "error.html")]
+struct ErrorTemplate<'a> {
+    error: &'a Error,
+    locale: Locale,
+}
+
+impl ErrorTemplate<'_> {
+    fn t<'a>(&self, text: &'a str) -> &'a str {
+        self.locale.translate(text)
+    }
+
+    fn message(&self) -> String {
+        self.error.message(self.locale)
+    }
+    /// The input to send again confirmed, if the error asks for that
+    fn confirmable(&self) -> Option<&'static str> {
+        let (Error::Game(libaitfoaq::Error::ConfirmationRequired { action })
+            | Error::State(crate::state::Rejection::Game(libaitfoaq::Error::ConfirmationRequired { action }))) = self.error
+        else {
+            return None;
+        };
+        match *action {
+            "FinishClue" => Some("finish_clue"),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::LobbyDeadline;
+    use std::collections::BTreeSet;
+    use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+    use tokio_util::sync::CancellationToken;
+
+    /// An in-memory websocket, the other end is a [Client]
+    struct TestSocket {
+        incoming: UnboundedReceiver<Message>,
+        outgoing: UnboundedSender<Message>,
+        /// How long sending a message takes
+        delay: Duration,
+    }
+
+    struct TestWriter {
+        outgoing: UnboundedSender<Message>,
+        delay: Duration,
+    }
+
+    impl Socket for TestSocket {
+        type Reader = UnboundedReceiver<Message>;
+        type Writer = TestWriter;
+        fn split(self) -> (Self::Reader, Self::Writer) {
+            (self.incoming, TestWriter { outgoing: self.outgoing, delay: self.delay })
+        }
+    }
+
+    impl SocketReader for UnboundedReceiver<Message> {
+        async fn recv(&mut self) -> Option<Result<Message, axum::Error>> {
+            UnboundedReceiver::recv(self).await.map(Ok)
+        }
+    }
+
+    impl SocketWriter for TestWriter {
+        async fn send(&mut self, msg: Message) -> Result<(), axum::Error> {
+            tokio::time::sleep(self.delay).await;
+            self.outgoing.send(msg).map_err(axum::Error::new)
+        }
+    }
+
+    struct Client {
+        to: UnboundedSender<Message>,
+        from: UnboundedReceiver<Message>,
+    }
+
+    impl Client {
+        fn send(&self, msg: &str) {
+            self.to.send(Message::Text(msg.to_owned())).unwrap();
+        }
+        /// The next frame, ignoring pings
+        async fn receive(&mut self) -> serde_json::Value {
+            let frame = self.receive_text().await;
+            serde_json::from_str(&frame).unwrap_or_else(|e| panic!("{}: {}", e, frame))
+        }
+        /// Like [Client::receive], in whatever format the frame is
+        async fn receive_text(&mut self) -> String {
+            loop {
+                match self.from.recv().await.expect("connection closed") {
+                    Message::Text(frame) => return frame,
+                    Message::Ping(_) => continue,
+                    msg => panic!("unexpected message {:?}", msg),
+                }
+            }
+        }
+        /// Skips frames, like states caused by earlier inputs, until one fits
+        async fn receive_where(&mut self, fits: impl Fn(&serde_json::Value) -> bool) -> serde_json::Value {
+            loop {
+                let frame = self.receive().await;
+                if fits(&frame) {
+                    return frame;
+                }
+            }
+        }
+    }
+
+    fn connect(channels: &StateChannelsAndToken, is_admin: bool) -> (Connection<UnboundedReceiver<Message>>, Client) {
+        connect_slowly(channels, is_admin, Duration::ZERO)
+    }
+
+    fn connect_slowly(channels: &StateChannelsAndToken, is_admin: bool, delay: Duration) -> (Connection<UnboundedReceiver<Message>>, Client) {
+        let (to, incoming) = unbounded_channel();
+        let (outgoing, from) = unbounded_channel();
+        let socket = TestSocket { incoming, outgoing, delay };
+        let connection = Connection::new(socket, "test".to_owned(), channels.clone(), is_admin, Serializer::Json, Locale::En);
+        (connection, Client { to, from })
+    }
+
+    #[tokio::test]
+    async fn gives_up_when_the_initial_state_cant_be_sent() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("journal.jsonl");
+        let state = State::with_journal_and_token(&journal, "token".to_owned()).unwrap();
+        let (connection, client) = connect(&state.clonable_channels(), false);
+        drop(client.from);
+        tokio::time::timeout(Duration::from_secs(1), connection.run()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn closing_disconnects_the_contestant() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("journal.jsonl");
+        let mut state = State::with_journal_and_token(&journal, "token".to_owned()).unwrap();
+        let channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                State::send(Event::OpenLobby, &channels.tx).await.unwrap();
+                let (connection, mut client) = connect(&channels, false);
+                let connection = tokio::spawn(connection.run());
+                client.receive().await;
+                client.send(r#"{"type": "connect_contestant", "name_hint": "phone"}"#);
+                let state = client.receive().await;
+                assert_eq!(state["game"]["contestants"][0]["connected"], true);
+                assert_eq!(state["connection"]["controlling"], 0);
+
+                client.send("this isn't json");
+                client.send(r#"{"type": "subscribe", "format": "json"}"#);
+                // still connected, and the garbage wasn't answered
+                assert_eq!(client.receive().await["connection"]["controlling"], 0);
+
+                client.to.send(Message::Close(None)).unwrap();
+                connection.await.unwrap();
+                assert!(!channels.rx.borrow().game.contestants[0].connected);
+                cancellation_token.cancel();
+            },
+        );
+    }
+
+    /// A phone that joined and a handset claiming the same contestant
+    async fn claim_twice(channels: &StateChannelsAndToken) -> [(JoinHandle<()>, Client); 2] {
+        let (connection, mut phone) = connect(channels, false);
+        let phone_connection = tokio::spawn(connection.run());
+        phone.receive().await;
+        phone.send(r#"{"type": "connect_contestant", "name_hint": "phone"}"#);
+        assert_eq!(phone.receive().await["connection"]["controlling"], 0);
+
+        let (connection, mut handset) = connect(channels, false);
+        let handset_connection = tokio::spawn(connection.run());
+        handset.receive().await;
+        handset.send(r#"{"type": "reconnect_contestant", "contestant": 0}"#);
+        [(phone_connection, phone), (handset_connection, handset)]
+    }
+
+    #[tokio::test]
+    async fn second_controllers_are_rejected() {
+        let mut state = State::ephemeral("token".to_owned());
+        state.start_with(libaitfoaq::state::Board::demo()).await.unwrap();
+        let channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                let [(phone_connection, phone), (handset_connection, mut handset)] = claim_twice(&channels).await;
+                assert_eq!(handset.receive().await["error"], "State(AlreadyControlled)");
+                assert_eq!(channels.rx.borrow().controllers[&0], 1);
+
+                // the rejected handset doesn't take the contestant with it
+                handset.to.send(Message::Close(None)).unwrap();
+                handset_connection.await.unwrap();
+                assert!(channels.rx.borrow().game.contestants[0].connected);
+                phone.to.send(Message::Close(None)).unwrap();
+                phone_connection.await.unwrap();
+                assert!(!channels.rx.borrow().game.contestants[0].connected);
+                cancellation_token.cancel();
+            },
+        );
+    }
+
+    #[tokio::test]
+    async fn imported_seats_are_claimed_once() {
+        // even with co-control, seats aren't shared
+        let mut state = State::ephemeral("token".to_owned()).with_co_control(true);
+        state.start_with(libaitfoaq::state::Board::demo()).await.unwrap();
+        let channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                let (connection, mut admin) = connect(&channels, true);
+                let admin_connection = tokio::spawn(connection.run());
+                admin.receive().await;
+                admin.send(r#"{"type": "import_contestants", "names": ["Alex", "Sam", "Kim"]}"#);
+                let state = admin.receive().await;
+                assert_eq!(state["game"]["contestants"][2]["name"], "Kim");
+                assert_eq!(state["game"]["contestants"][2]["connected"], false);
+
+                let spectator = ConnectionState { is_admin: false, ..connection_state() };
+                let html = Serializer::Html.game_state(&channels.rx.borrow(), &spectator, 0);
+                assert!(html.contains(r#"{"type": "claim_contestant", "contestant": 1}"#));
+
+                let claim = |contestant| {
+                    let (connection, mut client) = connect(&channels, false);
+                    let connection = tokio::spawn(connection.run());
+                    async move {
+                        client.receive().await;
+                        client.send(&format!(r#"{{"type": "claim_contestant", "contestant": {}}}"#, contestant));
+                        let frame = client.receive_where(|f| !f["error"].is_null() || !f["connection"]["controlling"].is_null()).await;
+                        (connection, client, frame)
+                    }
+                };
+                let (alex_connection, alex, frame) = claim(0).await;
+                assert_eq!(frame["connection"]["controlling"], 0);
+                let (sam_connection, sam, frame) = claim(1).await;
+                assert_eq!(frame["connection"]["controlling"], 1);
+                let (other_connection, other, frame) = claim(0).await;
+                assert_eq!(frame["error"], "State(AlreadyClaimed)");
+                let connected: Vec<_> = channels.rx.borrow().game.contestants.iter().map(|c| c.connected).collect();
+                assert_eq!(connected, [true, true, false]);
+                for (connection, client) in [(alex_connection, alex), (sam_connection, sam), (other_connection, other), (admin_connection, admin)] {
+                    client.to.send(Message::Close(None)).unwrap();
+                    connection.await.unwrap();
+                }
+                cancellation_token.cancel();
+            },
+        );
+    }
+
+    #[tokio::test]
+    async fn co_controllers_disconnect_with_the_last_one() {
+        let mut state = State::ephemeral("token".to_owned()).with_co_control(true);
+        state.start_with(libaitfoaq::state::Board::demo()).await.unwrap();
+        let channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                let (connection, mut admin) = connect(&channels, true);
+                let admin_connection = tokio::spawn(connection.run());
+                let [(phone_connection, phone), (handset_connection, mut handset)] = claim_twice(&channels).await;
+                assert_eq!(handset.receive().await["connection"]["controlling"], 0);
+                loop {
+                    if admin.receive().await["controllers"]["0"] == 2 { break; }
+                }
+
+                phone.to.send(Message::Close(None)).unwrap();
+                phone_connection.await.unwrap();
+                assert!(channels.rx.borrow().game.contestants[0].connected);
+                loop {
+                    if admin.receive().await["controllers"]["0"] == 1 { break; }
+                }
+                handset.to.send(Message::Close(None)).unwrap();
+                handset_connection.await.unwrap();
+                assert!(!channels.rx.borrow().game.contestants[0].connected);
+                assert!(channels.rx.borrow().controllers.is_empty());
+
+                admin.to.send(Message::Close(None)).unwrap();
+                admin_connection.await.unwrap();
+                cancellation_token.cancel();
+            },
+        );
+    }
+
+    #[tokio::test]
+    async fn controllers_can_leave_out_the_board() {
+        let mut state = State::ephemeral("token".to_owned());
+        state.start_with(libaitfoaq::state::Board::demo()).await.unwrap();
+        let channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                let (connection, mut client) = connect(&channels, false);
+                let connection = tokio::spawn(connection.run());
+                let full = client.receive().await;
+                assert!(full["game"]["board"].is_object());
+
+                client.send(r#"{"type": "subscribe", "scope": "contestants"}"#);
+                let trimmed = client.receive().await;
+                let keys: Vec<&String> = trimmed["game"].as_object().unwrap().keys().collect();
+                assert_eq!(keys, ["contestants", "legal_events", "phase", "phase_name"]);
+                assert_eq!(trimmed["game"]["phase_name"], "connecting");
+                assert!(trimmed.to_string().len() * 4 < full.to_string().len());
+
+                // registering works the same
+                client.send(r#"{"type": "connect_contestant", "name_hint": "pi-1"}"#);
+                let state = client.receive().await;
+                assert_eq!(state["connection"]["controlling"], 0);
+                assert_eq!(state["game"]["contestants"][0]["name_hint"], "pi-1");
+                assert!(state["game"].get("board").is_none());
+
+                client.to.send(Message::Close(None)).unwrap();
+                connection.await.unwrap();
+                cancellation_token.cancel();
+            },
+        );
+    }
+
+    #[test]
+    fn trimmed_frames_stay_redacted() {
+        let mut game = libaitfoaq::Game::new();
+        game.apply(Event::LoadBoard(libaitfoaq::state::Board::demo())).unwrap();
+        game.apply(Event::OpenLobby).unwrap();
+        game.apply(Event::ConnectContestant { name_hint: "pi-1".to_owned() }).unwrap();
+        game.apply(Event::StartGame).unwrap();
+        let clue = (0..3).map(|c| (c, 0)).find(|&c| !game.get_game_state().board.get(&c).unwrap().can_wager).unwrap();
+        game.apply(Event::Pick { clue: clue.into() }).unwrap();
+        game.apply(Event::ClueFullyShown).unwrap();
+        game.apply(Event::Buzz { contestant: 0 }).unwrap();
+        game.apply(Event::SubmitAnswer { contestant: 0, text: "Jupiter".to_owned() }).unwrap();
+        let out = Out::from(game.get_game_state());
+
+        let scoped = |is_admin| ConnectionState { is_admin, controlling: Some(0), scope: Scope::Contestants, ..connection_state() };
+        let frame = |is_admin| -> serde_json::Value {
+            serde_json::from_str(&Serializer::Json.game_state(&out, &scoped(is_admin), 0)).unwrap()
+        };
+        assert_eq!(frame(false)["game"]["phase"]["Buzzed"]["answer"], serde_json::Value::Null);
+        assert_eq!(frame(true)["game"]["phase"]["Buzzed"]["answer"], "Jupiter");
+        assert_eq!(frame(false)["connection"]["can_answer"], true);
+        assert_eq!(frame(false)["connection"]["scope"], "contestants");
+        // browsers render everything regardless
+        let html = Serializer::Html.game_state(&out, &scoped(false), 0);
+        assert!(html.contains(&game.get_game_state().board.categories[0].title));
+    }
+
+    #[test]
+    fn legal_events_depend_on_the_role() {
+        let mut game = libaitfoaq::Game::new();
+        game.apply(Event::LoadBoard(libaitfoaq::state::Board::demo())).unwrap();
+        game.apply(Event::OpenLobby).unwrap();
+        game.apply(Event::ConnectContestant { name_hint: "a".to_owned() }).unwrap();
+        game.apply(Event::ConnectContestant { name_hint: "b".to_owned() }).unwrap();
+        game.apply(Event::StartGame).unwrap();
+        let GamePhase::Picking { contestant: picking } = game.get_game_state().phase else { unreachable!() };
+        let out = Out::from(game.get_game_state());
+
+        let legal = |is_admin, controlling| -> serde_json::Value {
+            let connection = ConnectionState { is_admin, controlling, ..connection_state() };
+            let frame: serde_json::Value = serde_json::from_str(&Serializer::Json.game_state(&out, &connection, 0)).unwrap();
+            frame["connection"]["legal_events"].clone()
+        };
+        assert_eq!(legal(true, None), serde_json::to_value(&out.game.legal_events).unwrap());
+        assert!(legal(true, None).as_array().unwrap().contains(&"AwardPoints".into()));
+        assert_eq!(legal(false, Some(picking)), serde_json::json!(["DisconnectContestant", "Pick", "PassPick"]));
+        assert_eq!(legal(false, Some(1 - picking)), serde_json::json!(["DisconnectContestant"]));
+        assert_eq!(legal(false, None), serde_json::json!(["ReconnectContestant"]));
+    }
+
+    #[tokio::test]
+    async fn only_admins_control_replays() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("journal.jsonl");
+        let mut state = State::with_journal_and_token(&journal, "token".to_owned()).unwrap();
+        let channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                let (connection, mut player) = connect(&channels, false);
+                let player_connection = tokio::spawn(connection.run());
+                let (connection, mut admin) = connect(&channels, true);
+                let admin_connection = tokio::spawn(connection.run());
+                player.receive().await;
+                admin.receive().await;
+
+                player.send(r#"{"type": "replay_next"}"#);
+                assert_eq!(player.receive().await["error"], "Forbidden");
+                // allowed, but there is nothing to replay
+                admin.send(r#"{"type": "replay_next"}"#);
+                assert_eq!(admin.receive().await["error"], "State(NotReplaying)");
+
+                drop(player);
+                drop(admin);
+                player_connection.await.unwrap();
+                admin_connection.await.unwrap();
+                cancellation_token.cancel();
+            },
+        );
+    }
+
+    #[tokio::test]
+    async fn unanswered_pings_disconnect() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("journal.jsonl");
+        let state = State::with_journal_and_token(&journal, "token".to_owned()).unwrap()
+            .with_pinging(Pinging { interval: Duration::from_millis(10), window: Duration::from_secs(10), misses: 2 });
+        let channels = state.clonable_channels();
+        let (connection, mut client) = connect(&channels, false);
+        let connection = tokio::spawn(connection.run());
+        client.receive().await;
+        tokio::time::timeout(Duration::from_secs(1), connection).await.unwrap().unwrap();
+        let pings = std::iter::from_fn(|| client.from.try_recv().ok()).count();
+        assert_eq!(pings, 2);
+    }
+
+    fn connection_state() -> ConnectionState {
+        ConnectionState {
+            is_admin: true,
+            name: "test".to_owned(),
+            controlling: None,
+            replaying: false,
+            locale: Locale::En,
+            scope: Scope::Full,
+            display: DisplayPreferences::default(),
+            may: Capabilities::default(),
+            legal_events: vec![],
+            protocol_version: PROTOCOL_VERSION,
+            server_version: "0.0.0".to_owned(),
+        }
+    }
+
+    #[tokio::test]
+    async fn spectators_are_counted() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("journal.jsonl");
+        let mut state = State::with_journal_and_token(&journal, "token".to_owned()).unwrap();
+        let channels = state.clonable_channels();
+        let published = channels.spectators.subscribe();
+        let cancellation_token = CancellationToken::new();
+
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                State::send(Event::OpenLobby, &channels.tx).await.unwrap();
+                let (connection, mut admin) = connect(&channels, true);
+                let admin_connection = tokio::spawn(connection.run());
+                assert_eq!(admin.receive().await["spectators"], 0);
+
+                let mut watching = vec![];
+                for count in 1..=3 {
+                    let (connection, mut client) = connect(&channels, false);
+                    let connection = tokio::spawn(connection.run());
+                    client.receive().await;
+                    assert_eq!(*published.borrow(), count);
+                    // resent to everyone
+                    assert_eq!(admin.receive().await["spectators"], count);
+                    watching.push((connection, client));
+                }
+
+                // contestants aren't spectators
+                let (_, client) = &watching[0];
+                client.send(r#"{"type": "connect_contestant", "name_hint": "phone"}"#);
+                loop {
+                    let frame = admin.receive().await;
+                    if frame["spectators"] == 2 && frame["game"]["contestants"].as_array().unwrap().len() == 1 { break; }
+                }
+
+                for (connection, client) in watching {
+                    drop(client);
+                    connection.await.unwrap();
+                }
+                assert_eq!(*published.borrow(), 0);
+                drop(admin);
+                admin_connection.await.unwrap();
+                cancellation_token.cancel();
+            },
+        );
+    }
+
+    #[test]
+    fn spectator_counts_are_only_published_with_enough_change() {
+        let spectators = Spectators::new(2);
+        let published = spectators.subscribe();
+        let mut seen = vec![];
+        for join in [true, true, true, false, true, true, true, true, false, false, false, false, false, false] {
+            match join {
+                true => spectators.join(),
+                false => spectators.leave(),
+            }
+            seen.push(*published.borrow());
+        }
+        assert_eq!(seen, [0, 0, 3, 3, 3, 3, 3, 6, 6, 6, 3, 3, 3, 0]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn games_pause_without_a_host_until_resumed() {
+        let mut state = State::ephemeral("token".to_owned())
+            .with_pinging(Pinging { interval: Duration::from_secs(3600), ..Pinging::default() })
+            .with_host_watch(Duration::from_secs(10));
+        state.start_demo().await.unwrap();
+        let channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                let tx = &channels.tx;
+                let paused = || channels.rx.borrow().paused;
+                let host = || {
+                    let (connection, admin) = connect(&channels, true);
+                    (tokio::spawn(connection.run()), admin)
+                };
+                State::send(Event::ConnectContestant { name_hint: "alice".to_owned() }, tx).await.unwrap();
+                // left before the game started on schedule
+                let (admin_connection, admin) = host();
+                drop(admin);
+                admin_connection.await.unwrap();
+                State::schedule_start(Some(Duration::from_secs(5)), tx).await.unwrap();
+                tokio::time::sleep(Duration::from_secs(6)).await;
+                assert!(matches!(channels.rx.borrow().game.phase, GamePhase::Picking { .. }));
+                assert!(!paused());
+                tokio::time::sleep(Duration::from_secs(10)).await;
+                assert!(paused());
+                let (admin_connection, mut admin) = host();
+                admin.send(r#"{"type": "resume"}"#);
+                admin.receive_where(|frame| frame["paused"] == false).await;
+                drop(admin);
+                admin_connection.await.unwrap();
+
+                State::send(Event::Pick { clue: (0, 0).into() }, tx).await.unwrap();
+                State::send(Event::ClueFullyShown, tx).await.unwrap();
+
+                // back within the grace period
+                let (admin_connection, admin) = host();
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                drop(admin);
+                admin_connection.await.unwrap();
+                tokio::time::sleep(Duration::from_secs(9)).await;
+                let (admin_connection, mut admin) = host();
+                admin.receive().await;
+                tokio::time::sleep(Duration::from_secs(30)).await;
+                assert!(!paused());
+
+                drop(admin);
+                admin_connection.await.unwrap();
+                tokio::time::sleep(Duration::from_secs(11)).await;
+                assert!(paused());
+                assert!(matches!(State::send(Event::Buzz { contestant: 0 }, tx).await, Err(crate::state::Rejection::Paused)));
+                // contestants still come and go
+                State::send(Event::DisconnectContestant { contestant: 0 }, tx).await.unwrap();
+                State::send(Event::ReconnectContestant { contestant: 0 }, tx).await.unwrap();
+
+                let (connection, mut player) = connect(&channels, false);
+                let player_connection = tokio::spawn(connection.run());
+                assert_eq!(player.receive().await["paused"], true);
+                player.send(r#"{"type": "resume"}"#);
+                assert_eq!(player.receive().await["error"], "Forbidden");
+                // a host coming back has to resume
+                let (admin_connection, mut admin) = host();
+                assert_eq!(admin.receive().await["paused"], true);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                assert!(paused());
+                admin.send(r#"{"type": "resume"}"#);
+                admin.receive_where(|frame| frame["paused"] == false).await;
+                State::send(Event::Buzz { contestant: 0 }, tx).await.unwrap();
+                admin.send(r#"{"type": "resume"}"#);
+                assert_eq!(admin.receive_where(|frame| frame.get("error").is_some()).await["error"], "State(NotPaused)");
+
+                drop((admin, player));
+                admin_connection.await.unwrap();
+                player_connection.await.unwrap();
+                cancellation_token.cancel();
+            },
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn slow_clients_get_the_latest_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("journal.jsonl");
+        let mut state = State::with_journal_and_token(&journal, "token".to_owned()).unwrap()
+            .with_pinging(Pinging { interval: Duration::from_secs(3600), ..Pinging::default() });
+        let channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                let (connection, mut client) = connect_slowly(&channels, false, Duration::from_secs(1));
+                let connection = tokio::spawn(connection.run());
+                client.receive().await;
+
+                State::send(Event::OpenLobby, &channels.tx).await.unwrap();
+                for i in 0..5 {
+                    State::send(Event::ConnectContestant { name_hint: format!("pi-{}", i) }, &channels.tx).await.unwrap();
+                }
+                // more than fit into the outbox
+                for _ in 0..OUTBOX_SIZE * 2 {
+                    client.send(r#"{"type": "replay_next"}"#);
+                }
+
+                let mut states = 0;
+                let mut errors = 0;
+                loop {
+                    let frame = client.receive().await;
+                    if frame["error"] == "Forbidden" {
+                        errors += 1;
+                    } else {
+                        states += 1;
+                        if frame["game"]["contestants"].as_array().unwrap().len() == 5 { break; }
+                    }
+                }
+                // the one being sent while the others happened, and the last
+                assert!(states <= 2, "{} states", states);
+                while errors < OUTBOX_SIZE * 2 {
+                    assert_eq!(client.receive().await["error"], "Forbidden");
+                    errors += 1;
+                }
+                // slow, but never stuck for long
+                assert!(!connection.is_finished());
+
+                drop(client);
+                connection.await.unwrap();
+                cancellation_token.cancel();
+            },
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stuck_clients_are_given_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("journal.jsonl");
+        let state = State::with_journal_and_token(&journal, "token".to_owned()).unwrap()
+            .with_pinging(Pinging { interval: Duration::from_secs(3600), ..Pinging::default() });
+        let channels = state.clonable_channels();
+        let (connection, client) = connect_slowly(&channels, false, Duration::from_secs(3600));
+        let connection = tokio::spawn(connection.run());
+        for _ in 0..OUTBOX_SIZE * 2 {
+            client.send(r#"{"type": "replay_next"}"#);
+        }
+        let start = Instant::now();
+        tokio::time::timeout(Duration::from_secs(60), connection).await.unwrap().unwrap();
+        assert!(start.elapsed() >= LAGGING_DEADLINE);
+    }
+
+    #[test]
+    fn capabilities_follow_the_phase() {
+        let phases = [
+            GamePhase::Preparing,
+            GamePhase::Connecting,
+            GamePhase::Practice { winner: Some(1) },
+            GamePhase::Picking { contestant: 0 },
+            GamePhase::Waging { clue: (0, 0), contestant: 0, min_wager: 5, max_wager: 500, pending_wager: None },
+            GamePhase::Clue { clue: (0, 0), exclusive: None },
+            GamePhase::Clue { clue: (0, 0), exclusive: Some(0) },
+            GamePhase::Buzzing { clue: (0, 0) },
+            GamePhase::Buzzed { clue: (0, 0), contestant: 0, answer: None, exclusive: false },
+            GamePhase::Resolution { clue: (0, 0), contestant: 0, show_hint: false },
+            GamePhase::Score,
+        ];
+        // (controlling, is_admin): the contestant in the phase, another one,
+        // the moderator, and a spectator
+        let roles = [(Some(0), false), (Some(1), false), (None, true), (None, false)];
+        for phase in &phases {
+            for (controlling, is_admin) in roles {
+                let may = Capabilities::of(phase, controlling, is_admin);
+                let expected = match (phase.name(), controlling, is_admin) {
+                    ("buzzing" | "practice", Some(_), _) => Capabilities { can_buzz: true, ..Capabilities::default() },
+                    ("picking", Some(0), _) | ("picking", _, true) => Capabilities { can_pick: true, ..Capabilities::default() },
+                    ("waging", Some(0), _) | ("waging", _, true) => Capabilities { can_wage: true, ..Capabilities::default() },
+                    ("buzzed", Some(0), _) => Capabilities { can_answer: true, ..Capabilities::default() },
+                    _ => Capabilities::default(),
+                };
+                assert_eq!(may, expected, "{:?} as {:?}/{}", phase, controlling, is_admin);
+            }
+        }
+
+        let game = Out::from(libaitfoaq::Game::new().get_game_state());
+        let frame: serde_json::Value = serde_json::from_str(&Serializer::Json.game_state(&game, &connection_state(), 0)).unwrap();
+        assert_eq!(frame["connection"]["can_pick"], false);
+    }
+
+    #[tokio::test]
+    async fn subscribing_switches_the_format_of_the_connection() {
+        let mut state = State::ephemeral("token".to_owned());
+        let channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+        let is_json = |frame: &str| serde_json::from_str::<serde_json::Value>(frame).is_ok();
+
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                let (connection, mut client) = connect(&channels, true);
+                let connection = tokio::spawn(connection.run());
+                client.receive().await;
+
+                client.send(r#"{"type": "subscribe", "format": "html"}"#);
+                let frame = client.receive_text().await;
+                assert!(!is_json(&frame), "{}", frame);
+                // updates caused by later inputs, too
+                client.send(r#"{"type": "open_lobby"}"#);
+                let frame = client.receive_text().await;
+                assert!(!is_json(&frame), "{}", frame);
+
+                client.send(r#"{"type": "subscribe", "format": "json"}"#);
+                assert_eq!(client.receive().await["game"]["phase"], "Connecting");
+
+                drop(client);
+                connection.await.unwrap();
+                cancellation_token.cancel();
+            },
+        );
+    }
+
+    #[test]
+    fn subscribe_switches_formats() {
+        let game = Out::from(libaitfoaq::Game::new().get_game_state());
+        for (msg, is_html) in [
+            (r#"{"type": "subscribe", "format": "html"}"#, true),
+            (r#"{"type": "subscribe", "format": "json"}"#, false),
+            (r#"{"type": "subscribe", "format": "html"}"#, true),
+        ] {
+            let Input::Subscribe { format: Some(format), scope: None } = msg.parse::<InputMessage>().unwrap().input else {
+                panic!("not parsed as a subscription: {}", msg);
+            };
+            let frame = format.game_state(&game, &connection_state(), 0);
+            assert_eq!(serde_json::from_str::<serde_json::Value>(&frame).is_err(), is_html, "{}", frame);
+        }
+    }
+
+    #[test]
+    fn hidden_values_are_only_sent_to_admins() {
+        let mut game = libaitfoaq::Game::new();
+        let options = Options { hide_values: true, ..Options::default() };
+        game.apply(Event::Settings(options)).unwrap();
+        let board = std::fs::read("../example-boards/webengdus-2024-04-24-round1.json").unwrap();
+        game.apply(Event::LoadBoard(serde_json::from_slice(&board).unwrap())).unwrap();
+        let out = Out::from(game.get_game_state());
+        let points = |frame: String| -> Vec<serde_json::Value> {
+            let state: serde_json::Value = serde_json::from_str(&frame).unwrap();
+            state["game"]["board"]["categories"].as_array().unwrap().iter()
+                .flat_map(|c| c["clues"].as_array().unwrap().clone())
+                .map(|c| c["points"].clone())
+                .collect()
+        };
+
+        let player = ConnectionState { is_admin: false, ..connection_state() };
+        assert!(points(Serializer::Json.game_state(&out, &player, 0)).iter().all(|p| p == 0));
+        assert!(points(Serializer::Json.game_state(&out, &connection_state(), 0)).iter().all(|p| p != 0));
+
+        // single clues until they are picked
+        let mut board = libaitfoaq::state::Board::demo();
+        board.categories[0].clues[0].value_hidden = true;
+        let mut game = libaitfoaq::Game::new();
+        for event in [
+            Event::LoadBoard(board),
+            Event::OpenLobby,
+            Event::ConnectContestant { name_hint: "alice".to_owned() },
+            Event::StartGame,
+        ] {
+            game.apply(event).unwrap();
+        }
+        let first = |game: &libaitfoaq::Game| {
+            let out = Out::from(game.get_game_state());
+            points(Serializer::Json.game_state(&out, &player, 0))[0].clone()
+        };
+        assert_eq!(first(&game), 0);
+        let view = StateView::from(&StateFrame::new(&Out::from(game.get_game_state()), &player, 0));
+        assert_eq!(view.rows[0][0].label.as_deref(), Some("?"));
+        game.apply(Event::Pick { clue: (0, 0).into() }).unwrap();
+        game.apply(Event::FinishClue { confirmed: false }).unwrap();
+        assert_eq!(first(&game), 100);
+    }
+
+    #[tokio::test]
+    async fn pinger_tolerates_a_few_misses() {
+        const SECOND: Duration = Duration::from_secs(1);
+        let start = Instant::now();
+        let mut pinger = Pinger::new("test".to_owned(), Pinging { misses: 2, ..Pinging::default() });
+        let first = pinger.next(start).unwrap();
+        pinger.next(start + SECOND).unwrap();
+        let Err(Error::MissedPings(ages)) = pinger.next(start + 2 * SECOND) else {
+            panic!("two missed pings weren't noticed");
+        };
+        assert_eq!(ages, [SECOND, 2 * SECOND]);
+
+        assert_eq!(pinger.received(first.clone(), start + 2 * SECOND), Some(2 * SECOND));
+        assert_eq!(pinger.received(first, start + 2 * SECOND), None);
+        assert!(pinger.next(start + 2 * SECOND).is_ok());
+        // both outstanding pings are older than the window by now
+        assert!(pinger.next(start + 7 * SECOND).is_ok());
+    }
+
+    #[test]
+    fn format_overrides_a_single_message() {
+        let msg: InputMessage = r#"{"type": "open_lobby", "format": "html"}"#.parse().unwrap();
+        assert!(matches!(msg.input, Input::OpenLobby));
+        assert_eq!(msg.format, Some(Serializer::Html));
+
+        let msg: InputMessage = r#"{"type": "disconnect_contestant", "contestant": 1}"#.parse().unwrap();
+        assert!(matches!(msg.input, Input::DisconnectContestant { contestant: 1 }));
+
+        let msg: InputMessage = r#"{"type": "start_game", "HEADERS": {}}"#.parse().unwrap();
+        assert!(matches!(msg.input, Input::StartGame));
+        assert_eq!(msg.format, None);
+
+        let error = Serializer::Json.error(libaitfoaq::Error::NoContestants.into(), Locale::De);
+        assert!(serde_json::from_str::<serde_json::Value>(&error).unwrap()["error"].is_string());
+    }
+
+    #[test]
+    fn answer_matches_are_only_sent_to_admins() {
+        let mut game = libaitfoaq::Game::new();
+        let board = std::fs::read("../example-boards/webengdus-2024-04-24-round1.json").unwrap();
+        game.apply(Event::LoadBoard(serde_json::from_slice(&board).unwrap())).unwrap();
+        game.apply(Event::OpenLobby).unwrap();
+        game.apply(Event::ConnectContestant { name_hint: "alice".to_owned() }).unwrap();
+        game.apply(Event::StartGame).unwrap();
+        let clue = (0..6).map(|c| (c, 0)).find(|&c| !game.get_game_state().board.get(&c).unwrap().can_wager).unwrap();
+        game.apply(Event::Pick { clue: clue.into() }).unwrap();
+        game.apply(Event::ClueFullyShown).unwrap();
+        game.apply(Event::Buzz { contestant: 0 }).unwrap();
+        let response = game.get_game_state().board.get(&clue).unwrap().response.clone();
+        game.apply(Event::SubmitAnswer { contestant: 0, text: response }).unwrap();
+        let out = Out::from(game.get_game_state());
+
+        let frame: serde_json::Value = serde_json::from_str(&Serializer::Json.game_state(&out, &connection_state(), 0)).unwrap();
+        assert_eq!(frame["answer_match"]["suggestion"], "accept");
+        assert_eq!(frame["answer_match"]["similarity"], 1.0);
+        let player = ConnectionState { is_admin: false, controlling: Some(0), ..connection_state() };
+        let frame: serde_json::Value = serde_json::from_str(&Serializer::Json.game_state(&out, &player, 0)).unwrap();
+        assert!(frame.get("answer_match").is_none());
+        assert_eq!(frame["connection"]["can_answer"], true);
+        assert!(Serializer::Html.game_state(&out, &connection_state(), 0).contains("looks right"));
+    }
+
+    #[test]
+    fn decaying_values_are_shown_with_the_clue() {
+        let mut game = libaitfoaq::Game::new();
+        game.apply(Event::Settings(Options { decay_on_rejection: Some(50), ..Options::default() })).unwrap();
+        game.apply(Event::LoadBoard(libaitfoaq::state::Board::demo())).unwrap();
+        game.apply(Event::OpenLobby).unwrap();
+        game.apply(Event::ConnectContestant { name_hint: "alice".to_owned() }).unwrap();
+        game.apply(Event::StartGame).unwrap();
+        let clue = (0, 1);
+        let points = game.get_game_state().board.get(&clue).unwrap().points;
+        game.apply(Event::Pick { clue: clue.into() }).unwrap();
+        game.apply(Event::ClueFullyShown).unwrap();
+        let value = |game: &libaitfoaq::Game| {
+            let html = Serializer::Html.game_state(&Out::from(game.get_game_state()), &connection_state(), 0);
+            html.split_once(r#"<div id="clue-value">"#).and_then(|(_, rest)| rest.split_once('<')).map(|(value, _)| value.to_owned())
+        };
+        assert_eq!(value(&game), Some(points.to_string()));
+        game.apply(Event::Buzz { contestant: 0 }).unwrap();
+        game.apply(Event::RejectAnswer).unwrap();
+        assert_eq!(value(&game), Some((points / 2).to_string()));
+
+        // not once it's resolved
+        game.apply(Event::FinishClue { confirmed: true }).unwrap();
+        assert_eq!(value(&game), None);
+        let out = Out::from(libaitfoaq::Game::new().get_game_state());
+        assert!(!Serializer::Html.game_state(&out, &connection_state(), 0).contains("clue-value"));
+    }
+
+    #[test]
+    fn obscured_clues_are_public_once_fully_shown() {
+        let mut game = libaitfoaq::Game::new();
+        game.apply(Event::LoadBoard(libaitfoaq::state::Board::demo())).unwrap();
+        game.apply(Event::OpenLobby).unwrap();
+        game.apply(Event::ConnectContestant { name_hint: "alice".to_owned() }).unwrap();
+        game.apply(Event::StartGame).unwrap();
+        let clue = (0, 1);
+        let text = game.get_game_state().board.get(&clue).unwrap().clue.clone();
+        game.apply(Event::Pick { clue: clue.into() }).unwrap();
+        let public = ConnectionState { is_admin: false, ..connection_state() };
+        let html = |game: &libaitfoaq::Game, connection: &ConnectionState, obscure_clues: bool| {
+            let out = Out { obscure_clues, ..Out::from(game.get_game_state()) };
+            Serializer::Html.game_state(&out, connection, 0)
+        };
+
+        assert!(!html(&game, &public, true).contains(&text));
+        assert!(html(&game, &public, true).contains("obscured"));
+        assert!(html(&game, &connection_state(), true).contains(&text));
+        assert!(html(&game, &public, false).contains(&text));
+        game.apply(Event::ClueFullyShown).unwrap();
+        assert!(html(&game, &public, true).contains(&text));
+        assert!(!html(&game, &public, true).contains("obscured"));
+    }
+
+    #[test]
+    fn obscured_clues_are_left_out_of_json_frames() {
+        let mut game = libaitfoaq::Game::new();
+        game.apply(Event::LoadBoard(libaitfoaq::state::Board::demo())).unwrap();
+        game.apply(Event::OpenLobby).unwrap();
+        game.apply(Event::ConnectContestant { name_hint: "alice".to_owned() }).unwrap();
+        game.apply(Event::StartGame).unwrap();
+        let clue = (0, 1);
+        let text = game.get_game_state().board.get(&clue).unwrap().clue.clone();
+        game.apply(Event::Pick { clue: clue.into() }).unwrap();
+        let public = ConnectionState { is_admin: false, ..connection_state() };
+        let json = |game: &libaitfoaq::Game, connection: &ConnectionState, obscure_clues: bool| {
+            let out = Out { obscure_clues, ..Out::from(game.get_game_state()) };
+            Serializer::Json.game_state(&out, connection, 0)
+        };
+
+        assert!(!json(&game, &public, true).contains(&text));
+        assert!(json(&game, &connection_state(), true).contains(&text));
+        assert!(json(&game, &public, false).contains(&text));
+        game.apply(Event::ClueFullyShown).unwrap();
+        assert!(json(&game, &public, true).contains(&text));
+    }
+
+    #[test]
+    fn display_preferences_style_the_html() {
+        let display = DisplayPreferences::from_query(Some("lang=de&theme=high-contrast&scale=125"));
+        assert_eq!(display, DisplayPreferences { theme: Theme::HighContrast, scale: 125 });
+        assert_eq!(display.query(), ["theme=high-contrast", "scale=125"]);
+        assert_eq!(DisplayPreferences::from_query(Some("theme=neon&scale=5000")), DisplayPreferences { theme: Theme::Standard, scale: MAX_SCALE });
+        assert_eq!(DisplayPreferences::from_query(Some("scale=big")), DisplayPreferences::default());
+        assert!(DisplayPreferences::default().query().is_empty());
+
+        let out = Out::from(libaitfoaq::Game::new().get_game_state());
+        let styled = ConnectionState { is_admin: false, display, ..connection_state() };
+        let html = Serializer::Html.game_state(&out, &styled, 0);
+        assert!(html.contains(r#"id="board""#));
+        assert_eq!(html.matches("high-contrast").count(), 2, "{}", html);
+        assert_eq!(html.matches("--scale: 1.25").count(), 2, "{}", html);
+        // rendered separately from connections with the default look
+        let plain = ConnectionState { is_admin: false, ..connection_state() };
+        let html = Serializer::Html.game_state(&out, &plain, 0);
+        assert!(!html.contains("high-contrast"));
+        assert!(html.contains("--scale: 1\""));
+    }
+
+    #[test]
+    fn practice_leaderboards_rank_by_wins() {
+        let mut game = libaitfoaq::Game::new();
+        game.apply(Event::OpenLobby).unwrap();
+        for name_hint in ["alice", "bob", "carol"] {
+            game.apply(Event::ConnectContestant { name_hint: name_hint.to_owned() }).unwrap();
+        }
+        let html = |game: &libaitfoaq::Game, connection: &ConnectionState| {
+            Serializer::Html.game_state(&Out::from(game.get_game_state()), connection, 0)
+        };
+        assert!(!html(&game, &connection_state()).contains(r#"id="practice""#));
+        assert!(html(&game, &connection_state()).contains("start_practice"));
+
+        game.apply(Event::StartPractice).unwrap();
+        for winner in [2, 1, 2] {
+            game.apply(Event::ResetPractice).unwrap();
+            game.apply(Event::Buzz { contestant: winner }).unwrap();
+            game.apply(Event::Buzz { contestant: 0 }).unwrap();
+        }
+        let spectator = ConnectionState { is_admin: false, ..connection_state() };
+        let shown = html(&game, &spectator);
+        let leaderboard = shown.split_once(r#"<div id="practice">"#).unwrap().1.split_once("</ol>").unwrap().0;
+        let names: Vec<&str> = leaderboard.split(r#"<span class="name">"#).skip(1).map(|rest| rest.split_once('<').unwrap().0).collect();
+        assert_eq!(names, ["carol", "bob", "alice"]);
+        assert!(leaderboard.contains(r#"<li class="first"><span class="name">carol</span> <span class="wins">2</span>"#));
+        assert!(!shown.contains("reset_practice"));
+
+        let admin = html(&game, &connection_state());
+        assert!(admin.contains("carol was first") && admin.contains("reset_practice"));
+        game.apply(Event::ResetPractice).unwrap();
+        assert!(html(&game, &connection_state()).contains("Nobody buzzed yet"));
+        let controller = ConnectionState { is_admin: false, controlling: Some(0), ..connection_state() };
+        let buzzer = |game: &libaitfoaq::Game| {
+            let shown = html(game, &controller);
+            shown.split_once(r#"<div id="controls">"#).unwrap().1.split_once("Buzz!").unwrap().0.to_owned()
+        };
+        assert!(!buzzer(&game).contains("disabled"));
+
+        game.apply(Event::EndPractice).unwrap();
+        assert!(!html(&game, &spectator).contains(r#"id="practice""#));
+        assert!(buzzer(&game).contains("disabled"));
+    }
+
+    #[tokio::test]
+    async fn pending_wagers_are_shown_until_confirmed() {
+        let mut game = libaitfoaq::Game::new();
+        game.apply(Event::Settings(Options { confirm_wagers: true, ..Options::default() })).unwrap();
+        game.apply(Event::LoadBoard(libaitfoaq::state::Board::demo())).unwrap();
+        game.apply(Event::OpenLobby).unwrap();
+        game.apply(Event::ConnectContestant { name_hint: "alice".to_owned() }).unwrap();
+        game.apply(Event::StartGame).unwrap();
+        let board = game.get_game_state().board;
+        let clue = board.categories.iter().enumerate()
+            .find_map(|(c, category)| Some((c, category.clues.iter().position(|clue| clue.can_wager)?)))
+            .unwrap();
+        game.apply(Event::Pick { clue: clue.into() }).unwrap();
+        game.apply(Event::SetWage { points: 300 }).unwrap();
+        let out = Out::from(game.get_game_state());
+
+        let admin = Serializer::Html.game_state(&out, &connection_state(), 0);
+        assert!(admin.contains("pending-wager") && admin.contains("300") && admin.contains("confirm_wage"));
+        let player = ConnectionState { is_admin: false, controlling: Some(0), ..connection_state() };
+        let frame: serde_json::Value = serde_json::from_str(&Serializer::Json.game_state(&out, &player, 0)).unwrap();
+        assert_eq!(frame["game"]["phase"]["Waging"]["pending_wager"], 300);
+        let html = Serializer::Html.game_state(&out, &player, 0);
+        assert!(html.contains(r#"{"type": "set_wage"}"#) && html.contains("pending-wager"));
+        let watching = ConnectionState { is_admin: false, ..connection_state() };
+        assert!(!Serializer::Html.game_state(&out, &watching, 0).contains("set_wage"));
+
+        let msg: InputMessage = r#"{"type": "confirm_wage"}"#.parse().unwrap();
+        game.apply(handle_input(msg.input).await.unwrap().unwrap()).unwrap();
+        let admin = Serializer::Html.game_state(&Out::from(game.get_game_state()), &connection_state(), 0);
+        assert!(!admin.contains("pending-wager"));
+    }
+
+    #[test]
+    fn points_are_shown_formatted() {
+        let mut game = libaitfoaq::Game::new();
+        let euros = PointsFormat::Currency { symbol: "€".to_owned(), position: SymbolPosition::Before };
+        game.apply(Event::Settings(Options { points_format: euros, ..Options::default() })).unwrap();
+        game.apply(Event::LoadBoard(libaitfoaq::state::Board::demo())).unwrap();
+        game.apply(Event::OpenLobby).unwrap();
+        game.apply(Event::ConnectContestant { name_hint: "alice".to_owned() }).unwrap();
+        game.apply(Event::RevokePoints { contestant: 0, points: 1500, reason: None, clue: None }).unwrap();
+        let out = Out::from(game.get_game_state());
+        let player = ConnectionState { is_admin: false, controlling: Some(0), ..connection_state() };
+
+        let view = StateView::from(&StateFrame::new(&out, &player, 0));
+        assert_eq!(view.rows[0][0].label.as_deref(), Some("€100"));
+        assert_eq!(view.contestants[0].points, "-€1,500");
+        let html = Serializer::Html.game_state(&out, &player, 0);
+        assert!(html.contains("-€1,500"), "{}", html);
+        let frame: serde_json::Value = serde_json::from_str(&Serializer::Json.game_state(&out, &player, 0)).unwrap();
+        assert_eq!(frame["game"]["contestants"][0]["points"], -1500);
+        assert_eq!(frame["game"]["options"]["points_format"], serde_json::json!({"currency": {"symbol": "€", "position": "before"}}));
+
+        let admin = StateView::from(&StateFrame::new(&out, &connection_state(), 0)).admin.unwrap();
+        assert_eq!((admin.points_format, admin.currency), ("currency", ("€".to_owned(), false)));
+    }
+
+    #[test]
+    fn contestants_are_shown_in_display_order() {
+        let mut game = libaitfoaq::Game::new();
+        game.apply(Event::LoadBoard(libaitfoaq::state::Board::demo())).unwrap();
+        game.apply(Event::OpenLobby).unwrap();
+        for name_hint in ["alice", "bob", "carol"] {
+            game.apply(Event::ConnectContestant { name_hint: name_hint.to_owned() }).unwrap();
+        }
+        let msg: InputMessage = r#"{"type": "reorder_contestants", "order": [2, 0, 1]}"#.parse().unwrap();
+        assert!(matches!(&msg.input, Input::ReorderContestants { order } if *order == [2, 0, 1]));
+        game.apply(Event::ReorderContestants { order: vec![2, 0, 1] }).unwrap();
+        let out = Out::from(game.get_game_state());
+
+        let player = ConnectionState { is_admin: false, ..connection_state() };
+        let html = Serializer::Html.game_state(&out, &player, 0);
+        let at = |name: &str| html.find(name).unwrap();
+        assert!(at("carol") < at("alice") && at("alice") < at("bob"));
+        let frame: serde_json::Value = serde_json::from_str(&Serializer::Json.game_state(&out, &player, 0)).unwrap();
+        assert_eq!(frame["game"]["contestants"][2]["display_position"], 0);
+        // moving alice before carol
+        let admin = Serializer::Html.game_state(&out, &connection_state(), 0);
+        assert!(admin.contains(r#""order": [0,2,1]"#));
+        assert_eq!(admin.matches("reorder_contestants").count(), 2);
+    }
+
+    #[test]
+    fn html_is_localized() {
+        let mut game = libaitfoaq::Game::new();
+        let board = std::fs::read("../example-boards/webengdus-2024-04-24-round1.json").unwrap();
+        game.apply(Event::LoadBoard(serde_json::from_slice(&board).unwrap())).unwrap();
+        let out = Out::from(game.get_game_state());
+        let german = ConnectionState { locale: Locale::De, ..connection_state() };
+
+        let state = Serializer::Html.game_state(&out, &connection_state(), 3);
+        assert!(state.contains("Open Lobby") && state.contains("3 watching"));
+        let state = Serializer::Html.game_state(&out, &german, 3);
+        assert!(state.contains("Lobby öffnen") && state.contains("3 schauen zu"));
+        assert!(!state.contains("Open Lobby"));
+
+        let wager = || libaitfoaq::Error::InvalidWager { min: 5, max: 500 }.into();
+        let error = Serializer::Html.error(wager(), Locale::En);
+        assert!(error.contains("The wager has to be between 5 and 500"));
+        let error = Serializer::Html.error(wager(), Locale::De);
+        assert!(error.contains("Der Einsatz muss zwischen 5 und 500 liegen") && error.contains("schließen"));
+        // programs keep getting the same errors
+        assert_eq!(Serializer::Json.error(wager(), Locale::De), Serializer::Json.error(wager(), Locale::En));
+
+        let skip = || libaitfoaq::Error::ConfirmationRequired { action: "FinishClue" }.into();
+        assert!(Serializer::Html.error(skip(), Locale::En).contains(r#"{"type": "finish_clue", "confirmed": true}"#));
+        assert!(!Serializer::Html.error(wager(), Locale::En).contains("confirmed"));
+        let msg: InputMessage = r#"{"type": "finish_clue"}"#.parse().unwrap();
+        assert!(matches!(msg.input, Input::FinishClue { confirmed: false }));
+
+        let rejection = crate::state::Rejection::InvalidName.into();
+        assert!(Serializer::Html.error(rejection, Locale::De).contains("Dieser Name ist nicht erlaubt"));
+    }
+
+    #[test]
+    fn busy_servers_ask_for_a_retry() {
+        let busy = || Error::State(SendError::QueueFull.into());
+        let frame: serde_json::Value = serde_json::from_str(&Serializer::Json.error(busy(), Locale::En)).unwrap();
+        assert_eq!(frame["retryable"], true);
+        assert_eq!(frame["error"], "State(Send(QueueFull))");
+        assert_eq!(Reason::of(&busy(), Some(0)), Reason::Busy);
+        assert!(Serializer::Html.error(busy(), Locale::De).contains("Der Server ist ausgelastet"));
+
+        let gone = Error::State(SendError::StateTaskGone.into());
+        assert!(!gone.is_retryable());
+        let frame: serde_json::Value = serde_json::from_str(&Serializer::Json.error(gone, Locale::En)).unwrap();
+        assert!(frame.get("retryable").is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn countdowns_are_recomputed_per_frame() {
+        let mut out = Out::from(libaitfoaq::Game::new().get_game_state());
+        out.scheduled_start = Some(ScheduledStart::At(Instant::now() + Duration::from_secs(90)));
+        let player = ConnectionState { is_admin: false, ..connection_state() };
+        let starting_in = || {
+            let frame: serde_json::Value = serde_json::from_str(&Serializer::Json.game_state(&out, &player, 0)).unwrap();
+            frame["starting_in_ms"].clone()
+        };
+        assert_eq!(starting_in(), 90_000);
+        tokio::time::advance(Duration::from_secs(30)).await;
+        assert_eq!(starting_in(), 60_000);
+
+        out.scheduled_start = Some(ScheduledStart::Failed);
+        let frame: serde_json::Value = serde_json::from_str(&Serializer::Json.game_state(&out, &player, 0)).unwrap();
+        assert!(frame["starting_in_ms"].is_null() && frame.get("start_failed").is_none());
+        let frame: serde_json::Value = serde_json::from_str(&Serializer::Json.game_state(&out, &connection_state(), 0)).unwrap();
+        assert_eq!(frame["start_failed"], true);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn lobby_deadlines_are_shown_to_everyone() {
+        let mut out = Out::from(libaitfoaq::Game::new().get_game_state());
+        out.game.phase = GamePhase::Connecting;
+        out.lobby_deadline = Some(LobbyDeadline { at: Instant::now() + Duration::from_secs(150), reminder: None });
+        let player = ConnectionState { is_admin: false, ..connection_state() };
+        let frame = |out: &Out, connection: &ConnectionState| -> serde_json::Value {
+            serde_json::from_str(&Serializer::Json.game_state(out, connection, 0)).unwrap()
+        };
+        assert_eq!(frame(&out, &player)["lobby_remaining_ms"], 150_000);
+        assert!(Serializer::Html.game_state(&out, &player, 0).contains("The lobby closes in 2:30"));
+        tokio::time::advance(Duration::from_secs(30)).await;
+        out.lobby_deadline.as_mut().unwrap().reminder = Some(Duration::from_secs(120));
+        out.fragments = Default::default();
+        assert_eq!(frame(&out, &player)["lobby_remaining_ms"], 120_000);
+        assert!(frame(&out, &player).get("lobby_reminder_s").is_none());
+        assert_eq!(frame(&out, &connection_state())["lobby_reminder_s"], 120);
+        assert!(Serializer::Html.game_state(&out, &connection_state(), 0).contains("2:00 left in the lobby"));
+
+        tokio::time::advance(Duration::from_secs(150)).await;
+        out.lobby_deadline.as_mut().unwrap().reminder = Some(Duration::ZERO);
+        out.fragments = Default::default();
+        assert_eq!(frame(&out, &player)["lobby_remaining_ms"], 0);
+        assert!(Serializer::Html.game_state(&out, &player, 0).contains("The game starts soon"));
+        assert!(Serializer::Html.game_state(&out, &connection_state(), 0).contains("The lobby should be over by now"));
+    }
+
+    /// Where two JSON values differ, with array positions as `*`
+    fn differences(a: &serde_json::Value, b: &serde_json::Value, path: &str, found: &mut BTreeSet<String>) {
+        use serde_json::Value;
+        let at = |key: &str| if path.is_empty() { key.to_owned() } else { format!("{}.{}", path, key) };
+        match (a, b) {
+            (Value::Object(a), Value::Object(b)) => {
+                for key in a.keys().chain(b.keys()) {
+                    match (a.get(key), b.get(key)) {
+                        (Some(a), Some(b)) => differences(a, b, &at(key), found),
+                        _ => { found.insert(at(key)); },
+                    }
+                }
+            },
+            (Value::Array(a), Value::Array(b)) if a.len() == b.len() => {
+                for (a, b) in a.iter().zip(b) {
+                    differences(a, b, &at("*"), found);
+                }
+            },
+            (a, b) if a != b => { found.insert(path.to_owned()); },
+            _ => {},
+        }
+    }
+
+    #[tokio::test]
+    async fn admin_extras_never_reach_other_frames() {
+        let mut state = State::ephemeral("token".to_owned());
+        let channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+        let board = libaitfoaq::state::Board::demo();
+        let clue = (0, 1);
+        let points = board.get(&clue).unwrap().points;
+
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                let tx = &channels.tx;
+                let options = Options { hide_values: true, decay_on_rejection: Some(50), ..Options::default() };
+                for event in [
+                    Event::Settings(options),
+                    Event::LoadBoard(board.clone()),
+                    Event::OpenLobby,
+                    Event::ConnectContestant { name_hint: "alice".to_owned() },
+                    Event::ConnectContestant { name_hint: "bob".to_owned() },
+                    Event::StartGame,
+                    Event::Pick { clue: clue.into() },
+                    Event::ClueFullyShown,
+                    Event::Buzz { contestant: 0 },
+                    Event::SubmitAnswer { contestant: 0, text: "a guess".to_owned() },
+                    Event::RejectAnswer,
+                    Event::Buzz { contestant: 1 },
+                    Event::SubmitAnswer { contestant: 1, text: "another guess".to_owned() },
+                ] {
+                    State::send(event, tx).await.unwrap();
+                }
+                cancellation_token.cancel();
+            },
+        );
+
+        let out = channels.rx.borrow().clone();
+        let player = ConnectionState { is_admin: false, ..connection_state() };
+        let frame = |connection: &ConnectionState| -> serde_json::Value {
+            serde_json::from_str(&Serializer::Json.game_state(&out, connection, 0)).unwrap()
+        };
+        let (admin, public) = (frame(&connection_state()), frame(&player));
+        let mut found = BTreeSet::new();
+        differences(&admin, &public, "", &mut found);
+        assert_eq!(found, BTreeSet::from([
+            "admin",
+            "answer_match",
+            "caused_by.text",
+            "connection.is_admin",
+            "connection.legal_events",
+            "controllers",
+            "game.board.categories.*.clues.*.points",
+            "game.phase.Buzzed.answer",
+            "pending_names",
+            "profiles",
+        ].map(str::to_owned)));
+
+        let extras = &admin["admin"];
+        assert_eq!(extras["clue_value"], points / 2);
+        assert_eq!(extras["attempts"], serde_json::json!([{"contestant": 0, "answer": "a guess", "verdict": "rejected"}]));
+        assert_eq!(extras["pause_in_ms"], serde_json::Value::Null);
+        let recent = extras["recent_events"].as_array().unwrap();
+        assert_eq!(recent.len(), 10);
+        assert_eq!(recent[9]["event"], serde_json::json!({"type": "SubmitAnswer", "contestant": 1, "text": "another guess"}));
+        assert!(recent[9]["at"].is_string());
+
+        let html = Serializer::Html.game_state(&out, &connection_state(), 0);
+        assert!(html.contains("alice: a guess (rejected)"), "{}", html);
+        assert!(!Serializer::Html.game_state(&out, &player, 0).contains("admin-debug"));
+    }
+
+    #[tokio::test]
+    async fn states_name_their_cause() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("journal.jsonl");
+        let mut state = State::with_journal_and_token(&journal, "token".to_owned()).unwrap();
+        let channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                let tx = &channels.tx;
+                let board = std::fs::read("../example-boards/webengdus-2024-04-24-round1.json").unwrap();
+                State::send(Event::LoadBoard(serde_json::from_slice(&board).unwrap()), tx).await.unwrap();
+                let (connection, mut admin) = connect(&channels, true);
+                let admin_connection = tokio::spawn(connection.run());
+                assert!(admin.receive().await["caused_by"].is_null());
+
+                State::send(Event::OpenLobby, tx).await.unwrap();
+                assert_eq!(admin.receive().await["caused_by"], serde_json::json!({"type": "OpenLobby"}));
+                State::send(Event::LoadBoard(serde_json::from_slice(&board).unwrap()), tx).await.unwrap();
+                assert_eq!(admin.receive().await["caused_by"], serde_json::json!({"type": "LoadBoard"}));
+
+                let (connection, mut player) = connect(&channels, false);
+                let player_connection = tokio::spawn(connection.run());
+                assert!(player.receive().await["caused_by"].is_null());
+                // the admin hears about the new spectator, but nothing happened
+                assert!(admin.receive().await["caused_by"].is_null());
+
+                State::send(Event::ConnectContestant { name_hint: "alice".to_owned() }, tx).await.unwrap();
+                State::send(Event::AwardPoints { contestant: 0, points: 100, reason: None, clue: None }, tx).await.unwrap();
+                let cause = serde_json::json!({"type": "AwardPoints", "contestant": 0, "points": 100});
+                while player.receive().await["caused_by"] != cause {}
+
+                let hint = Event::EditClue { clue: (0, 0), field: libaitfoaq::state::ClueField::Hint, value: "secret".to_owned() };
+                State::send(hint, tx).await.unwrap();
+                assert_eq!(player.receive().await["caused_by"], serde_json::json!({"type": "EditClue", "clue": [0, 0]}));
+                loop {
+                    let frame = admin.receive().await;
+                    if frame["caused_by"]["type"] == "EditClue" {
+                        assert_eq!(frame["caused_by"]["value"], "secret");
+                        break;
+                    }
+                }
+
+                drop(player);
+                drop(admin);
+                player_connection.await.unwrap();
+                admin_connection.await.unwrap();
+                cancellation_token.cancel();
+            },
+        );
+    }
+
+    #[tokio::test]
+    async fn html_is_rendered_once_per_state() {
+        let mut state = State::ephemeral("token".to_owned());
+        state.start_demo().await.unwrap();
+        let channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                let tx = &channels.tx;
+                for n in 0..30 {
+                    State::send(Event::ConnectContestant { name_hint: format!("pi-{}", n) }, tx).await.unwrap();
+                }
+                let players: Vec<ConnectionState> = (0..30)
+                    .map(|c| ConnectionState { is_admin: false, controlling: Some(c), ..connection_state() })
+                    .collect();
+                for _ in 0..3 {
+                    State::send(Event::AwardPoints { contestant: 0, points: 10, reason: None, clue: None }, tx).await.unwrap();
+                    let out = channels.rx.borrow().clone();
+                    assert_eq!(out.fragments.renders(), 0);
+                    for (c, player) in players.iter().enumerate() {
+                        let html = Serializer::Html.game_state(&out, player, 0);
+                        assert!(html.contains(&format!(r#"{{"type": "buzz", "contestant": {}}}"#, c)), "{}", html);
+                    }
+                    assert_eq!(out.fragments.renders(), 1);
+                    // the moderator sees more
+                    let admin = Serializer::Html.game_state(&out, &connection_state(), 0);
+                    assert!(admin.contains(r#"id="admin""#) && out.fragments.renders() == 2);
+                }
+                cancellation_token.cancel();
+            },
+        );
+    }
+
+    #[test]
+    fn views_cover_every_phase() {
+        let mut game = libaitfoaq::Game::new();
+        let mut seen = std::collections::BTreeSet::new();
+        let mut check = |game: &libaitfoaq::Game| {
+            let out = Out::from(game.get_game_state());
+            let player = ConnectionState { is_admin: false, controlling: Some(0), ..connection_state() };
+            let admin = StateView::from(&StateFrame::new(&out, &connection_state(), 0));
+            let view = StateView::from(&StateFrame::new(&out, &player, 0));
+            admin.render().unwrap();
+            view.render().unwrap();
+            assert!(admin.admin.is_some() && view.admin.is_none());
+            seen.insert(view.phase);
+            (admin, view)
+        };
+
+        let (admin, view) = check(&game);
+        assert_eq!(view.banner.unwrap().text, "Loading...");
+        assert!(matches!(admin.admin.unwrap().controls, Controls::Preparing { can_shuffle: false, .. }));
+        game.apply(Event::LoadBoard(libaitfoaq::state::Board::demo())).unwrap();
+        let (admin, _) = check(&game);
+        assert!(admin.banner.is_none() && admin.categories.len() == 3);
+        let Controls::Preparing { editor, can_shuffle: true, .. } = admin.admin.unwrap().controls else { panic!() };
+        assert_eq!(editor[1].clues[0][3], ("hint", "Its mascot is a crab named Ferris".to_owned()));
+
+        game.apply(Event::OpenLobby).unwrap();
+        let (admin, _) = check(&game);
+        assert!(matches!(admin.admin.unwrap().controls, Controls::Connecting { can_start: false }));
+        game.apply(Event::ConnectContestant { name_hint: "alice".to_owned() }).unwrap();
+        game.apply(Event::ConnectContestant { name_hint: "bob".to_owned() }).unwrap();
+        game.apply(Event::NameContestant { index: 1, name: "Bob".to_owned() }).unwrap();
+        let (admin, view) = check(&game);
+        assert!(matches!(admin.admin.unwrap().controls, Controls::Connecting { can_start: true }));
+        assert_eq!(view.contestants.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), ["alice", "Bob"]);
+        let player = ConnectionState { is_admin: false, controlling: Some(0), ..connection_state() };
+        let controls = ConnectionView::new(&Out::from(game.get_game_state()), &player, 0).render().unwrap();
+        assert!(controls.contains(r#"{"type": "buzz", "contestant": 0}"#) && controls.contains(" disabled"));
+
+        game.apply(Event::StartGame).unwrap();
+        let (admin, view) = check(&game);
+        assert!(view.may.can_pick && view.caption.unwrap().starts_with("9 of 9"));
+        assert_eq!(view.rows[0][1].label.as_deref(), Some("100"));
+        assert!(view.rows[0][1].title.is_none() && admin.rows[0][1].title.is_some());
+
+        game.apply(Event::Pick { clue: (0, 0).into() }).unwrap();
+        let (admin, view) = check(&game);
+        let clue = view.clue.unwrap();
+        assert_eq!(clue.text, "The largest planet of the solar system");
+        assert!(!clue.buzzing && clue.hint.is_none());
+        assert_eq!(admin.admin.unwrap().notes.unwrap().0, "What is Jupiter?");
+        game.apply(Event::ClueFullyShown).unwrap();
+        let (_, view) = check(&game);
+        assert!(view.clue.unwrap().buzzing && view.may.can_buzz);
+        game.apply(Event::Buzz { contestant: 0 }).unwrap();
+        game.apply(Event::SubmitAnswer { contestant: 0, text: "Jupiter".to_owned() }).unwrap();
+        let (admin, view) = check(&game);
+        assert!(view.may.can_answer && view.contestants[0].indicated);
+        assert!(admin.admin.unwrap().answer_match.is_some());
+        game.apply(Event::AcceptAnswer).unwrap();
+        game.apply(Event::RevealHint).unwrap();
+        let (admin, view) = check(&game);
+        let clue = view.clue.unwrap();
+        assert_eq!((clue.text.as_str(), clue.hint.as_deref()), ("What is Jupiter?", Some("It has at least 95 moons")));
+        assert!(matches!(admin.admin.unwrap().controls, Controls::Resolution { show_hint: true }));
+        game.apply(Event::FinishClue { confirmed: false }).unwrap();
+        let (_, view) = check(&game);
+        assert_eq!(view.rows[0][0].label, None);
+
+        game.apply(Event::Pick { clue: (1, 2).into() }).unwrap();
+        let (admin, view) = check(&game);
+        assert!(view.clue.is_none());
+        assert!(matches!(admin.admin.unwrap().controls, Controls::Waging { min_wager: 5, stalled: false, .. }));
+        game.apply(Event::DisconnectContestant { contestant: 0 }).unwrap();
+        let (admin, _) = check(&game);
+        assert!(matches!(admin.admin.unwrap().controls, Controls::Waging { stalled: true, .. }));
+        game.apply(Event::ReconnectContestant { contestant: 0 }).unwrap();
+        game.apply(Event::FinishClue { confirmed: false }).unwrap();
+        game.apply(Event::FinishClue { confirmed: false }).unwrap();
+        for category in 0..3 {
+            for row in 0..3 {
+                if !game.get_game_state().board.categories[category].clues[row].solved {
+                    game.apply(Event::Pick { clue: (category, row).into() }).unwrap();
+                    // skipping shows the response first
+                    game.apply(Event::FinishClue { confirmed: false }).unwrap();
+                    game.apply(Event::FinishClue { confirmed: false }).unwrap();
+                }
+            }
+        }
+        let (_, view) = check(&game);
+        assert!(view.confetti && view.contestants[0].is_winner() && !view.contestants[1].is_winner());
+        assert_eq!(view.contestants.iter().map(|c| c.rank).collect::<Vec<_>>(), [Some(1), Some(2)]);
+
+        assert_eq!(seen.len(), 9, "{:?}", seen);
+    }
+
+    #[tokio::test]
+    async fn score_changes_are_journaled_with_reasons() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("journal.jsonl");
+        let mut state = State::with_journal_and_token(&journal, "token".to_owned()).unwrap();
+        let channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                let tx = &channels.tx;
+                State::send(Event::LoadBoard(libaitfoaq::state::Board::demo()), tx).await.unwrap();
+                State::send(Event::OpenLobby, tx).await.unwrap();
+                State::send(Event::ConnectContestant { name_hint: "alice".to_owned() }, tx).await.unwrap();
+                State::send(Event::StartGame, tx).await.unwrap();
+                let (connection, mut admin) = connect(&channels, true);
+                let admin_connection = tokio::spawn(connection.run());
+                assert_eq!(admin.receive().await["game"]["locked"], true);
+
+                admin.send(r#"{"type": "award_points", "contestant": 0, "points": "50"}"#);
+                assert_eq!(admin.receive().await["error"], "State(Game(ReasonRequired))");
+                admin.send(r#"{"type": "award_points", "contestant": 0, "points": "50", "reason": "misheard"}"#);
+                assert_eq!(admin.receive().await["caused_by"]["reason"], "misheard");
+                admin.send(r#"{"type": "unlock"}"#);
+                let frame = admin.receive().await;
+                assert_eq!(frame["caused_by"]["type"], "Unlock");
+                assert_eq!((&frame["game"]["locked"], &frame["game"]["unlocked"]), (&false.into(), &true.into()));
+
+                drop(admin);
+                admin_connection.await.unwrap();
+                cancellation_token.cancel();
+            },
+        );
+
+        let journaled = std::fs::read_to_string(&journal).unwrap();
+        // followed by when they were applied
+        assert!(journaled.contains(r#"{"type":"AwardPoints","contestant":0,"points":50,"reason":"misheard","at":"#));
+        assert!(journaled.lines().last().unwrap().starts_with(r#"{"type":"Unlock","at":"#));
+    }
+
+    #[tokio::test]
+    async fn only_moderators_unlock_or_change_scores() {
+        let mut state = State::ephemeral("token".to_owned());
+        let channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                let tx = &channels.tx;
+                State::send(Event::LoadBoard(libaitfoaq::state::Board::demo()), tx).await.unwrap();
+                State::send(Event::OpenLobby, tx).await.unwrap();
+                State::send(Event::ConnectContestant { name_hint: "alice".to_owned() }, tx).await.unwrap();
+                State::send(Event::StartGame, tx).await.unwrap();
+                let (connection, mut player) = connect(&channels, false);
+                let player_connection = tokio::spawn(connection.run());
+                player.receive().await;
+
+                for input in [
+                    r#"{"type": "unlock"}"#,
+                    r#"{"type": "award_points", "contestant": 0, "points": "50", "reason": "misheard"}"#,
+                    r#"{"type": "revoke_points", "contestant": 0, "points": "50", "reason": "misheard"}"#,
+                    r#"{"type": "load_board", "board": "../example-boards/webengdus-2024-04-24-round1.json"}"#,
+                    r#"{"type": "edit_clue", "clue": [0, 0], "field": "hint", "value": "hint"}"#,
+                ] {
+                    player.send(input);
+                    assert_eq!(player.receive().await["error"], "Forbidden", "{}", input);
+                }
+                let game = channels.rx.borrow().game.clone();
+                assert!(game.locked && !game.unlocked);
+                assert_eq!(game.contestants[0].points, 0);
+                assert!(game.corrections.is_empty());
+
+                drop(player);
+                player_connection.await.unwrap();
+                cancellation_token.cancel();
+            },
+        );
+    }
+
+    #[tokio::test]
+    async fn scores_can_be_adjusted_after_a_preview() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("journal.jsonl");
+        let mut state = State::with_journal_and_token(&journal, "token".to_owned()).unwrap();
+        let channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                let tx = &channels.tx;
+                State::send(Event::LoadBoard(libaitfoaq::state::Board::demo()), tx).await.unwrap();
+                State::send(Event::OpenLobby, tx).await.unwrap();
+                State::send(Event::ConnectContestant { name_hint: "alice".to_owned() }, tx).await.unwrap();
+                State::send(Event::ConnectContestant { name_hint: "bob".to_owned() }, tx).await.unwrap();
+                State::send(Event::AwardPoints { contestant: 1, points: 50, reason: None, clue: None }, tx).await.unwrap();
+                let (connection, mut admin) = connect(&channels, true);
+                let admin_connection = tokio::spawn(connection.run());
+                admin.receive().await;
+
+                let adjust = r#"{"type": "adjust_scores", "changes": [[0, 200], [1, 200]], "reason": "category bonus""#;
+                admin.send(&format!("{}, \"dry_run\": true}}", adjust));
+                assert_eq!(admin.receive().await, serde_json::json!({"preview": [
+                    {"contestant": 0, "name": "alice", "before": 0, "after": 200},
+                    {"contestant": 1, "name": "bob", "before": 50, "after": 250},
+                ]}));
+                admin.send(r#"{"type": "adjust_scores", "changes": [[0, 200], [2, 200]], "dry_run": true}"#);
+                assert_eq!(admin.receive().await["error"], "Game(InvalidAdjustments { entries: [1] })");
+                admin.send(r#"{"type": "adjust_scores", "changes": [[0, 200], [2, 200]]}"#);
+                assert_eq!(admin.receive().await["error"], "State(Game(InvalidAdjustments { entries: [1] }))");
+
+                admin.send(&format!("{}}}", adjust));
+                let frame = admin.receive().await;
+                let points = |c: usize| frame["game"]["contestants"][c]["points"].clone();
+                assert_eq!((points(0), points(1)), (200.into(), 250.into()));
+
+                let (connection, mut contestant) = connect(&channels, false);
+                let contestant_connection = tokio::spawn(connection.run());
+                contestant.receive().await;
+                contestant.send(&format!("{}, \"dry_run\": true}}", adjust));
+                assert_eq!(contestant.receive().await["error"], "Forbidden");
+
+                drop(contestant);
+                contestant_connection.await.unwrap();
+                drop(admin);
+                admin_connection.await.unwrap();
+                cancellation_token.cancel();
+            },
+        );
+
+        let journaled = std::fs::read_to_string(&journal).unwrap();
+        let adjustments: Vec<&str> = journaled.lines().filter(|line| line.contains("AdjustScores")).collect();
+        assert_eq!(adjustments.len(), 1);
+        assert!(adjustments[0].starts_with(r#"{"type":"AdjustScores","changes":[[0,200],[1,200]],"reason":"category bonus","at":"#));
+    }
+
+    #[tokio::test]
+    async fn picks_address_clues_by_handle_or_id() {
+        let mut state = State::ephemeral("token".to_owned());
+        let channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                let tx = &channels.tx;
+                let mut board = libaitfoaq::state::Board::demo();
+                board.categories[1].clues[0].id = Some("second".to_owned());
+                State::send(Event::LoadBoard(board), tx).await.unwrap();
+                State::send(Event::OpenLobby, tx).await.unwrap();
+                State::send(Event::ConnectContestant { name_hint: "alice".to_owned() }, tx).await.unwrap();
+                State::send(Event::StartGame, tx).await.unwrap();
+                let (connection, mut admin) = connect(&channels, true);
+                let admin_connection = tokio::spawn(connection.run());
+                admin.receive().await;
+
+                admin.send(r#"{"type": "pick", "clue": "missing"}"#);
+                assert_eq!(admin.receive().await["error"], "State(Game(ClueNotFound))");
+                admin.send(r#"{"type": "pick", "clue": "second"}"#);
+                let frame = admin.receive().await;
+                assert_eq!(frame["game"]["phase"]["Clue"]["clue"], serde_json::json!([1, 0]));
+                assert_eq!(frame["game"]["board"]["categories"][1]["clues"][0]["id"], "second");
+                admin.send(r#"{"type": "finish_clue", "confirmed": true}"#);
+                admin.send(r#"{"type": "finish_clue"}"#);
+                admin.receive_where(|frame| frame["game"]["phase"].get("Picking").is_some()).await;
+                admin.send(r#"{"type": "pick", "clue": [0, 0]}"#);
+                let frame = admin.receive_where(|frame| frame["game"]["phase"].get("Clue").is_some()).await;
+                assert_eq!(frame["game"]["phase"]["Clue"]["clue"], serde_json::json!([0, 0]));
+
+                drop(admin);
+                admin_connection.await.unwrap();
+                cancellation_token.cancel();
+            },
+        );
+    }
+
+    #[tokio::test]
+    async fn only_the_moderator_buzzes_for_others() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("journal.jsonl");
+        let mut state = State::with_journal_and_token(&journal, "token".to_owned()).unwrap();
+        let channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                let tx = &channels.tx;
+                State::send(Event::LoadBoard(libaitfoaq::state::Board::demo()), tx).await.unwrap();
+                State::send(Event::OpenLobby, tx).await.unwrap();
+                State::send(Event::ConnectContestant { name_hint: "alice".to_owned() }, tx).await.unwrap();
+                State::send(Event::StartGame, tx).await.unwrap();
+                State::send(Event::Pick { clue: (0, 0).into() }, tx).await.unwrap();
+                State::send(Event::ClueFullyShown, tx).await.unwrap();
+                let (connection, mut player) = connect(&channels, false);
+                let player_connection = tokio::spawn(connection.run());
+                let (connection, mut admin) = connect(&channels, true);
+                let admin_connection = tokio::spawn(connection.run());
+                player.receive().await;
+                admin.receive().await;
+
+                let buzz = r#"{"type": "buzz_for", "contestant": 0}"#;
+                player.send(buzz);
+                assert_eq!(player.receive().await["error"], "Forbidden");
+                admin.send(buzz);
+                let frame = admin.receive().await;
+                assert_eq!(frame["caused_by"]["type"], "Buzz");
+                assert_eq!(frame["game"]["phase"]["Buzzed"]["contestant"], 0);
+
+                drop((player, admin));
+                player_connection.await.unwrap();
+                admin_connection.await.unwrap();
+                cancellation_token.cancel();
+            },
+        );
+
+        let journaled = std::fs::read_to_string(&journal).unwrap();
+        assert!(journaled.contains(r#"{"type":"Buzz","contestant":0,"actor":"moderator","at":"#), "{}", journaled);
+        // and it still loads
+        State::with_journal_and_token(&journal, "token".to_owned()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn wagers_come_from_the_waging_contestant() {
+        let mut state = State::ephemeral("token".to_owned());
+        state.start_demo().await.unwrap();
+        let channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                let (connection, mut admin) = connect(&channels, true);
+                let admin_connection = tokio::spawn(connection.run());
+                let (connection, mut alice) = connect(&channels, false);
+                let alice_connection = tokio::spawn(connection.run());
+                let (connection, mut bob) = connect(&channels, false);
+                let bob_connection = tokio::spawn(connection.run());
+                // not by `caused_by`, spectators joining and leaving resend states without it
+                let phase = |name: &'static str| move |frame: &serde_json::Value| frame["game"]["phase"].get(name).is_some();
+                let error = |frame: &serde_json::Value| frame.get("error").is_some();
+
+                alice.send(r#"{"type": "connect_contestant", "name_hint": "alice"}"#);
+                alice.receive_where(|frame| frame["connection"]["controlling"] == 0).await;
+                bob.send(r#"{"type": "connect_contestant", "name_hint": "bob"}"#);
+                bob.receive_where(|frame| frame["connection"]["controlling"] == 1).await;
+                admin.send(r#"{"type": "start_game"}"#);
+                let frame = admin.receive_where(phase("Picking")).await;
+                assert_eq!(frame["game"]["phase"]["Picking"]["contestant"], 0);
+                let clue = frame["game"]["board"]["categories"].as_array().unwrap().iter().enumerate()
+                    .find_map(|(c, category)| {
+                        let clues = category["clues"].as_array()?;
+                        Some((c, clues.iter().position(|clue| clue["can_wager"] == true)?))
+                    })
+                    .unwrap();
+
+                alice.send(&format!(r#"{{"type": "pick", "clue": [{}, {}]}}"#, clue.0, clue.1));
+                let frame = alice.receive_where(phase("Waging")).await;
+                let waging = &frame["game"]["phase"]["Waging"];
+                assert_eq!((&waging["contestant"], frame["connection"]["can_wage"].as_bool()), (&0.into(), Some(true)));
+                let max = waging["max_wager"].as_i64().unwrap();
+                assert!(max >= 300, "{}", frame);
+                bob.send(r#"{"type": "set_wage", "points": "300"}"#);
+                assert_eq!(bob.receive_where(error).await["error"], "Forbidden");
+                bob.send(r#"{"type": "confirm_wage"}"#);
+                assert_eq!(bob.receive_where(error).await["error"], "Forbidden");
+                alice.send(&format!(r#"{{"type": "set_wage", "points": "{}"}}"#, max + 1));
+                assert!(alice.receive_where(error).await["error"].as_str().unwrap().contains("InvalidWager"));
+
+                alice.send(r#"{"type": "set_wage", "points": "300"}"#);
+                alice.receive_where(phase("Clue")).await;
+                admin.send(r#"{"type": "clue_fully_shown"}"#);
+                let frame = alice.receive_where(phase("Buzzed")).await;
+                assert_eq!(frame["game"]["phase"]["Buzzed"]["contestant"], 0);
+                admin.send(r#"{"type": "accept_answer"}"#);
+                admin.receive_where(|frame| frame["game"]["contestants"][0]["points"] == 300).await;
+                admin.send(r#"{"type": "finish_clue"}"#);
+                bob.receive_where(phase("Picking")).await;
+
+                drop((admin, alice, bob));
+                admin_connection.await.unwrap();
+                alice_connection.await.unwrap();
+                bob_connection.await.unwrap();
+                cancellation_token.cancel();
+            },
+        );
+    }
+
+    #[tokio::test]
+    async fn buzzes_are_withdrawn_by_who_buzzed() {
+        let mut state = State::ephemeral("token".to_owned());
+        state.start_demo().await.unwrap();
+        let channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                let (connection, mut admin) = connect(&channels, true);
+                let admin_connection = tokio::spawn(connection.run());
+                let (connection, mut alice) = connect(&channels, false);
+                let alice_connection = tokio::spawn(connection.run());
+                let (connection, mut bob) = connect(&channels, false);
+                let bob_connection = tokio::spawn(connection.run());
+                let phase = |name: &'static str| move |frame: &serde_json::Value| frame["game"]["phase"].get(name).is_some();
+                let error = |frame: &serde_json::Value| frame.get("error").is_some();
+
+                alice.send(r#"{"type": "connect_contestant", "name_hint": "alice"}"#);
+                alice.receive_where(|frame| frame["connection"]["controlling"] == 0).await;
+                bob.send(r#"{"type": "connect_contestant", "name_hint": "bob"}"#);
+                bob.receive_where(|frame| frame["connection"]["controlling"] == 1).await;
+                admin.send(r#"{"type": "start_game"}"#);
+                let frame = admin.receive_where(phase("Picking")).await;
+                let clue = frame["game"]["board"]["categories"].as_array().unwrap().iter().enumerate()
+                    .find_map(|(c, category)| {
+                        let clues = category["clues"].as_array()?;
+                        let open = |clue: &serde_json::Value| clue["can_wager"] == false && clue["exclusive"] == false;
+                        Some((c, clues.iter().position(open)?))
+                    })
+                    .unwrap();
+
+                admin.send(&format!(r#"{{"type": "pick", "clue": [{}, {}]}}"#, clue.0, clue.1));
+                admin.receive_where(phase("Clue")).await;
+                admin.send(r#"{"type": "clue_fully_shown"}"#);
+                admin.receive_where(phase("Buzzing")).await;
+                alice.send(r#"{"type": "buzz", "contestant": 0}"#);
+                alice.receive_where(phase("Buzzed")).await;
+                bob.send(r#"{"type": "withdraw_buzz"}"#);
+                assert_eq!(bob.receive_where(error).await["error"], "Forbidden");
+                alice.send(r#"{"type": "withdraw_buzz"}"#);
+                let frame = admin.receive_where(phase("Buzzing")).await;
+                assert_eq!(frame["game"]["contestants"][0]["points"], 0);
+
+                drop((admin, alice, bob));
+                admin_connection.await.unwrap();
+                alice_connection.await.unwrap();
+                bob_connection.await.unwrap();
+                cancellation_token.cancel();
+            },
+        );
+    }
+
+    #[tokio::test]
+    async fn buzzes_are_acknowledged_to_who_buzzed() {
+        let mut state = State::ephemeral("token".to_owned());
+        state.start_demo().await.unwrap();
+        let channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                let (connection, mut admin) = connect(&channels, true);
+                let admin_connection = tokio::spawn(connection.run());
+                let (connection, mut alice) = connect(&channels, false);
+                let alice_connection = tokio::spawn(connection.run());
+                let (connection, mut bob) = connect(&channels, false);
+                let bob_connection = tokio::spawn(connection.run());
+                let phase = |name: &'static str| move |frame: &serde_json::Value| frame["game"]["phase"].get(name).is_some();
+                let ack = |frame: &serde_json::Value| frame.get("ack").is_some();
+
+                alice.send(r#"{"type": "connect_contestant", "name_hint": "alice"}"#);
+                alice.receive_where(|frame| frame["connection"]["controlling"] == 0).await;
+                bob.send(r#"{"type": "connect_contestant", "name_hint": "bob"}"#);
+                bob.receive_where(|frame| frame["connection"]["controlling"] == 1).await;
+                alice.send(r#"{"type": "buzz", "contestant": 0}"#);
+                let frame = alice.receive_where(ack).await;
+                assert_eq!(frame["ack"], serde_json::json!({"input": "buzz", "accepted": true, "reason": null}));
+
+                admin.send(r#"{"type": "start_game"}"#);
+                let frame = admin.receive_where(phase("Picking")).await;
+                let clue = frame["game"]["board"]["categories"].as_array().unwrap().iter().enumerate()
+                    .find_map(|(c, category)| {
+                        let clues = category["clues"].as_array()?;
+                        let open = |clue: &serde_json::Value| clue["can_wager"] == false && clue["exclusive"] == false;
+                        Some((c, clues.iter().position(open)?))
+                    })
+                    .unwrap();
+                admin.send(&format!(r#"{{"type": "pick", "clue": [{}, {}]}}"#, clue.0, clue.1));
+                admin.receive_where(phase("Clue")).await;
+                bob.send(r#"{"type": "buzz", "contestant": 1}"#);
+                assert_eq!(bob.receive_where(ack).await["ack"]["reason"], "wrong_phase");
+                admin.send(r#"{"type": "clue_fully_shown"}"#);
+                admin.receive_where(phase("Buzzing")).await;
+                alice.send(r#"{"type": "buzz", "contestant": 0}"#);
+                assert_eq!(alice.receive_where(ack).await["ack"]["accepted"], true);
+                bob.send(r#"{"type": "buzz", "contestant": 1}"#);
+                let frame = bob.receive_where(ack).await;
+                assert_eq!(frame["ack"], serde_json::json!({"input": "buzz", "accepted": false, "reason": "too_late"}));
+                // JSON clients still get the error itself
+                bob.receive_where(|frame| frame.get("error").is_some()).await;
+                bob.send(r#"{"type": "withdraw_buzz"}"#);
+                assert_eq!(bob.receive_where(ack).await["ack"]["reason"], "locked_out");
+
+                // admins see the state, they aren't acknowledged
+                admin.send(r#"{"type": "clue_fully_shown"}"#);
+                let frame = admin.receive_where(|frame| ack(frame) || frame.get("error").is_some()).await;
+                assert!(frame.get("error").is_some());
+
+                // a toast instead of the error for HTML
+                bob.send(r#"{"type": "buzz", "contestant": 1, "format": "html"}"#);
+                let toast = loop {
+                    match bob.from.recv().await.expect("connection closed") {
+                        Message::Text(frame) if frame.contains(r#"id="ack""#) => break frame,
+                        _ => continue,
+                    }
+                };
+                assert!(toast.contains("rejected") && toast.contains("Too late"), "{}", toast);
+
+                drop((admin, alice, bob));
+                admin_connection.await.unwrap();
+                alice_connection.await.unwrap();
+                bob_connection.await.unwrap();
+                cancellation_token.cancel();
+            },
+        );
+    }
+
+    /// Every event can be sent over the websocket. The variants are taken
+    /// from serde, so a new event fails this until it has an [Input].
+    #[tokio::test]
+    async fn every_event_has_an_input() {
+        let inputs: &[(&str, &str)] = &[
+            ("Settings", r#"{"type": "settings", "options": {}}"#),
+            ("LoadBoard", r#"{"type": "load_board", "board": "../example-boards/webengdus-2024-04-24-round1.json"}"#),
+            ("ShuffleBoard", r#"{"type": "shuffle_board", "seed": null}"#),
+            ("EditClue", r#"{"type": "edit_clue", "clue": [0, 0], "field": "hint", "value": "hint"}"#),
+            ("EditCategory", r#"{"type": "edit_category_title", "category": 0, "title": "title"}"#),
+            ("OpenLobby", r#"{"type": "open_lobby"}"#),
+            ("ConnectContestant", r#"{"type": "connect_contestant", "name_hint": "phone"}"#),
+            ("ImportContestants", r#"{"type": "import_contestants", "names": ["Alex", "Sam"]}"#),
+            ("DisconnectContestant", r#"{"type": "disconnect_contestant", "contestant": 0}"#),
+            ("ReconnectContestant", r#"{"type": "reconnect_contestant", "contestant": 0}"#),
+            ("ReorderContestants", r#"{"type": "reorder_contestants", "order": [1, 0]}"#),
+            ("NameContestant", r#"{"type": "name_contestant", "contestant": 0, "name": "name"}"#),
+            ("AwardPoints", r#"{"type": "award_points", "contestant": 0, "points": "100", "reason": "typo"}"#),
+            ("RevokePoints", r#"{"type": "revoke_points", "contestant": 0, "points": "100", "reason": "typo"}"#),
+            ("StartPractice", r#"{"type": "start_practice"}"#),
+            ("ResetPractice", r#"{"type": "reset_practice"}"#),
+            ("EndPractice", r#"{"type": "end_practice"}"#),
+            ("StartGame", r#"{"type": "start_game"}"#),
+            ("Unlock", r#"{"type": "unlock"}"#),
+            ("Pick", r#"{"type": "pick", "clue": [0, 0]}"#),
+            ("PassPick", r#"{"type": "pass_pick", "to": null}"#),
+            ("SetWage", r#"{"type": "set_wage", "points": "100"}"#),
+            ("ConfirmWage", r#"{"type": "confirm_wage"}"#),
+            ("CancelWage", r#"{"type": "cancel_wage"}"#),
+            ("ClueFullyShown", r#"{"type": "clue_fully_shown"}"#),
+            ("Buzz", r#"{"type": "buzz", "contestant": 0}"#),
+            ("AcceptAnswer", r#"{"type": "accept_answer"}"#),
+            ("WithdrawBuzz", r#"{"type": "withdraw_buzz"}"#),
+            ("RejectAnswer", r#"{"type": "reject_answer"}"#),
+            ("RevealHint", r#"{"type": "reveal_hint"}"#),
+            ("FinishClue", r#"{"type": "finish_clue"}"#),
+        ];
+        // sent by the connection itself, which knows the contestant
+        let by_connection: &[(&str, &str)] = &[
+            ("SubmitAnswer", r#"{"type": "submit_answer_text", "text": "answer"}"#),
+            ("AdjustScores", r#"{"type": "adjust_scores", "changes": [[0, 100]], "reason": "bonus"}"#),
+        ];
+
+        let error = serde_json::from_str::<Event>(r#"{"type": "?"}"#).unwrap_err().to_string();
+        let (_, expected) = error.split_once("expected one of ").unwrap();
+        // every other part is quoted with backticks
+        let variants: Vec<&str> = expected.split('`').skip(1).step_by(2).collect();
+        assert!(variants.len() > 20, "{}", error);
+
+        for variant in variants {
+            if let Some((_, msg)) = by_connection.iter().find(|(name, _)| *name == variant) {
+                let msg: InputMessage = msg.parse().unwrap();
+                assert!(matches!(handle_input(msg.input).await, Ok(None)), "{}", variant);
+                continue;
+            }
+            let (_, msg) = inputs.iter().find(|(name, _)| *name == variant)
+                .unwrap_or_else(|| panic!("no input for {}", variant));
+            let msg: InputMessage = msg.parse().unwrap_or_else(|e| panic!("{}: {}", variant, e));
+            let event = handle_input(msg.input).await.unwrap().unwrap();
+            assert_eq!(event.name(), variant);
+        }
+    }
+
+    /// Regenerate after changing the wire format with
+    /// `UPDATE_SCHEMAS=1 cargo test -p libaitfoaq-server schemas`, which
+    /// also needs [PROTOCOL_VERSION] to be bumped
+    const SCHEMAS: &str = "schema";
+    /// The protocol version and a hash of the schemas it was bumped for
+    const PROTOCOL: &str = "PROTOCOL";
+
+    #[test]
+    fn the_schemas_are_up_to_date() {
+        let directory = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(SCHEMAS);
+        if std::env::var_os("UPDATE_SCHEMAS").is_some() {
+            dump_schemas(&directory).unwrap();
+        }
+        for (name, schema) in schemas() {
+            let checked_in = std::fs::read_to_string(directory.join(name)).unwrap_or_default();
+            let generated = serde_json::to_string_pretty(&schema).unwrap() + "\n";
+            assert!(checked_in == generated, "{}/{} is outdated, see the docs of SCHEMAS", SCHEMAS, name);
+        }
+    }
+
+    #[test]
+    fn changed_schemas_bump_the_protocol_version() {
+        use sha2::{Digest, Sha256};
+        let mut hasher = Sha256::new();
+        for (name, schema) in schemas() {
+            hasher.update(name);
+            hasher.update(serde_json::to_string(&schema).unwrap());
+        }
+        let fingerprint = hex::encode(hasher.finalize());
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(SCHEMAS).join(PROTOCOL);
+        let checked_in = std::fs::read_to_string(&path).unwrap_or_default();
+        let (version, checked_in) = checked_in.trim().split_once(' ').unwrap_or_default();
+        if checked_in == fingerprint {
+            assert_eq!(version, PROTOCOL_VERSION.to_string(), "{}/{} is outdated, see the docs of SCHEMAS", SCHEMAS, PROTOCOL);
+            return;
+        }
+        assert_ne!(version, PROTOCOL_VERSION.to_string(), "the schemas changed, bump PROTOCOL_VERSION");
+        if std::env::var_os("UPDATE_SCHEMAS").is_some() {
+            std::fs::write(&path, format!("{} {}\n", PROTOCOL_VERSION, fingerprint)).unwrap();
+        } else {
+            panic!("{}/{} is outdated, see the docs of SCHEMAS", SCHEMAS, PROTOCOL);
+        }
+    }
+
+    #[test]
+    fn the_schemas_cover_every_variant() {
+        let schema = |file: &str| serde_json::to_string(&schemas().into_iter().find(|(name, _)| *name == file).unwrap().1).unwrap();
+        let (events, state) = (schema("event.json"), schema("state.json"));
+        for kind in EventKind::ALL {
+            assert!(events.contains(&format!(r#""const":"{}""#, kind.name())), "{} isn't in the schema", kind.name());
+            assert!(state.contains(&format!(r#""{}""#, kind.name())), "{} isn't a legal event in the schema", kind.name());
+        }
+        for phase in ["Preparing", "Connecting", "Practice", "Picking", "Waging", "Clue", "Buzzing", "Buzzed", "Resolution", "Score"] {
+            assert!(state.contains(&format!(r#""{}""#, phase)), "{} isn't in the schema", phase);
+        }
+        assert!(schema("input.json").contains(r#""const":"reorder_contestants""#));
+    }
 }
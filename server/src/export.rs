@@ -0,0 +1,172 @@
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use askama::Template;
+use libaitfoaq::state::{Board, ClueHandle, GameState, Points, Standing};
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::state::Timeline;
+
+/// Where and what to write once a game reaches the score screen
+#[derive(Clone, Debug)]
+pub struct ExportOptions {
+    pub directory: PathBuf,
+    /// Also write a small HTML certificate for every contestant
+    pub certificates: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Results {
+    pub finished_at: String,
+    pub scores: Vec<Score>,
+    pub statistics: Statistics,
+    /// If the game was unlocked after it started, so the board or scores
+    /// might have been changed more freely
+    pub unlocked: bool,
+    pub board: Board,
+    pub timeline: Timeline,
+    /// Scores changed by hand for a clue, by clue
+    pub corrected_clues: Vec<CorrectedClue>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Score {
+    pub name: String,
+    pub points: Points,
+    /// As shown during the game, see
+    /// [Options::format_points](libaitfoaq::state::Options::format_points)
+    pub formatted: String,
+    /// 1 for the winner(s), contestants with equal points share a rank
+    pub rank: usize,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Statistics {
+    pub contestants: usize,
+    pub categories: usize,
+    pub clues: usize,
+    pub points_total: Points,
+    /// Scores changed by hand, with a clue or without, see
+    /// [GameState::corrections]
+    pub corrections: usize,
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct CorrectedClue {
+    pub clue: ClueHandle,
+    pub corrections: usize,
+    /// Awarded and revoked added up
+    pub points: Points,
+}
+
+#[derive(Template)]
+#[template(path = "certificate.html")]
+struct Certificate<'a> {
+    score: &'a Score,
+    contestants: usize,
+    finished_at: &'a str,
+}
+
+impl Results {
+    pub fn from_game_state(game: &GameState, finished_at: SystemTime, timeline: Timeline) -> Self {
+        let scores: Vec<Score> = Standing::rank(&game.contestants)
+            .into_iter()
+            .map(|s| {
+                let c = &game.contestants[s.contestant];
+                Score {
+                    name: c.display_name.clone(),
+                    points: s.points,
+                    formatted: game.options.format_points(s.points),
+                    rank: s.rank,
+                }
+            })
+            .collect();
+        Self {
+            finished_at: humantime::format_rfc3339_seconds(finished_at).to_string(),
+            statistics: Statistics {
+                contestants: game.contestants.len(),
+                categories: game.board.categories.len(),
+                clues: game.board.categories.iter().map(|c| c.clues.len()).sum(),
+                points_total: game.contestants.iter().map(|c| c.points).sum(),
+                corrections: game.corrections.len(),
+            },
+            scores,
+            unlocked: game.unlocked,
+            board: game.board.clone(),
+            timeline,
+            corrected_clues: CorrectedClue::of(game),
+        }
+    }
+
+    /// File name friendly version of [Results::finished_at]
+    fn stem(&self) -> String {
+        self.finished_at.replace(':', "-")
+    }
+}
+
+impl CorrectedClue {
+    fn of(game: &GameState) -> Vec<Self> {
+        let mut by_clue: std::collections::BTreeMap<ClueHandle, Self> = Default::default();
+        for correction in &game.corrections {
+            let Some(clue) = correction.clue else { continue };
+            let corrected = by_clue.entry(clue).or_insert(Self { clue, corrections: 0, points: 0 });
+            corrected.corrections += 1;
+            corrected.points += correction.points;
+        }
+        by_clue.into_values().collect()
+    }
+}
+
+/// Writes `results-<date>.json` and, if enabled, `certificate-<date>-<rank>-<n>.html`
+/// files. Returns the path of the results file.
+pub async fn write(options: &ExportOptions, results: &Results) -> Result<PathBuf, Error> {
+    tokio::fs::create_dir_all(&options.directory).await?;
+    let path = options.directory.join(format!("results-{}.json", results.stem()));
+    tokio::fs::write(&path, serde_json::to_vec_pretty(results)?).await?;
+
+    if options.certificates {
+        for (i, score) in results.scores.iter().enumerate() {
+            let certificate = Certificate {
+                score,
+                contestants: results.statistics.contestants,
+                finished_at: &results.finished_at,
+            };
+            let file = format!("certificate-{}-{}-{}.html", results.stem(), score.rank, i);
+            tokio::fs::write(options.directory.join(file), certificate.render()?).await?;
+        }
+    }
+    Ok(path)
+}
+
+/// The most recent results file in the directory, if any
+pub async fn latest(directory: &Path) -> Result<Option<PathBuf>, Error> {
+    let mut latest: Option<PathBuf> = None;
+    let mut entries = match tokio::fs::read_dir(directory).await {
+        Ok(entries) => entries,
+        // created by the first export
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        // file names contain the date, so they sort chronologically
+        if name.starts_with("results-") && name.ends_with(".json")
+            && latest.as_ref().is_none_or(|l| l.file_name().unwrap_or_default() < entry.file_name().as_os_str())
+        {
+            latest = Some(entry.path());
+        }
+    }
+    Ok(latest)
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Could not write export: {0}")]
+    IO(#[from] std::io::Error),
+    #[error("Could not serialize results: {0}")]
+    Serializing(#[from] serde_json::Error),
+    #[error("Could not render certificate: {0}")]
+    Rendering(#[from] askama::Error),
+}
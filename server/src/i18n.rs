@@ -0,0 +1,264 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+/// The language of the server's own strings. Boards are shown as they were
+/// written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+}
+
+/// English to German. English is the key, so untranslated strings show up
+/// in English instead of not at all.
+const GERMAN: &[(&str, &str)] = &[
+    // admin.html
+    ("Previous", "Zurück"),
+    ("Next", "Weiter"),
+    ("seek", "springen"),
+    ("Open Lobby", "Lobby öffnen"),
+    ("Approve names contestants choose", "Selbstgewählte Namen freigeben"),
+    ("Hide the values of clues until they are picked", "Punktwerte der Aufgaben bis zur Auswahl verbergen"),
+    ("Confirm skipping clues without scoring", "Überspringen von Aufgaben ohne Wertung bestätigen"),
+    ("Reveal hints after", "Hinweise zeigen nach"),
+    ("wrong answers", "falschen Antworten"),
+    ("Wrong answers leave", "Falsche Antworten lassen"),
+    ("percent of a clue's value", "Prozent vom Wert einer Aufgabe übrig"),
+    ("Points are called", "Punkte heißen"),
+    ("points", "Punkte"),
+    ("Show points as", "Punkte anzeigen als"),
+    ("an amount of money", "Geldbetrag"),
+    ("before the number", "vor der Zahl"),
+    ("after the number", "nach der Zahl"),
+    ("Board:", "Spielbrett:"),
+    ("load", "laden"),
+    ("Shuffle categories", "Kategorien mischen"),
+    ("Start Game!", "Spiel starten!"),
+    ("Cancel the countdown", "Countdown abbrechen"),
+    ("Practice buzzing", "Buzzern üben"),
+    ("{} was first", "{} war am schnellsten"),
+    ("Nobody buzzed yet", "Noch hat niemand gebuzzert"),
+    ("Next round", "Nächste Runde"),
+    ("End the practice", "Übung beenden"),
+    ("Resume the game", "Spiel fortsetzen"),
+    ("Start in", "Starten in"),
+    ("seconds", "Sekunden"),
+    ("schedule", "planen"),
+    ("One more minute for the lobby", "Eine Minute mehr für die Lobby"),
+    ("Close the lobby in", "Lobby schließen in"),
+    ("minutes", "Minuten"),
+    ("set", "festlegen"),
+    ("The lobby should be over by now", "Die Lobby sollte jetzt vorbei sein"),
+    ("{} left in the lobby", "Noch {} in der Lobby"),
+    ("The countdown ran out, but the game couldn't start", "Der Countdown ist abgelaufen, aber das Spiel konnte nicht starten"),
+    ("Pass the pick", "Auswahl weitergeben"),
+    ("Wager:", "Einsatz:"),
+    ("({} to {})", "({} bis {})"),
+    ("wager", "setzen"),
+    ("Abandon", "Abbrechen"),
+    ("Profile:", "Profil:"),
+    ("apply", "anwenden"),
+    ("Contestants, one per line:", "Teilnehmer:innen, eine pro Zeile:"),
+    ("import", "importieren"),
+    ("Invalid profile: {}", "Ungültiges Profil: {}"),
+    ("The waging contestant disconnected", "Die setzende Person hat die Verbindung verloren"),
+    ("Wager nothing for them", "Für sie nichts setzen"),
+    ("Clue fully shown / read out loud", "Aufgabe vollständig gezeigt / vorgelesen"),
+    ("Finish", "Beenden"),
+    ("Finish without answer", "Ohne Antwort beenden"),
+    ("Accept", "Annehmen"),
+    ("Reject", "Ablehnen"),
+    ("Reopen buzzing", "Buzzer wieder freigeben"),
+    ("Reveal Hint", "Hinweis aufdecken"),
+    ("Download results", "Ergebnisse herunterladen"),
+    ("Players", "Teilnehmende"),
+    ("Approve", "Freigeben"),
+    ("looks right", "sieht richtig aus"),
+    ("have a look", "genauer ansehen"),
+    ("looks wrong", "sieht falsch aus"),
+    ("Debugging", "Fehlersuche"),
+    ("The clue is worth {}", "Die Aufgabe ist {} wert"),
+    ("accepted", "richtig"),
+    ("rejected", "falsch"),
+    ("withdrawn", "zurückgezogen"),
+    ("The game pauses in {}", "Das Spiel pausiert in {}"),
+    ("{} is disconnected in {}", "{} wird getrennt in {}"),
+    // board.html
+    ("{} of {} clues remaining", "noch {} von {} Aufgaben"),
+    ("Loading...", "Lädt..."),
+    ("Waiting for the host to reconnect", "Warten, bis die Moderation wieder da ist"),
+    ("The game starts in {}", "Das Spiel startet in {}"),
+    ("The lobby closes in {}", "Die Lobby schließt in {}"),
+    ("The game starts soon", "Das Spiel startet gleich"),
+    ("Buzzer practice", "Buzzer-Übung"),
+    // contestants.html
+    ("disconnected", "nicht verbunden"),
+    ("idle", "abwesend"),
+    ("They are still here", "Ist noch da"),
+    ("This contestant isn't idle", "Diese Person ist nicht abwesend"),
+    ("Someone else already controls this contestant", "Diese Person wird schon von einem anderen Gerät gesteuert"),
+    ("Someone else already took this seat", "Diesen Platz hat schon jemand anderes"),
+    ("I'm {}", "Ich bin {}"),
+    ("Devices", "Geräte"),
+    ("Your name", "Dein Name"),
+    ("Your answer", "Deine Antwort"),
+    // state.html
+    ("{} watching", "{} schauen zu"),
+    // ack.html
+    ("Got it", "Angekommen"),
+    ("It's not your turn", "Du bist gerade nicht dran"),
+    ("Too late, someone else is answering", "Zu spät, jemand anderes antwortet"),
+    // error.html and communication::Error
+    ("dismiss", "schließen"),
+    ("Do it anyway", "Trotzdem ausführen"),
+    ("Not possible right now", "Gerade nicht möglich"),
+    ("No such contestant", "Teilnehmer:in nicht gefunden"),
+    ("That contestant is disconnected", "Teilnehmer:in ist nicht verbunden"),
+    ("Only the contestant who buzzed can answer", "Nur wer gebuzzert hat, darf antworten"),
+    ("Nobody is playing", "Niemand spielt mit"),
+    ("No such clue", "Aufgabe nicht gefunden"),
+    ("No such category", "Kategorie nicht gefunden"),
+    ("The game has started, unlock it first", "Das Spiel hat begonnen, entsperre es zuerst"),
+    ("The game isn't locked", "Das Spiel ist nicht gesperrt"),
+    ("Say why the score changes", "Gib einen Grund für die Punkteänderung an"),
+    ("These score changes aren't possible: {}", "Diese Punkteänderungen sind nicht möglich: {}"),
+    ("Someone else already goes by that name", "Diesen Namen hat schon jemand anderes"),
+    ("Refuse names another contestant already has", "Namen ablehnen, die schon jemand anderes hat"),
+    ("Confirm wagers before the clue is shown", "Einsätze bestätigen, bevor die Aufgabe gezeigt wird"),
+    ("Only one attempt at exclusive clues", "Nur ein Versuch bei exklusiven Aufgaben"),
+    ("There is no wager to confirm", "Es gibt keinen Einsatz zum Bestätigen"),
+    ("Pending wager: {}", "Offener Einsatz: {}"),
+    ("Confirm", "Bestätigen"),
+    ("Cancel", "Verwerfen"),
+    ("Every contestant has to appear exactly once", "Alle Teilnehmer:innen müssen genau einmal vorkommen"),
+    ("Two categories or clues have the same id", "Zwei Kategorien oder Aufgaben haben dieselbe ID"),
+    ("Show earlier", "Weiter vorne zeigen"),
+    ("Reason", "Grund"),
+    ("Corrections are for this clue", "Korrekturen gelten für diese Aufgabe"),
+    ("Unlock the game", "Spiel entsperren"),
+    ("The board has no clues", "Das Spielbrett hat keine Aufgaben"),
+    ("All categories need the same number of clues", "Alle Kategorien brauchen gleich viele Aufgaben"),
+    ("No more than {} parts can follow a clue", "Auf eine Aufgabe können höchstens {} Teile folgen"),
+    ("A clue can't be empty", "Eine Aufgabe darf nicht leer sein"),
+    ("Invalid points", "Ungültige Punkte"),
+    ("The wager has to be between {} and {}", "Der Einsatz muss zwischen {} und {} liegen"),
+    ("This skips the clue without scoring it", "Damit wird die Aufgabe ohne Wertung übersprungen"),
+    ("You aren't allowed to do that", "Das darfst du nicht"),
+    ("Could not understand the input", "Eingabe nicht verstanden"),
+    ("Could not read the file", "Die Datei konnte nicht gelesen werden"),
+    ("Something went wrong", "Etwas ist schiefgelaufen"),
+    // state::Rejection
+    ("The game can't be changed while replaying a journal", "Während der Wiedergabe kann das Spiel nicht verändert werden"),
+    ("No journal is being replayed", "Es wird gerade nichts wiedergegeben"),
+    ("This name is not allowed", "Dieser Name ist nicht erlaubt"),
+    ("No name is waiting for approval", "Kein Name wartet auf Freigabe"),
+    ("The start isn't scheduled", "Der Start ist nicht geplant"),
+    ("The server is busy, try again", "Der Server ist ausgelastet, bitte nochmal versuchen"),
+    ("The game isn't running anymore", "Das Spiel läuft nicht mehr"),
+    ("There's no deadline for the lobby", "Für die Lobby gibt es keine Frist"),
+    ("The game is paused until the host resumes it", "Das Spiel ist pausiert, bis die Moderation es fortsetzt"),
+    ("The game isn't paused", "Das Spiel ist nicht pausiert"),
+];
+
+impl Locale {
+    /// A language tag like `de` or `de-CH`
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let language = tag.trim().split(['-', '_']).next()?.to_ascii_lowercase();
+        match language.as_str() {
+            "en" => Some(Self::En),
+            "de" => Some(Self::De),
+            _ => None,
+        }
+    }
+
+    pub fn tag(self) -> &'static str {
+        match self {
+            Self::En => "en",
+            Self::De => "de",
+        }
+    }
+
+    /// A `lang` parameter in a query string like `lang=de`
+    pub fn from_query(query: Option<&str>) -> Option<Self> {
+        query
+            .into_iter()
+            .flat_map(|q| q.split('&'))
+            .filter_map(|pair| pair.strip_prefix("lang="))
+            .find_map(Self::from_tag)
+    }
+
+    /// The `lang` query parameter wins over the `Accept-Language` header,
+    /// which is picked by its weights
+    pub fn negotiate(query: Option<&str>, accept_language: Option<&str>) -> Self {
+        if let Some(locale) = Self::from_query(query) {
+            return locale;
+        }
+        let mut accepted: Vec<(f32, Self)> = accept_language
+            .into_iter()
+            .flat_map(|header| header.split(','))
+            .filter_map(|range| {
+                let mut parts = range.split(';');
+                let locale = Self::from_tag(parts.next()?)?;
+                let weight = parts
+                    .find_map(|p| p.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.parse().ok())?;
+                Some((weight, locale))
+            })
+            .collect();
+        // stable, so equally weighted languages keep their order
+        accepted.sort_by(|a, b| b.0.total_cmp(&a.0));
+        accepted.first().map(|(_, locale)| *locale).unwrap_or_default()
+    }
+
+    pub fn translate(self, text: &str) -> &str {
+        match self {
+            Self::En => text,
+            Self::De => GERMAN
+                .iter()
+                .find(|(en, _)| *en == text)
+                .map_or(text, |(_, de)| de),
+        }
+    }
+
+    /// Translates `text` and fills its `{}` in order
+    pub fn format<T: Display>(self, text: &str, values: &[T]) -> String {
+        let mut values = values.iter();
+        let mut parts = self.translate(text).split("{}");
+        let mut formatted = parts.next().unwrap_or_default().to_owned();
+        for part in parts {
+            if let Some(value) = values.next() {
+                formatted.push_str(&value.to_string());
+            }
+            formatted.push_str(part);
+        }
+        formatted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negotiates_languages() {
+        assert_eq!(Locale::negotiate(None, None), Locale::En);
+        assert_eq!(Locale::negotiate(None, Some("de-DE,de;q=0.9,en;q=0.8")), Locale::De);
+        assert_eq!(Locale::negotiate(None, Some("fr, en;q=0.5, de;q=0.7")), Locale::De);
+        assert_eq!(Locale::negotiate(None, Some("fr")), Locale::En);
+        assert_eq!(Locale::negotiate(Some("lang=en"), Some("de")), Locale::En);
+        assert_eq!(Locale::negotiate(Some("x=1&lang=de_AT"), None), Locale::De);
+        assert_eq!(Locale::negotiate(Some("lang=fr"), Some("de")), Locale::De);
+    }
+
+    #[test]
+    fn formats_translations() {
+        assert_eq!(Locale::De.translate("Start Game!"), "Spiel starten!");
+        assert_eq!(Locale::De.translate("not translated"), "not translated");
+        assert_eq!(Locale::En.format("({} to {})", &[5, 500]), "(5 to 500)");
+        assert_eq!(Locale::De.format("({} to {})", &[5, 500]), "(5 bis 500)");
+        assert!(GERMAN.iter().all(|(en, de)| en.matches("{}").count() == de.matches("{}").count()));
+    }
+}
@@ -0,0 +1,117 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use rolling_file::{BasicRollingFileAppender, RollingConditionBasic};
+use tracing_subscriber::prelude::*;
+
+/// Log files are rotated once they reach this size
+const MAX_LOG_SIZE: u64 = 10 * 1024 * 1024;
+/// Rotated files are named `<log-file>.1` to `<log-file>.<n>`, older ones are
+/// deleted
+const MAX_LOG_FILES: usize = 5;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    #[default]
+    Pretty,
+    /// One object per line
+    Json,
+}
+
+/// From `--log-file <path>`, `--log-format json|pretty` and `--tokio-console`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogConfig {
+    /// Instead of stdout, rotated by size
+    pub file: Option<PathBuf>,
+    pub format: LogFormat,
+    /// Only available with the `tokio-console` feature
+    pub tokio_console: bool,
+}
+
+impl LogConfig {
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let value = |flag: &str| -> Result<Option<&String>, String> {
+            match args.iter().position(|a| a == flag) {
+                Some(i) => args.get(i + 1).map(Some).ok_or_else(|| format!("{} needs a value", flag)),
+                None => Ok(None),
+            }
+        };
+        let format = match value("--log-format")?.map(String::as_str) {
+            None | Some("pretty") => LogFormat::Pretty,
+            Some("json") => LogFormat::Json,
+            Some(other) => return Err(format!("--log-format has to be json or pretty, not {}", other)),
+        };
+        Ok(Self {
+            file: value("--log-file")?.map(PathBuf::from),
+            format,
+            tokio_console: args.iter().any(|a| a == "--tokio-console"),
+        })
+    }
+
+    /// Installs the global subscriber. `RUST_LOG` still applies.
+    pub fn init(self) -> Result<(), std::io::Error> {
+        let filter = tracing_subscriber::EnvFilter::from_default_env()
+            .add_directive("libaitfoaq_server=trace".parse().unwrap());
+        let writer = match &self.file {
+            Some(path) => tracing_subscriber::fmt::writer::BoxMakeWriter::new(Mutex::new(appender(path, MAX_LOG_SIZE)?)),
+            None => tracing_subscriber::fmt::writer::BoxMakeWriter::new(std::io::stdout),
+        };
+        let fmt = tracing_subscriber::fmt::layer().with_writer(writer).with_ansi(self.file.is_none());
+        let fmt = match self.format {
+            LogFormat::Pretty => fmt.boxed(),
+            LogFormat::Json => fmt.json().boxed(),
+        };
+        let registry = tracing_subscriber::registry().with(fmt.with_filter(filter));
+        #[cfg(feature = "tokio-console")]
+        let registry = registry.with(self.tokio_console.then(console_subscriber::spawn));
+        registry.init();
+        if self.tokio_console && cfg!(not(feature = "tokio-console")) {
+            tracing::warn!("--tokio-console needs the server to be built with the tokio-console feature");
+        }
+        Ok(())
+    }
+}
+
+fn appender(path: &Path, max_size: u64) -> Result<BasicRollingFileAppender, std::io::Error> {
+    BasicRollingFileAppender::new(path, RollingConditionBasic::new().max_size(max_size), MAX_LOG_FILES)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn args(args: &str) -> Vec<String> {
+        std::iter::once("server").chain(args.split_whitespace()).map(str::to_owned).collect()
+    }
+
+    #[test]
+    fn parses_flags() {
+        assert_eq!(LogConfig::from_args(&args("")), Ok(LogConfig::default()));
+        assert_eq!(
+            LogConfig::from_args(&args("--demo --log-file server.log --log-format json --tokio-console")),
+            Ok(LogConfig { file: Some(PathBuf::from("server.log")), format: LogFormat::Json, tokio_console: true }),
+        );
+        assert_eq!(LogConfig::from_args(&args("--log-format pretty")).unwrap().format, LogFormat::Pretty);
+        assert!(LogConfig::from_args(&args("--log-format yaml")).is_err());
+        assert!(LogConfig::from_args(&args("--log-file")).is_err());
+    }
+
+    #[test]
+    fn log_files_are_rotated() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("server.log");
+        let mut appender = appender(&path, 100).unwrap();
+        let line = [b'x'; 59];
+        for _ in 0..(MAX_LOG_FILES + 3) * 2 {
+            appender.write_all(&line).unwrap();
+            appender.write_all(b"\n").unwrap();
+        }
+        appender.flush().unwrap();
+        assert!(std::fs::metadata(&path).unwrap().len() <= 120);
+        for n in 1..=MAX_LOG_FILES {
+            assert!(dir.path().join(format!("server.log.{}", n)).exists(), "{}", n);
+        }
+        assert!(!dir.path().join(format!("server.log.{}", MAX_LOG_FILES + 1)).exists());
+    }
+}
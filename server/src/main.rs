@@ -1,36 +1,59 @@
 use askama_axum::Template;
 use axum::{
     async_trait,
-    extract::{ws::WebSocketUpgrade, ConnectInfo, State, FromRequestParts, FromRef},
+    extract::{ws::WebSocketUpgrade, ConnectInfo, DefaultBodyLimit, Multipart, Query, RawQuery, State, FromRequestParts, FromRef},
     http::{
         header,
+        HeaderValue,
         StatusCode,
+        Uri,
         request::Parts,
     },
-    response::IntoResponse,
-    routing::get,
-    Router,
+    response::{IntoResponse, Redirect, Response},
+    body::Bytes,
+    routing::{get, post},
+    Json, Router,
 };
-use tracing_subscriber::prelude::*;
 use state::StateChannelsAndToken;
-use std::{net::SocketAddr, path::PathBuf};
+use std::{net::SocketAddr, path::{Path, PathBuf}};
 use tokio_util::sync::CancellationToken;
 use tower_http::services::ServeDir;
 use machineid_rs::{IdBuilder, HWIDComponent, Encryption};
+use hmac::{Hmac, Mac};
 
+const ADMIN_COOKIE: &str = "aitfoaq_admin";
+const ADMIN_PROTOCOL_PREFIX: &str = "admin.";
+/// Served as `/board-assets`, boards refer to their media in there
+const BOARD_ASSETS: &str = "board-assets";
+/// Room for the largest upload and the rest of the form, see
+/// [board_assets::MAX_UPLOAD]
+const UPLOAD_LIMIT: usize = board_assets::MAX_UPLOAD + 64 * 1024;
+
+mod assets;
+mod board_assets;
 mod communication;
+mod export;
+mod i18n;
+mod logging;
+mod profile;
+mod replay;
+mod self_check;
 mod state;
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() {
-    tracing_subscriber::registry()
-        .with(console_subscriber::spawn())
-        .with(tracing_subscriber::fmt::layer())
-        .with(
-            tracing_subscriber::EnvFilter::from_default_env()
-                .add_directive("libaitfoaq_server=trace".parse().unwrap())
-        )
-        .init();
+    let args: Vec<String> = std::env::args().collect();
+    crate::logging::LogConfig::from_args(&args)
+        .unwrap_or_else(|e| panic!("usage: {}", e))
+        .init()
+        .expect("Could not open the log file");
+
+    // for clients in other languages, see [crate::communication::schemas]
+    if let Some(i) = args.iter().position(|a| a == "--dump-schema") {
+        let directory = PathBuf::from(args.get(i + 1).expect("usage: --dump-schema <dir>"));
+        crate::communication::dump_schemas(&directory).expect("Could not write the schemas");
+        return;
+    }
 
     let cancellation_token = CancellationToken::new();
 
@@ -43,26 +66,165 @@ async fn main() {
         .expect("Can't generate a admin token");
     admin_token.truncate(16);
 
-    let journal = PathBuf::from("./journal.jsonl");
-    let mut state = crate::state::State::with_journal_and_token(&journal, admin_token.clone()).expect("Could not load or create journal file");
+    let replay = args.iter()
+        .position(|a| a == "--replay")
+        .map(|i| PathBuf::from(args.get(i + 1).expect("usage: --replay <journal>")));
+
+    // loads a demo board into a fresh journal, see [libaitfoaq::state::Board::demo]
+    let demo = args.iter().any(|a| a == "--demo");
+    // keeps the game in memory only, it's gone once the server stops
+    let ephemeral = args.iter().any(|a| a == "--ephemeral");
+    // loaded by `--ephemeral` instead of the demo board
+    let board = args.iter()
+        .position(|a| a == "--board")
+        .map(|i| PathBuf::from(args.get(i + 1).expect("usage: --board <board.json>")));
+    // a phone and a handset may control the same contestant, see
+    // [crate::state::State::with_co_control]
+    let co_control = args.iter().any(|a| a == "--co-control");
+    // only the moderator sees a clue until it was fully shown, see
+    // [crate::state::State::with_obscured_clues]
+    let obscure_clues = args.iter().any(|a| a == "--obscure-clues");
+    // pause the game once no moderator was connected for this many seconds,
+    // see [crate::state::State::with_host_watch]
+    let pause_without_host = args.iter()
+        .position(|a| a == "--pause-without-host")
+        .map(|i| args.get(i + 1).and_then(|s| s.parse().ok()).expect("usage: --pause-without-host <seconds>"))
+        .map(std::time::Duration::from_secs);
+    // comma separated seconds before a lobby deadline to remind the
+    // moderator at, see [crate::state::State::with_lobby_reminders]
+    let lobby_reminders = args.iter()
+        .position(|a| a == "--lobby-reminders")
+        .map(|i| args.get(i + 1)
+            .and_then(|s| s.split(',').map(|s| s.trim().parse().ok()).collect::<Option<Vec<u64>>>())
+            .expect("usage: --lobby-reminders <seconds,...>"))
+        .map(|reminders| reminders.into_iter().map(std::time::Duration::from_secs).collect());
+    // how many requests may wait for the game, see
+    // [crate::state::State::with_queue_capacity]
+    let queue_capacity = args.iter()
+        .position(|a| a == "--queue-capacity")
+        .map(|i| args.get(i + 1).and_then(|s| s.parse().ok()).expect("usage: --queue-capacity <requests>"));
+    // refuse to start if the self-check finds problems, see [crate::self_check]
+    let strict_start = args.iter().any(|a| a == "--strict-start");
+    // `--results-dir <path>` and `--no-certificates`, also self-checked
+    let export = export_options(&args);
+    if ephemeral && replay.is_some() {
+        panic!("--ephemeral and --replay can't be combined");
+    }
+    let journal = replay.clone().unwrap_or_else(|| PathBuf::from("./journal.jsonl"));
+    // comma separated list of origins like `http://display.local:8080`, in
+    // addition to the server's own host
+    let allowed_origins: Vec<String> = std::env::var("ALLOWED_ORIGINS")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|o| !o.is_empty())
+        .map(str::to_owned)
+        .collect();
+
+    let pinging = crate::communication::Pinging::from_env()
+        .expect("Invalid ping settings");
+    let spectators = crate::communication::Spectators::from_env()
+        .expect("Invalid spectator settings");
+    // applied on top of the environment, see [crate::profile]
+    let profile = args.iter()
+        .position(|a| a == "--profile")
+        .map(|i| PathBuf::from(args.get(i + 1).expect("usage: --profile <file.toml>")))
+        .map(|path| crate::profile::Profile::load(&path).unwrap_or_else(|e| panic!("Invalid profile {}", e)));
+
+    // one word per line, names containing any of them are rejected
+    let blocked_words: Vec<String> = std::fs::read_to_string("./blocked-words.txt")
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim)
+        .filter(|w| !w.is_empty())
+        .map(str::to_owned)
+        .collect();
+
+    let mut state = if replay.is_some() {
+        crate::state::State::replaying(&journal, admin_token.clone())
+            .expect("Could not load journal file for replaying")
+    } else {
+        let state = if ephemeral {
+            crate::state::State::ephemeral(admin_token.clone())
+        } else {
+            crate::state::State::with_journal_and_token(&journal, admin_token.clone())
+                .expect("Could not load or create journal file")
+                .with_export(export)
+        };
+        let state = match lobby_reminders {
+            Some(reminders) => state.with_lobby_reminders(reminders),
+            None => state,
+        };
+        let state = state
+            .with_blocked_words(blocked_words)
+            .with_profiles(PathBuf::from(crate::profile::PROFILES))
+            .with_idleness(crate::state::Idleness::from_env().expect("Invalid idle settings"));
+        match pause_without_host {
+            Some(grace) => state.with_host_watch(grace),
+            None => state,
+        }
+    }
+    .with_pinging(pinging)
+    .with_spectators(spectators)
+    .with_co_control(co_control)
+    .with_obscured_clues(obscure_clues);
+    if let Some(capacity) = queue_capacity {
+        state = state.with_queue_capacity(capacity);
+    }
+
+    if let (Some(profile), None) = (profile, &replay) {
+        let server = profile.server;
+        if let Err(e) = state.use_profile(profile).await {
+            tracing::warn!(%e, "the profile's options can only be applied while preparing the game");
+            state.use_server_settings(server);
+        }
+    }
+
+    let demo = if ephemeral {
+        let board = match board {
+            Some(path) => load_board(&path).unwrap_or_else(|e| panic!("Invalid board {}: {}", path.display(), e)),
+            None => libaitfoaq::state::Board::demo(),
+        };
+        state.start_with(board).await.expect("Could not load the board")
+    } else {
+        demo && replay.is_none() && state.start_demo().await.expect("Could not start the demo")
+    };
+
+    let self_check = startup_check(&state, Path::new(BOARD_ASSETS), strict_start)
+        .unwrap_or_else(|e| panic!("{}", e));
 
     let app = Router::new()
         .route("/", get(index))
         .route("/websocket", get(websocket))
-        .route("/favicon.ico", get(favicon))
-        .route("/style.css", get(style))
-        .route("/Mallanna-Regular.ttf", get(mallanna))
-        .route("/htmx.min.js", get(htmx))
-        .route("/htmx.ws.js", get(htmx_ws))
-        .route("/confetti.min.js", get(confetti))
-        .nest_service("/board-assets", ServeDir::new("board-assets"))
-        .with_state(state.clonable_channels());
+        .merge(assets::routes())
+        .route("/results", get(results))
+        .route("/timeline", get(timeline))
+        .route("/print", get(print))
+        .route("/readyz", get(readyz))
+        .route("/metrics", get(metrics))
+        .route("/validate-board", post(validate_board))
+        .route("/board", get(get_board).put(put_board))
+        .route("/board-assets-index", get(board_assets_index))
+        .nest("/board-assets", Router::new()
+            .route("/", post(upload_board_asset).layer(DefaultBodyLimit::max(UPLOAD_LIMIT)))
+            .fallback_service(ServeDir::new(BOARD_ASSETS)))
+        .with_state(StateChannelsAndToken {
+            allowed_origins,
+            self_check: std::sync::Arc::new(self_check),
+            ..state.clonable_channels()
+        });
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
 
     println!();
     println!("Admin interface: http://{}/?{}", listener.local_addr().unwrap(), &admin_token);
     println!();
+    if demo {
+        println!("The board was loaded and the lobby is open. Join as a contestant");
+        println!("on http://{}/ in another browser, then start the game from the", listener.local_addr().unwrap());
+        println!("admin interface.");
+        println!();
+    }
 
     tokio::join!(
         async {
@@ -87,82 +249,365 @@ async fn main() {
     );
 }
 
+/// Logs what the self-check found, which is an error with `strict`
+fn startup_check(
+    state: &crate::state::State,
+    assets: &Path,
+    strict: bool,
+) -> Result<crate::self_check::SelfCheck, String> {
+    let self_check = state.self_check(assets);
+    self_check.log();
+    if strict && !self_check.is_ready() {
+        return Err(format!("Not starting, the self-check found {} problem(s)", self_check.problems.len()));
+    }
+    Ok(self_check)
+}
+
+/// Where the results are exported to, `./results` unless `--results-dir`
+/// says otherwise, with certificates unless `--no-certificates` is given
+fn export_options(args: &[String]) -> crate::export::ExportOptions {
+    let directory = args.iter()
+        .position(|a| a == "--results-dir")
+        .map(|i| PathBuf::from(args.get(i + 1).expect("usage: --results-dir <path>")))
+        .unwrap_or_else(|| PathBuf::from("./results"));
+    crate::export::ExportOptions {
+        directory,
+        certificates: !args.iter().any(|a| a == "--no-certificates"),
+    }
+}
+
+/// A board file for `--board`, checked like [put_board] does
+fn load_board(path: &Path) -> Result<libaitfoaq::state::Board, String> {
+    let board = std::fs::read(path).map_err(|e| e.to_string())?;
+    let board: libaitfoaq::state::Board = serde_json::from_slice(&board).map_err(|e| e.to_string())?;
+    board.validate().map_err(|e| format!("{:?}", e))?;
+    Ok(board)
+}
+
 #[derive(Template)]
 #[template(path = "index.html")]
-struct Index{token: Option<String>}
+struct Index {
+    /// Passed on to the websocket, so `/?lang=de` works without changing the
+    /// browser's languages
+    lang: Option<crate::i18n::Locale>,
+    /// Passed on to the websocket like `lang`
+    display: crate::communication::DisplayPreferences,
+}
 
-#[tracing::instrument(skip(admin))]
-async fn index(ExtractAdminToken(admin): ExtractAdminToken) -> Index {
-    Index{token: admin}
+impl Index {
+    /// Where the page connects to, with the parameters it was opened with
+    fn websocket(&self) -> String {
+        let mut query = self.display.query();
+        if let Some(lang) = self.lang {
+            query.insert(0, format!("lang={}", lang.tag()));
+        }
+        match query.is_empty() {
+            true => "/websocket".to_owned(),
+            false => format!("/websocket?{}", query.join("&")),
+        }
+    }
 }
 
-async fn favicon() -> impl IntoResponse {
-    (
-        StatusCode::OK,
-        [(header::CONTENT_TYPE, "image/x-icon")],
-        include_bytes!("../templates/favicon.ico"),
-    )
+/// Visiting `/?<admin-token>` trades the token for a cookie and redirects,
+/// so the token doesn't stay in the address bar and doesn't get sent again.
+#[tracing::instrument(skip_all)]
+async fn index(
+    RawQuery(query): RawQuery,
+    State(channels_and_token): axum::extract::State<StateChannelsAndToken>,
+) -> Response {
+    let token = channels_and_token.admin_token;
+    if query.as_deref() == Some(token.as_str()) {
+        let cookie = format!(
+            "{}={}; Path=/; HttpOnly; SameSite=Strict",
+            ADMIN_COOKIE,
+            sign_admin_cookie(&token),
+        );
+        ([(header::SET_COOKIE, cookie)], Redirect::to("/")).into_response()
+    } else {
+        Index {
+            lang: crate::i18n::Locale::from_query(query.as_deref()),
+            display: crate::communication::DisplayPreferences::from_query(query.as_deref()),
+        }.into_response()
+    }
 }
 
-async fn style() -> impl IntoResponse {
-    (
+#[tracing::instrument(skip(admin, channels_and_token))]
+async fn results(
+    ExtractAdminToken(admin): ExtractAdminToken,
+    State(channels_and_token): axum::extract::State<StateChannelsAndToken>,
+) -> impl IntoResponse {
+    if admin.is_none() {
+        return Err((StatusCode::FORBIDDEN, "admin token required".to_owned()));
+    }
+    let not_found = || (StatusCode::NOT_FOUND, "no results exported yet".to_owned());
+    let directory = channels_and_token.export_directory.ok_or_else(not_found)?;
+    let path = crate::export::latest(&directory)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(not_found)?;
+    let file = tokio::fs::read(&path)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let disposition = format!(
+        "attachment; filename=\"{}\"",
+        path.file_name().unwrap_or_default().to_string_lossy()
+    );
+    Ok((
         StatusCode::OK,
-        [(header::CONTENT_TYPE, "text/css")],
-        include_str!("../templates/style.css"),
-    )
+        [
+            (header::CONTENT_TYPE, "application/json".to_owned()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        file,
+    ))
 }
 
-async fn mallanna() -> impl IntoResponse {
-    (
-        StatusCode::OK,
-        [(header::CONTENT_TYPE, "font/ttf")],
-        include_bytes!("../templates/Mallanna-Regular.ttf"),
-    )
+/// How long the game and its clues took so far, see [state::Timeline]
+#[tracing::instrument(skip_all)]
+async fn timeline(
+    ExtractAdminToken(admin): ExtractAdminToken,
+    State(channels_and_token): axum::extract::State<StateChannelsAndToken>,
+) -> impl IntoResponse {
+    if admin.is_none() {
+        return Err((StatusCode::FORBIDDEN, "admin token required".to_owned()));
+    }
+    Ok(Json(channels_and_token.rx.borrow().timeline.clone()))
 }
 
-async fn htmx() -> impl IntoResponse {
-    (
-        StatusCode::OK,
-        [(header::CONTENT_TYPE, "application/javascript")],
-        include_str!("../templates/htmx.min.js"),
-    )
+#[derive(Template)]
+#[template(path = "print.html")]
+struct Print {
+    categories: Vec<libaitfoaq::print::PrintedCategory>,
+    /// For the points, see [libaitfoaq::state::Options::format_points]
+    options: libaitfoaq::state::Options,
 }
 
-async fn htmx_ws() -> impl IntoResponse {
-    (
-        StatusCode::OK,
-        [(header::CONTENT_TYPE, "application/javascript")],
-        include_str!("../templates/htmx.ws.js"),
-    )
+impl Print {
+    fn points(&self, points: &libaitfoaq::state::Points) -> String {
+        self.options.format_points(*points)
+    }
 }
 
-async fn confetti() -> impl IntoResponse {
-    (
-        StatusCode::OK,
-        [(header::CONTENT_TYPE, "application/javascript")],
-        include_str!("../templates/confetti.min.js"),
-    )
+/// An answer sheet of the loaded board for the moderator, see
+/// [libaitfoaq::print]
+#[tracing::instrument(skip_all)]
+async fn print(
+    ExtractAdminToken(admin): ExtractAdminToken,
+    State(channels_and_token): axum::extract::State<StateChannelsAndToken>,
+) -> impl IntoResponse {
+    if admin.is_none() {
+        return Err((StatusCode::FORBIDDEN, "admin token required".to_owned()));
+    }
+    let out = channels_and_token.rx.borrow();
+    Ok(Print { categories: out.game.board.printable(), options: out.game.options.clone() })
+}
+
+/// 503 if the self-check at the start found problems. Which ones only the
+/// moderator sees, they can give clues away, like the names of media files.
+#[tracing::instrument(skip_all)]
+async fn readyz(
+    ExtractAdminToken(admin): ExtractAdminToken,
+    State(channels_and_token): axum::extract::State<StateChannelsAndToken>,
+) -> impl IntoResponse {
+    let self_check = &channels_and_token.self_check;
+    let status = match self_check.is_ready() {
+        true => StatusCode::OK,
+        false => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    let problems = match admin {
+        Some(_) => serde_json::json!(self_check.problems),
+        None => serde_json::json!(self_check.problems.len()),
+    };
+    (status, Json(serde_json::json!({ "ready": self_check.is_ready(), "problems": problems })))
+}
+
+/// Gauges in the Prometheus text format, for anyone to scrape
+#[tracing::instrument(skip_all)]
+async fn metrics(State(channels_and_token): axum::extract::State<StateChannelsAndToken>) -> impl IntoResponse {
+    let tx = &channels_and_token.tx;
+    let gauges = [
+        ("aitfoaq_state_queue_depth", "Requests waiting for the game", crate::state::State::queue_depth(tx)),
+        ("aitfoaq_state_queue_capacity", "How many requests may wait for the game", tx.max_capacity()),
+    ];
+    let text: String = gauges.iter()
+        .map(|(name, help, value)| format!("# HELP {name} {help}\n# TYPE {name} gauge\n{name} {value}\n"))
+        .collect();
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], text)
+}
+
+/// Lints a board without loading it, so it can be checked before game night
+#[tracing::instrument(skip_all)]
+async fn validate_board(
+    ExtractAdminToken(admin): ExtractAdminToken,
+    board: Bytes,
+) -> impl IntoResponse {
+    if admin.is_none() {
+        return Err((StatusCode::FORBIDDEN, "admin token required".to_owned()));
+    }
+    Ok(Json(lint_board(&board, Path::new(BOARD_ASSETS))))
+}
+
+fn lint_board(json: &[u8], assets: &Path) -> libaitfoaq::lint::LintReport {
+    match serde_json::from_slice(json) {
+        Ok(board) => libaitfoaq::lint::lint(&board, Some(assets)),
+        Err(e) => libaitfoaq::lint::LintReport::unreadable(format!("Could not parse the board: {}", e)),
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct BoardQuery {
+    /// Marks all clues as unsolved and unpicked, to play the board again
+    #[serde(default)]
+    clean: bool,
+}
+
+/// The loaded board, e.g. to fix a typo and load it again with [put_board]
+#[tracing::instrument(skip(admin, channels_and_token))]
+async fn get_board(
+    ExtractAdminToken(admin): ExtractAdminToken,
+    Query(query): Query<BoardQuery>,
+    State(channels_and_token): axum::extract::State<StateChannelsAndToken>,
+) -> impl IntoResponse {
+    if admin.is_none() {
+        return Err((StatusCode::FORBIDDEN, "admin token required".to_owned()));
+    }
+    let mut board = channels_and_token.rx.borrow().game.board.clone();
+    if query.clean {
+        for clue in board.categories.iter_mut().flat_map(|c| c.clues.iter_mut()) {
+            clue.solved = false;
+            clue.value_revealed = false;
+        }
+    }
+    let json = serde_json::to_string_pretty(&board)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(([(header::CONTENT_TYPE, "application/json")], json))
+}
+
+/// The files boards can refer to, see [board_assets::BoardAssets::list]
+#[tracing::instrument(skip_all)]
+async fn board_assets_index(
+    ExtractAdminToken(admin): ExtractAdminToken,
+    State(channels_and_token): axum::extract::State<StateChannelsAndToken>,
+) -> impl IntoResponse {
+    if admin.is_none() {
+        return Err((StatusCode::FORBIDDEN, "admin token required".to_owned()));
+    }
+    let files = channels_and_token.board_assets.list()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(files))
+}
+
+/// Stores the first file of a multipart form, see
+/// [board_assets::BoardAssets::store]
+#[tracing::instrument(skip_all)]
+async fn upload_board_asset(
+    ExtractAdminToken(admin): ExtractAdminToken,
+    State(channels_and_token): axum::extract::State<StateChannelsAndToken>,
+    mut multipart: Multipart,
+) -> impl IntoResponse {
+    if admin.is_none() {
+        return Err((StatusCode::FORBIDDEN, "admin token required".to_owned()));
+    }
+    while let Some(field) = multipart.next_field().await.map_err(|e| (e.status(), e.body_text()))? {
+        let Some(name) = field.file_name().map(str::to_owned) else { continue };
+        let bytes = field.bytes().await.map_err(|e| (e.status(), e.body_text()))?;
+        let file = channels_and_token.board_assets.store(&name, &bytes).await.map_err(|e| {
+            let status = match e {
+                board_assets::UploadError::InvalidName(_) => StatusCode::BAD_REQUEST,
+                board_assets::UploadError::NotAllowed => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                board_assets::UploadError::Exists(_) => StatusCode::CONFLICT,
+                board_assets::UploadError::TooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+                board_assets::UploadError::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            (status, e.to_string())
+        })?;
+        tracing::info!(path = %file.path, size = file.size, "board asset uploaded");
+        return Ok((StatusCode::CREATED, Json(file)));
+    }
+    Err((StatusCode::BAD_REQUEST, "no file uploaded".to_owned()))
+}
+
+/// Loads a board that passes [libaitfoaq::state::Board::validate], as long
+/// as the game allows it
+#[tracing::instrument(skip_all)]
+async fn put_board(
+    ExtractAdminToken(admin): ExtractAdminToken,
+    State(channels_and_token): axum::extract::State<StateChannelsAndToken>,
+    board: Bytes,
+) -> impl IntoResponse {
+    if admin.is_none() {
+        return Err((StatusCode::FORBIDDEN, "admin token required".to_owned()));
+    }
+    let board: libaitfoaq::state::Board = serde_json::from_slice(&board)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    board.validate()
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, format!("{:?}", e)))?;
+    crate::state::State::send(libaitfoaq::events::Event::LoadBoard(board), &channels_and_token.tx)
+        .await
+        .map_err(|e| (StatusCode::CONFLICT, e.to_string()))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct WebsocketQuery {
+    /// Like subscribing right away, see [Scope](crate::communication::Scope)
+    #[serde(default)]
+    scope: crate::communication::Scope,
 }
 
 #[tracing::instrument(skip(ws, admin, channels_and_token))]
+#[allow(clippy::too_many_arguments)] // one per extractor
 async fn websocket(
     ConnectInfo(peer_address): ConnectInfo<SocketAddr>,
     ExtractUserAgent(user_agent): ExtractUserAgent,
+    _: CheckOrigin,
     ExtractAdminToken(admin): ExtractAdminToken,
+    ExtractLocale(locale): ExtractLocale,
     headers: header::HeaderMap,
+    Query(query): Query<WebsocketQuery>,
+    RawQuery(raw_query): RawQuery,
     ws: WebSocketUpgrade,
     State(channels_and_token): axum::extract::State<StateChannelsAndToken>,
 ) -> impl IntoResponse {
     tracing::info!(%peer_address, "new websocket connection");
 
+    let display = crate::communication::DisplayPreferences::from_query(raw_query.as_deref());
     let json = header::HeaderValue::from_static("application/json");
     let serializer = match headers.get(header::ACCEPT) {
-        Some(value) if value == json => crate::communication::Serializer::JSON,
-        _ => crate::communication::Serializer::HTML,
+        Some(value) if value == json => crate::communication::Serializer::Json,
+        _ => crate::communication::Serializer::Html,
     };
-    ws.on_upgrade(move |socket| {
-        crate::communication::player_handler(socket, peer_address, channels_and_token, admin.is_some(), serializer)
-    })
+    // browsers refuse the connection unless the requested protocol is echoed
+    let ws = match &admin {
+        Some(token) => ws.protocols([format!("{}{}", ADMIN_PROTOCOL_PREFIX, token)]),
+        None => ws,
+    };
+    let mut response = ws.on_upgrade(move |socket| {
+        crate::communication::player_handler(socket, peer_address, channels_and_token, admin.is_some(), serializer, query.scope, locale, display)
+    });
+    // clients can tell before the first frame whether they understand us
+    response.headers_mut().insert(
+        crate::communication::PROTOCOL_HEADER,
+        HeaderValue::from(crate::communication::PROTOCOL_VERSION),
+    );
+    response
+}
+
+/// See [crate::i18n::Locale::negotiate], never rejects
+struct ExtractLocale(crate::i18n::Locale);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ExtractLocale
+where
+    S: Send + Sync,
+{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let accept_language = parts.headers.get(header::ACCEPT_LANGUAGE).and_then(|value| value.to_str().ok());
+        Ok(ExtractLocale(crate::i18n::Locale::negotiate(parts.uri.query(), accept_language)))
+    }
 }
 
 struct ExtractUserAgent(header::HeaderValue);
@@ -183,6 +628,9 @@ where
     }
 }
 
+/// The admin token, if presented either as a `admin.<token>` websocket
+/// sub-protocol or through the cookie set by [index]. The query string isn't
+/// accepted, it ends up in access logs.
 struct ExtractAdminToken(Option<String>);
 
 #[async_trait]
@@ -195,10 +643,351 @@ where
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
         let admin_token = StateChannelsAndToken::from_ref(state).admin_token;
-        if Some(admin_token.as_str()) == parts.uri.query() {
+        let protocol = parts.headers
+            .get_all(header::SEC_WEBSOCKET_PROTOCOL)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .filter_map(|p| p.trim().strip_prefix(ADMIN_PROTOCOL_PREFIX))
+            .any(|t| t == admin_token);
+        let cookie = parts.headers
+            .get_all(header::COOKIE)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(';'))
+            .filter_map(|c| c.trim().strip_prefix(ADMIN_COOKIE)?.strip_prefix('='))
+            .any(|c| verify_admin_cookie(&admin_token, c));
+        if protocol || cookie {
             Ok(Self(Some(admin_token)))
         } else {
             Ok(Self(None))
         }
     }
 }
+
+fn admin_cookie_mac(token: &str) -> Hmac<sha2::Sha256> {
+    let mut mac = Hmac::<sha2::Sha256>::new_from_slice(token.as_bytes())
+        .expect("HMAC can take a key of any size");
+    mac.update(ADMIN_COOKIE.as_bytes());
+    mac
+}
+
+fn sign_admin_cookie(token: &str) -> String {
+    hex::encode(admin_cookie_mac(token).finalize().into_bytes())
+}
+
+fn verify_admin_cookie(token: &str, cookie: &str) -> bool {
+    hex::decode(cookie).is_ok_and(|c| admin_cookie_mac(token).verify_slice(&c).is_ok())
+}
+
+/// Rejects websocket upgrades from foreign web pages. Requests without an
+/// `Origin` header aren't from browsers (e.g. the rpi-controller) and pass.
+struct CheckOrigin;
+
+#[async_trait]
+impl<S> FromRequestParts<S> for CheckOrigin
+where
+    StateChannelsAndToken: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let allowed_origins = StateChannelsAndToken::from_ref(state).allowed_origins;
+        let origin = parts.headers.get(header::ORIGIN);
+        let host = parts.headers.get(header::HOST);
+        if origin_allowed(origin, host, &allowed_origins) {
+            Ok(Self)
+        } else {
+            tracing::warn!(?origin, ?host, "rejected websocket from foreign origin");
+            Err((StatusCode::FORBIDDEN, "`Origin` not allowed"))
+        }
+    }
+}
+
+fn origin_allowed(origin: Option<&HeaderValue>, host: Option<&HeaderValue>, allowed: &[String]) -> bool {
+    let Some(origin) = origin else { return true };
+    let Ok(origin) = origin.to_str() else { return false };
+    if allowed.iter().any(|a| a.trim_end_matches('/') == origin) {
+        return true;
+    }
+    let authority = origin.parse::<Uri>().ok().and_then(|u| u.authority().cloned());
+    match (authority, host.and_then(|h| h.to_str().ok())) {
+        (Some(authority), Some(host)) => authority.as_str().eq_ignore_ascii_case(host),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn origins() {
+        let host = HeaderValue::from_static("gamehost:3000");
+        let allowed = vec!["http://display.local:8080".to_owned()];
+        let check = |origin: Option<&'static str>| {
+            origin_allowed(origin.map(HeaderValue::from_static).as_ref(), Some(&host), &allowed)
+        };
+        assert!(check(Some("http://gamehost:3000")));
+        assert!(check(Some("http://display.local:8080")));
+        assert!(check(None));
+        assert!(!check(Some("http://evil.example")));
+        assert!(!check(Some("http://gamehost:3001")));
+        assert!(!check(Some("null")));
+    }
+
+    #[test]
+    fn answer_sheets_escape_the_board() {
+        let mut board = libaitfoaq::state::Board::demo();
+        board.categories[0].clues[0].clue = "<img src=\"board-assets/x.png\">\n  in a\nbox".to_owned();
+        board.categories[0].clues[0].hint = String::new();
+        board.categories[0].clues[0].points = 1500;
+        let options = libaitfoaq::state::Options {
+            points_format: libaitfoaq::state::PointsFormat::Thousands,
+            ..Default::default()
+        };
+        let html = Print { categories: board.printable(), options }.render().unwrap();
+        assert!(html.contains("&lt;img src=&quot;board-assets/x.png&quot;&gt; in a box"), "{}", html);
+        assert!(html.contains("1,500"));
+        assert_eq!(html.matches("<section>").count(), board.categories.len());
+        assert_eq!(html.matches("Hint:").count(), board.categories.iter().flat_map(|c| &c.clues).count() - 1);
+    }
+
+    #[tokio::test]
+    async fn board_assets_are_uploaded_by_admins() {
+        use axum::extract::FromRequest;
+        let dir = tempfile::tempdir().unwrap();
+        let state = crate::state::State::ephemeral("token".to_owned());
+        let channels = StateChannelsAndToken {
+            board_assets: std::sync::Arc::new(board_assets::BoardAssets::new(dir.path().to_owned())),
+            ..state.clonable_channels()
+        };
+        let form = |filename: &str| {
+            let body = format!(
+                "--boundary\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\nContent-Type: image/png\r\n\r\npng\r\n--boundary--\r\n",
+                filename,
+            );
+            let request = axum::http::Request::builder()
+                .method("POST")
+                .header(header::CONTENT_TYPE, "multipart/form-data; boundary=boundary")
+                .body(axum::body::Body::from(body))
+                .unwrap();
+            Multipart::from_request(request, &())
+        };
+        let upload = |admin: Option<&str>, multipart| {
+            upload_board_asset(ExtractAdminToken(admin.map(str::to_owned)), State(channels.clone()), multipart)
+        };
+        let status = |response: Response| response.status();
+
+        let response = upload(None, form("logo.png").await.unwrap()).await.into_response();
+        assert_eq!(status(response), StatusCode::FORBIDDEN);
+        let response = upload(Some("token"), form("logo.png").await.unwrap()).await.into_response();
+        assert_eq!(status(response), StatusCode::CREATED);
+        assert_eq!(std::fs::read(dir.path().join("logo.png")).unwrap(), b"png");
+        for (filename, expected) in [
+            ("logo.png", StatusCode::CONFLICT),
+            ("../logo.png", StatusCode::BAD_REQUEST),
+            ("index.html", StatusCode::UNSUPPORTED_MEDIA_TYPE),
+        ] {
+            let response = upload(Some("token"), form(filename).await.unwrap()).await.into_response();
+            assert_eq!(status(response), expected, "{}", filename);
+        }
+
+        let response = board_assets_index(ExtractAdminToken(None), State(channels.clone())).await.into_response();
+        assert_eq!(status(response), StatusCode::FORBIDDEN);
+        let response = board_assets_index(ExtractAdminToken(Some("token".to_owned())), State(channels.clone())).await.into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let listing: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(listing[0]["path"], "logo.png");
+        assert_eq!(listing[0]["content_type"], "image/png");
+        assert_eq!(listing.as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn metrics_show_the_queue() {
+        let state = crate::state::State::ephemeral("token".to_owned()).with_queue_capacity(3);
+        let response = metrics(State(state.clonable_channels())).await.into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let text = String::from_utf8(body.to_vec()).unwrap();
+        assert!(text.contains("# TYPE aitfoaq_state_queue_depth gauge\naitfoaq_state_queue_depth 0\n"), "{}", text);
+        assert!(text.contains("\naitfoaq_state_queue_capacity 3\n"), "{}", text);
+    }
+
+    #[tokio::test]
+    async fn results_are_missing_until_exported() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = crate::state::State::ephemeral("token".to_owned());
+        let channels = StateChannelsAndToken {
+            export_directory: Some(dir.path().join("results")),
+            ..state.clonable_channels()
+        };
+        let get = |admin: Option<&str>| results(ExtractAdminToken(admin.map(str::to_owned)), State(channels.clone()));
+
+        assert_eq!(get(None).await.into_response().status(), StatusCode::FORBIDDEN);
+        assert_eq!(get(Some("token")).await.into_response().status(), StatusCode::NOT_FOUND);
+        std::fs::create_dir(dir.path().join("results")).unwrap();
+        assert_eq!(get(Some("token")).await.into_response().status(), StatusCode::NOT_FOUND);
+        std::fs::write(dir.path().join("results/results-2024-04-24.json"), b"{}").unwrap();
+        let response = get(Some("token")).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap(), "{}");
+    }
+
+    #[test]
+    fn admin_cookie() {
+        let cookie = sign_admin_cookie("token");
+        assert!(verify_admin_cookie("token", &cookie));
+        assert!(!verify_admin_cookie("other", &cookie));
+        assert!(!verify_admin_cookie("token", "token"));
+    }
+
+    #[test]
+    fn boards_are_linted() {
+        let assets = Path::new("../board-assets");
+        let board = std::fs::read("../example-boards/webengdus-2024-04-24-round1.json").unwrap();
+        let report = lint_board(&board, assets);
+        assert!(report.errors.iter().all(|e| e.rule == libaitfoaq::lint::Rule::MissingMedia));
+
+        let report = lint_board(b"{\"categories\": 3}", assets);
+        assert_eq!(report.errors.len(), 1);
+        assert!(report.errors[0].message.starts_with("Could not parse the board"));
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["errors"][0]["rule"], "invalid");
+        assert_eq!(json["warnings"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn strict_starts_need_every_asset() {
+        use libaitfoaq::{events::Event, state::Board};
+        let dir = tempfile::tempdir().unwrap();
+        let assets = dir.path().join("board-assets");
+        std::fs::create_dir(&assets).unwrap();
+        let mut board = Board::demo();
+        board.categories[1].clues[2].clue = r#"<img src="/board-assets/deleted.png">"#.to_owned();
+        let journal = dir.path().join("journal.jsonl");
+        std::fs::write(&journal, serde_json::to_string(&Event::LoadBoard(board)).unwrap() + "\n").unwrap();
+
+        let state = crate::state::State::with_journal_and_token(&journal, "token".to_owned()).unwrap();
+        let self_check = startup_check(&state, &assets, false).unwrap();
+        let json = serde_json::to_value(&self_check).unwrap();
+        assert_eq!(json["problems"], serde_json::json!([
+            {"check": "media", "clue": [1, 2], "message": "/board-assets/deleted.png doesn't exist"},
+        ]));
+        let error = startup_check(&state, &assets, true).unwrap_err();
+        assert!(error.contains("1 problem"), "{}", error);
+
+        std::fs::write(assets.join("deleted.png"), b"").unwrap();
+        assert!(startup_check(&state, &assets, true).unwrap().is_ready());
+    }
+
+    #[test]
+    fn results_are_exported_where_told() {
+        let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        let export = export_options(&args(&["server"]));
+        assert_eq!(export.directory, PathBuf::from("./results"));
+        assert!(export.certificates);
+
+        let dir = tempfile::tempdir().unwrap();
+        let taken = dir.path().join("taken");
+        std::fs::write(&taken, b"").unwrap();
+        let export = export_options(&args(&["server", "--results-dir", taken.to_str().unwrap(), "--no-certificates"]));
+        assert_eq!(export.directory, taken);
+        assert!(!export.certificates);
+        // the self-check looks at the same directory
+        let journal = dir.path().join("journal.jsonl");
+        let state = crate::state::State::with_journal_and_token(&journal, "token".to_owned()).unwrap().with_export(export);
+        let error = startup_check(&state, dir.path(), true).unwrap_err();
+        assert!(error.contains("1 problem"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn boards_round_trip() {
+        use libaitfoaq::{events::Event, state::Board};
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("journal.jsonl");
+        let fresh_journal = dir.path().join("fresh.jsonl");
+        let mut state = crate::state::State::with_journal_and_token(&journal, "token".to_owned()).unwrap();
+        let mut fresh = crate::state::State::with_journal_and_token(&fresh_journal, "token".to_owned()).unwrap();
+        let (channels, fresh_channels) = (state.clonable_channels(), fresh.clonable_channels());
+        let cancellation_token = CancellationToken::new();
+        let admin = || ExtractAdminToken(Some("token".to_owned()));
+        let body = |response: Response| async { axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap() };
+
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            fresh.process(cancellation_token.clone()),
+            async {
+                let tx = &channels.tx;
+                for event in [
+                    Event::LoadBoard(Board::demo()),
+                    Event::OpenLobby,
+                    Event::ConnectContestant { name_hint: "alice".to_owned() },
+                    Event::StartGame,
+                    Event::Pick { clue: (0, 0).into() },
+                    Event::FinishClue { confirmed: false },
+                    Event::FinishClue { confirmed: false },
+                ] {
+                    crate::state::State::send(event, tx).await.unwrap();
+                }
+
+                let get = |clean| get_board(admin(), Query(BoardQuery { clean }), State(channels.clone()));
+                let played: Board = serde_json::from_slice(&body(get(false).await.into_response()).await).unwrap();
+                assert!(played.categories[0].clues[0].solved);
+                let clean = body(get(true).await.into_response()).await;
+                assert_eq!(serde_json::from_slice::<Board>(&clean).unwrap(), Board::demo());
+                let response = get_board(ExtractAdminToken(None), Query(BoardQuery { clean: true }), State(channels.clone())).await;
+                assert_eq!(response.into_response().status(), StatusCode::FORBIDDEN);
+
+                // the game is locked
+                let response = put_board(admin(), State(channels.clone()), clean.clone()).await.into_response();
+                assert_eq!(response.status(), StatusCode::CONFLICT);
+                let response = put_board(admin(), State(fresh_channels.clone()), Bytes::from_static(b"{\"categories\": []}")).await;
+                assert_eq!(response.into_response().status(), StatusCode::UNPROCESSABLE_ENTITY);
+                let response = put_board(admin(), State(fresh_channels.clone()), clean).await;
+                assert_eq!(response.into_response().status(), StatusCode::NO_CONTENT);
+                assert_eq!(fresh_channels.rx.borrow().game.board, Board::demo());
+
+                cancellation_token.cancel();
+            },
+        );
+    }
+
+    #[tokio::test]
+    async fn upgrades_announce_the_protocol() {
+        use futures_util::StreamExt;
+        use tokio_tungstenite::tungstenite::{client::IntoClientRequest, Message};
+        let mut state = crate::state::State::ephemeral("token".to_owned());
+        let app = Router::new()
+            .route("/websocket", get(websocket))
+            .with_state(state.clonable_channels());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let cancellation_token = CancellationToken::new();
+
+        tokio::join!(
+            async {
+                axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+                    .with_graceful_shutdown(cancellation_token.clone().cancelled_owned())
+                    .await
+                    .unwrap();
+            },
+            state.process(cancellation_token.clone()),
+            async {
+                let mut request = format!("ws://{}/websocket", address).into_client_request().unwrap();
+                request.headers_mut().insert(header::ACCEPT, HeaderValue::from_static("application/json"));
+                request.headers_mut().insert(header::USER_AGENT, HeaderValue::from_static("test"));
+                let (mut socket, response) = tokio_tungstenite::connect_async(request).await.unwrap();
+                let announced = &response.headers()[crate::communication::PROTOCOL_HEADER];
+                assert_eq!(announced, &crate::communication::PROTOCOL_VERSION.to_string());
+
+                let Some(Ok(Message::Text(frame))) = socket.next().await else { panic!("no state") };
+                let frame: serde_json::Value = serde_json::from_str(&frame).unwrap();
+                assert_eq!(frame["connection"]["protocol_version"], crate::communication::PROTOCOL_VERSION);
+                assert_eq!(frame["connection"]["server_version"], env!("CARGO_PKG_VERSION"));
+                drop(socket);
+                cancellation_token.cancel();
+            },
+        );
+    }
+}
\ No newline at end of file
@@ -0,0 +1,163 @@
+//! Settings for a recurring format of the game, like a kids' or a pub quiz,
+//! read from a TOML file with an `[options]` and a `[server]` table. See
+//! `--profile` and [Input::ApplyProfile](crate::communication).
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+use libaitfoaq::state::Options;
+use serde::Deserialize;
+
+use crate::communication::Pinging;
+
+/// Where `<name>.toml` is looked for when a profile is applied by name
+pub const PROFILES: &str = "./profiles";
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Profile {
+    /// Applied through [Event::Settings](libaitfoaq::events::Event::Settings)
+    /// so they are journaled. Unset keys keep their defaults.
+    pub options: Options,
+    pub server: ServerSettings,
+}
+
+/// Knobs of the server rather than the game, unset ones stay as they are
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerSettings {
+    /// See [Pinging]
+    pub ping_interval_ms: Option<u64>,
+    pub ping_window_ms: Option<u64>,
+    pub ping_misses: Option<usize>,
+    /// See [Spectators](crate::communication::Spectators)
+    pub spectator_hysteresis: Option<usize>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawProfile {
+    // checked key by key, [Options] itself accepts anything
+    options: BTreeMap<String, serde_json::Value>,
+    server: ServerSettings,
+}
+
+impl Profile {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// `name` is the file name without `.toml`, anything that looks like a
+    /// path is refused
+    pub fn find(directory: &Path, name: &str) -> Result<Self, String> {
+        if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+            return Err(format!("invalid profile name {:?}", name));
+        }
+        Self::load(&directory.join(format!("{}.toml", name)))
+    }
+
+    /// The profiles in `directory` by name, sorted
+    pub fn names(directory: &Path) -> Vec<String> {
+        let Ok(entries) = std::fs::read_dir(directory) else { return vec![] };
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                (path.extension()? == "toml").then(|| path.file_stem()?.to_str().map(str::to_owned)).flatten()
+            })
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Errors name the offending key
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let raw: RawProfile = basic_toml::from_str(text).map_err(|e| e.to_string())?;
+        let serde_json::Value::Object(known) = serde_json::to_value(Options::default()).map_err(|e| e.to_string())? else {
+            unreachable!("options are a struct");
+        };
+        for (key, value) in &raw.options {
+            if !known.contains_key(key) {
+                return Err(format!("unknown key `options.{}`", key));
+            }
+            let single = serde_json::Value::Object([(key.clone(), value.clone())].into_iter().collect());
+            serde_json::from_value::<Options>(single).map_err(|e| format!("invalid `options.{}`: {}", key, e))?;
+        }
+        let options = serde_json::Value::Object(raw.options.into_iter().collect());
+        Ok(Self {
+            options: serde_json::from_value(options).map_err(|e| format!("invalid `options`: {}", e))?,
+            server: raw.server,
+        })
+    }
+}
+
+impl ServerSettings {
+    /// With the same lower bounds as [Pinging::from_env]
+    pub fn pinging(&self, mut pinging: Pinging) -> Pinging {
+        if let Some(ms) = self.ping_interval_ms {
+            pinging.interval = Duration::from_millis(ms.max(1));
+        }
+        if let Some(ms) = self.ping_window_ms {
+            pinging.window = Duration::from_millis(ms);
+        }
+        if let Some(misses) = self.ping_misses {
+            pinging.misses = misses.max(1);
+        }
+        pinging
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KIDS: &str = r#"
+        [options]
+        approve_names = true
+        hide_values = true
+        auto_reveal_hint_after = 1
+
+        [server]
+        ping_interval_ms = 500
+        ping_misses = 0
+        spectator_hysteresis = 5
+    "#;
+
+    #[test]
+    fn parses_profiles() {
+        let profile = Profile::parse(KIDS).unwrap();
+        assert_eq!(profile.options, Options {
+            approve_names: true,
+            hide_values: true,
+            auto_reveal_hint_after: Some(1),
+            ..Options::default()
+        });
+        assert_eq!(profile.server.spectator_hysteresis, Some(5));
+        assert_eq!(profile.server.pinging(Pinging::default()), Pinging {
+            interval: Duration::from_millis(500),
+            misses: 1,
+            ..Pinging::default()
+        });
+        assert_eq!(Profile::parse("").unwrap(), Profile::default());
+
+        let error = |text: &str| Profile::parse(text).unwrap_err();
+        assert!(error("[options]\nhide_value = true").contains("options.hide_value"));
+        assert!(error("[options]\nconfirm_skip = 1").contains("options.confirm_skip"));
+        let server = error("[server]\nping_misses = \"3\"");
+        assert!(server.contains("server.ping_misses"), "{}", server);
+        assert!(error("[timers]").contains("timers"));
+    }
+
+    #[test]
+    fn profiles_are_found_by_name() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("kids.toml"), KIDS).unwrap();
+        std::fs::write(dir.path().join("pub.toml"), "[options]\nconfirm_skip = true").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "").unwrap();
+        assert_eq!(Profile::names(dir.path()), ["kids", "pub"]);
+        assert!(Profile::find(dir.path(), "pub").unwrap().options.confirm_skip);
+        assert!(Profile::find(dir.path(), "corporate").unwrap_err().contains("corporate.toml"));
+        assert!(Profile::find(dir.path(), "../kids").is_err());
+        assert!(Profile::names(&dir.path().join("missing")).is_empty());
+    }
+}
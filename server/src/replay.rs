@@ -0,0 +1,65 @@
+use libaitfoaq::{events::Event, state::GameState, Error as GameError, Game};
+
+#[derive(Debug, Clone, Copy)]
+pub enum ReplayStep {
+    Next,
+    Prev,
+    Seek(usize),
+}
+
+/// A finished journal that can be stepped through. The cursor is the number
+/// of events applied, so 0 is a fresh game and `len()` the end of the journal.
+#[derive(Debug)]
+pub struct Replay {
+    events: Vec<Event>,
+    cursor: usize,
+    /// See [Game::accept_missing_reasons]
+    missing_reasons: bool,
+}
+
+impl Replay {
+    pub fn new(events: Vec<Event>) -> Self {
+        Self { events, cursor: 0, missing_reasons: false }
+    }
+
+    /// For journals written before score changes had a reason
+    pub fn with_missing_reasons(mut self, missing_reasons: bool) -> Self {
+        self.missing_reasons = missing_reasons;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Moves the cursor, staying within the journal, and returns the state at
+    /// the new position
+    pub fn step(&mut self, step: ReplayStep) -> Result<GameState, GameError> {
+        self.cursor = match step {
+            ReplayStep::Next => self.cursor + 1,
+            ReplayStep::Prev => self.cursor.saturating_sub(1),
+            ReplayStep::Seek(index) => index,
+        }
+        .min(self.len());
+        Ok(self.game()?.get_game_state())
+    }
+
+    /// Rebuilds the game up to the cursor from scratch. This is the place to
+    /// add cached snapshots should long journals become too slow. Nobody
+    /// plays along with a replay, so no contestant is connected.
+    pub fn game(&self) -> Result<Game, GameError> {
+        let mut game = Game::new();
+        if self.missing_reasons {
+            game.accept_missing_reasons();
+        }
+        for event in &self.events[..self.cursor] {
+            game.apply_ref(event)?;
+        }
+        game.mark_all_contestants_as_disconnected();
+        Ok(game)
+    }
+}
@@ -0,0 +1,115 @@
+//! What is checked once the journal is loaded, before anyone connects. A
+//! board whose media was deleted since it was loaded still plays, but every
+//! client shows broken images, so it's better found before the show. See
+//! `--strict-start` to not start at all then.
+
+use std::path::Path;
+
+use libaitfoaq::lint::{self, Rule};
+use libaitfoaq::state::{Board, ClueHandle};
+use serde::Serialize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Check {
+    /// See [Board::validate]
+    Board,
+    /// Media the board refers to, but that isn't in the assets directory
+    Media,
+    /// Events couldn't be journaled
+    Journal,
+    /// Results couldn't be exported
+    Export,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Problem {
+    pub check: Check,
+    pub clue: Option<ClueHandle>,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct SelfCheck {
+    pub problems: Vec<Problem>,
+}
+
+impl SelfCheck {
+    /// Nothing is checked that isn't used, like the journal of an ephemeral
+    /// game. A board without categories wasn't loaded yet.
+    pub fn run(board: &Board, assets: &Path, journal: Option<&Path>, export: Option<&Path>) -> Self {
+        let mut problems = vec![];
+        if !board.categories.is_empty() {
+            problems.extend(lint::lint(board, Some(assets)).errors.into_iter().map(|finding| Problem {
+                check: match finding.rule {
+                    Rule::MissingMedia => Check::Media,
+                    _ => Check::Board,
+                },
+                clue: finding.clue,
+                message: finding.message,
+            }));
+        }
+        if let Some(Err(e)) = journal.map(|journal| std::fs::OpenOptions::new().append(true).open(journal)) {
+            problems.push(Problem { check: Check::Journal, clue: None, message: e.to_string() });
+        }
+        if let Some(Err(e)) = export.map(writable) {
+            problems.push(Problem { check: Check::Export, clue: None, message: e.to_string() });
+        }
+        Self { problems }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    pub fn log(&self) {
+        for problem in &self.problems {
+            tracing::error!(check = ?problem.check, clue = ?problem.clue, message = %problem.message, "self-check failed");
+        }
+    }
+}
+
+/// Creates the directory if needed, like the export does, and a file in it
+fn writable(directory: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(directory)?;
+    let probe = directory.join(".self-check");
+    std::fs::write(&probe, b"")?;
+    std::fs::remove_file(probe)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_media_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let assets = dir.path().join("board-assets");
+        std::fs::create_dir(&assets).unwrap();
+        std::fs::write(assets.join("here.png"), b"").unwrap();
+        let mut board = Board::demo();
+        board.categories[0].clues[0].clue = r#"<img src="board-assets/here.png">"#.to_owned();
+        board.categories[0].clues[1].hint = r#"<img src="board-assets/gone.png">"#.to_owned();
+        let journal = dir.path().join("journal.jsonl");
+        std::fs::write(&journal, b"").unwrap();
+        let export = dir.path().join("results");
+
+        let report = SelfCheck::run(&board, &assets, Some(&journal), Some(&export));
+        assert_eq!(report.problems, [Problem {
+            check: Check::Media,
+            clue: Some((0, 1)),
+            message: "board-assets/gone.png doesn't exist".to_owned(),
+        }]);
+        assert!(!report.is_ready());
+        assert!(export.is_dir());
+        assert_eq!(std::fs::read_dir(&export).unwrap().count(), 0);
+
+        // nothing loaded, nothing to look for
+        let report = SelfCheck::run(&Board { categories: vec![] }, &assets, None, None);
+        assert!(report.is_ready());
+        // somewhere that can't be written to
+        let report = SelfCheck::run(&Board::demo(), &assets, Some(&dir.path().join("missing/journal.jsonl")), Some(&journal));
+        let checks: Vec<Check> = report.problems.iter().map(|p| p.check).collect();
+        assert_eq!(checks, [Check::Journal, Check::Export]);
+    }
+}
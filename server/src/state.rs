@@ -1,21 +1,388 @@
-use tokio::sync::{mpsc, watch, oneshot};
+use tokio::sync::{mpsc, mpsc::error::SendTimeoutError, watch, oneshot};
+use tokio::time::{interval, sleep_until, Duration, Instant};
 use tokio_util::sync::CancellationToken;
 use thiserror::Error;
 
-use libaitfoaq::{events::Event, state::GameState, Error as GameError, Game};
+use libaitfoaq::{events::Event, state::{Board, ClueHandle, ContestantHandle, GamePhase, GameState}, Error as GameError, Game};
 
-use std::path::Path;
+use crate::communication::Fragments;
+use crate::export::{self, ExportOptions, Results};
+use crate::profile::{Profile, ServerSettings};
+use crate::replay::{Replay, ReplayStep};
+use crate::self_check::SelfCheck;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
 use std::io::Write;
+use std::time::SystemTime;
+
+const MAX_NAME_LENGTH: usize = 32;
+/// How many [Request]s may wait for the game, see
+/// [State::with_queue_capacity]
+const QUEUE_CAPACITY: usize = 8;
+/// How long a [Request] waits for room in a full queue, see
+/// [SendError::QueueFull]
+const QUEUE_TIMEOUT: Duration = Duration::from_secs(2);
+/// How often the state is resent while counting down to the start
+const COUNTDOWN_REFRESH: Duration = Duration::from_secs(1);
+/// When the moderator is reminded of a [LobbyDeadline] by default, see
+/// [State::with_lobby_reminders]
+const LOBBY_REMINDERS: [Duration; 2] = [Duration::from_secs(120), Duration::from_secs(30)];
+/// How often contestants are checked for [Idleness]
+const IDLE_CHECK: Duration = Duration::from_secs(1);
+/// Longer without any event is a pause or a server restart, see
+/// [Timeline::gaps]
+const GAP: Duration = Duration::from_secs(15 * 60);
+/// How many of the last applied events [Out::recent] keeps
+const RECENT_EVENTS: usize = 10;
+
+#[derive(Clone, Debug)]
+pub struct Out {
+    pub game: GameState,
+    /// Names contestants suggested for themselves, waiting for the moderator
+    pub pending_names: BTreeMap<ContestantHandle, String>,
+    pub scheduled_start: Option<ScheduledStart>,
+    pub lobby_deadline: Option<LobbyDeadline>,
+    /// What [Request::ApplyProfile] can choose from
+    pub profiles: Vec<String>,
+    /// Contestants without input for a while, see [Idleness]
+    pub idle: BTreeSet<ContestantHandle>,
+    /// How many connections control each contestant, more than one only
+    /// with [State::with_co_control]
+    pub controllers: BTreeMap<ContestantHandle, usize>,
+    /// The event that led to this state, as it was journaled. `None` if it
+    /// changed for another reason, like stepping through a replay.
+    pub caused_by: Option<Event>,
+    /// Of the whole journal, also while replaying it
+    pub timeline: Timeline,
+    /// No moderator was connected for a while, see [State::with_host_watch]
+    pub paused: bool,
+    /// When the game pauses unless a moderator connects, see
+    /// [State::with_host_watch]
+    pub pause_at: Option<Instant>,
+    /// When idle contestants are disconnected, see [Idleness]
+    pub disconnect_at: BTreeMap<ContestantHandle, Instant>,
+    /// The answers to the clue being played, see [Attempt::of_current_clue]
+    pub attempts: Vec<Attempt>,
+    /// The last [RECENT_EVENTS] journaled events, oldest first. While
+    /// replaying the ones up to the cursor.
+    pub recent: Vec<Moment>,
+    /// Only the moderator sees a clue before it was fully shown, see
+    /// [State::with_obscured_clues]
+    pub obscure_clues: bool,
+    /// HTML rendered from this value, shared by all connections. Every new
+    /// value gets an empty one, see [State::send_out].
+    pub fragments: Fragments,
+}
+
+impl From<GameState> for Out {
+    fn from(game: GameState) -> Self {
+        Self {
+            game,
+            pending_names: BTreeMap::new(),
+            scheduled_start: None,
+            lobby_deadline: None,
+            profiles: vec![],
+            idle: BTreeSet::new(),
+            controllers: BTreeMap::new(),
+            caused_by: None,
+            timeline: Timeline::default(),
+            paused: false,
+            pause_at: None,
+            disconnect_at: BTreeMap::new(),
+            attempts: vec![],
+            recent: vec![],
+            obscure_clues: false,
+            fragments: Fragments::default(),
+        }
+    }
+}
+
+/// When a journaled event was applied and where it led, for [Timeline]
+#[derive(Clone, Debug)]
+pub struct Moment {
+    /// Older journals don't have it
+    pub at: Option<SystemTime>,
+    pub event: Event,
+    started: bool,
+    phase: GamePhase,
+}
+
+impl Moment {
+    fn new(at: Option<SystemTime>, event: Event, phase: &GamePhase) -> Self {
+        Self { at, started: matches!(event, Event::StartGame), event, phase: phase.clone() }
+    }
+}
+
+/// How the moderator judged an [Attempt]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    Accepted,
+    Rejected,
+    /// See [Event::WithdrawBuzz], also when the contestant disconnected
+    Withdrawn,
+}
+
+/// A contestant who buzzed and what became of it
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct Attempt {
+    pub contestant: ContestantHandle,
+    /// If they typed one, see [Event::SubmitAnswer]
+    pub answer: Option<String>,
+    pub verdict: Verdict,
+}
+
+impl Attempt {
+    /// Every judged answer since the clue of the last moment was picked,
+    /// followups included. Empty if no clue is played.
+    pub fn of_current_clue(moments: &[Moment]) -> Vec<Self> {
+        let Some(clue) = moments.last().and_then(|m| m.phase.clue()) else { return vec![] };
+        let picked = moments.iter().rposition(|m| m.phase.clue() != Some(clue)).map_or(0, |i| i + 1);
+        moments[picked..].windows(2)
+            .filter_map(|w| {
+                let GamePhase::Buzzed { contestant, answer, .. } = &w[0].phase else { return None };
+                let verdict = match w[1].event {
+                    Event::AcceptAnswer => Verdict::Accepted,
+                    Event::RejectAnswer => Verdict::Rejected,
+                    Event::WithdrawBuzz => Verdict::Withdrawn,
+                    _ => return None,
+                };
+                Some(Self { contestant: *contestant, answer: answer.clone(), verdict })
+            })
+            .collect()
+    }
+}
+
+/// How long the game and each clue took, for the recap at the end. Time
+/// within [Timeline::gaps] doesn't count, neither do events journaled
+/// without a timestamp. Times are RFC 3339 like [Results::finished_at].
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize)]
+pub struct Timeline {
+    /// When [Event::StartGame] was applied
+    pub started_at: Option<String>,
+    /// When the game reached [GamePhase::Score]
+    pub finished_at: Option<String>,
+    /// From the start until the end, or the last event so far
+    pub duration_s: u64,
+    /// From [Event::Pick] until the clue was finished, in the order they
+    /// were played
+    pub clues: Vec<ClueTime>,
+    pub longest_clue: Option<ClueTime>,
+    /// Nothing happened for longer than [GAP], because the game was paused
+    /// or the server restarted
+    pub gaps: Vec<Gap>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct ClueTime {
+    pub clue: ClueHandle,
+    pub picked_at: String,
+    pub duration_s: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct Gap {
+    pub from: String,
+    pub to: String,
+    pub duration_s: u64,
+}
+
+impl Timeline {
+    pub fn from_moments(moments: &[Moment]) -> Self {
+        let stamped: Vec<(SystemTime, &Moment)> = moments.iter().filter_map(|m| Some((m.at?, m))).collect();
+        let format = |at: SystemTime| humantime::format_rfc3339_seconds(at).to_string();
+        let between = |from: SystemTime, to: SystemTime| to.duration_since(from).unwrap_or_default();
+        // without the gaps
+        let active = |from: usize, to: usize| -> u64 {
+            stamped[from..=to].windows(2)
+                .map(|w| between(w[0].0, w[1].0))
+                .filter(|d| *d <= GAP)
+                .sum::<Duration>()
+                .as_secs()
+        };
+
+        let gaps = stamped.windows(2)
+            .filter(|w| between(w[0].0, w[1].0) > GAP)
+            .map(|w| Gap { from: format(w[0].0), to: format(w[1].0), duration_s: between(w[0].0, w[1].0).as_secs() })
+            .collect();
+        let start = stamped.iter().position(|(_, m)| m.started);
+        let end = start.and_then(|start| {
+            stamped[start..].iter().position(|(_, m)| matches!(m.phase, GamePhase::Score)).map(|end| start + end)
+        });
+
+        let mut clues: Vec<ClueTime> = vec![];
+        let mut picked: Option<(ClueHandle, usize)> = None;
+        for (i, (_, moment)) in stamped.iter().enumerate() {
+            match (moment.phase.clue(), picked) {
+                (Some(clue), None) => picked = Some((clue, i)),
+                (None, Some((clue, from))) => {
+                    clues.push(ClueTime { clue, picked_at: format(stamped[from].0), duration_s: active(from, i) });
+                    picked = None;
+                },
+                _ => {},
+            }
+        }
+        // the first of equally long ones
+        let longest_clue = clues.iter().rev().max_by_key(|c| c.duration_s).cloned();
+
+        Self {
+            started_at: start.map(|start| format(stamped[start].0)),
+            finished_at: end.map(|end| format(stamped[end].0)),
+            duration_s: start.map(|start| active(start, end.unwrap_or(stamped.len() - 1))).unwrap_or(0),
+            clues,
+            longest_clue,
+            gaps,
+        }
+    }
+}
+
+/// Tells connections apart in the registry of who controls which
+/// contestant, see [State::claim]
+pub type ConnectionId = u64;
+
+/// A countdown to [Event::StartGame], see [State::schedule_start]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScheduledStart {
+    At(Instant),
+    /// It ran out, but the game couldn't be started. Kept for the moderator
+    /// until the next countdown.
+    Failed,
+}
+
+/// When the lobby should be over, see [State::set_lobby_deadline]. Unlike
+/// [ScheduledStart] nothing happens once it passed, the moderator still
+/// starts the game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LobbyDeadline {
+    pub at: Instant,
+    /// The last of [State::with_lobby_reminders] that was reached, zero once
+    /// the deadline itself passed
+    pub reminder: Option<Duration>,
+}
+
+/// When contestants whose connection is open but who don't send any input
+/// count as away, e.g. with a sleeping browser tab that still answers pings.
+/// Their next input makes them active again.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Idleness {
+    /// Flags them in [Out::idle]
+    pub after: Option<Duration>,
+    /// Sends [Event::DisconnectContestant] for them, usually later than
+    /// they are flagged
+    pub disconnect_after: Option<Duration>,
+}
+
+impl Idleness {
+    /// Reads `IDLE_AFTER_S` and `IDLE_DISCONNECT_AFTER_S`, both are off
+    /// without them
+    pub fn from_env() -> Result<Self, String> {
+        let var = |name: &str| -> Result<Option<Duration>, String> {
+            match std::env::var(name) {
+                Ok(value) => value.trim().parse()
+                    .map(|seconds| Some(Duration::from_secs(seconds)))
+                    .map_err(|e| format!("invalid {} {:?}: {}", name, value, e)),
+                Err(_) => Ok(None),
+            }
+        };
+        Ok(Self { after: var("IDLE_AFTER_S")?, disconnect_after: var("IDLE_DISCONNECT_AFTER_S")? })
+    }
+
+    fn is_on(&self) -> bool {
+        self.after.is_some() || self.disconnect_after.is_some()
+    }
+}
+
+/// Who sent an event for someone else, journaled next to it as `actor` so
+/// it can be told apart later. Events without one come from whoever they
+/// are about, or from the moderator if they aren't about anyone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Actor {
+    Moderator,
+}
+
+pub struct In (Request, oneshot::Sender<Result<GameState, Rejection>>);
+
+pub enum Request {
+    Event(Event),
+    EventBy { event: Event, actor: Actor },
+    Replay(ReplayStep),
+    SuggestName { contestant: ContestantHandle, name: String },
+    ApproveName { contestant: ContestantHandle },
+    RejectName { contestant: ContestantHandle },
+    ScheduleStart { after: Duration },
+    SetLobbyDeadline { after: Duration },
+    /// From now if the [LobbyDeadline] already passed
+    ExtendLobbyDeadline { by: Duration },
+    /// See [crate::profile::PROFILES]
+    ApplyProfile { name: String },
+    /// Any input of the contestant's connection, see [Idleness]
+    Activity { contestant: ContestantHandle },
+    /// The moderator vouching for an idle contestant
+    ClearIdle { contestant: ContestantHandle },
+    /// [Event::ConnectContestant] for a connection that then controls them
+    Join { name_hint: String, connection: ConnectionId },
+    /// [Event::ReconnectContestant] for a connection that then controls them
+    Claim { contestant: ContestantHandle, connection: ConnectionId },
+    /// [Request::Claim], but only for a seat nobody took yet, see
+    /// [Event::ImportContestants]
+    ClaimSeat { contestant: ContestantHandle, connection: ConnectionId },
+    /// The connection stopped controlling the contestant
+    Release { contestant: ContestantHandle, connection: ConnectionId },
+    CancelScheduledStart,
+    /// The countdown ran out, but [Event::StartGame] was rejected
+    ScheduledStartFailed,
+    /// A moderator lifting the pause of [State::with_host_watch]
+    Resume,
+}
 
-pub type Out = GameState;
-pub struct In (Event, oneshot::Sender<Result<GameState, GameError>>);
+#[derive(Debug)]
+struct Journal<'a> {
+    path: &'a Path,
+    writer: std::fs::File,
+}
 
 #[derive(Debug)]
 pub struct State<'a> {
     admin_token: String,
     game: Game,
-    journal_path: &'a Path,
-    journal_writer: std::fs::File,
+    /// Not set when replaying a journal, which must stay untouched, or for
+    /// an [ephemeral](State::ephemeral) game
+    journal: Option<Journal<'a>>,
+    export: Option<ExportOptions>,
+    replay: Option<Replay>,
+    pending_names: BTreeMap<ContestantHandle, String>,
+    scheduled_start: Option<ScheduledStart>,
+    lobby_deadline: Option<LobbyDeadline>,
+    /// See [State::with_lobby_reminders], longest first
+    lobby_reminders: Vec<Duration>,
+    /// The reminders still due for the [LobbyDeadline], longest first
+    pending_reminders: Vec<Duration>,
+    /// The last event applied while handling the current [Request]
+    caused_by: Option<Event>,
+    /// Suggested names containing any of these are rejected
+    blocked_words: Vec<String>,
+    /// Where [Request::ApplyProfile] looks, `None` to not allow it
+    profiles: Option<PathBuf>,
+    pinging: watch::Sender<crate::communication::Pinging>,
+    spectators: crate::communication::Spectators,
+    idleness: Idleness,
+    /// The last input of each contestant, or when they (re)connected
+    activity: BTreeMap<ContestantHandle, Instant>,
+    idle: BTreeSet<ContestantHandle>,
+    /// Which connections control which contestant
+    controllers: BTreeMap<ContestantHandle, BTreeSet<ConnectionId>>,
+    /// One for every journaled event, see [Timeline]
+    moments: Vec<Moment>,
+    /// See [State::with_co_control]
+    co_control: bool,
+    /// See [State::with_host_watch]
+    host_grace: Option<Duration>,
+    hosts: crate::communication::Hosts,
+    /// When the game pauses unless a moderator connects until then
+    pause_at: Option<Instant>,
+    paused: bool,
     out_tx: watch::Sender<Out>,
     out_rx: watch::Receiver<Out>,
     in_tx: mpsc::Sender<In>,
@@ -25,6 +392,20 @@ pub struct State<'a> {
 #[derive(Clone, Debug)]
 pub struct StateChannelsAndToken {
     pub admin_token: String,
+    pub export_directory: Option<PathBuf>,
+    /// Origins allowed to open websockets besides the server's own
+    pub allowed_origins: Vec<String>,
+    /// If a journal is replayed instead of a game being played
+    pub replaying: bool,
+    /// How often websockets are pinged and when they are given up, read
+    /// when a connection opens
+    pub pinging: watch::Receiver<crate::communication::Pinging>,
+    pub spectators: crate::communication::Spectators,
+    pub hosts: crate::communication::Hosts,
+    /// Of the start, see [State::self_check]
+    pub self_check: std::sync::Arc<SelfCheck>,
+    /// Listed and added to by the moderator, see [crate::board_assets]
+    pub board_assets: std::sync::Arc<crate::board_assets::BoardAssets>,
     pub rx: watch::Receiver<Out>,
     pub tx: mpsc::Sender<In>,
 }
@@ -32,60 +413,431 @@ pub struct StateChannelsAndToken {
 impl<'a> State<'a> {
     pub fn with_journal_and_token(journal_path: &'a Path, token: String) -> Result<Self, Error> {
         let mut game = libaitfoaq::Game::new();
+        let mut moments = vec![];
 
         if journal_path.exists() {
-            let journal = std::fs::read(journal_path)
-                .map_err(|e| Error::IOLoading(journal_path.to_owned(), e))?;
-            for event in serde_json::Deserializer::from_slice(&journal).into_iter::<Event>() {
-                let event = event.map_err(|e| Error::Parsing(journal_path.to_owned(), e))?;
-                game.apply(event).map_err(|e| Error::Loading(journal_path.to_owned(), e))?;
+            set_aside_broken_tail(journal_path)?;
+            if predates_reasons(journal_path)? {
+                game.accept_missing_reasons();
+            }
+            for (event, at) in read_timed_journal(journal_path)? {
+                let state = game.apply_ref(&event).map_err(|e| Error::Loading(journal_path.to_owned(), e))?;
+                moments.push(Moment::new(at, event, &state.phase));
             }
             game.mark_all_contestants_as_disconnected();
         }
+        // only events of this game, not the ones loaded from the journal
+        game.set_observer(Box::new(|kind, phase| tracing::trace!(?kind, ?phase, "applied event")));
 
         let journal_writer = std::fs::OpenOptions::new()
-            .write(true)
             .create(true)
             .truncate(false)
             .append(true)
             .open(journal_path)
             .map_err(|e| Error::IOSaving(journal_path.to_owned(), e))?;
 
-        let (out_tx, out_rx) = watch::channel(game.get_game_state());
-        let (in_tx, in_rx) = mpsc::channel(8);
-        let state = State {
-            admin_token: token,
+        let journal = Journal { path: journal_path, writer: journal_writer };
+        Ok(Self::new(token, game, Some(journal), None, moments))
+    }
+
+    /// Loads a journal without changing it. The game starts at the beginning
+    /// of the journal and only moves with [ReplayStep]s, all other events are
+    /// rejected.
+    pub fn replaying(journal_path: &'a Path, token: String) -> Result<Self, Error> {
+        let events = read_timed_journal(journal_path)?;
+        let missing_reasons = predates_reasons(journal_path)?;
+        let mut moments = vec![];
+        let mut game = Game::new();
+        if missing_reasons {
+            game.accept_missing_reasons();
+        }
+        for (event, at) in &events {
+            let state = game.apply_ref(event).map_err(|e| Error::Loading(journal_path.to_owned(), e))?;
+            moments.push(Moment::new(*at, event.clone(), &state.phase));
+        }
+        let mut replay = Replay::new(events.into_iter().map(|(event, _)| event).collect())
+            .with_missing_reasons(missing_reasons);
+        // apply everything once to find errors early
+        replay.step(ReplayStep::Seek(replay.len()))
+            .map_err(|e| Error::Loading(journal_path.to_owned(), e))?;
+        replay.step(ReplayStep::Seek(0))
+            .map_err(|e| Error::Loading(journal_path.to_owned(), e))?;
+        let game = replay.game()
+            .map_err(|e| Error::Loading(journal_path.to_owned(), e))?;
+        Ok(Self::new(token, game, None, Some(replay), moments))
+    }
+
+    /// A game that only lives in memory, nothing is read or written
+    pub fn ephemeral(token: String) -> Self {
+        let mut game = Game::new();
+        game.set_observer(Box::new(|kind, phase| tracing::trace!(?kind, ?phase, "applied event")));
+        Self::new(token, game, None, None, vec![])
+    }
+
+    fn new(
+        admin_token: String,
+        game: Game,
+        journal: Option<Journal<'a>>,
+        replay: Option<Replay>,
+        moments: Vec<Moment>,
+    ) -> Self {
+        let out = Out { timeline: Timeline::from_moments(&moments), ..Out::from(game.get_game_state()) };
+        let (out_tx, out_rx) = watch::channel(out);
+        let (in_tx, in_rx) = mpsc::channel(QUEUE_CAPACITY);
+        State {
+            admin_token,
             game,
-            journal_path,
-            journal_writer,
+            journal,
+            export: None,
+            replay,
+            pending_names: BTreeMap::new(),
+            scheduled_start: None,
+            lobby_deadline: None,
+            lobby_reminders: LOBBY_REMINDERS.to_vec(),
+            pending_reminders: vec![],
+            caused_by: None,
+            blocked_words: Vec::new(),
+            profiles: None,
+            pinging: watch::Sender::new(Default::default()),
+            spectators: Default::default(),
+            idleness: Idleness::default(),
+            activity: BTreeMap::new(),
+            idle: BTreeSet::new(),
+            controllers: BTreeMap::new(),
+            co_control: false,
+            host_grace: None,
+            hosts: Default::default(),
+            pause_at: None,
+            paused: false,
+            moments,
             out_tx,
             out_rx,
             in_tx,
             in_rx,
+        }
+    }
+
+    /// Write results and certificates once the game reaches [GamePhase::Score]
+    pub fn with_export(mut self, options: ExportOptions) -> Self {
+        self.export = Some(options);
+        self
+    }
+
+    /// Words, matched case-insensitively, that aren't allowed in names
+    /// contestants suggest for themselves
+    pub fn with_blocked_words(mut self, words: Vec<String>) -> Self {
+        self.blocked_words = words.into_iter().map(|w| w.to_lowercase()).collect();
+        self
+    }
+
+    pub fn with_pinging(self, pinging: crate::communication::Pinging) -> Self {
+        self.pinging.send_replace(pinging);
+        self
+    }
+
+    pub fn with_spectators(mut self, spectators: crate::communication::Spectators) -> Self {
+        self.spectators = spectators;
+        self
+    }
+
+    pub fn with_idleness(mut self, idleness: Idleness) -> Self {
+        self.idleness = idleness;
+        self
+    }
+
+    /// How many requests may wait while one is processed, before senders
+    /// wait and eventually get [SendError::QueueFull]. Only before
+    /// [State::clonable_channels], which would still send to the old queue.
+    pub fn with_queue_capacity(mut self, capacity: usize) -> Self {
+        (self.in_tx, self.in_rx) = mpsc::channel(capacity.max(1));
+        self
+    }
+
+    /// Lets more than one connection control the same contestant, like a
+    /// phone and a handset. The contestant is only disconnected once the
+    /// last of them closes. Without it, claiming a contestant someone else
+    /// controls is rejected.
+    pub fn with_co_control(mut self, co_control: bool) -> Self {
+        self.co_control = co_control;
+        self
+    }
+
+    /// Keeps the clue off the public display until
+    /// [Event::ClueFullyShown], e.g. while the moderator reads it out
+    pub fn with_obscured_clues(self, obscure: bool) -> Self {
+        self.send_out(|out| out.obscure_clues = obscure);
+        self
+    }
+
+    /// Pauses the game once no moderator was connected for `grace` while
+    /// it's played, so contestants don't buzz with nobody to judge. Only
+    /// [Request::Resume] continues it, even when a moderator is back.
+    pub fn with_host_watch(mut self, grace: Duration) -> Self {
+        self.host_grace = Some(grace);
+        self
+    }
+
+    /// How long before a [LobbyDeadline] the moderator is reminded of it,
+    /// two minutes and 30 seconds by default. They are reminded once it
+    /// passed, too.
+    pub fn with_lobby_reminders(mut self, mut reminders: Vec<Duration>) -> Self {
+        reminders.sort_unstable_by(|a, b| b.cmp(a));
+        reminders.dedup();
+        self.lobby_reminders = reminders;
+        self
+    }
+
+    /// Allows [Request::ApplyProfile] with the profiles in `directory`,
+    /// which are listed once
+    pub fn with_profiles(mut self, directory: PathBuf) -> Self {
+        let names = Profile::names(&directory);
+        self.send_out(|out| out.profiles = names);
+        self.profiles = Some(directory);
+        self
+    }
+
+    /// Applies the options through [Event::Settings], so only before the
+    /// game is prepared, and the server settings right away
+    pub async fn use_profile(&mut self, profile: Profile) -> Result<GameState, Rejection> {
+        let new_state = self.apply(Event::Settings(profile.options)).await?;
+        self.use_server_settings(profile.server);
+        Ok(new_state)
+    }
+
+    /// Only affects connections opened afterwards
+    pub fn use_server_settings(&self, server: ServerSettings) {
+        self.pinging.send_modify(|pinging| *pinging = server.pinging(*pinging));
+        if let Some(hysteresis) = server.spectator_hysteresis {
+            self.spectators.set_hysteresis(hysteresis);
+        }
+        tracing::info!(?server, "applied server settings");
+    }
+
+    /// Loads [Board::demo] and opens the lobby, see [State::start_with]
+    pub async fn start_demo(&mut self) -> Result<bool, Rejection> {
+        self.start_with(Board::demo()).await
+    }
+
+    /// Loads the board and opens the lobby like the moderator would, unless
+    /// the journal already got further than an empty game
+    pub async fn start_with(&mut self, board: Board) -> Result<bool, Rejection> {
+        let game = self.game.get_game_state();
+        if !matches!(game.phase, GamePhase::Preparing) || !game.board.categories.is_empty() {
+            return Ok(false);
+        }
+        self.apply(Event::LoadBoard(board)).await?;
+        let new_state = self.apply(Event::OpenLobby).await?;
+        self.send_out(|out| *out = Out { profiles: std::mem::take(&mut out.profiles), ..Out::from(new_state) });
+        Ok(true)
+    }
+
+    pub async fn send(event: Event, sender: &mpsc::Sender<In>) -> Result<GameState, Rejection> {
+        Self::request(Request::Event(event), sender).await
+    }
+
+    pub async fn send_by(event: Event, actor: Actor, sender: &mpsc::Sender<In>) -> Result<GameState, Rejection> {
+        Self::request(Request::EventBy { event, actor }, sender).await
+    }
+
+    pub async fn replay(step: ReplayStep, sender: &mpsc::Sender<In>) -> Result<GameState, Rejection> {
+        Self::request(Request::Replay(step), sender).await
+    }
+
+    pub async fn suggest_name(contestant: ContestantHandle, name: String, sender: &mpsc::Sender<In>) -> Result<GameState, Rejection> {
+        Self::request(Request::SuggestName { contestant, name }, sender).await
+    }
+
+    pub async fn approve_name(contestant: ContestantHandle, approved: bool, sender: &mpsc::Sender<In>) -> Result<GameState, Rejection> {
+        let request = match approved {
+            true => Request::ApproveName { contestant },
+            false => Request::RejectName { contestant },
+        };
+        Self::request(request, sender).await
+    }
+
+    pub async fn apply_profile(name: String, sender: &mpsc::Sender<In>) -> Result<GameState, Rejection> {
+        Self::request(Request::ApplyProfile { name }, sender).await
+    }
+
+    /// Refreshes the contestant's [Idleness], and reconnects them if they
+    /// were disconnected for it
+    pub async fn active(contestant: ContestantHandle, sender: &mpsc::Sender<In>) -> Result<GameState, Rejection> {
+        Self::request(Request::Activity { contestant }, sender).await
+    }
+
+    pub async fn clear_idle(contestant: ContestantHandle, sender: &mpsc::Sender<In>) -> Result<GameState, Rejection> {
+        Self::request(Request::ClearIdle { contestant }, sender).await
+    }
+
+    /// Adds a contestant controlled by `connection`, the last one in the
+    /// returned state
+    pub async fn join(name_hint: String, connection: ConnectionId, sender: &mpsc::Sender<In>) -> Result<GameState, Rejection> {
+        Self::request(Request::Join { name_hint, connection }, sender).await
+    }
+
+    /// Reconnects the contestant, controlled by `connection` from now on
+    pub async fn claim(contestant: ContestantHandle, connection: ConnectionId, sender: &mpsc::Sender<In>) -> Result<GameState, Rejection> {
+        Self::request(Request::Claim { contestant, connection }, sender).await
+    }
+
+    /// Like [State::claim], but never alongside another connection
+    pub async fn claim_seat(contestant: ContestantHandle, connection: ConnectionId, sender: &mpsc::Sender<In>) -> Result<GameState, Rejection> {
+        Self::request(Request::ClaimSeat { contestant, connection }, sender).await
+    }
+
+    /// Disconnects the contestant, unless another connection still controls
+    /// them
+    pub async fn release(contestant: ContestantHandle, connection: ConnectionId, sender: &mpsc::Sender<In>) -> Result<GameState, Rejection> {
+        Self::request(Request::Release { contestant, connection }, sender).await
+    }
+
+    /// Starts the game once `after` passed, or cancels the countdown with
+    /// `None`
+    pub async fn schedule_start(after: Option<Duration>, sender: &mpsc::Sender<In>) -> Result<GameState, Rejection> {
+        let request = match after {
+            Some(after) => Request::ScheduleStart { after },
+            None => Request::CancelScheduledStart,
         };
-        Ok(state)
+        Self::request(request, sender).await
+    }
+
+    /// Only shows everyone how long the lobby should still be open, see
+    /// [LobbyDeadline]
+    pub async fn set_lobby_deadline(after: Duration, sender: &mpsc::Sender<In>) -> Result<GameState, Rejection> {
+        Self::request(Request::SetLobbyDeadline { after }, sender).await
     }
 
-    pub async fn send(event: Event, sender: &mpsc::Sender<In>) -> Result<GameState, GameError> {
+    pub async fn extend_lobby_deadline(by: Duration, sender: &mpsc::Sender<In>) -> Result<GameState, Rejection> {
+        Self::request(Request::ExtendLobbyDeadline { by }, sender).await
+    }
+
+    pub async fn resume(sender: &mpsc::Sender<In>) -> Result<GameState, Rejection> {
+        Self::request(Request::Resume, sender).await
+    }
+
+    /// How many requests wait to be processed, see
+    /// [State::with_queue_capacity]
+    pub fn queue_depth(sender: &mpsc::Sender<In>) -> usize {
+        sender.max_capacity() - sender.capacity()
+    }
+
+    async fn request(request: Request, sender: &mpsc::Sender<In>) -> Result<GameState, Rejection> {
         let (response_tx, response_rx) = oneshot::channel();
-        sender.send(In(event, response_tx)).await.expect("could not send message to internal state processor");
-        response_rx.await.expect("Can't receive answer from state processor")
+        match sender.send_timeout(In(request, response_tx), QUEUE_TIMEOUT).await {
+            Ok(()) => {},
+            Err(SendTimeoutError::Timeout(_)) => {
+                tracing::warn!(depth = Self::queue_depth(sender), timeout = ?QUEUE_TIMEOUT, "the state queue stayed full");
+                return Err(SendError::QueueFull.into());
+            },
+            Err(SendTimeoutError::Closed(_)) => return Err(SendError::StateTaskGone.into()),
+        }
+        response_rx.await.map_err(|_| SendError::StateTaskGone)?
     }
 
     pub async fn process(&mut self, cancellation_token: CancellationToken) {
+        let mut refresh = interval(COUNTDOWN_REFRESH);
+        let mut idle_check = interval(IDLE_CHECK);
+        let mut hosts = self.hosts.subscribe();
         loop {
+            let starting_at = match self.scheduled_start {
+                Some(ScheduledStart::At(at)) => Some(at),
+                _ => None,
+            };
+            let reminding_at = self.next_reminder();
+            let lobby_running = self.lobby_deadline.is_some_and(|deadline| deadline.reminder != Some(Duration::ZERO));
+            let pausing_at = self.pause_at;
             tokio::select! {
-                Some(In(event, response_channel)) = self.in_rx.recv() => {
-                    match self.game.apply(event.clone()) {
-                        Ok(new_state) => {
-                            self.write_to_journal(event).await.expect("Can't write to journal");
-                            let _ = response_channel.send(Ok(new_state.clone()));
-                            self.out_tx.send_replace(new_state);
+                Some(In(request, response_channel)) = self.in_rx.recv() => {
+                    self.caused_by = None;
+                    // an active contestant staying active changes nothing
+                    let quiet = matches!(&request, Request::Activity { contestant } if !self.idle.contains(contestant));
+                    let response = match request {
+                        Request::Event(event) => self.apply(event).await,
+                        Request::EventBy { event, actor } => self.apply_by(event, Some(actor)).await,
+                        Request::Replay(step) => self.step(step),
+                        Request::SuggestName { contestant, name } => self.suggest_name_for(contestant, name).await,
+                        Request::ApproveName { contestant } => match self.pending_names.remove(&contestant) {
+                            Some(name) => self.apply(Event::NameContestant { index: contestant, name }).await,
+                            None => Err(Rejection::NoPendingName),
+                        },
+                        Request::RejectName { contestant } => match self.pending_names.remove(&contestant) {
+                            Some(_) => Ok(self.game.get_game_state()),
+                            None => Err(Rejection::NoPendingName),
+                        },
+                        Request::ScheduleStart { after } => self.schedule_start_after(after),
+                        Request::SetLobbyDeadline { after } => self.set_lobby_deadline_after(after),
+                        Request::ExtendLobbyDeadline { by } => match self.lobby_deadline {
+                            Some(deadline) => self.set_lobby_deadline_at(deadline.at.max(Instant::now()) + by),
+                            None => Err(Rejection::NoLobbyDeadline),
+                        },
+                        Request::ApplyProfile { name } => match &self.profiles {
+                            Some(directory) => match Profile::find(directory, &name) {
+                                Ok(profile) => self.use_profile(profile).await,
+                                Err(error) => Err(Rejection::InvalidProfile(error)),
+                            },
+                            None => Err(Rejection::InvalidProfile(format!("no profiles for {:?}", name))),
+                        },
+                        Request::Activity { contestant } => self.refresh_activity(contestant).await,
+                        Request::ClearIdle { contestant } => match self.idle.remove(&contestant) {
+                            true => {
+                                self.activity.insert(contestant, Instant::now());
+                                Ok(self.game.get_game_state())
+                            },
+                            false => Err(Rejection::NotIdle),
                         },
-                        Err(error) => {
-                            let _ = response_channel.send(Err(error));
+                        Request::Join { name_hint, connection } => {
+                            let new_state = self.apply(Event::ConnectContestant { name_hint }).await;
+                            if let Ok(state) = &new_state {
+                                self.controllers.entry(state.contestants.len() - 1).or_default().insert(connection);
+                            }
+                            new_state
                         },
+                        Request::Claim { contestant, connection } => self.claim_for(contestant, connection).await,
+                        Request::ClaimSeat { contestant, connection } => self.claim_seat_for(contestant, connection).await,
+                        Request::Release { contestant, connection } => self.release_from(contestant, connection).await,
+                        Request::CancelScheduledStart => match self.scheduled_start.take() {
+                            Some(ScheduledStart::At(_)) => Ok(self.game.get_game_state()),
+                            _ => Err(Rejection::NotScheduled),
+                        },
+                        Request::ScheduledStartFailed => {
+                            self.scheduled_start = Some(ScheduledStart::Failed);
+                            Ok(self.game.get_game_state())
+                        },
+                        Request::Resume => match std::mem::take(&mut self.paused) {
+                            true => Ok(self.game.get_game_state()),
+                            false => Err(Rejection::NotPaused),
+                        },
+                    };
+                    match &response {
+                        Ok(_) if quiet && self.caused_by.is_none() => {},
+                        Ok(new_state) => self.publish(new_state.clone()),
+                        Err(_) => {},
                     }
+                    let _ = response_channel.send(response);
+                },
+                // clients show the remaining time, which they don't compute
+                _ = refresh.tick(), if starting_at.is_some() || lobby_running => {
+                    self.send_out(|out| out.caused_by = None);
+                },
+                _ = sleep_until(starting_at.unwrap_or_else(Instant::now)), if starting_at.is_some() => {
+                    self.start_on_schedule();
+                },
+                _ = sleep_until(reminding_at.unwrap_or_else(Instant::now)), if reminding_at.is_some() => {
+                    self.remind_of_lobby_deadline();
+                },
+                _ = idle_check.tick(), if self.idleness.is_on() => {
+                    self.check_idleness().await;
+                },
+                Ok(_) = hosts.changed(), if self.host_grace.is_some() => {
+                    let connected = *hosts.borrow_and_update();
+                    self.pause_at = match self.host_grace {
+                        Some(grace) if connected == 0 && is_played(self.game.phase()) => Some(Instant::now() + grace),
+                        _ => None,
+                    };
+                    self.send_out(|out| out.pause_at = self.pause_at);
+                },
+                _ = sleep_until(pausing_at.unwrap_or_else(Instant::now)), if pausing_at.is_some() => {
+                    self.pause_without_host();
                 },
                 _ = cancellation_token.cancelled() => { return; },
                 else => { return; },
@@ -93,22 +845,496 @@ impl<'a> State<'a> {
         }
     }
 
+    fn publish(&mut self, game: GameState) {
+        let moments = match &self.replay {
+            Some(replay) => &self.moments[..replay.cursor().min(self.moments.len())],
+            None => &self.moments[..],
+        };
+        let out = Out {
+            game,
+            pending_names: self.pending_names.clone(),
+            scheduled_start: self.scheduled_start,
+            lobby_deadline: self.lobby_deadline,
+            profiles: vec![],
+            idle: self.idle.clone(),
+            controllers: self.controllers.iter().map(|(&contestant, c)| (contestant, c.len())).collect(),
+            caused_by: self.caused_by.take(),
+            timeline: Timeline::from_moments(&self.moments),
+            paused: self.paused,
+            pause_at: self.pause_at,
+            disconnect_at: self.disconnect_at(),
+            attempts: Attempt::of_current_clue(moments),
+            recent: moments[moments.len().saturating_sub(RECENT_EVENTS)..].to_vec(),
+            obscure_clues: false,
+            fragments: Fragments::default(),
+        };
+        self.send_out(|old| *old = Out { profiles: std::mem::take(&mut old.profiles), obscure_clues: old.obscure_clues, ..out });
+    }
+
+    /// Every change to [Out] goes through here, so no connection is sent
+    /// HTML rendered from an older value
+    fn send_out(&self, modify: impl FnOnce(&mut Out)) {
+        self.out_tx.send_modify(|out| {
+            modify(out);
+            out.fragments = Fragments::default();
+        });
+    }
+
+    async fn apply(&mut self, event: Event) -> Result<GameState, Rejection> {
+        self.apply_by(event, None).await
+    }
+
+    async fn apply_by(&mut self, event: Event, actor: Option<Actor>) -> Result<GameState, Rejection> {
+        if self.replay.is_some() {
+            return Err(Rejection::Replaying);
+        }
+        // contestants still come and go
+        if self.paused && !event.is_presence() {
+            return Err(Rejection::Paused);
+        }
+        let old_phase = self.game.phase().clone();
+        let new_state = self.game.apply_ref(&event)?;
+        match event {
+            Event::StartGame => {
+                self.scheduled_start = None;
+                self.lobby_deadline = None;
+                self.pending_reminders.clear();
+            },
+            Event::ConnectContestant { .. } => {
+                self.activity.insert(new_state.contestants.len() - 1, Instant::now());
+            },
+            Event::ReconnectContestant { contestant } => {
+                self.activity.insert(contestant, Instant::now());
+                self.idle.remove(&contestant);
+            },
+            _ => {},
+        }
+        // the host might have left before, e.g. when the game starts on schedule
+        if self.pause_at.is_none() && !is_played(&old_phase) && is_played(&new_state.phase) && self.hosts.connected() == 0 {
+            self.pause_at = self.host_grace.map(|grace| Instant::now() + grace);
+        }
+        let event = resolved(event, &old_phase, &new_state.phase);
+        if !event.is_presence() {
+            let at = SystemTime::now();
+            self.write_to_journal(&event, actor, at).await.expect("Can't write to journal");
+            self.moments.push(Moment::new(Some(at), event.clone(), &new_state.phase));
+        }
+        let was_score = matches!(self.out_rx.borrow().game.phase, GamePhase::Score);
+        if !was_score && matches!(new_state.phase, GamePhase::Score) {
+            self.spawn_export(&new_state);
+        }
+        self.caused_by = Some(event);
+        Ok(new_state)
+    }
+
+    fn pause_without_host(&mut self) {
+        self.pause_at = None;
+        if self.paused || !is_played(self.game.phase()) {
+            return;
+        }
+        tracing::warn!("no moderator is connected, pausing the game");
+        self.paused = true;
+        self.publish(self.game.get_game_state());
+    }
+
+    async fn refresh_activity(&mut self, contestant: ContestantHandle) -> Result<GameState, Rejection> {
+        let game = self.game.get_game_state();
+        let connected = game.contestants.get(contestant).ok_or(GameError::ContestantNotFound)?.connected;
+        self.activity.insert(contestant, Instant::now());
+        self.idle.remove(&contestant);
+        match connected {
+            true => Ok(game),
+            false => self.apply(Event::ReconnectContestant { contestant }).await,
+        }
+    }
+
+    /// Only for contestants that were flagged as idle, everyone else's
+    /// timer keeps moving with their input
+    fn disconnect_at(&self) -> BTreeMap<ContestantHandle, Instant> {
+        let Some(after) = self.idleness.disconnect_after else { return BTreeMap::new() };
+        self.idle.iter()
+            .filter_map(|contestant| Some((*contestant, *self.activity.get(contestant)? + after)))
+            .collect()
+    }
+
+    /// Flags and disconnects connected contestants according to [Idleness]
+    async fn check_idleness(&mut self) {
+        let now = Instant::now();
+        let mut changed = false;
+        for (contestant, c) in self.game.get_game_state().contestants.iter().enumerate() {
+            if !c.connected {
+                continue;
+            }
+            let inactive = now.duration_since(*self.activity.entry(contestant).or_insert(now));
+            if self.idleness.after.is_some_and(|after| inactive >= after) && self.idle.insert(contestant) {
+                tracing::info!(contestant, ?inactive, "contestant is idle");
+                changed = true;
+            }
+            if self.idleness.disconnect_after.is_some_and(|after| inactive >= after) {
+                tracing::info!(contestant, ?inactive, "disconnecting idle contestant");
+                changed |= self.apply(Event::DisconnectContestant { contestant }).await.is_ok();
+            }
+        }
+        if changed {
+            self.publish(self.game.get_game_state());
+        }
+    }
+
+    async fn claim_for(&mut self, contestant: ContestantHandle, connection: ConnectionId) -> Result<GameState, Rejection> {
+        let others = self.controllers.get(&contestant).is_some_and(|c| c.iter().any(|&other| other != connection));
+        if others && !self.co_control {
+            return Err(Rejection::AlreadyControlled);
+        }
+        let new_state = self.apply(Event::ReconnectContestant { contestant }).await?;
+        self.controllers.entry(contestant).or_default().insert(connection);
+        Ok(new_state)
+    }
+
+    async fn claim_seat_for(&mut self, contestant: ContestantHandle, connection: ConnectionId) -> Result<GameState, Rejection> {
+        let game = self.game.get_game_state();
+        let Some(seat) = game.contestants.get(contestant) else {
+            return Err(GameError::ContestantNotFound.into());
+        };
+        if seat.connected || self.controllers.contains_key(&contestant) {
+            return Err(Rejection::AlreadyClaimed);
+        }
+        self.claim_for(contestant, connection).await
+    }
+
+    async fn release_from(&mut self, contestant: ContestantHandle, connection: ConnectionId) -> Result<GameState, Rejection> {
+        if let Some(connections) = self.controllers.get_mut(&contestant) {
+            connections.remove(&connection);
+            if !connections.is_empty() {
+                return Ok(self.game.get_game_state());
+            }
+            self.controllers.remove(&contestant);
+        }
+        self.apply(Event::DisconnectContestant { contestant }).await
+    }
+
+    async fn suggest_name_for(&mut self, contestant: ContestantHandle, name: String) -> Result<GameState, Rejection> {
+        let name = self.sanitize_name(&name)?;
+        let game = self.game.get_game_state();
+        if game.contestants.get(contestant).is_none() {
+            return Err(GameError::ContestantNotFound.into());
+        }
+        if game.options.approve_names {
+            self.pending_names.insert(contestant, name);
+            Ok(game)
+        } else {
+            self.apply(Event::NameContestant { index: contestant, name }).await
+        }
+    }
+
+    fn schedule_start_after(&mut self, after: Duration) -> Result<GameState, Rejection> {
+        if self.replay.is_some() {
+            return Err(Rejection::Replaying);
+        }
+        let game = self.game.get_game_state();
+        if !matches!(game.phase, GamePhase::Connecting) {
+            return Err(GameError::WrongPhase { is: game.phase }.into());
+        }
+        self.scheduled_start = Some(ScheduledStart::At(Instant::now() + after));
+        Ok(game)
+    }
+
+    fn set_lobby_deadline_after(&mut self, after: Duration) -> Result<GameState, Rejection> {
+        if self.replay.is_some() {
+            return Err(Rejection::Replaying);
+        }
+        let game = self.game.get_game_state();
+        if !matches!(game.phase, GamePhase::Connecting) {
+            return Err(GameError::WrongPhase { is: game.phase }.into());
+        }
+        self.set_lobby_deadline_at(Instant::now() + after)
+    }
+
+    /// Reminders that are already too late are skipped, earlier ones are
+    /// due again
+    fn set_lobby_deadline_at(&mut self, at: Instant) -> Result<GameState, Rejection> {
+        let remaining = at.saturating_duration_since(Instant::now());
+        self.pending_reminders = self.lobby_reminders.iter()
+            .copied()
+            .filter(|&reminder| reminder < remaining)
+            .chain([Duration::ZERO])
+            .collect();
+        self.lobby_deadline = Some(LobbyDeadline { at, reminder: None });
+        Ok(self.game.get_game_state())
+    }
+
+    fn next_reminder(&self) -> Option<Instant> {
+        let deadline = self.lobby_deadline?;
+        let reminder = self.pending_reminders.first()?;
+        Some(deadline.at.checked_sub(*reminder).unwrap_or(deadline.at))
+    }
+
+    fn remind_of_lobby_deadline(&mut self) {
+        if self.pending_reminders.is_empty() {
+            return;
+        }
+        let reminder = self.pending_reminders.remove(0);
+        if let Some(deadline) = &mut self.lobby_deadline {
+            deadline.reminder = Some(reminder);
+        }
+        let deadline = self.lobby_deadline;
+        self.send_out(|out| {
+            out.lobby_deadline = deadline;
+            out.caused_by = None;
+        });
+    }
+
+    /// Sends [Event::StartGame] like any client would, so it is journaled
+    /// and checked the same way
+    fn start_on_schedule(&mut self) {
+        self.scheduled_start = None;
+        let tx = self.in_tx.clone();
+        tokio::spawn(async move {
+            if let Err(rejection) = State::send(Event::StartGame, &tx).await {
+                tracing::warn!(?rejection, "could not start the game on schedule");
+                let _ = State::request(Request::ScheduledStartFailed, &tx).await;
+            }
+        });
+    }
+
+    fn sanitize_name(&self, name: &str) -> Result<String, Rejection> {
+        let name: String = name
+            .chars()
+            .filter(|c| !c.is_control())
+            .take(MAX_NAME_LENGTH)
+            .collect();
+        let name = name.trim();
+        let lowercase = name.to_lowercase();
+        if name.is_empty() || self.blocked_words.iter().any(|w| lowercase.contains(w)) {
+            return Err(Rejection::InvalidName);
+        }
+        Ok(name.to_owned())
+    }
+
+    fn step(&mut self, step: ReplayStep) -> Result<GameState, Rejection> {
+        let replay = self.replay.as_mut().ok_or(Rejection::NotReplaying)?;
+        let new_state = replay.step(step)?;
+        tracing::debug!(cursor = replay.cursor(), len = replay.len(), "replaying");
+        Ok(new_state)
+    }
+
+    fn spawn_export(&self, game: &GameState) {
+        let Some(options) = self.export.clone() else { return };
+        let results = Results::from_game_state(game, SystemTime::now(), Timeline::from_moments(&self.moments));
+        tokio::spawn(async move {
+            match export::write(&options, &results).await {
+                Ok(path) => tracing::info!(?path, "exported results"),
+                Err(error) => tracing::error!(%error, "failed to export results"),
+            }
+        });
+    }
+
+    /// The loaded board, its media in `assets`, and where the game is
+    /// written to
+    pub fn self_check(&self, assets: &Path) -> SelfCheck {
+        SelfCheck::run(
+            &self.game.get_game_state().board,
+            assets,
+            self.journal.as_ref().map(|journal| journal.path),
+            self.export.as_ref().map(|export| export.directory.as_path()),
+        )
+    }
+
     pub fn clonable_channels(&self) -> StateChannelsAndToken {
         StateChannelsAndToken {
             admin_token: self.admin_token.clone(),
+            export_directory: self.export.as_ref().map(|e| e.directory.clone()),
+            allowed_origins: Vec::new(),
+            replaying: self.replay.is_some(),
+            pinging: self.pinging.subscribe(),
+            spectators: self.spectators.clone(),
+            hosts: self.hosts.clone(),
+            self_check: Default::default(),
+            board_assets: Default::default(),
             rx: self.out_rx.clone(),
             tx: self.in_tx.clone(),
         }
     }
 
-    async fn write_to_journal(&mut self, event: Event) -> Result<(), Error> {
-        let mut bytes = serde_json::to_vec(&event)
-            .map_err(|e| Error::Saving(self.journal_path.to_owned(), e))?;
+    /// Events ignore the `actor` and when they were applied, `at`, when they
+    /// are read again
+    async fn write_to_journal(&mut self, event: &Event, actor: Option<Actor>, at: SystemTime) -> Result<(), Error> {
+        let Some(journal) = self.journal.as_mut() else { return Ok(()) };
+        let mut bytes = serde_json::to_vec(event)
+            .map_err(|e| Error::Saving(journal.path.to_owned(), e))?;
+        // appended, so the fields keep the order they have without them
+        bytes.pop(); // the closing brace
+        if let Some(actor) = actor {
+            bytes.extend_from_slice(format!(r#","actor":{}"#, serde_json::json!(actor)).as_bytes());
+        }
+        bytes.extend_from_slice(format!(r#","at":"{}"}}"#, humantime::format_rfc3339_millis(at)).as_bytes());
         bytes.push(0x0a); // add a newline
-        self.journal_writer.write(&bytes)
-            .map_err(|e| Error::IOSaving(self.journal_path.to_owned(), e))?;
-        Ok(())
+        // at once, so a crash is less likely to leave half a line
+        journal.writer.write_all(&bytes)
+            .map_err(|e| Error::IOSaving(journal.path.to_owned(), e))
+    }
+}
+
+/// Between [Event::StartGame] and the final score, when a moderator has to
+/// judge what contestants do
+fn is_played(phase: &GamePhase) -> bool {
+    !matches!(phase, GamePhase::Preparing | GamePhase::Connecting | GamePhase::Practice { .. } | GamePhase::Score)
+}
+
+/// The event as it has to be journaled to replay the same way. Passing the
+/// pick to whoever is next depends on who is connected, which isn't journaled.
+/// Neither are disconnects, so the clue or pick they handed on is journaled
+/// as the event that does the same.
+fn resolved(event: Event, before: &GamePhase, after: &GamePhase) -> Event {
+    match (event, before, after) {
+        (Event::PassPick { to: None }, _, GamePhase::Picking { contestant }) => {
+            Event::PassPick { to: Some(*contestant) }
+        },
+        (Event::DisconnectContestant { .. }, GamePhase::Picking { contestant: was }, GamePhase::Picking { contestant })
+            if was != contestant => Event::PassPick { to: Some(*contestant) },
+        (Event::DisconnectContestant { .. }, GamePhase::Buzzed { .. }, GamePhase::Buzzing { .. }) => Event::WithdrawBuzz,
+        (event, _, _) => event,
+    }
+}
+
+/// The events and when they were applied, if that was journaled. Older
+/// journals still contain presence events, they are skipped so
+/// [Contestant::connected](libaitfoaq::state::Contestant::connected) never
+/// depends on the journal. A broken last line is ignored, see
+/// [set_aside_broken_tail].
+fn read_timed_journal(journal_path: &Path) -> Result<Vec<Journaled>, Error> {
+    let journal = std::fs::read(journal_path)
+        .map_err(|e| Error::IOLoading(journal_path.to_owned(), e))?;
+    let (events, broken_tail) = parse_journal(&journal)
+        .map_err(|(line, e)| Error::Parsing(journal_path.to_owned(), line, e))?;
+    if broken_tail.is_some() {
+        tracing::warn!(?journal_path, "ignoring the broken last line of the journal");
+    }
+    Ok(events.into_iter().filter(|(event, _)| !event.is_presence()).collect())
+}
+
+/// If the journal has score changes without a `reason` field, which newer
+/// ones always write, see [Game::accept_missing_reasons]
+fn predates_reasons(journal_path: &Path) -> Result<bool, Error> {
+    let journal = std::fs::read(journal_path)
+        .map_err(|e| Error::IOLoading(journal_path.to_owned(), e))?;
+    Ok(journal
+        .split(|b| *b == b'\n')
+        .filter_map(|line| serde_json::from_slice::<serde_json::Map<String, serde_json::Value>>(line).ok())
+        .any(|fields| {
+            matches!(fields.get("type").and_then(|t| t.as_str()), Some("AwardPoints" | "RevokePoints"))
+                && !fields.contains_key("reason")
+        }))
+}
+
+/// An event and when it was applied, if that was journaled
+type Journaled = (Event, Option<SystemTime>);
+
+/// The metadata [State::write_to_journal] appends to events
+#[derive(serde::Deserialize)]
+struct Stamp {
+    #[serde(default)]
+    at: Option<String>,
+}
+
+/// One event per line. The server might have died while writing the last
+/// one, so if only that doesn't parse, the events before it are returned
+/// with the offset it starts at. A broken line anywhere else is an error
+/// with its line number. An unreadable timestamp is left out like a
+/// missing one.
+fn parse_journal(journal: &[u8]) -> Result<(Vec<Journaled>, Option<usize>), (usize, serde_json::Error)> {
+    let mut events = Vec::new();
+    let mut offset = 0;
+    let mut lines = journal.split_inclusive(|b| *b == b'\n').enumerate().peekable();
+    while let Some((number, line)) = lines.next() {
+        if !line.trim_ascii().is_empty() {
+            match serde_json::from_slice(line) {
+                Ok(event) => {
+                    let at = serde_json::from_slice::<Stamp>(line).ok()
+                        .and_then(|stamp| humantime::parse_rfc3339(&stamp.at?).ok());
+                    events.push((event, at));
+                },
+                Err(_) if lines.peek().is_none() => return Ok((events, Some(offset))),
+                Err(e) => return Err((number + 1, e)),
+            }
+        }
+        offset += line.len();
     }
+    Ok((events, None))
+}
+
+/// Moves a broken last line, see [parse_journal], to `<journal>.corrupt` so
+/// new events aren't appended to it
+fn set_aside_broken_tail(journal_path: &Path) -> Result<(), Error> {
+    let journal = std::fs::read(journal_path)
+        .map_err(|e| Error::IOLoading(journal_path.to_owned(), e))?;
+    let Ok((_, Some(offset))) = parse_journal(&journal) else { return Ok(()) };
+
+    let mut corrupt_path = journal_path.as_os_str().to_owned();
+    corrupt_path.push(".corrupt");
+    let corrupt_path = PathBuf::from(corrupt_path);
+    tracing::warn!(?journal_path, ?corrupt_path, "moving the broken last line of the journal");
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&corrupt_path)
+        .and_then(|mut corrupt| corrupt.write_all(&journal[offset..]))
+        .map_err(|e| Error::IOSaving(corrupt_path, e))?;
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open(journal_path)
+        .and_then(|journal| journal.set_len(offset as u64))
+        .map_err(|e| Error::IOSaving(journal_path.to_owned(), e))
+}
+
+/// Why a [Request] wasn't processed
+#[derive(Debug, Error)]
+pub enum Rejection {
+    #[error("{0:?}")]
+    Game(GameError),
+    #[error("The game can't be changed while replaying a journal")]
+    Replaying,
+    #[error("No journal is being replayed")]
+    NotReplaying,
+    #[error("This name is not allowed")]
+    InvalidName,
+    #[error("No name is waiting for approval")]
+    NoPendingName,
+    #[error("The start isn't scheduled")]
+    NotScheduled,
+    #[error("There's no deadline for the lobby")]
+    NoLobbyDeadline,
+    #[error("This contestant isn't idle")]
+    NotIdle,
+    /// See [State::with_co_control]
+    #[error("Someone else already controls this contestant")]
+    AlreadyControlled,
+    /// See [State::claim_seat]
+    #[error("Someone else already took this seat")]
+    AlreadyClaimed,
+    /// Names the file or key, see [Profile::parse]
+    #[error("Invalid profile: {0}")]
+    InvalidProfile(String),
+    /// See [State::with_host_watch]
+    #[error("The game is paused until the host resumes it")]
+    Paused,
+    #[error("The game isn't paused")]
+    NotPaused,
+    #[error(transparent)]
+    Send(#[from] SendError),
+}
+impl From<GameError> for Rejection {
+    fn from(other: GameError) -> Self { Self::Game(other) }
+}
+
+/// Why a [Request] wasn't answered at all
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum SendError {
+    /// For longer than [QUEUE_TIMEOUT], the same request can be sent again
+    #[error("The server is busy, try again")]
+    QueueFull,
+    /// [State::process] stopped, like when the server shuts down
+    #[error("The game isn't running anymore")]
+    StateTaskGone,
 }
 
 #[derive(Debug, Error)]
@@ -119,8 +1345,792 @@ pub enum Error {
     IOSaving(std::path::PathBuf, std::io::Error),
     #[error("Could not save to journal file: {0}: {1}")]
     Saving(std::path::PathBuf, serde_json::Error),
-    #[error("Could not parse journal file: {0}: {1}")]
-    Parsing(std::path::PathBuf, serde_json::Error),
+    #[error("Could not parse line {1} of journal file: {0}: {2}")]
+    Parsing(std::path::PathBuf, usize, serde_json::Error),
     #[error("Could not load journal file: {0}: {1:?}")]
     Loading(std::path::PathBuf, GameError),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libaitfoaq::state::{Category, Clue, ClueRef};
+
+    fn read_journal(journal_path: &Path) -> Result<Vec<Event>, Error> {
+        Ok(read_timed_journal(journal_path)?.into_iter().map(|(event, _)| event).collect())
+    }
+
+    fn one_clue_board() -> Board {
+        Board {
+            categories: vec![Category {
+                title: "Only".to_owned(),
+                id: None,
+                clues: vec![Clue {
+                    id: None,
+                    clue: "clue".to_owned(),
+                    response: "response".to_owned(),
+                    hint: "hint".to_owned(),
+                    points: 100,
+                    can_wager: false,
+                    exclusive: false,
+                    solved: false,
+                    followup: None,
+                    value_hidden: false,
+                    value_revealed: false,
+                }],
+            }],
+        }
+    }
+
+    #[tokio::test]
+    async fn exports_results_once_at_score() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("journal.jsonl");
+        let results = dir.path().join("results");
+        let mut state = State::with_journal_and_token(&journal, "token".to_owned())
+            .unwrap()
+            .with_export(ExportOptions { directory: results.clone(), certificates: true });
+        let channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                for event in [
+                    Event::LoadBoard(one_clue_board()),
+                    Event::OpenLobby,
+                    Event::ConnectContestant { name_hint: "alice".to_owned() },
+                    Event::ConnectContestant { name_hint: "bob".to_owned() },
+                    Event::StartGame,
+                    Event::Pick { clue: (0, 0).into() },
+                    Event::ClueFullyShown,
+                    Event::Buzz { contestant: 1 },
+                    Event::AcceptAnswer,
+                    Event::AwardPoints { contestant: 0, points: 50, reason: Some("close enough".to_owned()), clue: Some((0, 0)) },
+                    Event::FinishClue { confirmed: false },
+                    // toggling lights in Score must not export again
+                    Event::Buzz { contestant: 0 },
+                ] {
+                    State::send(event, &channels.tx).await.unwrap();
+                }
+                for _ in 0..100 {
+                    if export::latest(&results).await.is_ok_and(|l| l.is_some()) { break; }
+                    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+                }
+                cancellation_token.cancel();
+            },
+        );
+
+        let files: Vec<_> = std::fs::read_dir(&results).unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        assert_eq!(files.iter().filter(|f| f.starts_with("results-")).count(), 1, "{:?}", files);
+        assert_eq!(files.iter().filter(|f| f.starts_with("certificate-")).count(), 2, "{:?}", files);
+
+        let latest = export::latest(&results).await.unwrap().unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&std::fs::read(latest).unwrap()).unwrap();
+        assert_eq!(json["scores"][0]["name"], "bob");
+        assert_eq!(json["scores"][0]["points"], 100);
+        assert_eq!(json["scores"][0]["rank"], 1);
+        assert_eq!(json["scores"][1]["rank"], 2);
+        assert_eq!(json["statistics"]["clues"], 1);
+        assert_eq!(json["statistics"]["corrections"], 1);
+        assert_eq!(json["corrected_clues"], serde_json::json!([{"clue": [0, 0], "corrections": 1, "points": 50}]));
+        assert_eq!(json["board"]["categories"][0]["title"], "Only");
+        assert_eq!(json["timeline"]["clues"][0]["clue"], serde_json::json!([0, 0]));
+    }
+
+    #[tokio::test]
+    async fn replays_a_journal() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("journal.jsonl");
+        let events = [
+            Event::LoadBoard(one_clue_board()),
+            Event::OpenLobby,
+            Event::ConnectContestant { name_hint: "alice".to_owned() },
+            Event::StartGame,
+            Event::Pick { clue: (0, 0).into() },
+            Event::ClueFullyShown,
+        ];
+        let lines: Vec<String> = events.iter().map(|e| serde_json::to_string(e).unwrap()).collect();
+        std::fs::write(&journal, lines.join("\n")).unwrap();
+
+        let mut state = State::replaying(&journal, "token".to_owned()).unwrap();
+        let channels = state.clonable_channels();
+        assert!(channels.replaying);
+        assert!(matches!(channels.rx.borrow().game.phase, GamePhase::Preparing));
+        let cancellation_token = CancellationToken::new();
+
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                let phase = |s: Result<GameState, Rejection>| s.unwrap().phase;
+                assert!(matches!(phase(State::replay(ReplayStep::Seek(2), &channels.tx).await), GamePhase::Connecting));
+                assert!(matches!(phase(State::replay(ReplayStep::Next, &channels.tx).await), GamePhase::Connecting));
+                assert!(matches!(phase(State::replay(ReplayStep::Next, &channels.tx).await), GamePhase::Picking { .. }));
+                assert!(matches!(phase(State::replay(ReplayStep::Seek(100), &channels.tx).await), GamePhase::Buzzing { .. }));
+                assert!(matches!(phase(State::replay(ReplayStep::Prev, &channels.tx).await), GamePhase::Clue { .. }));
+                assert!(matches!(channels.rx.borrow().game.phase, GamePhase::Clue { .. }));
+                assert!(matches!(
+                    State::send(Event::Buzz { contestant: 0 }, &channels.tx).await,
+                    Err(Rejection::Replaying)
+                ));
+                cancellation_token.cancel();
+            },
+        );
+        assert_eq!(std::fs::read_to_string(&journal).unwrap(), lines.join("\n"));
+    }
+
+    #[tokio::test]
+    async fn presence_is_not_journaled() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("journal.jsonl");
+        let mut state = State::with_journal_and_token(&journal, "token".to_owned()).unwrap();
+        let channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                for event in [
+                    Event::LoadBoard(one_clue_board()),
+                    Event::OpenLobby,
+                    Event::ConnectContestant { name_hint: "alice".to_owned() },
+                    Event::ConnectContestant { name_hint: "bob".to_owned() },
+                    Event::ConnectContestant { name_hint: "carol".to_owned() },
+                    Event::DisconnectContestant { contestant: 1 },
+                    Event::StartGame,
+                    Event::PassPick { to: Some(0) },
+                    Event::ReconnectContestant { contestant: 1 },
+                    Event::DisconnectContestant { contestant: 2 },
+                    Event::PassPick { to: None },
+                    // hands the pick on, skipping carol
+                    Event::DisconnectContestant { contestant: 1 },
+                ] {
+                    State::send(event, &channels.tx).await.unwrap();
+                }
+                cancellation_token.cancel();
+                assert_eq!(channels.rx.borrow().game.phase, GamePhase::Picking { contestant: 0 });
+            },
+        );
+
+        let events = read_journal(&journal).unwrap();
+        assert_eq!(
+            events.iter().map(Event::name).collect::<Vec<_>>(),
+            ["LoadBoard", "OpenLobby", "ConnectContestant", "ConnectContestant", "ConnectContestant", "StartGame", "PassPick", "PassPick", "PassPick"],
+        );
+        assert!(matches!(events[7], Event::PassPick { to: Some(1) }));
+        assert!(matches!(events[8], Event::PassPick { to: Some(0) }));
+
+        let state = State::with_journal_and_token(&journal, "token".to_owned()).unwrap();
+        let game = state.game.get_game_state();
+        assert!(game.contestants.iter().all(|c| !c.connected));
+        assert_eq!(game.phase, GamePhase::Picking { contestant: 0 });
+    }
+
+    #[tokio::test]
+    async fn first_pickers_replay_the_same() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("journal.jsonl");
+        let mut state = State::with_journal_and_token(&journal, "token".to_owned()).unwrap();
+        let channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                State::send(Event::LoadBoard(one_clue_board()), &channels.tx).await.unwrap();
+                State::send(Event::OpenLobby, &channels.tx).await.unwrap();
+                for name_hint in ["alice", "bob", "carol", "dave", "erin", "frank", "grace"] {
+                    State::send(Event::ConnectContestant { name_hint: name_hint.to_owned() }, &channels.tx).await.unwrap();
+                }
+                State::send(Event::StartGame, &channels.tx).await.unwrap();
+                cancellation_token.cancel();
+            },
+        );
+
+        let picker = channels.rx.borrow().game.phase.clone();
+        assert!(matches!(picker, GamePhase::Picking { .. }));
+        // all alive at once, so nothing lands where the previous one was
+        let restarts: Vec<_> = (0..4)
+            .map(|_| State::with_journal_and_token(&journal, "token".to_owned()).unwrap())
+            .collect();
+        for restarted in &restarts {
+            assert_eq!(restarted.game.get_game_state().phase, picker);
+        }
+    }
+
+    #[tokio::test]
+    async fn disconnects_during_a_buzz_replay_the_same() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("journal.jsonl");
+        let mut state = State::with_journal_and_token(&journal, "token".to_owned()).unwrap();
+        let channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                for event in [
+                    Event::LoadBoard(one_clue_board()),
+                    Event::OpenLobby,
+                    Event::ConnectContestant { name_hint: "alice".to_owned() },
+                    Event::StartGame,
+                    Event::Pick { clue: (0, 0).into() },
+                    Event::ClueFullyShown,
+                    Event::Buzz { contestant: 0 },
+                    Event::DisconnectContestant { contestant: 0 },
+                    Event::ReconnectContestant { contestant: 0 },
+                ] {
+                    State::send(event, &channels.tx).await.unwrap();
+                }
+                cancellation_token.cancel();
+                assert_eq!(channels.rx.borrow().game.phase, GamePhase::Buzzing { clue: (0, 0) });
+            },
+        );
+
+        let events = read_journal(&journal).unwrap();
+        assert!(matches!(events.last(), Some(Event::WithdrawBuzz)));
+        let state = State::with_journal_and_token(&journal, "token".to_owned()).unwrap();
+        assert_eq!(state.game.get_game_state().phase, GamePhase::Buzzing { clue: (0, 0) });
+    }
+
+    #[test]
+    fn timelines_leave_out_gaps() {
+        let mut board = one_clue_board();
+        let clue = board.categories[0].clues[0].clone();
+        board.categories[0].clues.push(clue);
+        let play = |clue| [
+            (0, Event::Pick { clue: ClueRef::from(clue) }),
+            (10, Event::ClueFullyShown),
+            (20, Event::Buzz { contestant: 0 }),
+            (90, Event::AcceptAnswer),
+        ];
+        let mut events: Vec<(Option<u64>, Event)> = vec![
+            // older journals don't have timestamps
+            (None, Event::LoadBoard(board)),
+            (Some(0), Event::OpenLobby),
+            (Some(30), Event::ConnectContestant { name_hint: "alice".to_owned() }),
+            (Some(60), Event::StartGame),
+        ];
+        events.extend(play((0, 0)).map(|(s, event)| (Some(120 + s), event)));
+        events.push((Some(380), Event::FinishClue { confirmed: false }));
+        // an hour long break
+        events.extend(play((0, 1)).map(|(s, event)| (Some(3980 + s), event)));
+        events.push((Some(4080), Event::FinishClue { confirmed: false }));
+        let lines: Vec<String> = events.into_iter().map(|(s, event)| {
+            let mut line = serde_json::to_value(event).unwrap();
+            if let Some(s) = s {
+                line["at"] = format!("2024-04-24T{}:{:02}:{:02}.250Z", 19 + s / 3600, s / 60 % 60, s % 60).into();
+            }
+            line.to_string() + "\n"
+        }).collect();
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("journal.jsonl");
+        std::fs::write(&journal, lines.concat()).unwrap();
+
+        let state = State::with_journal_and_token(&journal, "token".to_owned()).unwrap();
+        let timeline = state.out_rx.borrow().timeline.clone();
+        assert!(matches!(state.game.get_game_state().phase, GamePhase::Score));
+        assert_eq!(timeline.started_at.as_deref(), Some("2024-04-24T19:01:00Z"));
+        assert_eq!(timeline.finished_at.as_deref(), Some("2024-04-24T20:08:00Z"));
+        let clue = |clue, picked_at: &str, duration_s| ClueTime { clue, picked_at: picked_at.to_owned(), duration_s };
+        assert_eq!(timeline.clues, [
+            clue((0, 0), "2024-04-24T19:02:00Z", 260),
+            clue((0, 1), "2024-04-24T20:06:20Z", 100),
+        ]);
+        assert_eq!(timeline.longest_clue, Some(timeline.clues[0].clone()));
+        assert_eq!(timeline.gaps, [Gap {
+            from: "2024-04-24T19:06:20Z".to_owned(),
+            to: "2024-04-24T20:06:20Z".to_owned(),
+            duration_s: 3600,
+        }]);
+        // 60s until the first clue, 260s for it, then 100s after the break
+        assert_eq!(timeline.duration_s, 420);
+
+        // the same while replaying
+        let replaying = State::replaying(&journal, "token".to_owned()).unwrap();
+        assert_eq!(replaying.out_rx.borrow().timeline, timeline);
+    }
+
+    #[tokio::test]
+    async fn replays_ignore_presence() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("journal.jsonl");
+        // written before presence events were left out
+        let events = [
+            Event::LoadBoard(one_clue_board()),
+            Event::OpenLobby,
+            Event::ConnectContestant { name_hint: "alice".to_owned() },
+            Event::DisconnectContestant { contestant: 0 },
+            Event::ReconnectContestant { contestant: 0 },
+            Event::StartGame,
+        ];
+        let lines: Vec<String> = events.iter().map(|e| serde_json::to_string(e).unwrap()).collect();
+        std::fs::write(&journal, lines.join("\n")).unwrap();
+
+        let mut state = State::replaying(&journal, "token".to_owned()).unwrap();
+        let channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                for cursor in 3..=4 {
+                    let r = State::replay(ReplayStep::Seek(cursor), &channels.tx).await.unwrap();
+                    assert!(!r.contestants[0].connected);
+                }
+                // the journal without its presence events ends here
+                let r = State::replay(ReplayStep::Seek(100), &channels.tx).await.unwrap();
+                assert!(matches!(r.phase, GamePhase::Picking { contestant: 0 }));
+                let r = State::replay(ReplayStep::Prev, &channels.tx).await.unwrap();
+                assert!(matches!(r.phase, GamePhase::Connecting));
+                cancellation_token.cancel();
+            },
+        );
+
+        // resuming the same journal
+        let state = State::with_journal_and_token(&journal, "token".to_owned()).unwrap();
+        let game = state.game.get_game_state();
+        assert!(matches!(game.phase, GamePhase::Picking { contestant: 0 }));
+        assert!(!game.contestants[0].connected);
+    }
+
+    #[test]
+    fn journals_from_before_reasons_still_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("journal.jsonl");
+        let events = [
+            Event::LoadBoard(one_clue_board()),
+            Event::OpenLobby,
+            Event::ConnectContestant { name_hint: "alice".to_owned() },
+            Event::StartGame,
+        ];
+        let mut lines: Vec<String> = events.iter().map(|e| serde_json::to_string(e).unwrap()).collect();
+        lines.push(r#"{"type": "AwardPoints", "contestant": 0, "points": 5}"#.to_owned());
+        std::fs::write(&journal, lines.join("\n")).unwrap();
+
+        let state = State::with_journal_and_token(&journal, "token".to_owned()).unwrap();
+        assert_eq!(state.game.get_game_state().contestants[0].points, 5);
+        State::replaying(&journal, "token".to_owned()).unwrap();
+
+        // newer journals always have the field
+        lines.pop();
+        lines.push(r#"{"type": "AwardPoints", "contestant": 0, "points": 5, "reason": null}"#.to_owned());
+        std::fs::write(&journal, lines.join("\n")).unwrap();
+        assert!(matches!(
+            State::with_journal_and_token(&journal, "token".to_owned()),
+            Err(Error::Loading(_, GameError::ReasonRequired)),
+        ));
+    }
+
+    #[tokio::test]
+    async fn contestants_name_themselves() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("journal.jsonl");
+        let mut state = State::with_journal_and_token(&journal, "token".to_owned())
+            .unwrap()
+            .with_blocked_words(vec!["Rude".to_owned()]);
+        let channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                let tx = &channels.tx;
+                let name = |s: &Result<GameState, Rejection>| s.as_ref().unwrap().contestants[0].name.clone();
+                State::send(Event::OpenLobby, tx).await.unwrap();
+                State::send(Event::ConnectContestant { name_hint: "phone".to_owned() }, tx).await.unwrap();
+
+                // direct mode
+                let r = State::suggest_name(0, " Al\u{7}ice ".to_owned(), tx).await;
+                assert_eq!(name(&r), Some("Alice".to_owned()));
+                assert!(matches!(
+                    State::suggest_name(0, "so rude".to_owned(), tx).await,
+                    Err(Rejection::InvalidName)
+                ));
+                assert!(matches!(State::suggest_name(0, "\n".to_owned(), tx).await, Err(Rejection::InvalidName)));
+                assert!(matches!(
+                    State::suggest_name(1, "Bob".to_owned(), tx).await,
+                    Err(Rejection::Game(GameError::ContestantNotFound))
+                ));
+                cancellation_token.cancel();
+            },
+        );
+
+        // approval mode, settings are only possible while preparing
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("journal.jsonl");
+        let mut state = State::with_journal_and_token(&journal, "token".to_owned()).unwrap();
+        let channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                let tx = &channels.tx;
+                let options = libaitfoaq::state::Options { approve_names: true, ..Default::default() };
+                State::send(Event::Settings(options), tx).await.unwrap();
+                State::send(Event::OpenLobby, tx).await.unwrap();
+                State::send(Event::ConnectContestant { name_hint: "phone".to_owned() }, tx).await.unwrap();
+
+                let r = State::suggest_name(0, "Alice".to_owned(), tx).await.unwrap();
+                assert_eq!(r.contestants[0].name, None);
+                assert_eq!(channels.rx.borrow().pending_names.get(&0), Some(&"Alice".to_owned()));
+                State::approve_name(0, false, tx).await.unwrap();
+                assert!(channels.rx.borrow().pending_names.is_empty());
+                assert!(matches!(State::approve_name(0, true, tx).await, Err(Rejection::NoPendingName)));
+
+                State::suggest_name(0, "Alicia".to_owned(), tx).await.unwrap();
+                let r = State::approve_name(0, true, tx).await.unwrap();
+                assert_eq!(r.contestants[0].name, Some("Alicia".to_owned()));
+                assert!(channels.rx.borrow().pending_names.is_empty());
+                cancellation_token.cancel();
+            },
+        );
+    }
+
+    #[tokio::test]
+    async fn typed_answers_are_journaled() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("journal.jsonl");
+        let mut state = State::with_journal_and_token(&journal, "token".to_owned()).unwrap();
+        let channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                for event in [
+                    Event::LoadBoard(one_clue_board()),
+                    Event::OpenLobby,
+                    Event::ConnectContestant { name_hint: "alice".to_owned() },
+                    Event::StartGame,
+                    Event::Pick { clue: (0, 0).into() },
+                    Event::ClueFullyShown,
+                    Event::Buzz { contestant: 0 },
+                    Event::SubmitAnswer { contestant: 0, text: "respnse".to_owned() },
+                ] {
+                    State::send(event, &channels.tx).await.unwrap();
+                }
+                cancellation_token.cancel();
+            },
+        );
+
+        let mut game = Game::new();
+        for event in read_journal(&journal).unwrap() {
+            game.apply(event).unwrap();
+        }
+        assert!(matches!(
+            game.get_game_state().phase,
+            GamePhase::Buzzed { answer: Some(answer), .. } if answer == "respnse",
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn scheduled_starts_count_down() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("journal.jsonl");
+        let mut state = State::with_journal_and_token(&journal, "token".to_owned()).unwrap();
+        let mut channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                let tx = &channels.tx;
+                let minute = Some(Duration::from_secs(60));
+                assert!(matches!(State::schedule_start(minute, tx).await, Err(Rejection::Game(GameError::WrongPhase { .. }))));
+                State::send(Event::LoadBoard(one_clue_board()), tx).await.unwrap();
+                State::send(Event::OpenLobby, tx).await.unwrap();
+
+                // nobody is there when it runs out
+                State::schedule_start(minute, tx).await.unwrap();
+                assert!(matches!(channels.rx.borrow().scheduled_start, Some(ScheduledStart::At(_))));
+                channels.rx.wait_for(|out| out.scheduled_start == Some(ScheduledStart::Failed)).await.unwrap();
+                assert!(matches!(channels.rx.borrow().game.phase, GamePhase::Connecting));
+
+                // cancelled
+                State::send(Event::ConnectContestant { name_hint: "alice".to_owned() }, tx).await.unwrap();
+                State::schedule_start(minute, tx).await.unwrap();
+                tokio::time::sleep(Duration::from_secs(30)).await;
+                State::schedule_start(None, tx).await.unwrap();
+                assert_eq!(channels.rx.borrow().scheduled_start, None);
+                assert!(matches!(State::schedule_start(None, tx).await, Err(Rejection::NotScheduled)));
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                assert!(matches!(channels.rx.borrow().game.phase, GamePhase::Connecting));
+
+                // refreshed while counting down, then started
+                State::schedule_start(Some(Duration::from_secs(10)), tx).await.unwrap();
+                let start = Instant::now();
+                let mut refreshes = 0;
+                while channels.rx.borrow_and_update().scheduled_start.is_some() {
+                    channels.rx.changed().await.unwrap();
+                    refreshes += 1;
+                }
+                assert!(refreshes >= 10, "{} refreshes", refreshes);
+                assert!(start.elapsed() >= Duration::from_secs(10));
+                channels.rx.wait_for(|out| matches!(out.game.phase, GamePhase::Picking { .. })).await.unwrap();
+                cancellation_token.cancel();
+            },
+        );
+
+        let events = read_journal(&journal).unwrap();
+        assert_eq!(events.iter().filter(|e| matches!(e, Event::StartGame)).count(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn lobby_deadlines_remind_without_starting() {
+        let mut state = State::ephemeral("token".to_owned())
+            .with_lobby_reminders(vec![Duration::from_secs(30), Duration::from_secs(120)]);
+        let mut channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                let tx = &channels.tx;
+                let reminder = |rx: &watch::Receiver<Out>| rx.borrow().lobby_deadline.and_then(|d| d.reminder);
+                let five_minutes = Duration::from_secs(5 * 60);
+                assert!(matches!(State::set_lobby_deadline(five_minutes, tx).await, Err(Rejection::Game(GameError::WrongPhase { .. }))));
+                assert!(matches!(State::extend_lobby_deadline(five_minutes, tx).await, Err(Rejection::NoLobbyDeadline)));
+                State::send(Event::LoadBoard(one_clue_board()), tx).await.unwrap();
+                State::send(Event::OpenLobby, tx).await.unwrap();
+
+                // escalating checkpoints, refreshed in between
+                State::set_lobby_deadline(five_minutes, tx).await.unwrap();
+                let set = Instant::now();
+                let mut refreshes = 0;
+                while reminder(&channels.rx).is_none() {
+                    channels.rx.changed().await.unwrap();
+                    refreshes += 1;
+                }
+                assert_eq!(set.elapsed(), Duration::from_secs(3 * 60));
+                assert_eq!(reminder(&channels.rx), Some(Duration::from_secs(120)));
+                assert!(refreshes >= 3 * 60, "{} refreshes", refreshes);
+                channels.rx.wait_for(|out| out.lobby_deadline.unwrap().reminder == Some(Duration::from_secs(30))).await.unwrap();
+                assert_eq!(set.elapsed(), Duration::from_secs(4 * 60 + 30));
+                channels.rx.wait_for(|out| out.lobby_deadline.unwrap().reminder == Some(Duration::ZERO)).await.unwrap();
+                assert_eq!(set.elapsed(), five_minutes);
+                // nothing forces the start
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                assert!(matches!(channels.rx.borrow().game.phase, GamePhase::Connecting));
+
+                // extended from now, the reminders are due again
+                State::extend_lobby_deadline(Duration::from_secs(60), tx).await.unwrap();
+                let extended = Instant::now();
+                let deadline = channels.rx.borrow().lobby_deadline.unwrap();
+                assert_eq!(deadline, LobbyDeadline { at: extended + Duration::from_secs(60), reminder: None });
+                channels.rx.wait_for(|out| out.lobby_deadline.unwrap().reminder.is_some()).await.unwrap();
+                assert_eq!(extended.elapsed(), Duration::from_secs(30));
+                assert_eq!(reminder(&channels.rx), Some(Duration::from_secs(30)));
+
+                // starting cancels it
+                State::send(Event::ConnectContestant { name_hint: "alice".to_owned() }, tx).await.unwrap();
+                State::send(Event::StartGame, tx).await.unwrap();
+                assert_eq!(channels.rx.borrow().lobby_deadline, None);
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                assert_eq!(channels.rx.borrow().lobby_deadline, None);
+                assert!(matches!(State::extend_lobby_deadline(five_minutes, tx).await, Err(Rejection::NoLobbyDeadline)));
+                cancellation_token.cancel();
+            },
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn full_queues_are_reported() {
+        let state = State::ephemeral("token".to_owned()).with_queue_capacity(2);
+        let channels = state.clonable_channels();
+        let tx = &channels.tx;
+        // nothing processes them
+        let waiting: Vec<_> = (0..2)
+            .map(|_| {
+                let tx = tx.clone();
+                tokio::spawn(async move { State::send(Event::OpenLobby, &tx).await })
+            })
+            .collect();
+        while State::queue_depth(tx) < 2 {
+            tokio::task::yield_now().await;
+        }
+        let start = Instant::now();
+        assert!(matches!(State::send(Event::OpenLobby, tx).await, Err(Rejection::Send(SendError::QueueFull))));
+        assert_eq!(start.elapsed(), QUEUE_TIMEOUT);
+
+        drop(state);
+        for waiting in waiting {
+            assert!(matches!(waiting.await.unwrap(), Err(Rejection::Send(SendError::StateTaskGone))));
+        }
+        assert!(matches!(State::send(Event::OpenLobby, tx).await, Err(Rejection::Send(SendError::StateTaskGone))));
+        assert_eq!(State::queue_depth(tx), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn idle_contestants_are_flagged_and_disconnected() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("journal.jsonl");
+        let mut state = State::with_journal_and_token(&journal, "token".to_owned())
+            .unwrap()
+            .with_idleness(Idleness {
+                after: Some(Duration::from_secs(60)),
+                disconnect_after: Some(Duration::from_secs(300)),
+            });
+        let mut channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                let tx = &channels.tx;
+                State::send(Event::LoadBoard(one_clue_board()), tx).await.unwrap();
+                State::send(Event::OpenLobby, tx).await.unwrap();
+                State::send(Event::ConnectContestant { name_hint: "alice".to_owned() }, tx).await.unwrap();
+                State::send(Event::ConnectContestant { name_hint: "bob".to_owned() }, tx).await.unwrap();
+                let idle = |rx: &watch::Receiver<Out>| rx.borrow().idle.iter().copied().collect::<Vec<_>>();
+
+                // alice keeps pressing buttons
+                for _ in 0..4 {
+                    tokio::time::sleep(Duration::from_secs(30)).await;
+                    State::active(0, tx).await.unwrap();
+                }
+                assert_eq!(idle(&channels.rx), [1]);
+
+                assert!(matches!(State::clear_idle(0, tx).await, Err(Rejection::NotIdle)));
+                State::clear_idle(1, tx).await.unwrap();
+                assert_eq!(idle(&channels.rx), [] as [ContestantHandle; 0]);
+                channels.rx.wait_for(|out| out.idle.contains(&1)).await.unwrap();
+                State::active(0, tx).await.unwrap();
+
+                // flagged long before, then disconnected
+                let started = Instant::now();
+                channels.rx.wait_for(|out| !out.game.contestants[1].connected).await.unwrap();
+                assert!(started.elapsed() >= Duration::from_secs(200), "{:?}", started.elapsed());
+                assert!(channels.rx.borrow().game.contestants[0].connected);
+
+                // coming back is enough to reconnect
+                State::active(1, tx).await.unwrap();
+                let out = channels.rx.borrow().clone();
+                assert!(out.game.contestants[1].connected);
+                assert!(!out.idle.contains(&1));
+                cancellation_token.cancel();
+            },
+        );
+
+        // nothing of it ends up in the journal
+        let events = read_journal(&journal).unwrap();
+        assert_eq!(events.len(), 4, "{:?}", events);
+    }
+
+    #[test]
+    fn broken_last_lines_are_set_aside() {
+        let lines: Vec<String> = [
+            Event::LoadBoard(one_clue_board()),
+            Event::OpenLobby,
+            Event::ConnectContestant { name_hint: "alice".to_owned() },
+        ].iter().map(|e| serde_json::to_string(e).unwrap() + "\n").collect();
+        let clean = lines.concat();
+        let journal_with = |contents: &str| {
+            let dir = tempfile::tempdir().unwrap();
+            let journal = dir.path().join("journal.jsonl");
+            std::fs::write(&journal, contents).unwrap();
+            (dir, journal)
+        };
+
+        let (dir, journal) = journal_with(&clean);
+        let state = State::with_journal_and_token(&journal, "token".to_owned()).unwrap();
+        assert_eq!(state.game.get_game_state().contestants.len(), 1);
+        drop(state);
+        assert_eq!(std::fs::read_to_string(&journal).unwrap(), clean);
+        assert!(!dir.path().join("journal.jsonl.corrupt").exists());
+
+        // the server died while writing the last event
+        let (dir, journal) = journal_with(&(clean.clone() + r#"{"type":"StartGa"#));
+        let replaying = State::replaying(&journal, "token".to_owned()).unwrap();
+        assert_eq!(replaying.replay.as_ref().unwrap().len(), 3);
+        drop(replaying);
+        let state = State::with_journal_and_token(&journal, "token".to_owned()).unwrap();
+        assert!(matches!(state.game.get_game_state().phase, GamePhase::Connecting));
+        drop(state);
+        assert_eq!(std::fs::read_to_string(&journal).unwrap(), clean);
+        assert_eq!(std::fs::read_to_string(dir.path().join("journal.jsonl.corrupt")).unwrap(), r#"{"type":"StartGa"#);
+
+        // anywhere else it's not clear what's missing
+        let (dir, journal) = journal_with(&[lines[0].as_str(), "garbage\n", lines[1].as_str()].concat());
+        assert!(matches!(
+            State::with_journal_and_token(&journal, "token".to_owned()),
+            Err(Error::Parsing(_, 2, _))
+        ));
+        assert!(!dir.path().join("journal.jsonl.corrupt").exists());
+    }
+
+    #[tokio::test]
+    async fn ephemeral_games_leave_no_files() {
+        let files = || -> Vec<_> { std::fs::read_dir(".").unwrap().map(|e| e.unwrap().file_name()).collect() };
+        let before = files();
+        let mut state = State::ephemeral("token".to_owned());
+        assert!(state.start_with(one_clue_board()).await.unwrap());
+        let channels = state.clonable_channels();
+        let cancellation_token = CancellationToken::new();
+
+        tokio::join!(
+            state.process(cancellation_token.clone()),
+            async {
+                let tx = &channels.tx;
+                State::send(Event::ConnectContestant { name_hint: "alice".to_owned() }, tx).await.unwrap();
+                State::send(Event::StartGame, tx).await.unwrap();
+                let game = State::send(Event::Pick { clue: (0, 0).into() }, tx).await.unwrap();
+                assert!(matches!(game.phase, GamePhase::Clue { .. }));
+                assert!(matches!(channels.rx.borrow().caused_by, Some(Event::Pick { .. })));
+                cancellation_token.cancel();
+            },
+        );
+        assert!(state.journal.is_none());
+        assert_eq!(files(), before);
+    }
+
+    #[tokio::test]
+    async fn demos_start_in_the_lobby() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("journal.jsonl");
+        let mut state = State::with_journal_and_token(&journal, "token".to_owned()).unwrap();
+        assert!(state.start_demo().await.unwrap());
+        let out = state.clonable_channels().rx.borrow().clone();
+        assert!(matches!(out.game.phase, GamePhase::Connecting));
+        assert_eq!(out.game.board, Board::demo());
+        drop(state);
+
+        // resuming the demo doesn't load it again
+        let mut state = State::with_journal_and_token(&journal, "token".to_owned()).unwrap();
+        assert!(!state.start_demo().await.unwrap());
+        assert!(matches!(state.game.get_game_state().phase, GamePhase::Connecting));
+        let events = read_journal(&journal).unwrap();
+        assert_eq!(events.iter().map(Event::name).collect::<Vec<_>>(), ["LoadBoard", "OpenLobby"]);
+    }
+
+    #[tokio::test]
+    async fn profiles_are_journaled() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = dir.path().join("journal.jsonl");
+        let profiles = dir.path().join("profiles");
+        std::fs::create_dir(&profiles).unwrap();
+        std::fs::write(profiles.join("pub.toml"), "[options]\nconfirm_skip = true\n[server]\nping_misses = 7").unwrap();
+        std::fs::write(profiles.join("broken.toml"), "[options]\nconfirm_skips = true").unwrap();
+        let mut state = State::with_journal_and_token(&journal, "token".to_owned()).unwrap()
+            .with_profiles(profiles);
+        let channels = state.clonable_channels();
+        assert_eq!(channels.rx.borrow().profiles, ["broken", "pub"]);
+        let cancellation_token = CancellationToken::new();
+        tokio::join!(state.process(cancellation_token.clone()), async {
+            let error = State::apply_profile("broken".to_owned(), &channels.tx).await.unwrap_err();
+            assert!(error.to_string().contains("options.confirm_skips"), "{}", error);
+            assert!(State::apply_profile("../pub".to_owned(), &channels.tx).await.is_err());
+
+            let game = State::apply_profile("pub".to_owned(), &channels.tx).await.unwrap();
+            assert!(game.options.confirm_skip);
+            assert_eq!(channels.pinging.borrow().misses, 7);
+            assert_eq!(channels.rx.borrow().profiles, ["broken", "pub"]);
+
+            // options only change before the lobby opens
+            State::send(Event::OpenLobby, &channels.tx).await.unwrap();
+            let error = State::apply_profile("pub".to_owned(), &channels.tx).await.unwrap_err();
+            assert!(matches!(error, Rejection::Game(GameError::WrongPhase { .. })));
+            cancellation_token.cancel();
+        });
+        let events = read_journal(&journal).unwrap();
+        assert_eq!(events.iter().map(Event::name).collect::<Vec<_>>(), ["Settings", "OpenLobby"]);
+    }
+}